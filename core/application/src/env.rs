@@ -25,6 +25,8 @@
     ProtocolParamValue,
     Service,
     ServiceId,
+    TokenInfo,
+    Tokens,
     TotalServed,
     TransactionReceipt,
     TransactionResponse,
@@ -39,7 +41,7 @@
 use types::{NodeRegistryChange, NodeRegistryChanges, Nonce};
 
 use crate::config::ApplicationConfig;
-use crate::state::{ApplicationState, QueryRunner};
+use crate::state::{migration, ApplicationState, QueryRunner};
 use crate::storage::AtomoStorage;
 
 pub struct Env<B: StorageBackend, S: SerdeBackend, T: StateTree> {
@@ -59,9 +61,13 @@ impl ApplicationEnv {
             checkpoint.map(|(hash, checkpoint)| (hash, checkpoint, state_tree_tables.as_slice())),
         )?;
 
-        Ok(Self {
+        let mut env = Self {
             inner: ApplicationState::build(builder)?,
-        })
+        };
+        env.migrate_state()
+            .context("Failed to migrate application state")?;
+
+        Ok(env)
     }
 
     pub fn query_runner(&self) -> QueryRunner {
@@ -117,11 +123,13 @@ async fn run<C, F, P>(
                         response.change_epoch = true;
                     }
 
-                    let mut event = None;
+                    // The events emitted by the state changes are dropped with the changes if
+                    // the transaction reverted.
+                    let emitted = app.take_events();
+                    let mut events = Vec::new();
                     if let TransactionResponse::Success(_) = results {
-                        if let Some(e) = txn.event() {
-                            event = Some(e);
-                        }
+                        events.extend(txn.event());
+                        events.extend(emitted);
                     }
 
                     let receipt = TransactionReceipt {
@@ -132,7 +140,7 @@ async fn run<C, F, P>(
                         from: txn.sender(),
                         to: txn.to(),
                         response: results,
-                        event,
+                        events,
                     };
                     /* Todo(dalton): Check if the transaction resulted in the committee change(Like a current validator getting slashed)
                         if so acknowledge that in the block response
@@ -229,6 +237,9 @@ pub fn apply_genesis_block(&mut self, genesis: Genesis) -> Result<bool> {
                 ctx.get_table::<(NodeIndex, NodeIndex), Duration>("latencies");
             let mut consensus_key_to_index_table = ctx.get_table::<ConsensusPublicKey, NodeIndex>("consensus_key_to_index");
             let mut pub_key_to_index_table = ctx.get_table::<NodePublicKey, NodeIndex>("pub_key_to_index");
+            let mut tokens_table = ctx.get_table::<EthAddress, TokenInfo>("tokens");
+            let mut token_balances_table =
+                ctx.get_table::<(EthAddress, EthAddress), HpUfixed<18>>("token_balances");
 
             // TODO(matthias): should we hash the genesis state instead?
             metadata_table.insert(Metadata::LastEpochHash, Value::Hash([0; 32]));
@@ -249,7 +260,6 @@ pub fn apply_genesis_block(&mut self, genesis: Genesis) -> Result<bool> {
             let governance_account = AccountInfo {
                 client_key: None,
                 flk_balance: 0u64.into(),
-                stables_balance: 0u64.into(),
                 bandwidth_balance: 0u64.into(),
                 nonce: 0,
             };
@@ -420,19 +430,32 @@ pub fn apply_genesis_block(&mut self, genesis: Genesis) -> Result<bool> {
                         owner: service.owner,
                         commodity_type: service.commodity_type,
                         slashing: (),
+                        binary: service.binary,
+                        version: service.version,
+                        resources: service.resources,
                     },
                 )
             }
 
+            for (token, info) in Tokens::builtin() {
+                tokens_table.insert(token.address(), info);
+            }
+
             for account in genesis.account {
                 let info = AccountInfo {
                     flk_balance: account.flk_balance,
-                    stables_balance: account.stables_balance.into(),
                     bandwidth_balance: account.bandwidth_balance.into(),
                     nonce: 0,
                     client_key: None,
                 };
                 account_table.insert(account.public_key, info);
+                if account.stables_balance > 0 {
+                    let balance = HpUfixed::<6>::from(account.stables_balance);
+                    token_balances_table.insert(
+                        (account.public_key, Tokens::USDC.address()),
+                        balance.convert_precision::<18>(),
+                    );
+                }
             }
 
             for (client_key, address) in genesis.client {
@@ -469,12 +492,22 @@ pub fn apply_genesis_block(&mut self, genesis: Genesis) -> Result<bool> {
 
             metadata_table.insert(Metadata::Epoch, Value::Epoch(0));
             metadata_table.insert(Metadata::EpochEra, Value::EpochEra(0));
+            metadata_table.insert(
+                Metadata::StateVersion,
+                Value::StateVersion(migration::CURRENT_STATE_VERSION),
+            );
 
             tracing::info!("Genesis block loaded into application state.");
             Ok(true)
         })?
     }
 
+    /// Migrates the application state to the current version if it was written by an older one.
+    /// Returns true if the state was migrated.
+    pub fn migrate_state(&mut self) -> Result<bool> {
+        self.inner.run(migration::migrate)
+    }
+
     // Should only be called after saving or loading from an epoch checkpoint
     pub fn update_last_epoch_hash(&mut self, state_hash: [u8; 32]) -> Result<()> {
         self.inner
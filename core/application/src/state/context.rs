@@ -2,7 +2,14 @@
 use std::cell::RefCell;
 use std::hash::Hash;
 
-use atomo::{KeyIterator, SerdeBackend, StorageBackend, TableRef as AtomoTableRef, TableSelector};
+use atomo::{
+    Checkpoint,
+    KeyIterator,
+    SerdeBackend,
+    StorageBackend,
+    TableRef as AtomoTableRef,
+    TableSelector,
+};
 use fxhash::FxHashMap;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
@@ -10,6 +17,7 @@
 pub trait Backend {
     type Ref<K: Eq + Hash + Send + Serialize + DeserializeOwned
      + 'static, V: Clone + Send + Serialize + DeserializeOwned + 'static>: TableRef<K, V>;
+    type Checkpoint;
 
     fn get_table_reference<
         K: Eq + Hash + Send + Serialize + DeserializeOwned,
@@ -18,6 +26,15 @@ fn get_table_reference<
         &self,
         id: &str,
     ) -> Self::Ref<K, V>;
+
+    /// Returns a checkpoint of the changes made so far through the table references.
+    fn checkpoint(&self) -> Self::Checkpoint;
+
+    /// Discards the changes made after the given checkpoint was taken.
+    fn revert(&self, checkpoint: Self::Checkpoint);
+
+    /// Keeps the changes made after the given checkpoint was taken.
+    fn commit(&self, checkpoint: Self::Checkpoint);
 }
 
 pub trait TableRef<K, V> {
@@ -38,6 +55,7 @@ impl<'selector, B: StorageBackend, S: SerdeBackend> Backend for StateContext<'se
         K: Eq + Hash + Send + Serialize + DeserializeOwned + 'static,
         V: Clone + Send + Serialize + DeserializeOwned + 'static,
     > = AtomoTable<'selector, K, V, B, S>;
+    type Checkpoint = Checkpoint;
 
     fn get_table_reference<
         K: Eq + Hash + Send + Serialize + DeserializeOwned,
@@ -48,6 +66,18 @@ fn get_table_reference<
     ) -> Self::Ref<K, V> {
         AtomoTable(RefCell::new(self.table_selector.get_table(id)))
     }
+
+    fn checkpoint(&self) -> Self::Checkpoint {
+        self.table_selector.checkpoint()
+    }
+
+    fn revert(&self, checkpoint: Self::Checkpoint) {
+        self.table_selector.revert(checkpoint)
+    }
+
+    fn commit(&self, checkpoint: Self::Checkpoint) {
+        self.table_selector.commit(checkpoint)
+    }
 }
 
 pub struct AtomoTable<
@@ -200,6 +230,24 @@ fn test_as_map() {
         });
     }
 
+    #[test]
+    fn test_revert() {
+        let mut db = new_test_db();
+        db.run(|ctx| {
+            let ctx = StateContext {
+                table_selector: ctx,
+            };
+            let table = ctx.get_table_reference::<String, String>("data");
+            table.set("key1".to_string(), "value1".to_string());
+            let checkpoint = ctx.checkpoint();
+            table.set("key1".to_string(), "value2".to_string());
+            table.set("key2".to_string(), "value2".to_string());
+            ctx.revert(checkpoint);
+            assert_eq!(table.get(&"key1".to_string()), Some("value1".to_string()));
+            assert_eq!(table.get(&"key2".to_string()), None);
+        });
+    }
+
     #[test]
     #[should_panic]
     fn test_as_map_should_panic_without_enable_iter() {
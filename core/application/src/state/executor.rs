@@ -1,3 +1,4 @@
+use std::cell::RefCell;
 use std::cmp::Ordering;
 use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::net::IpAddr;
@@ -23,13 +24,17 @@
     CommitteeSelectionBeaconReveal,
     CommodityTypes,
     ContentUpdate,
+    Delegation,
+    DelegationPool,
     DeliveryAcknowledgmentProof,
     Epoch,
+    Event,
     ExecutionData,
     ExecutionError,
     Metadata,
     NodeIndex,
     NodeInfo,
+    NodeInfoUpdate,
     NodePorts,
     NodeRegistryChange,
     NodeServed,
@@ -43,8 +48,10 @@
     ReputationMeasurements,
     Service,
     ServiceId,
+    ServiceResources,
     ServiceRevenue,
     Staking,
+    TokenInfo,
     Tokens,
     TotalServed,
     TransactionRequest,
@@ -54,6 +61,7 @@
     UpdateRequest,
     Value,
     WithdrawInfo,
+    MAX_BATCH_SIZE,
     MAX_MEASUREMENTS_PER_TX,
     MAX_MEASUREMENTS_SUBMIT,
     MAX_UPDATES_CONTENT_REGISTRY,
@@ -136,7 +144,16 @@ pub struct StateExecutor<B: Backend> {
     >,
     pub committee_selection_beacon_non_revealing_node: B::Ref<NodeIndex, ()>,
     pub withdraws: B::Ref<u64, WithdrawInfo>,
+    pub delegations: B::Ref<(EthAddress, NodeIndex), Delegation>,
+    pub delegator_nodes: B::Ref<EthAddress, BTreeSet<NodeIndex>>,
+    pub delegation_pools: B::Ref<NodeIndex, DelegationPool>,
+    pub pending_node_info_updates: B::Ref<NodeIndex, NodeInfoUpdate>,
+    pub pending_commissions: B::Ref<NodeIndex, u8>,
+    pub tokens: B::Ref<EthAddress, TokenInfo>,
+    pub token_balances: B::Ref<(EthAddress, EthAddress), HpUfixed<18>>,
     pub backend: B,
+    /// The events emitted while executing the current transaction, see [`Self::take_events`].
+    events: RefCell<Vec<Event>>,
 }
 
 impl<B: Backend> StateExecutor<B> {
@@ -168,22 +185,43 @@ pub fn new(backend: B) -> Self {
             committee_selection_beacon_non_revealing_node: backend
                 .get_table_reference("committee_selection_beacon_non_revealing_node"),
             withdraws: backend.get_table_reference("withdraws"),
+            delegations: backend.get_table_reference("delegations"),
+            delegator_nodes: backend.get_table_reference("delegator_nodes"),
+            delegation_pools: backend.get_table_reference("delegation_pools"),
+            pending_node_info_updates: backend.get_table_reference("pending_node_info_updates"),
+            pending_commissions: backend.get_table_reference("pending_commissions"),
+            tokens: backend.get_table_reference("tokens"),
+            token_balances: backend.get_table_reference("token_balances"),
             backend,
+            events: Default::default(),
         }
     }
 
+    /// Returns the events emitted by the state changes since the last call. They are only
+    /// meaningful if the transaction that emitted them succeeded.
+    pub fn take_events(&self) -> Vec<Event> {
+        self.events.take()
+    }
+
+    fn emit(&self, event: Event) {
+        self.events.borrow_mut().push(event);
+    }
+
     /// Executes a generic transaction.
     pub fn execute_transaction(&self, txn: TransactionRequest) -> TransactionResponse {
         let hash = txn.hash();
-        let (sender, response) = match txn {
-            TransactionRequest::UpdateRequest(payload) => (
-                payload.payload.sender,
-                self.execute_fleek_transaction(payload),
-            ),
-            TransactionRequest::EthereumRequest(payload) => (
-                TransactionSender::AccountOwner(EthAddress(payload.from.0)),
-                self.execute_ethereum_transaction(payload.into()),
-            ),
+        let sender = txn.sender();
+        // The fee is paid before the transaction is executed, and is kept even if it reverts.
+        let response = match self.charge_fee(sender, txn.fee()) {
+            Ok(()) => match txn {
+                TransactionRequest::UpdateRequest(payload) => {
+                    self.execute_fleek_transaction(payload)
+                },
+                TransactionRequest::EthereumRequest(payload) => {
+                    self.execute_ethereum_transaction(payload.into())
+                },
+            },
+            Err(e) => e,
         };
         self.executed_digests.set(hash, ());
         // Increment nonce of the sender
@@ -193,34 +231,55 @@ pub fn execute_transaction(&self, txn: TransactionRequest) -> TransactionRespons
 
     /// Executes a fleek transaction.
     fn execute_fleek_transaction(&self, txn: UpdateRequest) -> TransactionResponse {
-        let response = match txn.payload.method {
+        let response = self.execute_method(txn.payload.sender, txn.payload.method);
+
+        #[cfg(debug_assertions)]
+        {
+            let node_info_len = self.node_info.keys().count();
+            let consensus_key_to_index_len = self.consensus_key_to_index.keys().count();
+            let pub_key_to_index_len = self.pub_key_to_index.keys().count();
+            assert_eq!(node_info_len, consensus_key_to_index_len);
+            assert_eq!(node_info_len, pub_key_to_index_len);
+            assert_eq!(pub_key_to_index_len, consensus_key_to_index_len);
+        }
+
+        response
+    }
+
+    /// Executes a single update method on behalf of the sender.
+    fn execute_method(
+        &self,
+        sender: TransactionSender,
+        method: UpdateMethod,
+    ) -> TransactionResponse {
+        match method {
             UpdateMethod::SubmitDeliveryAcknowledgmentAggregation {
                 commodity,
                 service_id,
                 proofs,
                 metadata: _,
-            } => self.submit_pod(txn.payload.sender, commodity, service_id, proofs),
+            } => self.submit_pod(sender, commodity, service_id, proofs),
 
             UpdateMethod::Withdraw {
                 amount,
                 token,
                 receiving_address,
-            } => self.withdraw(txn.payload.sender, receiving_address, amount, token),
+            } => self.withdraw(sender, receiving_address, amount, token),
 
             UpdateMethod::Mint {
                 amount,
                 token,
                 receiving_address,
-            } => self.mint(txn.payload.sender, receiving_address, amount, token),
+            } => self.mint(sender, receiving_address, amount, token),
 
             UpdateMethod::Deposit {
                 proof,
                 token,
                 amount,
-            } => self.deposit(txn.payload.sender, proof, amount, token),
+            } => self.deposit(sender, proof, amount, token),
 
             UpdateMethod::Transfer { amount, token, to } => {
-                self.transfer(txn.payload.sender, amount, token, to)
+                self.transfer(sender, amount, token, to)
             },
 
             UpdateMethod::Stake {
@@ -232,7 +291,7 @@ fn execute_fleek_transaction(&self, txn: UpdateRequest) -> TransactionResponse {
                 worker_domain,
                 ports,
             } => self.stake(
-                txn.payload.sender,
+                sender,
                 amount,
                 node_public_key,
                 consensus_key,
@@ -242,75 +301,139 @@ fn execute_fleek_transaction(&self, txn: UpdateRequest) -> TransactionResponse {
                 ports,
             ),
             UpdateMethod::StakeLock { node, locked_for } => {
-                self.stake_lock(txn.payload.sender, node, locked_for)
+                self.stake_lock(sender, node, locked_for)
             },
 
-            UpdateMethod::Unstake { amount, node } => {
-                self.unstake(txn.payload.sender, amount, node)
-            },
+            UpdateMethod::Unstake { amount, node } => self.unstake(sender, amount, node),
 
             UpdateMethod::WithdrawUnstaked { node, recipient } => {
-                self.withdraw_unstaked(txn.payload.sender, node, recipient)
+                self.withdraw_unstaked(sender, node, recipient)
+            },
+
+            UpdateMethod::Delegate { node, amount } => self.delegate(sender, amount, node),
+
+            UpdateMethod::Undelegate { node, amount } => self.undelegate(sender, amount, node),
+
+            UpdateMethod::WithdrawUndelegated { node, recipient } => {
+                self.withdraw_undelegated(sender, node, recipient)
             },
 
-            UpdateMethod::ChangeEpoch { epoch } => self.change_epoch(txn.payload.sender, epoch),
+            UpdateMethod::SetCommissionRate { node, commission } => {
+                self.set_commission_rate(sender, node, commission)
+            },
+
+            UpdateMethod::UpdateNodeInfo { node, update } => {
+                self.update_node_info(sender, node, update)
+            },
+
+            UpdateMethod::ChangeEpoch { epoch } => self.change_epoch(sender, epoch),
 
             UpdateMethod::CommitteeSelectionBeaconCommit { commit } => {
-                self.committee_selection_beacon_commit(txn.payload.sender, commit)
+                self.committee_selection_beacon_commit(sender, commit)
             },
 
             UpdateMethod::CommitteeSelectionBeaconReveal { reveal } => {
-                self.committee_selection_beacon_reveal(txn.payload.sender, reveal)
+                self.committee_selection_beacon_reveal(sender, reveal)
             },
 
             UpdateMethod::CommitteeSelectionBeaconCommitPhaseTimeout => {
-                self.committee_selection_beacon_commit_phase_timeout(txn.payload.sender)
+                self.committee_selection_beacon_commit_phase_timeout(sender)
             },
 
             UpdateMethod::CommitteeSelectionBeaconRevealPhaseTimeout => {
-                self.committee_selection_beacon_reveal_phase_timeout(txn.payload.sender)
+                self.committee_selection_beacon_reveal_phase_timeout(sender)
             },
 
             UpdateMethod::AddService {
                 service,
                 service_id,
-            } => self.add_service(txn.payload.sender, service, service_id),
+            } => self.add_service(sender, service, service_id),
+
+            UpdateMethod::RemoveService { service_id } => self.remove_service(sender, service_id),
+
+            UpdateMethod::UpgradeService {
+                service_id,
+                binary,
+                version,
+                resources,
+            } => self.upgrade_service(sender, service_id, binary, version, resources),
+
+            UpdateMethod::RegisterToken { address, info } => {
+                self.register_token(sender, address, info)
+            },
 
-            UpdateMethod::RemoveService { service_id } => {
-                self.remove_service(txn.payload.sender, service_id)
+            UpdateMethod::SetTokenBridged { token, bridged } => {
+                self.set_token_bridged(sender, token, bridged)
             },
 
             UpdateMethod::Slash {
                 service_id,
                 node,
                 proof_of_misbehavior,
-            } => self.slash(txn.payload.sender, proof_of_misbehavior, service_id, node),
+            } => self.slash(sender, proof_of_misbehavior, service_id, node),
 
             UpdateMethod::SubmitReputationMeasurements { measurements } => {
-                self.submit_reputation_measurements(txn.payload.sender, measurements)
+                self.submit_reputation_measurements(sender, measurements)
             },
             UpdateMethod::ChangeProtocolParam { param, value } => {
-                self.change_protocol_param(txn.payload.sender, param, value)
+                self.change_protocol_param(sender, param, value)
             },
-            UpdateMethod::OptIn {} => self.opt_in(txn.payload.sender),
-            UpdateMethod::OptOut {} => self.opt_out(txn.payload.sender),
+            UpdateMethod::OptIn {} => self.opt_in(sender),
+            UpdateMethod::OptOut {} => self.opt_out(sender),
             UpdateMethod::UpdateContentRegistry { updates } => {
-                self.update_content_registry(txn.payload.sender, updates)
+                self.update_content_registry(sender, updates)
             },
             UpdateMethod::IncrementNonce {} => TransactionResponse::Success(ExecutionData::None),
-        };
+            UpdateMethod::Batch(methods) => self.execute_batch(sender, methods),
+        }
+    }
 
-        #[cfg(debug_assertions)]
+    /// Executes the methods of a batch transaction in order. If one of them reverts, the changes
+    /// made by the methods before it are discarded and the batch reverts with the index and the
+    /// error of the reverted method.
+    fn execute_batch(
+        &self,
+        sender: TransactionSender,
+        methods: Vec<UpdateMethod>,
+    ) -> TransactionResponse {
+        if methods.is_empty() {
+            return TransactionResponse::Revert(ExecutionError::EmptyBatch);
+        }
+        if methods.len() > MAX_BATCH_SIZE {
+            return TransactionResponse::Revert(ExecutionError::TooManyBatchMethods);
+        }
+        // Batches can not be nested, and the methods driving the epoch change must be sent on
+        // their own so that the epoch change is reported in the block response.
+        if methods
+            .iter()
+            .any(|method| matches!(method, UpdateMethod::Batch(_)) || method.is_system())
         {
-            let node_info_len = self.node_info.keys().count();
-            let consensus_key_to_index_len = self.consensus_key_to_index.keys().count();
-            let pub_key_to_index_len = self.pub_key_to_index.keys().count();
-            assert_eq!(node_info_len, consensus_key_to_index_len);
-            assert_eq!(node_info_len, pub_key_to_index_len);
-            assert_eq!(pub_key_to_index_len, consensus_key_to_index_len);
+            return TransactionResponse::Revert(ExecutionError::InvalidBatchMethod);
+        }
+
+        let checkpoint = self.backend.checkpoint();
+        let mut results = Vec::with_capacity(methods.len());
+        for (index, method) in methods.into_iter().enumerate() {
+            let event = method.event(sender);
+            match self.execute_method(sender, method) {
+                TransactionResponse::Success(data) => {
+                    results.push(data);
+                    if let Some(event) = event {
+                        self.emit(event);
+                    }
+                },
+                TransactionResponse::Revert(error) => {
+                    self.backend.revert(checkpoint);
+                    return TransactionResponse::Revert(ExecutionError::BatchMethodReverted {
+                        index: index as u32,
+                        error: Box::new(error),
+                    });
+                },
+            }
         }
 
-        response
+        self.backend.commit(checkpoint);
+        TransactionResponse::Success(ExecutionData::Batch(results))
     }
 
     /// Executes an ethereum transaction.
@@ -498,52 +621,47 @@ fn withdraw(
         let Some(mut account) = self.account_info.get(&sender) else {
             return TransactionResponse::Revert(ExecutionError::AccountDoesNotExist);
         };
+        let info = match self.get_token(&token, true) {
+            Ok(info) => info,
+            Err(e) => return e,
+        };
 
         let withdraw_id = match self.metadata.get(&Metadata::WithdrawId) {
             Some(Value::WithdrawId(epoch)) => epoch,
             _ => 0,
         };
 
-        match token {
-            Tokens::FLK => {
-                if amount > account.flk_balance {
-                    return TransactionResponse::Revert(ExecutionError::InsufficientBalance);
-                }
-                account.flk_balance -= amount.clone();
-                self.withdraws.set(
-                    withdraw_id,
-                    WithdrawInfo {
-                        epoch: 0,
-                        token: Tokens::FLK,
-                        receiver,
-                        amount,
-                    },
-                );
-                self.metadata
-                    .set(Metadata::WithdrawId, Value::WithdrawId(withdraw_id + 1));
-            },
-            Tokens::USDC => {
-                // TODO(matthias): make sure that this conversion is safe
-                let amount = amount.convert_precision::<6>();
-                if amount > account.stables_balance {
-                    return TransactionResponse::Revert(ExecutionError::InsufficientBalance);
-                }
-                account.stables_balance -= amount.clone();
-                self.withdraws.set(
-                    withdraw_id,
-                    WithdrawInfo {
-                        epoch: 0,
-                        token: Tokens::USDC,
-                        receiver,
-                        amount: amount.convert_precision::<18>(),
-                    },
-                );
-                self.metadata
-                    .set(Metadata::WithdrawId, Value::WithdrawId(withdraw_id + 1));
-            },
+        // The amount can't be more precise than the token on the L2.
+        if amount.truncate(info.decimals as usize) != amount {
+            return TransactionResponse::Revert(ExecutionError::InvalidAmount);
+        }
+        if token.is_flk() {
+            if amount > account.flk_balance {
+                return TransactionResponse::Revert(ExecutionError::InsufficientBalance);
+            }
+            account.flk_balance -= amount.clone();
+            self.account_info.set(sender, account);
+        } else {
+            let key = (sender, token.address());
+            let balance = self.token_balances.get(&key).unwrap_or_default();
+            if amount > balance {
+                return TransactionResponse::Revert(ExecutionError::InsufficientBalance);
+            }
+            self.token_balances.set(key, balance - amount.clone());
         }
 
-        self.account_info.set(sender, account);
+        self.withdraws.set(
+            withdraw_id,
+            WithdrawInfo {
+                epoch: 0,
+                token,
+                receiver,
+                amount,
+            },
+        );
+        self.metadata
+            .set(Metadata::WithdrawId, Value::WithdrawId(withdraw_id + 1));
+
         TransactionResponse::Success(ExecutionData::None)
     }
 
@@ -570,15 +688,14 @@ fn mint(
             return TransactionResponse::Revert(ExecutionError::OnlyGovernance);
         }
 
-        let mut account = self.account_info.get(&reciever).unwrap_or_default();
+        if let Err(e) = self.get_token(&token, true) {
+            return e;
+        }
 
         // Check the token bridged and increment that amount
-        match token {
-            Tokens::FLK => account.flk_balance += amount,
-            Tokens::USDC => account.bandwidth_balance += TryInto::<u128>::try_into(amount).unwrap(),
+        if let Err(e) = self.credit_deposit(reciever, &token, amount) {
+            return e;
         }
-
-        self.account_info.set(reciever, account);
         TransactionResponse::Success(ExecutionData::None)
     }
 
@@ -601,15 +718,14 @@ fn deposit(
             return TransactionResponse::Revert(ExecutionError::InvalidProof);
         }
 
-        let mut account = self.account_info.get(&sender).unwrap_or_default();
+        if let Err(e) = self.get_token(&token, true) {
+            return e;
+        }
 
         // Check the token bridged and increment that amount
-        match token {
-            Tokens::FLK => account.flk_balance += amount,
-            Tokens::USDC => account.bandwidth_balance += TryInto::<u128>::try_into(amount).unwrap(),
+        if let Err(e) = self.credit_deposit(sender, &token, amount) {
+            return e;
         }
-
-        self.account_info.set(sender, account);
         TransactionResponse::Success(ExecutionData::None)
     }
 
@@ -617,18 +733,34 @@ fn transfer(
         &self,
         sender: TransactionSender,
         amount: HpUfixed<18>,
-        _token: Tokens,
+        token: Tokens,
         to: EthAddress,
     ) -> TransactionResponse {
-        // Todo(Dalton): Figure out if we should support transferring other tokens like usdc within
-        // our network for now im going to treat this function as just transferring FLK
-
         // This transaction is only callable by AccountOwners and not nodes
         // So revert if the sender is a node public key
         let sender = match self.only_account_owner(sender) {
             Ok(account) => account,
             Err(e) => return e,
         };
+
+        if !token.is_flk() {
+            if let Err(e) = self.get_token(&token, false) {
+                return e;
+            }
+            if sender == to {
+                return TransactionResponse::Revert(ExecutionError::CantSendToYourself);
+            }
+            let sender_key = (sender, token.address());
+            let sender_balance = self.token_balances.get(&sender_key).unwrap_or_default();
+            if sender_balance < amount {
+                return TransactionResponse::Revert(ExecutionError::InsufficientBalance);
+            }
+            self.token_balances
+                .set(sender_key, sender_balance - amount.clone());
+            self.credit_token(to, &token, amount);
+            return TransactionResponse::Success(ExecutionData::None);
+        }
+
         let mut sender_account = self.account_info.get(&sender).unwrap_or_default();
         let mut to_account = self.account_info.get(&to).unwrap_or_default();
 
@@ -688,7 +820,9 @@ fn stake(
         if let Some(consensus_key) = node_consensus_key {
             // If the consensus key is indexed make sure it is the same as the indexed node public
             // key, if its none indexed and the node key is, that is fine
-            if self.consensus_key_to_index.get(&consensus_key) != node_index {
+            if self.consensus_key_to_index.get(&consensus_key) != node_index
+                || self.is_consensus_key_reserved(&consensus_key, node_index)
+            {
                 return TransactionResponse::Revert(ExecutionError::ConsensusKeyAlreadyIndexed);
             }
         }
@@ -862,12 +996,12 @@ fn unstake(
             _ => 0,
         };
 
-        // decrease the stake, add to the locked amount, and set the locked time for the withdrawal
-        // current epoch + lock time todo(dalton): we should be storing unstaked tokens in a
-        // list so we can have multiple locked stakes with dif lock times
+        // decrease the stake and queue the amount for withdrawal at current epoch + lock time,
+        // without affecting the lock of the previous unstakes
         node.stake.staked -= amount.clone();
-        node.stake.locked += amount.clone();
-        node.stake.locked_until = current_epoch + lock_time;
+        node.stake
+            .unbonding
+            .push(amount.clone(), current_epoch + lock_time);
 
         // Save the changed node state.
         self.node_info.set(node_index, node.clone());
@@ -911,11 +1045,12 @@ fn withdraw_unstaked(
             Some(Value::Epoch(epoch)) => epoch,
             _ => 0,
         };
-        // Make sure the node has locked tokens and that the lock time is passed
-        if node.stake.locked == HpUfixed::zero() {
+        // Make sure the node has locked tokens and that the lock time of some of them is passed
+        if node.stake.unbonding.is_empty() {
             return TransactionResponse::Revert(ExecutionError::NoLockedTokens);
         }
-        if node.stake.locked_until > current_epoch {
+        let withdrawn = node.stake.unbonding.withdraw_matured(current_epoch);
+        if withdrawn == HpUfixed::zero() {
             return TransactionResponse::Revert(ExecutionError::TokensLocked);
         }
 
@@ -923,11 +1058,9 @@ fn withdraw_unstaked(
         let recipient = recipient.unwrap_or(sender_public_key);
         let mut receiver = self.account_info.get(&recipient).unwrap_or_default();
 
-        // add the withdrawn tokens to the recipient and reset the nodes locked stake state
-        // no need to reset locked_until on the node because that will get adjusted the next time
-        // the node unstakes
-        receiver.flk_balance += node.stake.locked;
-        node.stake.locked = HpUfixed::zero();
+        // add the withdrawn tokens to the recipient, the entries that are still locked stay in the
+        // node's unbonding queue
+        receiver.flk_balance += withdrawn;
 
         // Todo(dalton): if the nodes stake+locked are equal to 0 here should we remove him from the
         // state tables completely?
@@ -938,6 +1071,273 @@ fn withdraw_unstaked(
         TransactionResponse::Success(ExecutionData::None)
     }
 
+    fn delegate(
+        &self,
+        sender: TransactionSender,
+        amount: HpUfixed<18>,
+        node_public_key: NodePublicKey,
+    ) -> TransactionResponse {
+        // This transaction is only callable by AccountOwners and not nodes
+        // So revert if the sender is a node public key
+        let sender = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+
+        let (node_index, _) = match self.get_node_info(node_public_key.into()) {
+            Some(node) => node,
+            None => return TransactionResponse::Revert(ExecutionError::NodeDoesNotExist),
+        };
+
+        if amount == HpUfixed::zero() {
+            return TransactionResponse::Revert(ExecutionError::InvalidAmount);
+        }
+
+        let mut delegator = self.account_info.get(&sender).unwrap_or_default();
+
+        // Make sure the sender has at least the amount of FLK they are trying to delegate
+        if delegator.flk_balance < amount {
+            return TransactionResponse::Revert(ExecutionError::InsufficientBalance);
+        }
+
+        let mut delegation = self
+            .delegations
+            .get(&(sender, node_index))
+            .unwrap_or_default();
+        let mut pool = self.delegation_pools.get(&node_index).unwrap_or_default();
+
+        // Move the tokens from the balance of the delegator to the delegation
+        delegator.flk_balance -= amount.clone();
+        delegation.staked += amount.clone();
+        pool.delegated += amount;
+        if pool.delegators.insert(sender) {
+            let mut nodes = self.delegator_nodes.get(&sender).unwrap_or_default();
+            nodes.insert(node_index);
+            self.delegator_nodes.set(sender, nodes);
+        }
+
+        // Save state changes and return response
+        self.account_info.set(sender, delegator);
+        self.delegations.set((sender, node_index), delegation);
+        self.delegation_pools.set(node_index, pool);
+        TransactionResponse::Success(ExecutionData::None)
+    }
+
+    fn undelegate(
+        &self,
+        sender: TransactionSender,
+        amount: HpUfixed<18>,
+        node_public_key: NodePublicKey,
+    ) -> TransactionResponse {
+        // This transaction is only callable by AccountOwners and not nodes
+        // So revert if the sender is a node public key
+        let sender = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+
+        let (node_index, mut node) = match self.get_node_info(node_public_key.into()) {
+            Some(node) => node,
+            None => return TransactionResponse::Revert(ExecutionError::NodeDoesNotExist),
+        };
+
+        let mut delegation = match self.delegations.get(&(sender, node_index)) {
+            Some(delegation) => delegation,
+            None => return TransactionResponse::Revert(ExecutionError::NoDelegation),
+        };
+
+        // Make sure the delegator has at least that much delegated
+        if delegation.staked < amount {
+            return TransactionResponse::Revert(ExecutionError::InsufficientBalance);
+        }
+
+        let current_epoch = match self.metadata.get(&Metadata::Epoch) {
+            Some(Value::Epoch(epoch)) => epoch,
+            _ => 0,
+        };
+
+        let lock_time = match self.parameters.get(&ProtocolParamKey::LockTime) {
+            Some(ProtocolParamValue::LockTime(v)) => v,
+            _ => 0,
+        };
+
+        // decrease the delegation and queue the amount for withdrawal at current epoch + lock time
+        let mut pool = self.delegation_pools.get(&node_index).unwrap_or_default();
+        delegation.staked -= amount.clone();
+        delegation
+            .unbonding
+            .push(amount.clone(), current_epoch + lock_time);
+        pool.delegated -= amount.clone();
+        pool.locked += amount;
+
+        // Save the changed delegation state.
+        self.delegations.set((sender, node_index), delegation);
+        self.delegation_pools.set(node_index, pool);
+
+        // The delegated tokens count towards the stake of the node, so the node can be left
+        // without sufficient unlocked stake in the same way as when its owner unstakes.
+        if !self.has_sufficient_unlocked_stake(&node_index) && self.is_participating(&node_index) {
+            node.participation = Participation::OptedOut;
+            self.node_info.set(node_index, node);
+        }
+
+        TransactionResponse::Success(ExecutionData::None)
+    }
+
+    fn withdraw_undelegated(
+        &self,
+        sender: TransactionSender,
+        node_public_key: NodePublicKey,
+        recipient: Option<EthAddress>,
+    ) -> TransactionResponse {
+        // This transaction is only callable by AccountOwners and not nodes
+        // So revert if the sender is a node public key
+        let sender = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+
+        let (node_index, _) = match self.get_node_info(node_public_key.into()) {
+            Some(node) => node,
+            None => return TransactionResponse::Revert(ExecutionError::NodeDoesNotExist),
+        };
+
+        let mut delegation = match self.delegations.get(&(sender, node_index)) {
+            Some(delegation) => delegation,
+            None => return TransactionResponse::Revert(ExecutionError::NoDelegation),
+        };
+
+        let current_epoch = match self.metadata.get(&Metadata::Epoch) {
+            Some(Value::Epoch(epoch)) => epoch,
+            _ => 0,
+        };
+        // Make sure the delegation has locked tokens and that the lock time of some of them is
+        // passed
+        if delegation.unbonding.is_empty() {
+            return TransactionResponse::Revert(ExecutionError::NoLockedTokens);
+        }
+        let withdrawn = delegation.unbonding.withdraw_matured(current_epoch);
+        if withdrawn == HpUfixed::zero() {
+            return TransactionResponse::Revert(ExecutionError::TokensLocked);
+        }
+
+        let mut pool = self.delegation_pools.get(&node_index).unwrap_or_default();
+        pool.locked -= withdrawn.clone();
+
+        // if there is no recipient the delegator will receive the withdrawal
+        let recipient = recipient.unwrap_or(sender);
+        let mut receiver = self.account_info.get(&recipient).unwrap_or_default();
+        receiver.flk_balance += withdrawn;
+        self.account_info.set(recipient, receiver);
+
+        // Remove the delegation once nothing is left in it
+        if delegation.staked == HpUfixed::zero() && delegation.unbonding.is_empty() {
+            self.delegations.remove(&(sender, node_index));
+            pool.delegators.remove(&sender);
+            let mut nodes = self.delegator_nodes.get(&sender).unwrap_or_default();
+            nodes.remove(&node_index);
+            if nodes.is_empty() {
+                self.delegator_nodes.remove(&sender);
+            } else {
+                self.delegator_nodes.set(sender, nodes);
+            }
+        } else {
+            self.delegations.set((sender, node_index), delegation);
+        }
+        self.delegation_pools.set(node_index, pool);
+
+        TransactionResponse::Success(ExecutionData::None)
+    }
+
+    fn set_commission_rate(
+        &self,
+        sender: TransactionSender,
+        node_public_key: NodePublicKey,
+        commission: u8,
+    ) -> TransactionResponse {
+        // This transaction is only callable by AccountOwners and not nodes
+        // So revert if the sender is a node public key
+        let sender = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+
+        let (node_index, node) = match self.get_node_info(node_public_key.into()) {
+            Some(node) => node,
+            None => return TransactionResponse::Revert(ExecutionError::NodeDoesNotExist),
+        };
+
+        // Make sure the caller is the owner of the node
+        if sender != node.owner {
+            return TransactionResponse::Revert(ExecutionError::NotNodeOwner);
+        }
+        if commission > 100 {
+            return TransactionResponse::Revert(ExecutionError::InvalidCommission);
+        }
+
+        // The rewards of the current epoch are still shared with the current commission, the new
+        // one is applied at the end of the epoch.
+        self.pending_commissions.set(node_index, commission);
+        TransactionResponse::Success(ExecutionData::None)
+    }
+
+    fn update_node_info(
+        &self,
+        sender: TransactionSender,
+        node_public_key: NodePublicKey,
+        update: NodeInfoUpdate,
+    ) -> TransactionResponse {
+        let (node_index, node) = match self.get_node_info(node_public_key.into()) {
+            Some(node) => node,
+            None => return TransactionResponse::Revert(ExecutionError::NodeDoesNotExist),
+        };
+
+        // Make sure the caller is either the owner of the node or the node itself
+        let authorized = match sender {
+            TransactionSender::AccountOwner(account) => account == node.owner,
+            TransactionSender::NodeMain(public_key) => public_key == node.public_key,
+            TransactionSender::NodeConsensus(_) => false,
+        };
+        if !authorized {
+            return TransactionResponse::Revert(ExecutionError::NotNodeOwner);
+        }
+
+        if update.is_empty() {
+            return TransactionResponse::Revert(ExecutionError::InsufficientNodeDetails);
+        }
+
+        // Make sure the new consensus key isn't used or reserved by another node
+        if let Some(consensus_key) = &update.consensus_key {
+            if self
+                .consensus_key_to_index
+                .get(consensus_key)
+                .is_some_and(|index| index != node_index)
+                || self.is_consensus_key_reserved(consensus_key, Some(node_index))
+            {
+                return TransactionResponse::Revert(ExecutionError::ConsensusKeyAlreadyIndexed);
+            }
+        }
+
+        // The committee members keep their current details until the end of the epoch, so that
+        // the committee doesn't change in the middle of it. Later updates in the same epoch are
+        // merged into the pending one.
+        let is_committee_member = self
+            .get_current_committee()
+            .is_some_and(|committee| committee.members.contains(&node_index));
+        if is_committee_member {
+            let mut pending = self
+                .pending_node_info_updates
+                .get(&node_index)
+                .unwrap_or_default();
+            pending.merge(update);
+            self.pending_node_info_updates.set(node_index, pending);
+        } else {
+            self.apply_node_info_update(node_index, update);
+        }
+
+        TransactionResponse::Success(ExecutionData::None)
+    }
+
     // This method can panic if the governance address wasn't previously stored in the application
     // state. The governance address should be seeded though the genesis.
     fn change_protocol_param(
@@ -1023,11 +1423,121 @@ fn get_node_registry(&self) -> BTreeMap<NodeIndex, NodeInfo> {
 
     fn add_service(
         &self,
-        _sender: TransactionSender,
-        _service: Service,
-        _service_id: ServiceId,
+        sender: TransactionSender,
+        service: Service,
+        service_id: ServiceId,
     ) -> TransactionResponse {
-        TransactionResponse::Revert(ExecutionError::Unimplemented)
+        let sender = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+        let governance_address = match self.metadata.get(&Metadata::GovernanceAddress) {
+            Some(Value::AccountPublicKey(address)) => address,
+            _ => unreachable!("Governance address is missing from state."),
+        };
+        if sender != governance_address {
+            return TransactionResponse::Revert(ExecutionError::OnlyGovernance);
+        }
+
+        if self.services.get(&service_id).is_some() {
+            return TransactionResponse::Revert(ExecutionError::ServiceAlreadyExists);
+        }
+
+        self.services.set(service_id, service);
+        TransactionResponse::Success(ExecutionData::None)
+    }
+
+    fn register_token(
+        &self,
+        sender: TransactionSender,
+        address: EthAddress,
+        info: TokenInfo,
+    ) -> TransactionResponse {
+        let sender = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+        let governance_address = match self.metadata.get(&Metadata::GovernanceAddress) {
+            Some(Value::AccountPublicKey(address)) => address,
+            _ => unreachable!("Governance address is missing from state."),
+        };
+        if sender != governance_address {
+            return TransactionResponse::Revert(ExecutionError::OnlyGovernance);
+        }
+
+        // Balances are kept with 18 decimals.
+        if info.decimals > 18 {
+            return TransactionResponse::Revert(ExecutionError::InvalidToken);
+        }
+        if self.tokens.get(&address).is_some() {
+            return TransactionResponse::Revert(ExecutionError::TokenAlreadyExists);
+        }
+
+        self.tokens.set(address, info);
+        TransactionResponse::Success(ExecutionData::None)
+    }
+
+    fn set_token_bridged(
+        &self,
+        sender: TransactionSender,
+        token: Tokens,
+        bridged: bool,
+    ) -> TransactionResponse {
+        let sender = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+        let governance_address = match self.metadata.get(&Metadata::GovernanceAddress) {
+            Some(Value::AccountPublicKey(address)) => address,
+            _ => unreachable!("Governance address is missing from state."),
+        };
+        if sender != governance_address {
+            return TransactionResponse::Revert(ExecutionError::OnlyGovernance);
+        }
+
+        let Some(mut info) = self.tokens.get(&token.address()) else {
+            return TransactionResponse::Revert(ExecutionError::InvalidToken);
+        };
+        info.bridged = bridged;
+        self.tokens.set(token.address(), info);
+        TransactionResponse::Success(ExecutionData::None)
+    }
+
+    fn upgrade_service(
+        &self,
+        sender: TransactionSender,
+        service_id: ServiceId,
+        binary: Blake3Hash,
+        version: u32,
+        resources: ServiceResources,
+    ) -> TransactionResponse {
+        let sender = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+
+        let Some(mut service) = self.services.get(&service_id) else {
+            return TransactionResponse::Revert(ExecutionError::NonExistingService);
+        };
+
+        // The service can be upgraded by its owner or the governance.
+        let governance_address = match self.metadata.get(&Metadata::GovernanceAddress) {
+            Some(Value::AccountPublicKey(address)) => address,
+            _ => unreachable!("Governance address is missing from state."),
+        };
+        if sender != service.owner && sender != governance_address {
+            return TransactionResponse::Revert(ExecutionError::OnlyAccountOwner);
+        }
+
+        if version <= service.version {
+            return TransactionResponse::Revert(ExecutionError::InvalidServiceVersion);
+        }
+
+        service.binary = Some(binary);
+        service.version = version;
+        service.resources = resources;
+        self.services.set(service_id, service);
+        TransactionResponse::Success(ExecutionData::None)
     }
 
     fn remove_service(
@@ -1274,10 +1784,105 @@ fn compare_nodes_in_auction(
     }
 
     fn mint_and_transfer_stables(&self, amount: HpUfixed<6>, owner: EthAddress) {
-        let mut account = self.account_info.get(&owner).unwrap_or_default();
+        self.credit_token(owner, &Tokens::USDC, amount.convert_precision());
+    }
 
-        account.stables_balance += amount;
-        self.account_info.set(owner, account);
+    /// Returns the info of a registered token. Reverts if the token is not registered, or if it
+    /// has to be bridged but its bridge is disabled.
+    fn get_token(&self, token: &Tokens, bridge: bool) -> Result<TokenInfo, TransactionResponse> {
+        let Some(info) = self.tokens.get(&token.address()) else {
+            return Err(TransactionResponse::Revert(ExecutionError::InvalidToken));
+        };
+        if bridge && !info.bridged {
+            return Err(TransactionResponse::Revert(ExecutionError::TokenNotBridged));
+        }
+        Ok(info)
+    }
+
+    /// Credits a deposit bridged from the L2. Deposits of USDC are credited to the bandwidth
+    /// balance of the account, which clients pay for their usage with.
+    fn credit_deposit(
+        &self,
+        account: EthAddress,
+        token: &Tokens,
+        amount: HpUfixed<18>,
+    ) -> Result<(), TransactionResponse> {
+        if *token != Tokens::USDC {
+            self.credit_token(account, token, amount);
+            return Ok(());
+        }
+        let mut info = self.account_info.get(&account).unwrap_or_default();
+        info.bandwidth_balance = TryInto::<u128>::try_into(amount)
+            .ok()
+            .and_then(|amount| info.bandwidth_balance.checked_add(amount))
+            .ok_or(TransactionResponse::Revert(ExecutionError::InvalidAmount))?;
+        self.account_info.set(account, info);
+        Ok(())
+    }
+
+    /// Adds the amount to the balance of the account in the given token. FLK is kept on the
+    /// account itself, the other tokens in the token balances table.
+    fn credit_token(&self, account: EthAddress, token: &Tokens, amount: HpUfixed<18>) {
+        if token.is_flk() {
+            let mut info = self.account_info.get(&account).unwrap_or_default();
+            info.flk_balance += amount;
+            self.account_info.set(account, info);
+        } else {
+            let key = (account, token.address());
+            let balance = self.token_balances.get(&key).unwrap_or_default();
+            self.token_balances.set(key, balance + amount);
+        }
+    }
+
+    /// Charges the fee of a transaction to its sender. Only account owners pay fees. The share of
+    /// the fee set by the protocol goes to the protocol fund and the rest of it is burned.
+    fn charge_fee(
+        &self,
+        sender: TransactionSender,
+        fee: Option<HpUfixed<18>>,
+    ) -> Result<(), TransactionResponse> {
+        let (TransactionSender::AccountOwner(account), Some(fee)) = (sender, fee) else {
+            return Ok(());
+        };
+        if fee == HpUfixed::zero() {
+            return Ok(());
+        }
+
+        let mut account_info = self.account_info.get(&account).unwrap_or_default();
+        if account_info.flk_balance < fee {
+            return Err(TransactionResponse::Revert(
+                ExecutionError::InsufficientBalanceForFee,
+            ));
+        }
+        account_info.flk_balance -= fee.clone();
+        self.account_info.set(account, account_info);
+
+        let protocol_share = match self.parameters.get(&ProtocolParamKey::FeeProtocolShare) {
+            Some(ProtocolParamValue::FeeProtocolShare(share)) => {
+                HpUfixed::<18>::from(share.min(100)) / &(*BIG_HUNDRED)
+            },
+            _ => HpUfixed::zero(),
+        };
+        let protocol_fee = fee.clone() * &protocol_share;
+        if protocol_fee > HpUfixed::zero() {
+            let protocol_fund = match self.metadata.get(&Metadata::ProtocolFundAddress) {
+                Some(Value::AccountPublicKey(owner)) => owner,
+                _ => panic!("ProtocolFundAddress is added at Genesis and should exist"),
+            };
+            let mut protocol_fund_info = self.account_info.get(&protocol_fund).unwrap_or_default();
+            protocol_fund_info.flk_balance += protocol_fee.clone();
+            self.account_info.set(protocol_fund, protocol_fund_info);
+        }
+
+        let total_supply = match self.metadata.get(&Metadata::TotalSupply) {
+            Some(Value::HpUfixed(supply)) => supply,
+            _ => panic!("TotalSupply is set genesis and should never be empty"),
+        };
+        self.metadata.set(
+            Metadata::TotalSupply,
+            Value::HpUfixed(total_supply - (fee - protocol_fee)),
+        );
+        Ok(())
     }
 
     fn mint_and_transfer_flk(&self, amount: HpUfixed<18>, owner: EthAddress) {
@@ -1504,6 +2109,63 @@ fn create_node(&self, node: NodeInfo) -> bool {
         true
     }
 
+    /// Applies an update to the network details of a node and keeps the consensus key index in
+    /// sync. The consensus key is checked against the other nodes when the update is submitted,
+    /// but a deferred update is checked again and rejected if another node has taken the key.
+    fn apply_node_info_update(&self, node_index: NodeIndex, update: NodeInfoUpdate) {
+        let Some(mut node) = self.node_info.get(&node_index) else {
+            return;
+        };
+
+        if update.consensus_key.is_some_and(|key| {
+            self.consensus_key_to_index
+                .get(&key)
+                .is_some_and(|index| index != node_index)
+        }) {
+            self.emit(Event::node_info_update_rejected(node.public_key, update));
+            return;
+        }
+        self.emit(Event::node_info_updated(node.public_key, update.clone()));
+
+        if let Some(consensus_key) = update.consensus_key {
+            if self.consensus_key_to_index.get(&consensus_key).is_none() {
+                self.consensus_key_to_index.remove(&node.consensus_key);
+                self.consensus_key_to_index.set(consensus_key, node_index);
+                node.consensus_key = consensus_key;
+            }
+        }
+        if let Some(domain) = update.domain {
+            node.domain = domain;
+        }
+        if let Some(worker_public_key) = update.worker_public_key {
+            node.worker_public_key = worker_public_key;
+        }
+        if let Some(worker_domain) = update.worker_domain {
+            node.worker_domain = worker_domain;
+        }
+        if let Some(ports) = update.ports {
+            node.ports = ports;
+        }
+
+        self.node_info.set(node_index, node);
+    }
+
+    /// Returns true if the consensus key is claimed by a pending update of another node than the
+    /// given one, which is applied at the end of the epoch.
+    fn is_consensus_key_reserved(
+        &self,
+        consensus_key: &ConsensusPublicKey,
+        node_index: Option<NodeIndex>,
+    ) -> bool {
+        self.pending_node_info_updates.keys().any(|index| {
+            Some(index) != node_index
+                && self
+                    .pending_node_info_updates
+                    .get(&index)
+                    .is_some_and(|update| update.consensus_key.as_ref() == Some(consensus_key))
+        })
+    }
+
     /// Remove a node. A node should only be removed through this function.
     #[allow(unused)]
     fn remove_node(&self, node: NodePublicKey) -> bool {
@@ -1638,28 +2300,43 @@ fn only_node_with_sufficient_unlocked_stake(
         Ok(node_index)
     }
 
-    /// Whether a node has sufficient stake, including both unlocked and locked stake.
+    /// Whether a node has sufficient stake, including both unlocked and locked stake, and the
+    /// stake delegated to it. Like the locked stake of the owner, the undelegated stake that is
+    /// still locked counts since it is slashed along with the node.
     ///
     /// Returns `false` if the node does not exist.
     fn has_sufficient_stake(&self, node_index: &NodeIndex) -> bool {
         self.node_info
             .get(node_index)
             .map(|node_info| {
-                node_info.stake.staked + node_info.stake.locked >= self.get_min_stake()
+                let pool = self.delegation_pools.get(node_index).unwrap_or_default();
+                node_info.stake.locked() + node_info.stake.staked + pool.delegated + pool.locked
+                    >= self.get_min_stake()
             })
             .unwrap_or(false)
     }
 
-    /// Whether the node has sufficient unlocked stake.
+    /// Whether the node has sufficient unlocked stake, including the stake delegated to it.
     ///
     /// Returns `false` if the node does not exist.
     fn has_sufficient_unlocked_stake(&self, node_index: &NodeIndex) -> bool {
         self.node_info
             .get(node_index)
-            .map(|node_info| node_info.stake.staked >= self.get_min_stake())
+            .map(|node_info| {
+                node_info.stake.staked + self.get_delegated_stake(node_index)
+                    >= self.get_min_stake()
+            })
             .unwrap_or(false)
     }
 
+    /// Returns the amount of FLK currently delegated to a node.
+    fn get_delegated_stake(&self, node_index: &NodeIndex) -> HpUfixed<18> {
+        self.delegation_pools
+            .get(node_index)
+            .map(|pool| pool.delegated)
+            .unwrap_or_default()
+    }
+
     /// Whether the node is participating.
     fn is_participating(&self, node_index: &NodeIndex) -> bool {
         self.node_info.get(node_index).map_or(false, |info| {
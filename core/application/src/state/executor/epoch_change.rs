@@ -1,7 +1,7 @@
 use std::cmp::Ordering;
 use std::collections::HashMap;
 
-use fleek_crypto::TransactionSender;
+use fleek_crypto::{EthAddress, TransactionSender};
 use fxhash::FxHashMap;
 use hp_fixed::unsigned::HpUfixed;
 use lightning_interfaces::types::{
@@ -682,6 +682,14 @@ fn execute_epoch_change(
             .set(Metadata::WithdrawId, Value::WithdrawId(0));
 
         self.committee_info.set(epoch, current_committee);
+
+        // Apply the node info updates that were submitted by committee members during the epoch,
+        // before the new committee is chosen.
+        self.apply_pending_node_info_updates();
+
+        // The rewards of the epoch are distributed, so the new commission rates can be applied.
+        self.apply_pending_commissions();
+
         // Get new committee
         let new_committee = self.choose_new_committee(beacons);
 
@@ -695,6 +703,30 @@ fn execute_epoch_change(
         self.metadata.set(Metadata::Epoch, Value::Epoch(epoch));
     }
 
+    fn apply_pending_node_info_updates(&self) {
+        let mut node_indices = self.pending_node_info_updates.keys().collect::<Vec<_>>();
+        node_indices.sort();
+        for node_index in node_indices {
+            if let Some(update) = self.pending_node_info_updates.get(&node_index) {
+                self.apply_node_info_update(node_index, update);
+            }
+        }
+        self.pending_node_info_updates.clear();
+    }
+
+    fn apply_pending_commissions(&self) {
+        let mut node_indices = self.pending_commissions.keys().collect::<Vec<_>>();
+        node_indices.sort();
+        for node_index in node_indices {
+            if let Some(commission) = self.pending_commissions.get(&node_index) {
+                let mut pool = self.delegation_pools.get(&node_index).unwrap_or_default();
+                pool.commission = commission;
+                self.delegation_pools.set(node_index, pool);
+            }
+        }
+        self.pending_commissions.clear();
+    }
+
     fn choose_new_committee(
         &self,
         beacons: FxHashMap<
@@ -812,6 +844,8 @@ fn distribute_rewards(&self) {
 
         let mut total_reward_share: HpUfixed<18> = HpUfixed::from(0_u64);
         let mut local_shares_map: HashMap<NodeIndex, HpUfixed<18>> = HashMap::new();
+        let mut reward_shares_map: HashMap<NodeIndex, Vec<(EthAddress, HpUfixed<18>)>> =
+            HashMap::new();
         let mut node_info_map: HashMap<NodeIndex, NodeInfo> = HashMap::new();
 
         for node in self.current_epoch_served.keys() {
@@ -828,10 +862,15 @@ fn distribute_rewards(&self) {
 
             let node_service_proportion =
                 &stables_revenue.convert_precision::<18>() / &reward_pool.convert_precision::<18>();
-            self.mint_and_transfer_stables(
-                stables_revenue * &node_share.convert_precision(),
-                node_info.owner,
-            );
+            let stables_rewards = stables_revenue * &node_share.convert_precision();
+            let reward_shares = self.get_reward_shares(&node, &node_info);
+            for (account, share) in &reward_shares {
+                self.mint_and_transfer_stables(
+                    &stables_rewards * &share.convert_precision(),
+                    *account,
+                );
+            }
+            reward_shares_map.insert(node, reward_shares);
 
             let locked_until = node_info.stake.stake_locked_until;
             let local_boost: HpUfixed<3> = self.get_boost(locked_until, &epoch);
@@ -842,12 +881,14 @@ fn distribute_rewards(&self) {
 
         let base_reward = &emissions_for_node / &total_reward_share;
 
-        for (node, node_info) in node_info_map.iter() {
+        for node in node_info_map.keys() {
             let local_share = local_shares_map.get(node).unwrap();
             let flk_rewards = &base_reward * local_share;
 
             // todo: add service builders and protocols share in stables too
-            self.mint_and_transfer_flk(flk_rewards, node_info.owner);
+            for (account, share) in reward_shares_map.get(node).unwrap() {
+                self.mint_and_transfer_flk(&flk_rewards * share, *account);
+            }
             self.current_epoch_served.remove(node);
         }
 
@@ -894,6 +935,41 @@ fn distribute_rewards(&self) {
         self.mint_and_transfer_flk(&emissions * &protocol_share, protocol_owner);
     }
 
+    /// Returns the shares of the rewards of a node that go to its owner and to each of its
+    /// delegators. The rewards are split pro rata to the stake of the owner and the delegations,
+    /// and the owner takes its commission from the shares of the delegators.
+    fn get_reward_shares(
+        &self,
+        node: &NodeIndex,
+        node_info: &NodeInfo,
+    ) -> Vec<(EthAddress, HpUfixed<18>)> {
+        let pool = self.delegation_pools.get(node).unwrap_or_default();
+        if pool.delegated == HpUfixed::zero() {
+            return vec![(node_info.owner, HpUfixed::from(1_u64))];
+        }
+
+        let total_stake = &node_info.stake.staked + &pool.delegated;
+        let commission = HpUfixed::<18>::from(pool.commission as u64) / &(*BIG_HUNDRED);
+        let mut owner_share = &node_info.stake.staked / &total_stake;
+        let mut shares = Vec::with_capacity(pool.delegators.len() + 1);
+        for delegator in pool.delegators {
+            let staked = self
+                .delegations
+                .get(&(delegator, *node))
+                .map(|delegation| delegation.staked)
+                .unwrap_or_default();
+            if staked == HpUfixed::zero() {
+                continue;
+            }
+            let share = &staked / &total_stake;
+            let owner_commission = &share * &commission;
+            owner_share += owner_commission.clone();
+            shares.push((delegator, share - owner_commission));
+        }
+        shares.push((node_info.owner, owner_share));
+        shares
+    }
+
     fn calculate_reputation_scores(&self) {
         let mut rep_scores = HashMap::new();
         self.rep_scores.keys().for_each(|node| {
@@ -1083,21 +1159,20 @@ pub fn slash_node_after_non_reveal_and_maybe_kick(
     ) {
         let node_index = *node_index;
 
-        // Remove the given slash amount from the node's staked balance.
+        // Remove the share of the delegators from their delegations, and the rest of the slash
+        // amount from the node's staked balance.
         let mut node_info = self.node_info.get(&node_index).unwrap();
-        let remaining_amount = if node_info.stake.staked >= amount.clone() {
-            node_info.stake.staked -= amount.clone();
+        let owner_amount = self.slash_delegations(&node_index, &node_info, amount);
+        let remaining_amount = if node_info.stake.staked >= owner_amount {
+            node_info.stake.staked -= owner_amount;
             HpUfixed::<18>::zero()
         } else {
-            let remaining = amount.clone() - node_info.stake.staked;
+            let remaining = owner_amount - node_info.stake.staked;
             node_info.stake.staked = HpUfixed::zero();
             remaining
         };
-        if node_info.stake.locked >= remaining_amount {
-            node_info.stake.locked -= remaining_amount;
-        } else {
-            node_info.stake.locked = HpUfixed::zero();
-        }
+        // Take whatever is left from the locked stake, starting with the most recent unstakes.
+        node_info.stake.unbonding.slash(remaining_amount);
         self.node_info.set(node_index, node_info.clone());
         tracing::info!("slashing node {:?} by {:?}", node_index, amount);
 
@@ -1132,6 +1207,59 @@ pub fn slash_node_after_non_reveal_and_maybe_kick(
         }
     }
 
+    /// Slash the delegations of a node pro rata to their stake, including their locked stake,
+    /// relative to the stake of the node owner.
+    ///
+    /// Returns the part of the slash amount that is left for the node owner.
+    fn slash_delegations(
+        &self,
+        node_index: &NodeIndex,
+        node_info: &NodeInfo,
+        amount: &HpUfixed<18>,
+    ) -> HpUfixed<18> {
+        let Some(mut pool) = self.delegation_pools.get(node_index) else {
+            return amount.clone();
+        };
+        let delegations = pool
+            .delegators
+            .iter()
+            .filter_map(|delegator| {
+                self.delegations
+                    .get(&(*delegator, *node_index))
+                    .map(|delegation| (*delegator, delegation))
+            })
+            .collect::<Vec<_>>();
+        let delegated_total = delegations
+            .iter()
+            .fold(HpUfixed::<18>::zero(), |total, (_, delegation)| {
+                total + &delegation.staked + delegation.unbonding.total()
+            });
+        if delegated_total == HpUfixed::zero() {
+            return amount.clone();
+        }
+
+        let total = &node_info.stake.staked + node_info.stake.locked() + &delegated_total;
+        let fraction = HpUfixed::<18>::min(&(amount / &total), &HpUfixed::from(1_u64)).to_owned();
+        let mut slashed = HpUfixed::<18>::zero();
+        for (delegator, mut delegation) in delegations {
+            let staked_slash = &delegation.staked * &fraction;
+            let locked_slash = delegation.unbonding.total() * &fraction;
+            delegation.staked -= staked_slash.clone();
+            let locked_slash = locked_slash.clone() - delegation.unbonding.slash(locked_slash);
+            pool.delegated -= staked_slash.clone();
+            pool.locked -= locked_slash.clone();
+            slashed += staked_slash + locked_slash;
+            self.delegations.set((delegator, *node_index), delegation);
+        }
+        self.delegation_pools.set(*node_index, pool);
+
+        if slashed >= *amount {
+            HpUfixed::zero()
+        } else {
+            amount - &slashed
+        }
+    }
+
     /// Get the slash amount for non-revealing nodes in the committee selection beacon process.
     ///
     /// Returns 0 if the slash amount is not set in the protocol parameters.
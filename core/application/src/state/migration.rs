@@ -0,0 +1,328 @@
+//! Migrations of the application state between versions of the stored types.
+//!
+//! The state is stored with a non self-describing serde backend, so a change to the layout of a
+//! stored type makes the existing values unreadable. Each migration decodes the affected values
+//! using the legacy layout and rewrites them with the current one. The version of the state is
+//! kept in the metadata table under [`Metadata::StateVersion`], a state without it is at version
+//! `0`.
+
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+
+use atomo::{DefaultSerdeBackend, SerdeBackend, StorageBackend, TableSelector};
+use fleek_crypto::{ClientPublicKey, ConsensusPublicKey, EthAddress, NodePublicKey};
+use hp_fixed::unsigned::HpUfixed;
+use lightning_interfaces::types::{
+    AccountInfo,
+    BlockNumber,
+    Committee,
+    CommodityTypes,
+    Epoch,
+    Metadata,
+    NodeIndex,
+    NodeInfo,
+    NodePorts,
+    NodeRegistryChange,
+    NodeRegistryChangeSlashReason,
+    Participation,
+    Service,
+    ServiceId,
+    ServiceResources,
+    Staking,
+    TokenInfo,
+    Tokens,
+    UnbondingQueue,
+    Value,
+};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+/// The version of the state written by this version of the application.
+pub const CURRENT_STATE_VERSION: u64 = 3;
+
+/// Migrates the state to [`CURRENT_STATE_VERSION`].
+///
+/// Returns true if the state was migrated, and false if it was empty or already up to date.
+pub fn migrate<B: StorageBackend>(ctx: &mut TableSelector<B, DefaultSerdeBackend>) -> bool {
+    let mut metadata_table = ctx.get_table::<Metadata, Value>("metadata");
+
+    // Nothing to migrate before the genesis is applied.
+    let Some(Value::Epoch(epoch)) = metadata_table.get(Metadata::Epoch) else {
+        return false;
+    };
+    let version = match metadata_table.get(Metadata::StateVersion) {
+        Some(Value::StateVersion(version)) => version,
+        _ => 0,
+    };
+    if version >= CURRENT_STATE_VERSION {
+        return false;
+    }
+
+    if version < 1 {
+        info!("Migrating application state to version 1 (unbonding queues)");
+        let next_node_index = match metadata_table.get(Metadata::NextNodeIndex) {
+            Some(Value::NextNodeIndex(index)) => index,
+            _ => 0,
+        };
+        migrate_unbonding_queues(ctx, next_node_index, epoch);
+    }
+
+    if version < 2 {
+        info!("Migrating application state to version 2 (service binaries)");
+        migrate_services(ctx);
+    }
+
+    if version < 3 {
+        info!("Migrating application state to version 3 (token balances)");
+        migrate_token_balances(ctx);
+    }
+
+    metadata_table.insert(
+        Metadata::StateVersion,
+        Value::StateVersion(CURRENT_STATE_VERSION),
+    );
+    true
+}
+
+/// Version 1 replaced the single locked amount of [`Staking`] with an [`UnbondingQueue`]. The
+/// locked amount, if any, becomes the only entry of the queue.
+fn migrate_unbonding_queues<B: StorageBackend>(
+    ctx: &TableSelector<B, DefaultSerdeBackend>,
+    next_node_index: NodeIndex,
+    epoch: Epoch,
+) {
+    let mut node_table = ctx.get_table::<NodeIndex, NodeInfo>("node");
+    for index in 0..next_node_index {
+        if let Some(node) = get_legacy::<_, _, v0::NodeInfo>(ctx, "node", &index) {
+            node_table.insert(index, NodeInfo::from(node));
+        }
+    }
+
+    let mut committee_table = ctx.get_table::<Epoch, Committee>("committee");
+    for epoch in 0..=epoch {
+        if let Some(committee) = get_legacy::<_, _, v0::Committee>(ctx, "committee", &epoch) {
+            committee_table.insert(epoch, Committee::from(committee));
+        }
+    }
+}
+
+/// Version 2 added the binary, the version and the required resources to [`Service`]. The existing
+/// services are built into the node, so they don't have a binary.
+fn migrate_services<B: StorageBackend>(ctx: &TableSelector<B, DefaultSerdeBackend>) {
+    let mut service_table = ctx.get_table::<ServiceId, Service>("service");
+    for id in legacy_keys::<_, ServiceId>(ctx, "service") {
+        if let Some(service) = get_legacy::<_, _, v1::Service>(ctx, "service", &id) {
+            service_table.insert(id, Service::from(service));
+        }
+    }
+}
+
+/// Version 3 moved the stables balance out of [`AccountInfo`] into the token balances table, and
+/// registered the built-in tokens in the tokens table.
+fn migrate_token_balances<B: StorageBackend>(ctx: &TableSelector<B, DefaultSerdeBackend>) {
+    let mut tokens_table = ctx.get_table::<EthAddress, TokenInfo>("tokens");
+    for (token, info) in Tokens::builtin() {
+        tokens_table.insert(token.address(), info);
+    }
+
+    let mut account_table = ctx.get_table::<EthAddress, AccountInfo>("account");
+    let mut token_balances_table =
+        ctx.get_table::<(EthAddress, EthAddress), HpUfixed<18>>("token_balances");
+    for address in legacy_keys::<_, EthAddress>(ctx, "account") {
+        if let Some(account) = get_legacy::<_, _, v2::AccountInfo>(ctx, "account", &address) {
+            if account.stables_balance > HpUfixed::zero() {
+                token_balances_table.insert(
+                    (address, Tokens::USDC.address()),
+                    account.stables_balance.convert_precision::<18>(),
+                );
+            }
+            account_table.insert(address, AccountInfo::from(account));
+        }
+    }
+}
+
+/// Reads the committed keys of the given table, the tables don't need to be iterable for this.
+fn legacy_keys<B: StorageBackend, K: for<'de> Deserialize<'de>>(
+    ctx: &TableSelector<B, DefaultSerdeBackend>,
+    table: &str,
+) -> Vec<K> {
+    ctx.get_raw_keys(table)
+        .iter()
+        .map(|key| DefaultSerdeBackend::deserialize(key))
+        .collect()
+}
+
+/// Reads the committed value of the given key and decodes it with a legacy layout.
+fn get_legacy<B: StorageBackend, K: Serialize, V: for<'de> Deserialize<'de>>(
+    ctx: &TableSelector<B, DefaultSerdeBackend>,
+    table: &str,
+    key: &K,
+) -> Option<V> {
+    let key = DefaultSerdeBackend::serialize(key);
+    ctx.get_raw_value(table, &key)
+        .map(|value| DefaultSerdeBackend::deserialize(&value))
+}
+
+fn unbonding_queue(locked: HpUfixed<18>, locked_until: Epoch) -> UnbondingQueue {
+    let mut queue = UnbondingQueue::default();
+    if locked > HpUfixed::zero() {
+        queue.push(locked, locked_until);
+    }
+    queue
+}
+
+/// The layout of the stored types at version 0.
+pub(crate) mod v0 {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Staking {
+        pub staked: HpUfixed<18>,
+        pub stake_locked_until: u64,
+        pub locked: HpUfixed<18>,
+        pub locked_until: u64,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct NodeInfo {
+        pub owner: EthAddress,
+        pub public_key: NodePublicKey,
+        pub consensus_key: ConsensusPublicKey,
+        pub staked_since: Epoch,
+        pub stake: Staking,
+        pub domain: IpAddr,
+        pub worker_domain: IpAddr,
+        pub ports: NodePorts,
+        pub worker_public_key: NodePublicKey,
+        pub participation: Participation,
+        pub nonce: u64,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub enum NodeRegistryChange {
+        New,
+        Removed,
+        Slashed((HpUfixed<18>, Staking, NodeRegistryChangeSlashReason)),
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Committee {
+        pub members: Vec<NodeIndex>,
+        pub ready_to_change: Vec<NodeIndex>,
+        pub epoch_end_timestamp: u64,
+        pub active_node_set: Vec<NodeIndex>,
+        pub node_registry_changes: BTreeMap<BlockNumber, Vec<(NodePublicKey, NodeRegistryChange)>>,
+    }
+}
+
+/// The layout of the stored types at version 1.
+pub(crate) mod v1 {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Service {
+        pub owner: EthAddress,
+        pub commodity_type: CommodityTypes,
+        pub slashing: (),
+    }
+}
+
+/// The layout of the stored types at version 2.
+pub(crate) mod v2 {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    pub struct AccountInfo {
+        pub flk_balance: HpUfixed<18>,
+        pub stables_balance: HpUfixed<6>,
+        pub bandwidth_balance: u128,
+        pub nonce: u64,
+        pub client_key: Option<ClientPublicKey>,
+    }
+}
+
+impl From<v0::Staking> for Staking {
+    fn from(value: v0::Staking) -> Self {
+        Self {
+            staked: value.staked,
+            stake_locked_until: value.stake_locked_until,
+            unbonding: unbonding_queue(value.locked, value.locked_until),
+        }
+    }
+}
+
+impl From<v0::NodeInfo> for NodeInfo {
+    fn from(value: v0::NodeInfo) -> Self {
+        Self {
+            owner: value.owner,
+            public_key: value.public_key,
+            consensus_key: value.consensus_key,
+            staked_since: value.staked_since,
+            stake: value.stake.into(),
+            domain: value.domain,
+            worker_domain: value.worker_domain,
+            ports: value.ports,
+            worker_public_key: value.worker_public_key,
+            participation: value.participation,
+            nonce: value.nonce,
+        }
+    }
+}
+
+impl From<v0::NodeRegistryChange> for NodeRegistryChange {
+    fn from(value: v0::NodeRegistryChange) -> Self {
+        match value {
+            v0::NodeRegistryChange::New => Self::New,
+            v0::NodeRegistryChange::Removed => Self::Removed,
+            v0::NodeRegistryChange::Slashed((amount, stake, reason)) => {
+                Self::Slashed((amount, stake.into(), reason))
+            },
+        }
+    }
+}
+
+impl From<v0::Committee> for Committee {
+    fn from(value: v0::Committee) -> Self {
+        Self {
+            members: value.members,
+            ready_to_change: value.ready_to_change,
+            epoch_end_timestamp: value.epoch_end_timestamp,
+            active_node_set: value.active_node_set,
+            node_registry_changes: value
+                .node_registry_changes
+                .into_iter()
+                .map(|(block, changes)| {
+                    let changes = changes
+                        .into_iter()
+                        .map(|(public_key, change)| (public_key, change.into()))
+                        .collect();
+                    (block, changes)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<v1::Service> for Service {
+    fn from(value: v1::Service) -> Self {
+        Self {
+            owner: value.owner,
+            commodity_type: value.commodity_type,
+            slashing: value.slashing,
+            binary: None,
+            version: 0,
+            resources: ServiceResources::default(),
+        }
+    }
+}
+
+impl From<v2::AccountInfo> for AccountInfo {
+    fn from(value: v2::AccountInfo) -> Self {
+        Self {
+            flk_balance: value.flk_balance,
+            bandwidth_balance: value.bandwidth_balance,
+            nonce: value.nonce,
+            client_key: value.client_key,
+        }
+    }
+}
@@ -1,5 +1,6 @@
 mod context;
 mod executor;
+pub(crate) mod migration;
 mod query;
 mod writer;
 
@@ -22,10 +22,13 @@
     CommitteeSelectionBeaconCommit,
     CommitteeSelectionBeaconReveal,
     CommodityTypes,
+    Delegation,
+    DelegationPool,
     Epoch,
     Metadata,
     NodeIndex,
     NodeInfo,
+    NodeInfoUpdate,
     NodeServed,
     Nonce,
     ProtocolParamKey,
@@ -36,6 +39,8 @@
     ServiceRevenue,
     StateProofKey,
     StateProofValue,
+    TokenInfo,
+    Tokens,
     TotalServed,
     TransactionRequest,
     TransactionResponse,
@@ -83,6 +88,13 @@ pub struct QueryRunner {
     >,
     committee_selection_beacon_non_revealing_node: ResolvedTableReference<NodeIndex, ()>,
     withdraws: ResolvedTableReference<u64, WithdrawInfo>,
+    delegations: ResolvedTableReference<(EthAddress, NodeIndex), Delegation>,
+    delegator_nodes: ResolvedTableReference<EthAddress, BTreeSet<NodeIndex>>,
+    delegation_pools: ResolvedTableReference<NodeIndex, DelegationPool>,
+    pending_node_info_updates: ResolvedTableReference<NodeIndex, NodeInfoUpdate>,
+    pending_commissions: ResolvedTableReference<NodeIndex, u8>,
+    tokens: ResolvedTableReference<EthAddress, TokenInfo>,
+    token_balances: ResolvedTableReference<(EthAddress, EthAddress), HpUfixed<18>>,
 }
 
 impl QueryRunner {
@@ -127,6 +139,15 @@ fn new(atomo: Atomo<QueryPerm, AtomoStorage>) -> Self {
             committee_selection_beacon_non_revealing_node: atomo
                 .resolve::<NodeIndex, ()>("committee_selection_beacon_non_revealing_node"),
             withdraws: atomo.resolve::<u64, WithdrawInfo>("withdraws"),
+            delegations: atomo.resolve::<(EthAddress, NodeIndex), Delegation>("delegations"),
+            delegator_nodes: atomo.resolve::<EthAddress, BTreeSet<NodeIndex>>("delegator_nodes"),
+            delegation_pools: atomo.resolve::<NodeIndex, DelegationPool>("delegation_pools"),
+            pending_node_info_updates: atomo
+                .resolve::<NodeIndex, NodeInfoUpdate>("pending_node_info_updates"),
+            pending_commissions: atomo.resolve::<NodeIndex, u8>("pending_commissions"),
+            tokens: atomo.resolve::<EthAddress, TokenInfo>("tokens"),
+            token_balances: atomo
+                .resolve::<(EthAddress, EthAddress), HpUfixed<18>>("token_balances"),
             inner: atomo,
         }
     }
@@ -403,4 +424,73 @@ fn get_withdraws(&self, paging: WithdrawPagingParams) -> Vec<WithdrawInfoWithId>
             .take(paging.limit)
             .collect()
     }
+
+    fn get_delegation(&self, delegator: &EthAddress, node: &NodeIndex) -> Option<Delegation> {
+        self.inner
+            .run(|ctx| self.delegations.get(ctx).get((*delegator, *node)))
+    }
+
+    fn get_delegations(&self, delegator: &EthAddress) -> Vec<(NodeIndex, Delegation)> {
+        self.inner.run(|ctx| {
+            let delegations = self.delegations.get(ctx);
+            self.delegator_nodes
+                .get(ctx)
+                .get(delegator)
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|node| {
+                    delegations
+                        .get((*delegator, node))
+                        .map(|delegation| (node, delegation))
+                })
+                .collect()
+        })
+    }
+
+    fn get_delegation_pool(&self, node: &NodeIndex) -> Option<DelegationPool> {
+        self.inner
+            .run(|ctx| self.delegation_pools.get(ctx).get(node))
+    }
+
+    fn get_pending_node_info_update(&self, node: &NodeIndex) -> Option<NodeInfoUpdate> {
+        self.inner
+            .run(|ctx| self.pending_node_info_updates.get(ctx).get(node))
+    }
+
+    fn get_pending_commission(&self, node: &NodeIndex) -> Option<u8> {
+        self.inner
+            .run(|ctx| self.pending_commissions.get(ctx).get(node))
+    }
+
+    fn get_token_info(&self, token: &Tokens) -> Option<TokenInfo> {
+        self.inner
+            .run(|ctx| self.tokens.get(ctx).get(token.address()))
+    }
+
+    fn get_tokens(&self) -> Vec<(EthAddress, TokenInfo)> {
+        self.inner.run(|ctx| {
+            let table = self.tokens.get(ctx);
+            let mut tokens: Vec<_> = table
+                .keys()
+                .filter_map(|address| table.get(address).map(|info| (address, info)))
+                .collect();
+            tokens.sort_by_key(|(address, _)| *address);
+            tokens
+        })
+    }
+
+    fn get_token_balance(&self, account: &EthAddress, token: &Tokens) -> HpUfixed<18> {
+        if token.is_flk() {
+            return self
+                .get_account_info(account, |a| a.flk_balance)
+                .unwrap_or_default();
+        }
+        self.inner
+            .run(|ctx| {
+                self.token_balances
+                    .get(ctx)
+                    .get((*account, token.address()))
+            })
+            .unwrap_or_default()
+    }
 }
@@ -22,10 +22,13 @@
     CommitteeSelectionBeaconCommit,
     CommitteeSelectionBeaconReveal,
     CommodityTypes,
+    Delegation,
+    DelegationPool,
     Epoch,
     Metadata,
     NodeIndex,
     NodeInfo,
+    NodeInfoUpdate,
     NodeServed,
     ProtocolParamKey,
     ProtocolParamValue,
@@ -33,6 +36,7 @@
     Service,
     ServiceId,
     ServiceRevenue,
+    TokenInfo,
     TotalServed,
     TxHash,
     Value,
@@ -190,6 +194,13 @@ pub fn register_tables<C: StorageBackendConstructor>(
             )>("committee_selection_beacon")
             .with_table::<NodeIndex, ()>("committee_selection_beacon_non_revealing_node")
             .with_table::<u64, WithdrawInfo>("withdraws")
+            .with_table::<(EthAddress, NodeIndex), Delegation>("delegations")
+            .with_table::<EthAddress, BTreeSet<NodeIndex>>("delegator_nodes")
+            .with_table::<NodeIndex, DelegationPool>("delegation_pools")
+            .with_table::<NodeIndex, NodeInfoUpdate>("pending_node_info_updates")
+            .with_table::<NodeIndex, u8>("pending_commissions")
+            .with_table::<EthAddress, TokenInfo>("tokens")
+            .with_table::<(EthAddress, EthAddress), HpUfixed<18>>("token_balances")
             .enable_iter("current_epoch_served")
             .enable_iter("rep_measurements")
             .enable_iter("submitted_rep_measurements")
@@ -203,7 +214,10 @@ pub fn register_tables<C: StorageBackendConstructor>(
             .enable_iter("node_to_uri")
             .enable_iter("committee_selection_beacon")
             .enable_iter("committee_selection_beacon_non_revealing_node")
-            .enable_iter("withdraws");
+            .enable_iter("withdraws")
+            .enable_iter("pending_node_info_updates")
+            .enable_iter("pending_commissions")
+            .enable_iter("tokens");
 
         #[cfg(debug_assertions)]
         {
@@ -264,8 +278,7 @@ fn build(account_count: usize, node_count: usize) -> Self {
                             stake: Staking {
                                 staked: HpUfixed::<18>::zero(),
                                 stake_locked_until: 0,
-                                locked: HpUfixed::<18>::zero(),
-                                locked_until: 0,
+                                unbonding: Default::default(),
                             },
                             domain: "127.0.0.1".parse().unwrap(),
                             worker_domain: "127.0.0.1".parse().unwrap(),
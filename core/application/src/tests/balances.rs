@@ -5,6 +5,7 @@
     ExecutionError,
     GenesisAccount,
     ProofOfConsensus,
+    TokenInfo,
     Tokens,
     UpdateMethod,
 };
@@ -218,10 +219,15 @@ async fn test_deposit_usdc_works_properly() {
     let update = prepare_update_request_account(deposit, &owner_secret_key, 1);
     expect_tx_success(update, &update_socket, ExecutionData::None).await;
 
+    // Deposits of USDC buy bandwidth, they are not kept as a token balance.
     assert_eq!(
         get_account_balance(&query_runner, &owner),
         intial_balance + deposit_amount
     );
+    assert_eq!(
+        query_runner.get_token_balance(&owner, &Tokens::USDC),
+        HpUfixed::zero()
+    );
 }
 
 #[tokio::test]
@@ -261,6 +267,49 @@ async fn test_withdraw_usdc_works_properly() {
     });
     assert_eq!(withdraws[0].info.receiver, receiver);
     assert_eq!(withdraws[0].info.amount, withdraw_amount.into());
+    assert_eq!(
+        query_runner.get_token_balance(&owner, &Tokens::USDC),
+        500_u64.into()
+    );
+}
+
+#[tokio::test]
+async fn test_withdraw_rejects_amount_more_precise_than_token() {
+    let temp_dir = tempdir().unwrap();
+
+    let mut genesis = test_genesis();
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let receiver: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+
+    genesis.account = vec![GenesisAccount {
+        public_key: owner,
+        flk_balance: 0_u64.into(),
+        stables_balance: 1000,
+        bandwidth_balance: 0,
+    }];
+
+    let (update_socket, query_runner) = init_app_with_genesis(&temp_dir, &genesis);
+
+    // USDC has 6 decimals on the L2, so a more precise amount can't be withdrawn.
+    let withdraw = UpdateMethod::Withdraw {
+        amount: HpUfixed::<18>::from(1.0000001),
+        token: Tokens::USDC,
+        receiving_address: receiver,
+    };
+    let update = prepare_update_request_account(withdraw, &owner_secret_key, 1);
+    expect_tx_revert(update, &update_socket, ExecutionError::InvalidAmount).await;
+
+    let withdraws = query_runner.get_withdraws(WithdrawPagingParams {
+        start: 0,
+        limit: 100,
+    });
+    assert!(withdraws.is_empty());
+    assert_eq!(
+        query_runner.get_token_balance(&owner, &Tokens::USDC),
+        1000_u64.into()
+    );
 }
 
 #[tokio::test]
@@ -301,3 +350,146 @@ async fn test_withdraw_flk_works_properly() {
     assert_eq!(withdraws[0].info.receiver, receiver);
     assert_eq!(withdraws[0].info.amount, withdraw_amount.into());
 }
+
+#[tokio::test]
+async fn test_register_token() {
+    let temp_dir = tempdir().unwrap();
+
+    let governance_secret_key = AccountOwnerSecretKey::generate();
+    let mut genesis = test_genesis();
+    genesis.governance_address = governance_secret_key.to_pk().into();
+
+    let (update_socket, query_runner) = init_app_with_genesis(&temp_dir, &genesis);
+
+    // The built-in tokens are registered at genesis.
+    assert_eq!(query_runner.get_tokens().len(), 2);
+
+    let address = EthAddress([7; 20]);
+    let info = TokenInfo {
+        symbol: "USDT".to_string(),
+        decimals: 6,
+        bridged: true,
+    };
+    let register = UpdateMethod::RegisterToken {
+        address,
+        info: info.clone(),
+    };
+
+    // Only the governance can register tokens.
+    let some_secret_key = AccountOwnerSecretKey::generate();
+    let update = prepare_update_request_account(register.clone(), &some_secret_key, 1);
+    expect_tx_revert(update, &update_socket, ExecutionError::OnlyGovernance).await;
+
+    let update = prepare_update_request_account(register.clone(), &governance_secret_key, 1);
+    expect_tx_success(update, &update_socket, ExecutionData::None).await;
+    assert_eq!(
+        query_runner.get_token_info(&Tokens::Erc20(address)),
+        Some(info)
+    );
+    assert!(
+        query_runner
+            .get_tokens()
+            .iter()
+            .any(|(token, info)| *token == address && info.symbol == "USDT")
+    );
+
+    // A token can't be registered twice.
+    let update = prepare_update_request_account(register, &governance_secret_key, 2);
+    expect_tx_revert(update, &update_socket, ExecutionError::TokenAlreadyExists).await;
+
+    // Balances are kept with 18 decimals, so tokens can't have more.
+    let register = UpdateMethod::RegisterToken {
+        address: EthAddress([8; 20]),
+        info: TokenInfo {
+            symbol: "HIGH".to_string(),
+            decimals: 24,
+            bridged: true,
+        },
+    };
+    let update = prepare_update_request_account(register, &governance_secret_key, 3);
+    expect_tx_revert(update, &update_socket, ExecutionError::InvalidToken).await;
+}
+
+#[tokio::test]
+async fn test_deposit_and_transfer_registered_token() {
+    let temp_dir = tempdir().unwrap();
+
+    let governance_secret_key = AccountOwnerSecretKey::generate();
+    let mut genesis = test_genesis();
+    genesis.governance_address = governance_secret_key.to_pk().into();
+
+    let (update_socket, query_runner) = init_app_with_genesis(&temp_dir, &genesis);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let recipient: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+    let token = Tokens::Erc20(EthAddress([7; 20]));
+    let deposit = UpdateMethod::Deposit {
+        proof: ProofOfConsensus {},
+        token: token.clone(),
+        amount: 100_u64.into(),
+    };
+
+    // Unregistered tokens can't be deposited.
+    let update = prepare_update_request_account(deposit.clone(), &owner_secret_key, 1);
+    expect_tx_revert(update, &update_socket, ExecutionError::InvalidToken).await;
+
+    let register = UpdateMethod::RegisterToken {
+        address: token.address(),
+        info: TokenInfo {
+            symbol: "USDT".to_string(),
+            decimals: 6,
+            bridged: true,
+        },
+    };
+    let update = prepare_update_request_account(register, &governance_secret_key, 1);
+    expect_tx_success(update, &update_socket, ExecutionData::None).await;
+
+    let update = prepare_update_request_account(deposit.clone(), &owner_secret_key, 2);
+    expect_tx_success(update, &update_socket, ExecutionData::None).await;
+    assert_eq!(
+        query_runner.get_token_balance(&owner, &token),
+        100_u64.into()
+    );
+
+    let transfer = UpdateMethod::Transfer {
+        amount: 40_u64.into(),
+        token: token.clone(),
+        to: recipient,
+    };
+    let update = prepare_update_request_account(transfer, &owner_secret_key, 3);
+    expect_tx_success(update, &update_socket, ExecutionData::None).await;
+    assert_eq!(
+        query_runner.get_token_balance(&owner, &token),
+        60_u64.into()
+    );
+    assert_eq!(
+        query_runner.get_token_balance(&recipient, &token),
+        40_u64.into()
+    );
+
+    // The balances of the other tokens are untouched.
+    assert_eq!(
+        query_runner.get_token_balance(&owner, &Tokens::USDC),
+        HpUfixed::zero()
+    );
+
+    // Once the bridge of the token is disabled, it can still be transferred but not deposited.
+    let disable = UpdateMethod::SetTokenBridged {
+        token: token.clone(),
+        bridged: false,
+    };
+    let update = prepare_update_request_account(disable, &governance_secret_key, 2);
+    expect_tx_success(update, &update_socket, ExecutionData::None).await;
+
+    let update = prepare_update_request_account(deposit, &owner_secret_key, 4);
+    expect_tx_revert(update, &update_socket, ExecutionError::TokenNotBridged).await;
+
+    let transfer = UpdateMethod::Transfer {
+        amount: 70_u64.into(),
+        token,
+        to: recipient,
+    };
+    let update = prepare_update_request_account(transfer, &owner_secret_key, 5);
+    expect_tx_revert(update, &update_socket, ExecutionError::InsufficientBalance).await;
+}
@@ -0,0 +1,210 @@
+use fleek_crypto::{AccountOwnerSecretKey, EthAddress, NodeSecretKey, SecretKey};
+use hp_fixed::unsigned::HpUfixed;
+use lightning_interfaces::types::{
+    Event,
+    ExecutionData,
+    ExecutionError,
+    ProofOfConsensus,
+    Tokens,
+    UpdateMethod,
+    MAX_BATCH_SIZE,
+};
+use lightning_interfaces::SyncQueryRunnerInterface;
+use tempfile::tempdir;
+
+use super::utils::*;
+
+fn deposit_method(amount: u64) -> UpdateMethod {
+    UpdateMethod::Deposit {
+        proof: ProofOfConsensus {},
+        token: Tokens::FLK,
+        amount: amount.into(),
+    }
+}
+
+#[tokio::test]
+async fn test_batch_deposit_and_stake() {
+    let temp_dir = tempdir().unwrap();
+
+    let (update_socket, query_runner) = init_app(&temp_dir, None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let node_pub_key = NodeSecretKey::generate().to_pk();
+
+    let update = prepare_update_request_account(
+        UpdateMethod::Batch(vec![
+            deposit_method(1_000),
+            UpdateMethod::Stake {
+                amount: 1_000u64.into(),
+                node_public_key: node_pub_key,
+                consensus_key: Some([0; 96].into()),
+                node_domain: Some("127.0.0.1".parse().unwrap()),
+                worker_public_key: Some([0; 32].into()),
+                worker_domain: Some("127.0.0.1".parse().unwrap()),
+                ports: Some(Default::default()),
+            },
+        ]),
+        &owner_secret_key,
+        1,
+    );
+    expect_tx_success(
+        update,
+        &update_socket,
+        ExecutionData::Batch(vec![ExecutionData::None, ExecutionData::None]),
+    )
+    .await;
+
+    assert_eq!(get_staked(&query_runner, &node_pub_key), 1_000u64.into());
+    assert_eq!(get_flk_balance(&query_runner, &owner), HpUfixed::zero());
+
+    // The whole batch uses a single nonce.
+    assert_eq!(query_runner.get_account_info(&owner, |a| a.nonce), Some(1));
+}
+
+#[tokio::test]
+async fn test_batch_reverts_all_methods() {
+    let temp_dir = tempdir().unwrap();
+
+    let (update_socket, query_runner) = init_app(&temp_dir, None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let recipient: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+
+    deposit(&update_socket, &owner_secret_key, 1, &100u64.into()).await;
+
+    // The transfer exceeds the balance, so the deposit before it is discarded as well.
+    let update = prepare_update_request_account(
+        UpdateMethod::Batch(vec![
+            deposit_method(1_000),
+            UpdateMethod::Transfer {
+                amount: 2_000u64.into(),
+                token: Tokens::FLK,
+                to: recipient,
+            },
+        ]),
+        &owner_secret_key,
+        2,
+    );
+    let resp = expect_tx_revert(
+        update,
+        &update_socket,
+        ExecutionError::BatchMethodReverted {
+            index: 1,
+            error: Box::new(ExecutionError::InsufficientBalance),
+        },
+    )
+    .await;
+    assert!(resp.txn_receipts[0].events.is_empty());
+
+    assert_eq!(get_flk_balance(&query_runner, &owner), 100u64.into());
+    assert_eq!(get_flk_balance(&query_runner, &recipient), HpUfixed::zero());
+
+    // The nonce is still consumed by the reverted batch.
+    assert_eq!(query_runner.get_account_info(&owner, |a| a.nonce), Some(2));
+
+    // A later method of the batch sees the changes made by the methods before it.
+    let update = prepare_update_request_account(
+        UpdateMethod::Batch(vec![
+            deposit_method(1_000),
+            UpdateMethod::Transfer {
+                amount: 1_100u64.into(),
+                token: Tokens::FLK,
+                to: recipient,
+            },
+        ]),
+        &owner_secret_key,
+        3,
+    );
+    expect_tx_success(
+        update,
+        &update_socket,
+        ExecutionData::Batch(vec![ExecutionData::None, ExecutionData::None]),
+    )
+    .await;
+
+    assert_eq!(get_flk_balance(&query_runner, &owner), HpUfixed::zero());
+    assert_eq!(get_flk_balance(&query_runner, &recipient), 1_100u64.into());
+}
+
+#[tokio::test]
+async fn test_batch_emits_events_of_its_methods() {
+    let temp_dir = tempdir().unwrap();
+
+    let (update_socket, _query_runner) = init_app(&temp_dir, None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let recipient: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+    let other_recipient: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+
+    let update = prepare_update_request_account(
+        UpdateMethod::Batch(vec![
+            deposit_method(1_000),
+            UpdateMethod::Transfer {
+                amount: 100u64.into(),
+                token: Tokens::FLK,
+                to: recipient,
+            },
+            UpdateMethod::Transfer {
+                amount: 200u64.into(),
+                token: Tokens::FLK,
+                to: other_recipient,
+            },
+        ]),
+        &owner_secret_key,
+        1,
+    );
+    let resp = expect_tx_success(
+        update,
+        &update_socket,
+        ExecutionData::Batch(vec![ExecutionData::None; 3]),
+    )
+    .await;
+
+    // Each transfer of the batch is reported, in order.
+    assert_eq!(
+        resp.txn_receipts[0].events,
+        vec![
+            Event::transfer(Tokens::FLK.address(), owner, recipient, 100u64.into()),
+            Event::transfer(Tokens::FLK.address(), owner, other_recipient, 200u64.into()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_batch_invalid_methods() {
+    let temp_dir = tempdir().unwrap();
+
+    let (update_socket, _query_runner) = init_app(&temp_dir, None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+
+    let update = prepare_update_request_account(UpdateMethod::Batch(vec![]), &owner_secret_key, 1);
+    expect_tx_revert(update, &update_socket, ExecutionError::EmptyBatch).await;
+
+    let update = prepare_update_request_account(
+        UpdateMethod::Batch(vec![deposit_method(1); MAX_BATCH_SIZE + 1]),
+        &owner_secret_key,
+        2,
+    );
+    expect_tx_revert(update, &update_socket, ExecutionError::TooManyBatchMethods).await;
+
+    let update = prepare_update_request_account(
+        UpdateMethod::Batch(vec![
+            deposit_method(1),
+            UpdateMethod::Batch(vec![deposit_method(1)]),
+        ]),
+        &owner_secret_key,
+        3,
+    );
+    expect_tx_revert(update, &update_socket, ExecutionError::InvalidBatchMethod).await;
+
+    let update = prepare_update_request_account(
+        UpdateMethod::Batch(vec![UpdateMethod::ChangeEpoch { epoch: 0 }]),
+        &owner_secret_key,
+        4,
+    );
+    expect_tx_revert(update, &update_socket, ExecutionError::InvalidBatchMethod).await;
+}
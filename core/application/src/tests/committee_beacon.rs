@@ -849,8 +849,7 @@ async fn test_committee_beacon_non_revealing_node_fully_slashed() {
                 Staking {
                     staked: 0u64.into(),
                     stake_locked_until: 0,
-                    locked: 0u64.into(),
-                    locked_until: 0,
+                    unbonding: Default::default(),
                 },
                 NodeRegistryChangeSlashReason::CommitteeBeaconNonReveal,
             )),
@@ -868,8 +867,7 @@ async fn test_committee_beacon_non_revealing_node_fully_slashed() {
                 Staking {
                     staked: 0u64.into(),
                     stake_locked_until: 0,
-                    locked: 0u64.into(),
-                    locked_until: 0,
+                    unbonding: Default::default(),
                 },
                 NodeRegistryChangeSlashReason::CommitteeBeaconNonReveal,
             )),
@@ -1059,8 +1057,7 @@ async fn test_committee_beacon_non_revealing_node_partially_slashed_insufficient
                 Staking {
                     staked: 500u64.into(),
                     stake_locked_until: 0,
-                    locked: 0u64.into(),
-                    locked_until: 0,
+                    unbonding: Default::default(),
                 },
                 NodeRegistryChangeSlashReason::CommitteeBeaconNonReveal,
             )),
@@ -1078,8 +1075,7 @@ async fn test_committee_beacon_non_revealing_node_partially_slashed_insufficient
                 Staking {
                     staked: 500u64.into(),
                     stake_locked_until: 0,
-                    locked: 0u64.into(),
-                    locked_until: 0,
+                    unbonding: Default::default(),
                 },
                 NodeRegistryChangeSlashReason::CommitteeBeaconNonReveal,
             )),
@@ -1268,8 +1264,7 @@ async fn test_committee_beacon_non_revealing_node_partially_slashed_sufficient_s
             Staking {
                 staked: 500u64.into(),
                 stake_locked_until: 0,
-                locked: 0u64.into(),
-                locked_until: 0,
+                unbonding: Default::default(),
             },
             NodeRegistryChangeSlashReason::CommitteeBeaconNonReveal,
         )),
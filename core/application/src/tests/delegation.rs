@@ -0,0 +1,435 @@
+use std::time::Duration;
+
+use fleek_crypto::{AccountOwnerSecretKey, EthAddress, NodeSecretKey, SecretKey};
+use hp_fixed::unsigned::HpUfixed;
+use lightning_committee_beacon::{CommitteeBeaconConfig, CommitteeBeaconTimerConfig};
+use lightning_interfaces::types::{
+    CommitteeSelectionBeaconCommit,
+    DeliveryAcknowledgmentProof,
+    ExecutionData,
+    ExecutionError,
+    ProofOfConsensus,
+    Tokens,
+    UnbondingEntry,
+    UnbondingQueue,
+    UpdateMethod,
+};
+use lightning_interfaces::SyncQueryRunnerInterface;
+use lightning_test_utils::consensus::MockConsensusConfig;
+use lightning_test_utils::e2e::{
+    DowncastToTestFullNode,
+    TestFullNodeComponentsWithMockConsensus,
+    TestNetwork,
+};
+use lightning_utils::application::QueryRunnerExt;
+use lightning_utils::transaction::TransactionSigner;
+use tempfile::tempdir;
+use utils::{
+    deposit,
+    deposit_and_stake,
+    expect_tx_revert,
+    expect_tx_success,
+    get_flk_balance,
+    get_node_index,
+    get_staked,
+    init_app,
+    init_app_with_genesis,
+    prepare_delegate_update,
+    prepare_set_commission_rate_update,
+    prepare_undelegate_update,
+    prepare_update_request_node,
+    prepare_withdraw_undelegated_update,
+    test_genesis,
+};
+
+use super::*;
+
+#[tokio::test]
+async fn test_delegate_and_undelegate() {
+    let temp_dir = tempdir().unwrap();
+
+    let (update_socket, query_runner) = init_app(&temp_dir, None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let node_pub_key = NodeSecretKey::generate().to_pk();
+    let stake: HpUfixed<18> = 1_000u64.into();
+    deposit_and_stake(
+        &update_socket,
+        &owner_secret_key,
+        1,
+        &stake,
+        &node_pub_key,
+        [0; 96].into(),
+    )
+    .await;
+    let node_index = get_node_index(&query_runner, &node_pub_key);
+
+    // Deposit some FLK into the account of the delegator and delegate part of it
+    let delegator_secret_key = AccountOwnerSecretKey::generate();
+    let delegator: EthAddress = delegator_secret_key.to_pk().into();
+    deposit(&update_socket, &delegator_secret_key, 1, &1_000u64.into()).await;
+
+    let update = prepare_delegate_update(&600u64.into(), &node_pub_key, &delegator_secret_key, 2);
+    expect_tx_success(update, &update_socket, ExecutionData::None).await;
+
+    assert_eq!(get_flk_balance(&query_runner, &delegator), 400u64.into());
+    let delegation = query_runner
+        .get_delegation(&delegator, &node_index)
+        .unwrap();
+    assert_eq!(delegation.staked, 600u64.into());
+    let pool = query_runner.get_delegation_pool(&node_index).unwrap();
+    assert_eq!(pool.delegated, 600u64.into());
+    assert!(pool.delegators.contains(&delegator));
+    assert_eq!(
+        query_runner.get_delegations(&delegator),
+        vec![(node_index, delegation)]
+    );
+
+    // The stake of the node itself is unchanged
+    assert_eq!(get_staked(&query_runner, &node_pub_key), stake);
+
+    // Undelegate and make sure it moves the tokens to locked status
+    let update = prepare_undelegate_update(&200u64.into(), &node_pub_key, &delegator_secret_key, 3);
+    expect_tx_success(update, &update_socket, ExecutionData::None).await;
+
+    let delegation = query_runner
+        .get_delegation(&delegator, &node_index)
+        .unwrap();
+    assert_eq!(delegation.staked, 400u64.into());
+    // Since this test starts at epoch 0 locked_until will be == lock_time
+    assert_eq!(
+        delegation.unbonding,
+        UnbondingQueue(vec![UnbondingEntry {
+            amount: 200u64.into(),
+            locked_until: test_genesis().lock_time,
+        }])
+    );
+    let pool = query_runner.get_delegation_pool(&node_index).unwrap();
+    assert_eq!(pool.delegated, 400u64.into());
+    assert_eq!(pool.locked, 200u64.into());
+
+    // Try to withdraw the locked tokens and it should revert
+    let update = prepare_withdraw_undelegated_update(&node_pub_key, None, &delegator_secret_key, 4);
+    expect_tx_revert(update, &update_socket, ExecutionError::TokensLocked).await;
+}
+
+#[tokio::test]
+async fn test_delegate_reverts() {
+    let temp_dir = tempdir().unwrap();
+
+    let (update_socket, _query_runner) = init_app(&temp_dir, None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let node_pub_key = NodeSecretKey::generate().to_pk();
+    let delegator_secret_key = AccountOwnerSecretKey::generate();
+    deposit(&update_socket, &delegator_secret_key, 1, &1_000u64.into()).await;
+
+    let update = prepare_delegate_update(&100u64.into(), &node_pub_key, &delegator_secret_key, 2);
+    expect_tx_revert(update, &update_socket, ExecutionError::NodeDoesNotExist).await;
+
+    deposit_and_stake(
+        &update_socket,
+        &owner_secret_key,
+        1,
+        &1_000u64.into(),
+        &node_pub_key,
+        [0; 96].into(),
+    )
+    .await;
+
+    let update = prepare_delegate_update(&2_000u64.into(), &node_pub_key, &delegator_secret_key, 3);
+    expect_tx_revert(update, &update_socket, ExecutionError::InsufficientBalance).await;
+
+    let update =
+        prepare_delegate_update(&HpUfixed::zero(), &node_pub_key, &delegator_secret_key, 4);
+    expect_tx_revert(update, &update_socket, ExecutionError::InvalidAmount).await;
+
+    let update = prepare_undelegate_update(&100u64.into(), &node_pub_key, &delegator_secret_key, 5);
+    expect_tx_revert(update, &update_socket, ExecutionError::NoDelegation).await;
+
+    let update = prepare_delegate_update(&100u64.into(), &node_pub_key, &delegator_secret_key, 6);
+    expect_tx_success(update, &update_socket, ExecutionData::None).await;
+
+    let update = prepare_undelegate_update(&200u64.into(), &node_pub_key, &delegator_secret_key, 7);
+    expect_tx_revert(update, &update_socket, ExecutionError::InsufficientBalance).await;
+
+    let update = prepare_withdraw_undelegated_update(&node_pub_key, None, &delegator_secret_key, 8);
+    expect_tx_revert(update, &update_socket, ExecutionError::NoLockedTokens).await;
+}
+
+#[tokio::test]
+async fn test_withdraw_undelegated_works_properly() {
+    let temp_dir = tempdir().unwrap();
+
+    let mut genesis = test_genesis();
+    genesis.lock_time = 0;
+    let (update_socket, query_runner) = init_app_with_genesis(&temp_dir, &genesis);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let node_pub_key = NodeSecretKey::generate().to_pk();
+    deposit_and_stake(
+        &update_socket,
+        &owner_secret_key,
+        1,
+        &1_000u64.into(),
+        &node_pub_key,
+        [0; 96].into(),
+    )
+    .await;
+    let node_index = get_node_index(&query_runner, &node_pub_key);
+
+    let delegator_secret_key = AccountOwnerSecretKey::generate();
+    let delegator: EthAddress = delegator_secret_key.to_pk().into();
+    let recipient: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+    let amount: HpUfixed<18> = 1_000u64.into();
+    deposit(&update_socket, &delegator_secret_key, 1, &amount).await;
+
+    let update = prepare_delegate_update(&amount, &node_pub_key, &delegator_secret_key, 2);
+    expect_tx_success(update, &update_socket, ExecutionData::None).await;
+    let update = prepare_undelegate_update(&amount, &node_pub_key, &delegator_secret_key, 3);
+    expect_tx_success(update, &update_socket, ExecutionData::None).await;
+    let update = prepare_withdraw_undelegated_update(
+        &node_pub_key,
+        Some(recipient),
+        &delegator_secret_key,
+        4,
+    );
+    expect_tx_success(update, &update_socket, ExecutionData::None).await;
+
+    // The recipient received the tokens, and the empty delegation was removed
+    assert_eq!(get_flk_balance(&query_runner, &recipient), amount);
+    assert!(
+        query_runner
+            .get_delegation(&delegator, &node_index)
+            .is_none()
+    );
+    let pool = query_runner.get_delegation_pool(&node_index).unwrap();
+    assert_eq!(pool.delegated, HpUfixed::zero());
+    assert_eq!(pool.locked, HpUfixed::zero());
+    assert!(pool.delegators.is_empty());
+    assert!(query_runner.get_delegations(&delegator).is_empty());
+}
+
+#[tokio::test]
+async fn test_locked_delegations_count_towards_stake() {
+    let temp_dir = tempdir().unwrap();
+
+    let (update_socket, query_runner) = init_app(&temp_dir, None);
+
+    // The owner stakes half of the minimum stake.
+    let half_stake: HpUfixed<18> =
+        HpUfixed::<18>::from(query_runner.get_staking_amount()) / HpUfixed::from(2u64);
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let node_secret_key = NodeSecretKey::generate();
+    let node_pub_key = node_secret_key.to_pk();
+    deposit_and_stake(
+        &update_socket,
+        &owner_secret_key,
+        1,
+        &half_stake,
+        &node_pub_key,
+        [0; 96].into(),
+    )
+    .await;
+
+    // Transactions that require sufficient stake are rejected before checking anything else.
+    let commit = |nonce| {
+        prepare_update_request_node(
+            UpdateMethod::CommitteeSelectionBeaconCommit {
+                commit: CommitteeSelectionBeaconCommit::build(0, 0, [0; 32]),
+            },
+            &node_secret_key,
+            nonce,
+        )
+    };
+    expect_tx_revert(commit(1), &update_socket, ExecutionError::InsufficientStake).await;
+
+    // The delegated stake makes up for the rest.
+    let delegator_secret_key = AccountOwnerSecretKey::generate();
+    deposit(&update_socket, &delegator_secret_key, 1, &half_stake).await;
+    let update = prepare_delegate_update(&half_stake, &node_pub_key, &delegator_secret_key, 2);
+    expect_tx_success(update, &update_socket, ExecutionData::None).await;
+    expect_tx_revert(
+        commit(2),
+        &update_socket,
+        ExecutionError::CommitteeSelectionBeaconNodeNotActive,
+    )
+    .await;
+
+    // The undelegated stake still counts while it is locked, since it can still be slashed.
+    let update = prepare_undelegate_update(&half_stake, &node_pub_key, &delegator_secret_key, 3);
+    expect_tx_success(update, &update_socket, ExecutionData::None).await;
+    expect_tx_revert(
+        commit(3),
+        &update_socket,
+        ExecutionError::CommitteeSelectionBeaconNodeNotActive,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_set_commission_rate() {
+    let temp_dir = tempdir().unwrap();
+
+    let (update_socket, query_runner) = init_app(&temp_dir, None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let node_pub_key = NodeSecretKey::generate().to_pk();
+    deposit_and_stake(
+        &update_socket,
+        &owner_secret_key,
+        1,
+        &1_000u64.into(),
+        &node_pub_key,
+        [0; 96].into(),
+    )
+    .await;
+
+    let other_secret_key = AccountOwnerSecretKey::generate();
+    let update = prepare_set_commission_rate_update(&node_pub_key, 10, &other_secret_key, 1);
+    expect_tx_revert(update, &update_socket, ExecutionError::NotNodeOwner).await;
+
+    let update = prepare_set_commission_rate_update(&node_pub_key, 101, &owner_secret_key, 3);
+    expect_tx_revert(update, &update_socket, ExecutionError::InvalidCommission).await;
+
+    let update = prepare_set_commission_rate_update(&node_pub_key, 10, &owner_secret_key, 4);
+    expect_tx_success(update, &update_socket, ExecutionData::None).await;
+
+    // The commission is only applied at the next epoch.
+    let node_index = get_node_index(&query_runner, &node_pub_key);
+    assert_eq!(query_runner.get_pending_commission(&node_index), Some(10));
+    assert_eq!(
+        query_runner
+            .get_delegation_pool(&node_index)
+            .unwrap_or_default()
+            .commission,
+        0
+    );
+}
+
+#[tokio::test]
+async fn test_distribute_rewards_with_delegations() {
+    let mut network = TestNetwork::builder()
+        .with_mock_consensus(MockConsensusConfig {
+            block_buffering_interval: Duration::from_millis(100),
+            max_ordering_time: 1,
+            ..Default::default()
+        })
+        .with_committee_beacon_config(CommitteeBeaconConfig {
+            timer: CommitteeBeaconTimerConfig {
+                tick_delay: Duration::from_millis(100),
+            },
+            ..Default::default()
+        })
+        .with_committee_nodes::<TestFullNodeComponentsWithMockConsensus>(4)
+        .await
+        .with_genesis_mutator(|genesis| {
+            genesis.node_share = 80;
+            genesis.protocol_share = 10;
+            genesis.service_builder_share = 10;
+            genesis.committee_selection_beacon_commit_phase_duration = 3;
+            genesis.committee_selection_beacon_reveal_phase_duration = 3;
+        })
+        .build()
+        .await
+        .unwrap();
+    let node = network
+        .node(0)
+        .downcast::<TestFullNodeComponentsWithMockConsensus>();
+    let node_share = HpUfixed::<18>::from(network.genesis.node_share) / HpUfixed::from(100_u16);
+
+    // Set the commission of the owner, it takes effect at the next epoch.
+    node.execute_transaction_from_owner(UpdateMethod::SetCommissionRate {
+        node: node.get_node_public_key(),
+        commission: 10,
+    })
+    .await
+    .unwrap();
+    let node_index = node
+        .app_query()
+        .pubkey_to_index(&node.get_node_public_key())
+        .unwrap();
+    assert_eq!(
+        node.app_query().get_pending_commission(&node_index),
+        Some(10)
+    );
+    network.change_epoch_and_wait_for_complete().await.unwrap();
+    assert_eq!(node.app_query().get_pending_commission(&node_index), None);
+    assert_eq!(
+        node.app_query()
+            .get_delegation_pool(&node_index)
+            .unwrap()
+            .commission,
+        10
+    );
+
+    // Delegate as much as the node owner has staked, so the rewards are split in half before the
+    // commission of the owner.
+    let delegator_secret_key = AccountOwnerSecretKey::generate();
+    let delegator: EthAddress = delegator_secret_key.to_pk().into();
+    let amount = node.get_stake();
+    let client = node
+        .transaction_client(TransactionSigner::AccountOwner(delegator_secret_key))
+        .await;
+    client
+        .execute_transaction_and_wait_for_receipt(
+            UpdateMethod::Deposit {
+                proof: ProofOfConsensus {},
+                token: Tokens::FLK,
+                amount: amount.clone(),
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    client
+        .execute_transaction_and_wait_for_receipt(
+            UpdateMethod::Delegate {
+                node: node.get_node_public_key(),
+                amount,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    // Serve some content and distribute the rewards.
+    let commodity = 10_000;
+    node.execute_transaction_from_node(
+        UpdateMethod::SubmitDeliveryAcknowledgmentAggregation {
+            commodity,
+            service_id: 0,
+            proofs: vec![DeliveryAcknowledgmentProof],
+            metadata: None,
+        },
+        None,
+    )
+    .await
+    .unwrap();
+    let owner_flk = node.get_flk_balance(node.get_owner_address());
+    network.change_epoch_and_wait_for_complete().await.unwrap();
+
+    // The delegator gets half of the rewards minus the commission of the owner.
+    let stables_rewards =
+        HpUfixed::<6>::from(0.1 * commodity as f64) * node_share.convert_precision();
+    let half = HpUfixed::<18>::from(1_u64) / HpUfixed::from(2_u64);
+    let commission = HpUfixed::<18>::from(10_u64) / HpUfixed::from(100_u64);
+    let delegator_share = &half - &half * &commission;
+    let owner_share = &half + &half * &commission;
+    assert_eq!(
+        node.get_stables_balance(delegator),
+        &stables_rewards * &delegator_share.convert_precision()
+    );
+    assert_eq!(
+        node.get_stables_balance(node.get_owner_address()),
+        &stables_rewards * &owner_share.convert_precision()
+    );
+
+    let delegator_flk = node.get_flk_balance(delegator);
+    let owner_flk_rewards = node.get_flk_balance(node.get_owner_address()) - owner_flk;
+    assert!(delegator_flk > HpUfixed::zero());
+    assert!(delegator_flk < owner_flk_rewards);
+
+    // Shutdown the network.
+    network.shutdown().await;
+}
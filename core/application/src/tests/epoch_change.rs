@@ -339,11 +339,11 @@ async fn test_change_epoch_with_only_locked_stake() {
 
             // First node has only locked stake.
             genesis.node_info[0].stake.staked = 0u32.into();
-            genesis.node_info[0].stake.locked = 1000u32.into();
+            genesis.node_info[0].stake.unbonding.push(1000u32.into(), 0);
 
             // Second node has unlocked stake.
             genesis.node_info[1].stake.staked = 1000u32.into();
-            genesis.node_info[1].stake.locked = 0u32.into();
+            genesis.node_info[1].stake.unbonding = Default::default();
         })
         .build()
         .await
@@ -701,17 +701,11 @@ async fn test_distribute_rewards() {
 
     // Check node stables balances.
     assert_eq!(
-        node1
-            .app_query()
-            .get_account_info(&node1.get_owner_address(), |a| a.stables_balance)
-            .unwrap(),
+        node1.get_stables_balance(node1.get_owner_address()),
         HpUfixed::<6>::from(node_1_usd) * node_share.convert_precision()
     );
     assert_eq!(
-        node1
-            .app_query()
-            .get_account_info(&node2.get_owner_address(), |a| a.stables_balance)
-            .unwrap(),
+        node1.get_stables_balance(node2.get_owner_address()),
         HpUfixed::<6>::from(node_2_usd) * node_share.convert_precision()
     );
 
@@ -747,10 +741,7 @@ async fn test_distribute_rewards() {
     let protocol_rewards = &emissions * &protocol_share;
     assert_eq!(protocol_balance, protocol_rewards);
 
-    let protocol_stables_balance = node1
-        .app_query()
-        .get_account_info(&protocol_account, |a| a.stables_balance)
-        .unwrap();
+    let protocol_stables_balance = node1.get_stables_balance(protocol_account);
     assert_eq!(
         &reward_pool * &protocol_share.convert_precision(),
         protocol_stables_balance
@@ -767,10 +758,7 @@ async fn test_distribute_rewards() {
             service_balance,
             &emissions * &service_share * &service_proportions[s as usize]
         );
-        let service_stables_balance = node1
-            .app_query()
-            .get_account_info(&service_owner, |a| a.stables_balance)
-            .unwrap();
+        let service_stables_balance = node1.get_stables_balance(service_owner);
         assert_eq!(
             service_stables_balance,
             &reward_pool
@@ -219,6 +219,7 @@ async fn test_invalid_chain_id() {
         nonce: 1,
         method: UpdateMethod::OptIn {},
         chain_id,
+        fee: None,
     };
     let digest = payload.to_digest();
     let signature = secret_key.sign(&digest);
@@ -298,8 +299,7 @@ fn quick_sort_mock_node_list() -> Vec<(NodeIndex, NodeInfo)> {
                 stake: Staking {
                     staked: i.into(),
                     stake_locked_until: 0,
-                    locked: HpUfixed::zero(),
-                    locked_until: 0,
+                    unbonding: Default::default(),
                 },
                 domain: [0, 0, 0, 0].into(),
                 worker_domain: [0, 0, 0, 0].into(),
@@ -0,0 +1,168 @@
+use fleek_crypto::{AccountOwnerSecretKey, EthAddress, SecretKey};
+use hp_fixed::unsigned::HpUfixed;
+use lightning_interfaces::types::{
+    ExecutionData,
+    ExecutionError,
+    Metadata,
+    ProtocolParamKey,
+    ProtocolParamValue,
+    Tokens,
+    UpdateMethod,
+    UpdateRequest,
+    Value,
+};
+use lightning_interfaces::SyncQueryRunnerInterface;
+use tempfile::tempdir;
+
+use super::utils::*;
+use crate::state::QueryRunner;
+
+fn get_total_supply(query_runner: &QueryRunner) -> HpUfixed<18> {
+    match query_runner.get_metadata(&Metadata::TotalSupply) {
+        Some(Value::HpUfixed(supply)) => supply,
+        _ => unreachable!("TotalSupply is set at genesis"),
+    }
+}
+
+fn get_protocol_fund(query_runner: &QueryRunner) -> EthAddress {
+    match query_runner.get_metadata(&Metadata::ProtocolFundAddress) {
+        Some(Value::AccountPublicKey(address)) => address,
+        _ => unreachable!("ProtocolFundAddress is set at genesis"),
+    }
+}
+
+fn prepare_transfer_with_fee(
+    amount: u64,
+    to: EthAddress,
+    secret_key: &AccountOwnerSecretKey,
+    nonce: u64,
+    fee: u64,
+) -> UpdateRequest {
+    prepare_update_request_account_with_fee(
+        UpdateMethod::Transfer {
+            amount: amount.into(),
+            token: Tokens::FLK,
+            to,
+        },
+        secret_key,
+        nonce,
+        Some(fee.into()),
+    )
+}
+
+#[tokio::test]
+async fn test_fee_is_burned() {
+    let temp_dir = tempdir().unwrap();
+
+    let (update_socket, query_runner) = init_app(&temp_dir, None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let recipient: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+
+    deposit(&update_socket, &owner_secret_key, 1, &1_000_u64.into()).await;
+    let supply = get_total_supply(&query_runner);
+    let protocol_fund = get_protocol_fund(&query_runner);
+    let protocol_fund_balance = get_flk_balance(&query_runner, &protocol_fund);
+
+    let update = prepare_transfer_with_fee(100, recipient, &owner_secret_key, 2, 10);
+    expect_tx_success(update, &update_socket, ExecutionData::None).await;
+
+    assert_eq!(get_flk_balance(&query_runner, &owner), 890_u64.into());
+    assert_eq!(get_flk_balance(&query_runner, &recipient), 100_u64.into());
+    // Without a protocol share the whole fee is burned.
+    assert_eq!(
+        get_flk_balance(&query_runner, &protocol_fund),
+        protocol_fund_balance
+    );
+    assert_eq!(
+        get_total_supply(&query_runner),
+        supply - HpUfixed::<18>::from(10_u64)
+    );
+}
+
+#[tokio::test]
+async fn test_fee_protocol_share() {
+    let temp_dir = tempdir().unwrap();
+
+    let governance_secret_key = AccountOwnerSecretKey::generate();
+    let mut genesis = test_genesis();
+    genesis.governance_address = governance_secret_key.to_pk().into();
+    let (update_socket, query_runner) = init_app_with_genesis(&temp_dir, &genesis);
+
+    let update = prepare_change_protocol_param_request(
+        &ProtocolParamKey::FeeProtocolShare,
+        &ProtocolParamValue::FeeProtocolShare(20),
+        &governance_secret_key,
+        1,
+    );
+    expect_tx_success(update, &update_socket, ExecutionData::None).await;
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let recipient: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+    deposit(&update_socket, &owner_secret_key, 1, &1_000_u64.into()).await;
+    let supply = get_total_supply(&query_runner);
+    let protocol_fund = get_protocol_fund(&query_runner);
+    let protocol_fund_balance = get_flk_balance(&query_runner, &protocol_fund);
+
+    let update = prepare_transfer_with_fee(100, recipient, &owner_secret_key, 2, 10);
+    expect_tx_success(update, &update_socket, ExecutionData::None).await;
+
+    // 20% of the fee goes to the protocol fund and the rest is burned.
+    assert_eq!(
+        get_flk_balance(&query_runner, &protocol_fund),
+        protocol_fund_balance + HpUfixed::<18>::from(2_u64)
+    );
+    assert_eq!(
+        get_total_supply(&query_runner),
+        supply - HpUfixed::<18>::from(8_u64)
+    );
+}
+
+#[tokio::test]
+async fn test_fee_is_kept_when_transaction_reverts() {
+    let temp_dir = tempdir().unwrap();
+
+    let (update_socket, query_runner) = init_app(&temp_dir, None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+
+    deposit(&update_socket, &owner_secret_key, 1, &1_000_u64.into()).await;
+
+    let update = prepare_transfer_with_fee(100, owner, &owner_secret_key, 2, 10);
+    expect_tx_revert(update, &update_socket, ExecutionError::CantSendToYourself).await;
+
+    assert_eq!(get_flk_balance(&query_runner, &owner), 990_u64.into());
+}
+
+#[tokio::test]
+async fn test_revert_insufficient_balance_for_fee() {
+    let temp_dir = tempdir().unwrap();
+
+    let (update_socket, query_runner) = init_app(&temp_dir, None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let recipient: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+
+    deposit(&update_socket, &owner_secret_key, 1, &10_u64.into()).await;
+    let supply = get_total_supply(&query_runner);
+
+    let update = prepare_transfer_with_fee(1, recipient, &owner_secret_key, 2, 11);
+    expect_tx_revert(
+        update,
+        &update_socket,
+        ExecutionError::InsufficientBalanceForFee,
+    )
+    .await;
+
+    assert_eq!(get_flk_balance(&query_runner, &owner), 10_u64.into());
+    assert_eq!(get_flk_balance(&query_runner, &recipient), 0_u64.into());
+    assert_eq!(get_total_supply(&query_runner), supply);
+
+    // The nonce is still used up.
+    let update = prepare_transfer_with_fee(1, recipient, &owner_secret_key, 3, 1);
+    expect_tx_success(update, &update_socket, ExecutionData::None).await;
+    assert_eq!(get_flk_balance(&query_runner, &owner), 8_u64.into());
+}
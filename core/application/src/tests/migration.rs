@@ -0,0 +1,294 @@
+use std::collections::BTreeMap;
+
+use atomo::batch::{Operation, VerticalBatch};
+use atomo::{AtomoBuilder, DefaultSerdeBackend, InMemoryStorage, SerdeBackend, StorageBackend};
+use fleek_crypto::{EthAddress, NodePublicKey};
+use hp_fixed::unsigned::HpUfixed;
+use lightning_interfaces::types::{
+    AccountInfo,
+    Committee,
+    CommodityTypes,
+    Epoch,
+    Metadata,
+    NodeIndex,
+    NodeInfo,
+    NodePorts,
+    NodeRegistryChange,
+    NodeRegistryChangeSlashReason,
+    Participation,
+    Service,
+    ServiceId,
+    ServiceResources,
+    TokenInfo,
+    Tokens,
+    UnbondingEntry,
+    UnbondingQueue,
+    Value,
+};
+
+use crate::state::migration::{self, v0, v1, v2, CURRENT_STATE_VERSION};
+
+fn legacy_staking(locked: u64, locked_until: Epoch) -> v0::Staking {
+    v0::Staking {
+        staked: 1_000u64.into(),
+        stake_locked_until: 0,
+        locked: locked.into(),
+        locked_until,
+    }
+}
+
+fn raw_insert<K: serde::Serialize, V: serde::Serialize>(
+    batch: &mut VerticalBatch,
+    table: usize,
+    key: &K,
+    value: &V,
+) {
+    batch.get_mut(table).insert(
+        DefaultSerdeBackend::serialize(key).into(),
+        Operation::Insert(DefaultSerdeBackend::serialize(value).into()),
+    );
+}
+
+#[test]
+fn test_migrate_unbonding_queues() {
+    let storage = InMemoryStorage::default();
+    let mut db = AtomoBuilder::<_, DefaultSerdeBackend>::new(storage.clone())
+        .with_table::<Metadata, Value>("metadata")
+        .with_table::<NodeIndex, NodeInfo>("node")
+        .with_table::<Epoch, Committee>("committee")
+        .with_table::<ServiceId, Service>("service")
+        .with_table::<EthAddress, AccountInfo>("account")
+        .with_table::<EthAddress, TokenInfo>("tokens")
+        .with_table::<(EthAddress, EthAddress), HpUfixed<18>>("token_balances")
+        .build()
+        .unwrap();
+
+    // Write the state the way it was stored before the unbonding queues were introduced.
+    let public_key = NodePublicKey([1; 32]);
+    let mut batch = VerticalBatch::new(7);
+    raw_insert(&mut batch, 0, &Metadata::Epoch, &Value::Epoch(1));
+    raw_insert(
+        &mut batch,
+        0,
+        &Metadata::NextNodeIndex,
+        &Value::NextNodeIndex(2),
+    );
+    for (index, locked) in [(0, 500), (1, 0)] {
+        let node = v0::NodeInfo {
+            owner: EthAddress([index as u8; 20]),
+            public_key,
+            consensus_key: [0; 96].into(),
+            staked_since: 0,
+            stake: legacy_staking(locked, 3),
+            domain: "127.0.0.1".parse().unwrap(),
+            worker_domain: "127.0.0.1".parse().unwrap(),
+            ports: NodePorts::default(),
+            worker_public_key: [0; 32].into(),
+            participation: Participation::True,
+            nonce: 0,
+        };
+        raw_insert(&mut batch, 1, &(index as NodeIndex), &node);
+    }
+    let committee = v0::Committee {
+        members: vec![0, 1],
+        ready_to_change: vec![],
+        epoch_end_timestamp: 0,
+        active_node_set: vec![0, 1],
+        node_registry_changes: BTreeMap::from([(
+            5,
+            vec![(
+                public_key,
+                v0::NodeRegistryChange::Slashed((
+                    100u64.into(),
+                    legacy_staking(200, 2),
+                    NodeRegistryChangeSlashReason::CommitteeBeaconNonReveal,
+                )),
+            )],
+        )]),
+    };
+    raw_insert(&mut batch, 2, &1u64, &committee);
+    storage.commit(batch);
+
+    assert!(db.run(migration::migrate));
+
+    let (nodes, committee, version) = db.query().run(|ctx| {
+        let node_table = ctx.get_table::<NodeIndex, NodeInfo>("node");
+        let committee_table = ctx.get_table::<Epoch, Committee>("committee");
+        let metadata_table = ctx.get_table::<Metadata, Value>("metadata");
+        (
+            [node_table.get(0).unwrap(), node_table.get(1).unwrap()],
+            committee_table.get(1).unwrap(),
+            metadata_table.get(Metadata::StateVersion),
+        )
+    });
+
+    // The locked stake becomes the single entry of the unbonding queue.
+    assert_eq!(nodes[0].owner, EthAddress([0; 20]));
+    assert_eq!(nodes[0].stake.staked, 1_000u64.into());
+    assert_eq!(
+        nodes[0].stake.unbonding,
+        UnbondingQueue(vec![UnbondingEntry {
+            amount: 500u64.into(),
+            locked_until: 3,
+        }])
+    );
+    assert!(nodes[1].stake.unbonding.is_empty());
+
+    // The stake recorded with the node registry changes is migrated as well.
+    let NodeRegistryChange::Slashed((amount, stake, _)) =
+        &committee.node_registry_changes.get(&5).unwrap()[0].1
+    else {
+        panic!("expected a slashed node registry change");
+    };
+    assert_eq!(*amount, 100u64.into());
+    assert_eq!(stake.locked(), 200u64.into());
+    assert_eq!(stake.unbonding.0[0].locked_until, 2);
+
+    // The state is marked as migrated, so the migration doesn't run again.
+    assert_eq!(version, Some(Value::StateVersion(CURRENT_STATE_VERSION)));
+    assert!(!db.run(migration::migrate));
+}
+
+#[test]
+fn test_migrate_services() {
+    let storage = InMemoryStorage::default();
+    let mut db = AtomoBuilder::<_, DefaultSerdeBackend>::new(storage.clone())
+        .with_table::<Metadata, Value>("metadata")
+        .with_table::<ServiceId, Service>("service")
+        .with_table::<EthAddress, AccountInfo>("account")
+        .with_table::<EthAddress, TokenInfo>("tokens")
+        .with_table::<(EthAddress, EthAddress), HpUfixed<18>>("token_balances")
+        .build()
+        .unwrap();
+
+    // Write the services the way they were stored at version 1.
+    let mut batch = VerticalBatch::new(5);
+    raw_insert(&mut batch, 0, &Metadata::Epoch, &Value::Epoch(1));
+    raw_insert(
+        &mut batch,
+        0,
+        &Metadata::StateVersion,
+        &Value::StateVersion(1),
+    );
+    for (id, commodity_type) in [(0, CommodityTypes::Bandwidth), (1, CommodityTypes::Compute)] {
+        let service = v1::Service {
+            owner: EthAddress([id as u8; 20]),
+            commodity_type,
+            slashing: (),
+        };
+        raw_insert(&mut batch, 1, &(id as ServiceId), &service);
+    }
+    storage.commit(batch);
+
+    assert!(db.run(migration::migrate));
+
+    let services = db.query().run(|ctx| {
+        let service_table = ctx.get_table::<ServiceId, Service>("service");
+        [service_table.get(0).unwrap(), service_table.get(1).unwrap()]
+    });
+
+    // The existing services are built into the node.
+    assert_eq!(
+        services[1],
+        Service {
+            owner: EthAddress([1; 20]),
+            commodity_type: CommodityTypes::Compute,
+            slashing: (),
+            binary: None,
+            version: 0,
+            resources: ServiceResources::default(),
+        }
+    );
+    assert_eq!(services[0].commodity_type, CommodityTypes::Bandwidth);
+    assert!(!db.run(migration::migrate));
+}
+
+#[test]
+fn test_migrate_token_balances() {
+    let storage = InMemoryStorage::default();
+    let mut db = AtomoBuilder::<_, DefaultSerdeBackend>::new(storage.clone())
+        .with_table::<Metadata, Value>("metadata")
+        .with_table::<EthAddress, AccountInfo>("account")
+        .with_table::<EthAddress, TokenInfo>("tokens")
+        .with_table::<(EthAddress, EthAddress), HpUfixed<18>>("token_balances")
+        .build()
+        .unwrap();
+
+    // Write the accounts the way they were stored at version 2.
+    let mut batch = VerticalBatch::new(4);
+    raw_insert(&mut batch, 0, &Metadata::Epoch, &Value::Epoch(1));
+    raw_insert(
+        &mut batch,
+        0,
+        &Metadata::StateVersion,
+        &Value::StateVersion(2),
+    );
+    for (index, stables_balance) in [(0u8, 1.5), (1, 0.0)] {
+        let account = v2::AccountInfo {
+            flk_balance: 10u64.into(),
+            stables_balance: stables_balance.into(),
+            bandwidth_balance: 20,
+            nonce: 3,
+            client_key: None,
+        };
+        raw_insert(&mut batch, 1, &EthAddress([index; 20]), &account);
+    }
+    storage.commit(batch);
+
+    assert!(db.run(migration::migrate));
+
+    let (accounts, balances, tokens) = db.query().run(|ctx| {
+        let account_table = ctx.get_table::<EthAddress, AccountInfo>("account");
+        let token_balances_table =
+            ctx.get_table::<(EthAddress, EthAddress), HpUfixed<18>>("token_balances");
+        let tokens_table = ctx.get_table::<EthAddress, TokenInfo>("tokens");
+        (
+            [
+                account_table.get(EthAddress([0; 20])).unwrap(),
+                account_table.get(EthAddress([1; 20])).unwrap(),
+            ],
+            [
+                token_balances_table.get((EthAddress([0; 20]), Tokens::USDC.address())),
+                token_balances_table.get((EthAddress([1; 20]), Tokens::USDC.address())),
+            ],
+            [
+                tokens_table.get(Tokens::USDC.address()),
+                tokens_table.get(Tokens::FLK.address()),
+            ],
+        )
+    });
+
+    // The stables balance moves to the token balances table, the rest of the account is kept.
+    assert_eq!(
+        accounts[0],
+        AccountInfo {
+            flk_balance: 10u64.into(),
+            bandwidth_balance: 20,
+            nonce: 3,
+            client_key: None,
+        }
+    );
+    assert_eq!(accounts[1].nonce, 3);
+    assert_eq!(balances[0], Some(1.5.into()));
+    assert_eq!(balances[1], None);
+
+    // The built-in tokens are registered.
+    assert_eq!(tokens[0].as_ref().unwrap().decimals, 6);
+    assert_eq!(tokens[1].as_ref().unwrap().symbol, "FLK");
+    assert!(!db.run(migration::migrate));
+}
+
+#[test]
+fn test_migrate_skips_empty_state() {
+    let mut db = AtomoBuilder::<_, DefaultSerdeBackend>::new(InMemoryStorage::default())
+        .with_table::<Metadata, Value>("metadata")
+        .build()
+        .unwrap();
+
+    assert!(!db.run(migration::migrate));
+    assert!(db.query().run(|ctx| {
+        ctx.get_table::<Metadata, Value>("metadata")
+            .get(Metadata::StateVersion)
+            .is_none()
+    }));
+}
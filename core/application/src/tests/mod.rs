@@ -5,15 +5,21 @@
 use crate::Application;
 
 mod balances;
+mod batch;
 mod committee_beacon;
 mod content_registry;
+mod delegation;
 mod epoch_change;
 mod everything_else;
+mod fees;
 mod genesis;
+mod migration;
+mod node_info;
 mod participation;
 mod pod;
 mod protocol_params;
 mod reputation;
+mod service;
 mod staking;
 mod utils;
 
@@ -0,0 +1,388 @@
+use std::net::IpAddr;
+
+use fleek_crypto::{
+    AccountOwnerSecretKey,
+    ConsensusPublicKey,
+    ConsensusSecretKey,
+    NodeSecretKey,
+    SecretKey,
+};
+use lightning_interfaces::types::{
+    CommitteeSelectionBeaconCommit,
+    Event,
+    ExecutionData,
+    ExecutionError,
+    NodeIndex,
+    NodeInfoUpdate,
+    NodePorts,
+    TransactionResponse,
+    UpdateMethod,
+};
+use lightning_interfaces::{KeystoreInterface, SyncQueryRunnerInterface};
+use lightning_utils::application::QueryRunnerExt;
+use tempfile::tempdir;
+
+use super::utils::{
+    self,
+    deposit_and_stake,
+    expect_tx_revert,
+    expect_tx_success,
+    get_node_index,
+    init_app,
+    prepare_update_node_info_update,
+    prepare_update_request_node,
+};
+
+fn new_ports() -> NodePorts {
+    NodePorts {
+        primary: 5310,
+        worker: 5311,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_update_node_info_as_owner() {
+    let temp_dir = tempdir().unwrap();
+
+    let (update_socket, query_runner) = init_app(&temp_dir, None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let node_pub_key = NodeSecretKey::generate().to_pk();
+    deposit_and_stake(
+        &update_socket,
+        &owner_secret_key,
+        1,
+        &1_000u64.into(),
+        &node_pub_key,
+        [0; 96].into(),
+    )
+    .await;
+    let node_index = get_node_index(&query_runner, &node_pub_key);
+
+    let consensus_key = ConsensusSecretKey::generate().to_pk();
+    let update = NodeInfoUpdate {
+        consensus_key: Some(consensus_key),
+        domain: Some("10.0.0.1".parse().unwrap()),
+        ports: Some(new_ports()),
+        ..Default::default()
+    };
+    let resp = expect_tx_success(
+        prepare_update_node_info_update(&node_pub_key, update.clone(), &owner_secret_key, 3),
+        &update_socket,
+        ExecutionData::None,
+    )
+    .await;
+    assert_eq!(
+        resp.txn_receipts[0].events,
+        vec![Event::node_info_updated(node_pub_key, update)]
+    );
+
+    // The node isn't a committee member, so the update is applied immediately.
+    let node = query_runner.get_node_info(&node_index, |n| n).unwrap();
+    assert_eq!(node.consensus_key, consensus_key);
+    assert_eq!(node.domain, "10.0.0.1".parse::<IpAddr>().unwrap());
+    assert_eq!(node.worker_domain, "127.0.0.1".parse::<IpAddr>().unwrap());
+    assert_eq!(node.ports, new_ports());
+    assert!(
+        query_runner
+            .get_pending_node_info_update(&node_index)
+            .is_none()
+    );
+
+    // The consensus key index follows the new key.
+    query_runner.run(|ctx| {
+        let table = ctx.get_table::<ConsensusPublicKey, NodeIndex>("consensus_key_to_index");
+        assert_eq!(table.get(consensus_key), Some(node_index));
+        assert_eq!(table.get(ConsensusPublicKey::from([0; 96])), None);
+    });
+}
+
+#[tokio::test]
+async fn test_update_node_info_as_node() {
+    let temp_dir = tempdir().unwrap();
+
+    let (update_socket, query_runner) = init_app(&temp_dir, None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let node_secret_key = NodeSecretKey::generate();
+    let node_pub_key = node_secret_key.to_pk();
+    deposit_and_stake(
+        &update_socket,
+        &owner_secret_key,
+        1,
+        &1_000u64.into(),
+        &node_pub_key,
+        [0; 96].into(),
+    )
+    .await;
+    let node_index = get_node_index(&query_runner, &node_pub_key);
+
+    let worker_public_key = NodeSecretKey::generate().to_pk();
+    let update = prepare_update_request_node(
+        UpdateMethod::UpdateNodeInfo {
+            node: node_pub_key,
+            update: NodeInfoUpdate {
+                worker_public_key: Some(worker_public_key),
+                worker_domain: Some("10.0.0.2".parse().unwrap()),
+                ..Default::default()
+            },
+        },
+        &node_secret_key,
+        1,
+    );
+    expect_tx_success(update, &update_socket, ExecutionData::None).await;
+
+    let node = query_runner.get_node_info(&node_index, |n| n).unwrap();
+    assert_eq!(node.worker_public_key, worker_public_key);
+    assert_eq!(node.worker_domain, "10.0.0.2".parse::<IpAddr>().unwrap());
+    assert_eq!(node.domain, "127.0.0.1".parse::<IpAddr>().unwrap());
+}
+
+#[tokio::test]
+async fn test_update_node_info_reverts() {
+    let temp_dir = tempdir().unwrap();
+
+    let (update_socket, _query_runner) = init_app(&temp_dir, None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let node_secret_key = NodeSecretKey::generate();
+    let node_pub_key = node_secret_key.to_pk();
+    let other_owner_secret_key = AccountOwnerSecretKey::generate();
+    let other_node_secret_key = NodeSecretKey::generate();
+    let other_consensus_key = ConsensusSecretKey::generate().to_pk();
+    let update = NodeInfoUpdate {
+        domain: Some("10.0.0.1".parse().unwrap()),
+        ..Default::default()
+    };
+
+    expect_tx_revert(
+        prepare_update_node_info_update(&node_pub_key, update.clone(), &owner_secret_key, 1),
+        &update_socket,
+        ExecutionError::NodeDoesNotExist,
+    )
+    .await;
+
+    deposit_and_stake(
+        &update_socket,
+        &owner_secret_key,
+        2,
+        &1_000u64.into(),
+        &node_pub_key,
+        [0; 96].into(),
+    )
+    .await;
+    deposit_and_stake(
+        &update_socket,
+        &other_owner_secret_key,
+        1,
+        &1_000u64.into(),
+        &other_node_secret_key.to_pk(),
+        other_consensus_key,
+    )
+    .await;
+
+    // Only the owner of the node or the node itself can update it.
+    expect_tx_revert(
+        prepare_update_node_info_update(&node_pub_key, update.clone(), &other_owner_secret_key, 3),
+        &update_socket,
+        ExecutionError::NotNodeOwner,
+    )
+    .await;
+    expect_tx_revert(
+        prepare_update_request_node(
+            UpdateMethod::UpdateNodeInfo {
+                node: node_pub_key,
+                update,
+            },
+            &other_node_secret_key,
+            1,
+        ),
+        &update_socket,
+        ExecutionError::NotNodeOwner,
+    )
+    .await;
+
+    // The update must change something.
+    expect_tx_revert(
+        prepare_update_node_info_update(
+            &node_pub_key,
+            NodeInfoUpdate::default(),
+            &owner_secret_key,
+            4,
+        ),
+        &update_socket,
+        ExecutionError::InsufficientNodeDetails,
+    )
+    .await;
+
+    // The consensus key of another node can't be taken.
+    expect_tx_revert(
+        prepare_update_node_info_update(
+            &node_pub_key,
+            NodeInfoUpdate {
+                consensus_key: Some(other_consensus_key),
+                ..Default::default()
+            },
+            &owner_secret_key,
+            5,
+        ),
+        &update_socket,
+        ExecutionError::ConsensusKeyAlreadyIndexed,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_update_node_info_of_committee_member_takes_effect_at_next_epoch() {
+    let network = utils::TestNetwork::builder()
+        .with_committee_nodes(4)
+        .build()
+        .await
+        .unwrap();
+    let query = network.query();
+    let epoch = query.get_current_epoch();
+    let node = network.node(0);
+    let node_pub_key = node.keystore.get_ed25519_pk();
+    let node_index = query.pubkey_to_index(&node_pub_key).unwrap();
+    let domain = query.get_node_info(&node_index, |n| n.domain).unwrap();
+
+    // Submit two updates, the later one takes precedence for the fields set in both.
+    let resp = network
+        .execute(vec![
+            node.build_transaction(UpdateMethod::UpdateNodeInfo {
+                node: node_pub_key,
+                update: NodeInfoUpdate {
+                    domain: Some("10.0.0.1".parse().unwrap()),
+                    ports: Some(NodePorts::default()),
+                    ..Default::default()
+                },
+            }),
+            node.build_transaction(UpdateMethod::UpdateNodeInfo {
+                node: node_pub_key,
+                update: NodeInfoUpdate {
+                    ports: Some(new_ports()),
+                    ..Default::default()
+                },
+            }),
+        ])
+        .await
+        .unwrap();
+
+    // The update is pending until the end of the epoch, and isn't reported until then.
+    let update = NodeInfoUpdate {
+        domain: Some("10.0.0.1".parse().unwrap()),
+        ports: Some(new_ports()),
+        ..Default::default()
+    };
+    assert!(resp.txn_receipts.iter().all(|r| r.events.is_empty()));
+    assert_eq!(
+        query.get_pending_node_info_update(&node_index),
+        Some(update.clone())
+    );
+    assert_eq!(
+        query.get_node_info(&node_index, |n| n.domain).unwrap(),
+        domain
+    );
+
+    // Change the epoch.
+    network.execute_change_epoch(epoch).await.unwrap();
+    network
+        .execute(
+            network
+                .nodes
+                .iter()
+                .enumerate()
+                .map(|(i, n)| {
+                    n.build_transaction(UpdateMethod::CommitteeSelectionBeaconCommit {
+                        commit: CommitteeSelectionBeaconCommit::build(epoch, 0, [i as u8; 32]),
+                    })
+                })
+                .collect(),
+        )
+        .await
+        .unwrap();
+    let resp = network
+        .execute(
+            network
+                .nodes
+                .iter()
+                .enumerate()
+                .map(|(i, n)| {
+                    n.build_transaction(UpdateMethod::CommitteeSelectionBeaconReveal {
+                        reveal: [i as u8; 32],
+                    })
+                })
+                .collect(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(query.get_current_epoch(), epoch + 1);
+
+    // The update is now applied.
+    let info = query.get_node_info(&node_index, |n| n).unwrap();
+    assert_eq!(info.domain, "10.0.0.1".parse::<IpAddr>().unwrap());
+    assert_eq!(info.ports, new_ports());
+    assert!(query.get_pending_node_info_update(&node_index).is_none());
+
+    // The update is reported by the transaction that ended the epoch.
+    let events = resp
+        .txn_receipts
+        .iter()
+        .flat_map(|r| r.events.iter().cloned())
+        .collect::<Vec<_>>();
+    assert_eq!(events, vec![Event::node_info_updated(node_pub_key, update)]);
+
+    // Shutdown the network.
+    network.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_update_node_info_rejects_consensus_key_of_pending_update() {
+    let network = utils::TestNetwork::builder()
+        .with_committee_nodes(4)
+        .build()
+        .await
+        .unwrap();
+    let query = network.query();
+    let node = network.node(0);
+    let node_pub_key = node.keystore.get_ed25519_pk();
+    let node_index = query.pubkey_to_index(&node_pub_key).unwrap();
+    let other_node = network.node(1);
+    let other_node_pub_key = other_node.keystore.get_ed25519_pk();
+
+    // The update of a committee member is deferred, but reserves the consensus key.
+    let consensus_key = ConsensusSecretKey::generate().to_pk();
+    network
+        .execute(vec![node.build_transaction(UpdateMethod::UpdateNodeInfo {
+            node: node_pub_key,
+            update: NodeInfoUpdate {
+                consensus_key: Some(consensus_key),
+                ..Default::default()
+            },
+        })])
+        .await
+        .unwrap();
+    assert!(query.get_pending_node_info_update(&node_index).is_some());
+
+    // Another node can't claim the reserved consensus key.
+    let resp = network
+        .maybe_execute(vec![other_node.build_transaction(
+            UpdateMethod::UpdateNodeInfo {
+                node: other_node_pub_key,
+                update: NodeInfoUpdate {
+                    consensus_key: Some(consensus_key),
+                    ..Default::default()
+                },
+            },
+        )])
+        .await
+        .unwrap();
+    assert_eq!(
+        resp.txn_receipts[0].response,
+        TransactionResponse::Revert(ExecutionError::ConsensusKeyAlreadyIndexed)
+    );
+
+    // Shutdown the network.
+    network.shutdown().await;
+}
@@ -0,0 +1,173 @@
+use fleek_crypto::{AccountOwnerSecretKey, EthAddress, SecretKey};
+use lightning_interfaces::types::{
+    CommodityTypes,
+    ExecutionData,
+    ExecutionError,
+    Service,
+    ServiceResources,
+    UpdateMethod,
+};
+use lightning_interfaces::SyncQueryRunnerInterface;
+use tempfile::tempdir;
+
+use super::utils::*;
+
+fn new_service(owner: EthAddress) -> Service {
+    Service {
+        owner,
+        commodity_type: CommodityTypes::Compute,
+        slashing: (),
+        binary: None,
+        version: 0,
+        resources: ServiceResources::default(),
+    }
+}
+
+fn upgrade_method(service_id: u32, version: u32) -> UpdateMethod {
+    UpdateMethod::UpgradeService {
+        service_id,
+        binary: [version as u8; 32],
+        version,
+        resources: ServiceResources {
+            cpu_cores: 2,
+            memory_mb: 512,
+        },
+    }
+}
+
+#[tokio::test]
+async fn test_add_service() {
+    let temp_dir = tempdir().unwrap();
+
+    let governance_secret_key = AccountOwnerSecretKey::generate();
+    let mut genesis = test_genesis();
+    genesis.governance_address = governance_secret_key.to_pk().into();
+
+    let (update_socket, query_runner) = init_app_with_genesis(&temp_dir, &genesis);
+
+    let owner: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+    let update = prepare_update_request_account(
+        UpdateMethod::AddService {
+            service: new_service(owner),
+            service_id: 7,
+        },
+        &governance_secret_key,
+        1,
+    );
+    expect_tx_success(update, &update_socket, ExecutionData::None).await;
+    assert_eq!(query_runner.get_service_info(&7), Some(new_service(owner)));
+
+    // The service id can't be taken twice.
+    let update = prepare_update_request_account(
+        UpdateMethod::AddService {
+            service: new_service(owner),
+            service_id: 7,
+        },
+        &governance_secret_key,
+        2,
+    );
+    expect_tx_revert(update, &update_socket, ExecutionError::ServiceAlreadyExists).await;
+
+    // Only the governance can add services.
+    let some_secret_key = AccountOwnerSecretKey::generate();
+    let update = prepare_update_request_account(
+        UpdateMethod::AddService {
+            service: new_service(owner),
+            service_id: 8,
+        },
+        &some_secret_key,
+        1,
+    );
+    expect_tx_revert(update, &update_socket, ExecutionError::OnlyGovernance).await;
+    assert_eq!(query_runner.get_service_info(&8), None);
+}
+
+#[tokio::test]
+async fn test_upgrade_service() {
+    let temp_dir = tempdir().unwrap();
+
+    let governance_secret_key = AccountOwnerSecretKey::generate();
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let mut genesis = test_genesis();
+    genesis.governance_address = governance_secret_key.to_pk().into();
+
+    let (update_socket, query_runner) = init_app_with_genesis(&temp_dir, &genesis);
+
+    let update = prepare_update_request_account(
+        UpdateMethod::AddService {
+            service: new_service(owner),
+            service_id: 7,
+        },
+        &governance_secret_key,
+        1,
+    );
+    expect_tx_success(update, &update_socket, ExecutionData::None).await;
+
+    // The owner of the service can upgrade it.
+    let update = prepare_update_request_account(upgrade_method(7, 1), &owner_secret_key, 1);
+    expect_tx_success(update, &update_socket, ExecutionData::None).await;
+
+    let service = query_runner.get_service_info(&7).unwrap();
+    assert_eq!(service.binary, Some([1; 32]));
+    assert_eq!(service.version, 1);
+    assert_eq!(
+        service.resources,
+        ServiceResources {
+            cpu_cores: 2,
+            memory_mb: 512,
+        }
+    );
+
+    // So can the governance.
+    let update = prepare_update_request_account(upgrade_method(7, 3), &governance_secret_key, 2);
+    expect_tx_success(update, &update_socket, ExecutionData::None).await;
+    assert_eq!(query_runner.get_service_info(&7).unwrap().version, 3);
+}
+
+#[tokio::test]
+async fn test_upgrade_service_reverts() {
+    let temp_dir = tempdir().unwrap();
+
+    let governance_secret_key = AccountOwnerSecretKey::generate();
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let mut genesis = test_genesis();
+    genesis.governance_address = governance_secret_key.to_pk().into();
+
+    let (update_socket, query_runner) = init_app_with_genesis(&temp_dir, &genesis);
+
+    let update = prepare_update_request_account(upgrade_method(7, 1), &owner_secret_key, 1);
+    expect_tx_revert(update, &update_socket, ExecutionError::NonExistingService).await;
+
+    let update = prepare_update_request_account(
+        UpdateMethod::AddService {
+            service: new_service(owner),
+            service_id: 7,
+        },
+        &governance_secret_key,
+        1,
+    );
+    expect_tx_success(update, &update_socket, ExecutionData::None).await;
+
+    // Only the owner of the service or the governance can upgrade it.
+    let some_secret_key = AccountOwnerSecretKey::generate();
+    let update = prepare_update_request_account(upgrade_method(7, 1), &some_secret_key, 1);
+    expect_tx_revert(update, &update_socket, ExecutionError::OnlyAccountOwner).await;
+
+    let update = prepare_update_request_account(upgrade_method(7, 2), &owner_secret_key, 2);
+    expect_tx_success(update, &update_socket, ExecutionData::None).await;
+
+    // The version must increase.
+    let update = prepare_update_request_account(upgrade_method(7, 2), &owner_secret_key, 3);
+    expect_tx_revert(
+        update,
+        &update_socket,
+        ExecutionError::InvalidServiceVersion,
+    )
+    .await;
+    assert_eq!(
+        query_runner.get_service_info(&7).unwrap().binary,
+        Some([2; 32])
+    );
+}
@@ -19,6 +19,8 @@
     NodePorts,
     NodeRegistryChange,
     Participation,
+    TransactionResponse,
+    UnbondingEntry,
     UpdateMethod,
 };
 use lightning_interfaces::{KeystoreInterface, SyncQueryRunnerInterface};
@@ -43,6 +45,7 @@
     get_node_info,
     get_stake_locked_until,
     get_staked,
+    get_unbonding,
     init_app,
     prepare_deposit_update,
     prepare_initial_stake_update,
@@ -848,7 +851,7 @@ async fn test_withdraw_unstaked_works_properly() {
                     .app_query()
                     .pubkey_to_index(&node.get_node_public_key())
                     .unwrap(),
-                |n| n.stake.locked
+                |n| n.stake.locked()
             )
             .unwrap(),
         HpUfixed::zero()
@@ -858,6 +861,129 @@ async fn test_withdraw_unstaked_works_properly() {
     network.shutdown().await;
 }
 
+#[tokio::test]
+async fn test_multiple_unstakes_are_unbonded_separately() {
+    let network = utils::TestNetwork::builder()
+        .with_committee_nodes(4)
+        .with_min_stake(500)
+        .with_stake_lock_time(1)
+        .build()
+        .await
+        .unwrap();
+    let query = network.query();
+    let epoch = query.get_current_epoch();
+    let node = network.node(0);
+    let node_public_key = node.keystore.get_ed25519_pk();
+    let recipient: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+
+    // Unstake some of the stake, it is locked until the next epoch.
+    network
+        .execute(vec![node.build_transasction_as_owner(
+            UpdateMethod::Unstake {
+                amount: 200u64.into(),
+                node: node_public_key,
+            },
+            1,
+        )])
+        .await
+        .unwrap();
+
+    // The withdrawal reverts while the only entry is still locked.
+    let resp = network
+        .maybe_execute(vec![node.build_transasction_as_owner(
+            UpdateMethod::WithdrawUnstaked {
+                node: node_public_key,
+                recipient: Some(recipient),
+            },
+            2,
+        )])
+        .await
+        .unwrap();
+    assert_eq!(
+        resp.txn_receipts[0].response,
+        TransactionResponse::Revert(ExecutionError::TokensLocked)
+    );
+
+    // Change the epoch, so that the first unstake is unlocked.
+    network.execute_change_epoch(epoch).await.unwrap();
+    network
+        .execute(
+            network
+                .nodes
+                .iter()
+                .enumerate()
+                .map(|(i, n)| {
+                    n.build_transaction(UpdateMethod::CommitteeSelectionBeaconCommit {
+                        commit: CommitteeSelectionBeaconCommit::build(epoch, 0, [i as u8; 32]),
+                    })
+                })
+                .collect(),
+        )
+        .await
+        .unwrap();
+    network
+        .execute(
+            network
+                .nodes
+                .iter()
+                .enumerate()
+                .map(|(i, n)| {
+                    n.build_transaction(UpdateMethod::CommitteeSelectionBeaconReveal {
+                        reveal: [i as u8; 32],
+                    })
+                })
+                .collect(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(query.get_current_epoch(), epoch + 1);
+
+    // Unstake again, this doesn't extend the lock of the first unstake.
+    network
+        .execute(vec![node.build_transasction_as_owner(
+            UpdateMethod::Unstake {
+                amount: 300u64.into(),
+                node: node_public_key,
+            },
+            3,
+        )])
+        .await
+        .unwrap();
+    assert_eq!(
+        get_unbonding(&query, &node_public_key),
+        vec![
+            UnbondingEntry {
+                amount: 200u64.into(),
+                locked_until: epoch + 1,
+            },
+            UnbondingEntry {
+                amount: 300u64.into(),
+                locked_until: epoch + 2,
+            },
+        ]
+    );
+
+    // Only the unlocked entry is withdrawn.
+    network
+        .execute(vec![node.build_transasction_as_owner(
+            UpdateMethod::WithdrawUnstaked {
+                node: node_public_key,
+                recipient: Some(recipient),
+            },
+            4,
+        )])
+        .await
+        .unwrap();
+    assert_eq!(get_flk_balance(&query, &recipient), 200u64.into());
+    assert_eq!(
+        get_unbonding(&query, &node_public_key),
+        vec![UnbondingEntry {
+            amount: 300u64.into(),
+            locked_until: epoch + 2,
+        }]
+    );
+}
+
 #[tokio::test]
 async fn test_unstake_as_non_committee_node_opts_out_node_and_removes_after_epoch_change() {
     let network = utils::TestNetwork::builder()
@@ -872,7 +998,7 @@ async fn test_unstake_as_non_committee_node_opts_out_node_and_removes_after_epoc
     // Check the initial stake.
     let stake = query.get_node_info(&4, |n| n.stake).unwrap();
     assert_eq!(stake.staked, 1000u64.into());
-    assert_eq!(stake.locked, 0u64.into());
+    assert_eq!(stake.locked(), 0u64.into());
 
     // Execute unstake transaction from the first node.
     let resp = network
@@ -890,7 +1016,7 @@ async fn test_unstake_as_non_committee_node_opts_out_node_and_removes_after_epoc
     // Check that the stake is now locked.
     let stake = query.get_node_info(&4, |n| n.stake).unwrap();
     assert_eq!(stake.staked, 0u64.into());
-    assert_eq!(stake.locked, 1000u64.into());
+    assert_eq!(stake.locked(), 1000u64.into());
 
     // Execute epoch change transactions.
     let resp = network.execute_change_epoch(epoch).await.unwrap();
@@ -953,7 +1079,7 @@ async fn test_unstake_as_committee_node_opts_out_node_and_removes_after_epoch_ch
     // Check the initial stake.
     let stake = query.get_node_info(&4, |n| n.stake).unwrap();
     assert_eq!(stake.staked, 1000u64.into());
-    assert_eq!(stake.locked, 0u64.into());
+    assert_eq!(stake.locked(), 0u64.into());
 
     // Execute unstake transaction from the first node.
     let resp = network
@@ -971,7 +1097,7 @@ async fn test_unstake_as_committee_node_opts_out_node_and_removes_after_epoch_ch
     // Check that the stake is now locked.
     let stake = query.get_node_info(&4, |n| n.stake).unwrap();
     assert_eq!(stake.staked, 0u64.into());
-    assert_eq!(stake.locked, 1000u64.into());
+    assert_eq!(stake.locked(), 1000u64.into());
 
     // Execute epoch change transactions from participating nodes.
     let resp = network
@@ -52,6 +52,7 @@
     GenesisAccount,
     NodeIndex,
     NodeInfo,
+    NodeInfoUpdate,
     Participation,
     ProofOfConsensus,
     ProtocolParamKey,
@@ -59,6 +60,7 @@
     Tokens,
     TransactionRequest,
     TransactionResponse,
+    UnbondingEntry,
     UpdateMethod,
     UpdatePayload,
     UpdateRequest,
@@ -334,8 +336,7 @@ pub(crate) fn test_genesis() -> Genesis {
     let test_staking = Staking {
         staked: HpUfixed::<18>::from(1000u32),
         stake_locked_until: 0,
-        locked: HpUfixed::<18>::zero(),
-        locked_until: 0,
+        unbonding: Default::default(),
     };
 
     let genesis_nodes: Vec<GenesisNode> = vec![
@@ -393,11 +394,17 @@ pub(crate) fn test_genesis() -> Genesis {
                 id: 0,
                 owner: EthAddress::from_str("0xDC0A31F9eeb151f82BF1eE6831095284fC215Ee7").unwrap(),
                 commodity_type: CommodityTypes::Bandwidth,
+                binary: None,
+                version: 0,
+                resources: Default::default(),
             },
             GenesisService {
                 id: 1,
                 owner: EthAddress::from_str("0x684166BDbf530a256d7c92Fa0a4128669aFd9B9F").unwrap(),
                 commodity_type: CommodityTypes::Compute,
+                binary: None,
+                version: 0,
+                resources: Default::default(),
             },
         ],
         account: vec![GenesisAccount {
@@ -566,6 +573,7 @@ pub(crate) fn prepare_update_request_node(
         nonce,
         method,
         chain_id: CHAIN_ID,
+        fee: None,
     };
     let digest = payload.to_digest();
     let signature = secret_key.sign(&digest);
@@ -587,6 +595,7 @@ pub(crate) fn prepare_update_request_consensus(
         nonce,
         method,
         chain_id: CHAIN_ID,
+        fee: None,
     };
     let digest = payload.to_digest();
     let signature = secret_key.sign(&digest);
@@ -602,12 +611,25 @@ pub(crate) fn prepare_update_request_account(
     method: UpdateMethod,
     secret_key: &AccountOwnerSecretKey,
     nonce: u64,
+) -> UpdateRequest {
+    prepare_update_request_account_with_fee(method, secret_key, nonce, None)
+}
+
+/// Prepare an `UpdateRequest` from an `UpdateMethod` that pays the given fee, signed with
+/// `AccountOwnerSecretKey`. Passing the private key around like this should only be done for
+/// testing.
+pub(crate) fn prepare_update_request_account_with_fee(
+    method: UpdateMethod,
+    secret_key: &AccountOwnerSecretKey,
+    nonce: u64,
+    fee: Option<HpUfixed<18>>,
 ) -> UpdateRequest {
     let payload = UpdatePayload {
         sender: secret_key.to_pk().into(),
         nonce,
         method,
         chain_id: CHAIN_ID,
+        fee,
     };
     let digest = payload.to_digest();
     let signature = secret_key.sign(&digest);
@@ -743,6 +765,99 @@ pub(crate) fn prepare_stake_lock_update(
     )
 }
 
+/// Prepare an `UpdateRequest` for `UpdateMethod::Delegate` signed with `AccountOwnerSecretKey`.
+/// Passing the private key around like this should only be done for testing.
+pub(crate) fn prepare_delegate_update(
+    amount: &HpUfixed<18>,
+    node_public_key: &NodePublicKey,
+    secret_key: &AccountOwnerSecretKey,
+    nonce: u64,
+) -> UpdateRequest {
+    prepare_update_request_account(
+        UpdateMethod::Delegate {
+            node: *node_public_key,
+            amount: amount.clone(),
+        },
+        secret_key,
+        nonce,
+    )
+}
+
+/// Prepare an `UpdateRequest` for `UpdateMethod::Undelegate` signed with `AccountOwnerSecretKey`.
+/// Passing the private key around like this should only be done for testing.
+pub(crate) fn prepare_undelegate_update(
+    amount: &HpUfixed<18>,
+    node_public_key: &NodePublicKey,
+    secret_key: &AccountOwnerSecretKey,
+    nonce: u64,
+) -> UpdateRequest {
+    prepare_update_request_account(
+        UpdateMethod::Undelegate {
+            node: *node_public_key,
+            amount: amount.clone(),
+        },
+        secret_key,
+        nonce,
+    )
+}
+
+/// Prepare an `UpdateRequest` for `UpdateMethod::WithdrawUndelegated` signed with
+/// `AccountOwnerSecretKey`. Passing the private key around like this should only be done for
+/// testing.
+pub(crate) fn prepare_withdraw_undelegated_update(
+    node_public_key: &NodePublicKey,
+    recipient: Option<EthAddress>,
+    secret_key: &AccountOwnerSecretKey,
+    nonce: u64,
+) -> UpdateRequest {
+    prepare_update_request_account(
+        UpdateMethod::WithdrawUndelegated {
+            node: *node_public_key,
+            recipient,
+        },
+        secret_key,
+        nonce,
+    )
+}
+
+/// Prepare an `UpdateRequest` for `UpdateMethod::SetCommissionRate` signed with
+/// `AccountOwnerSecretKey`. Passing the private key around like this should only be done for
+/// testing.
+pub(crate) fn prepare_set_commission_rate_update(
+    node_public_key: &NodePublicKey,
+    commission: u8,
+    secret_key: &AccountOwnerSecretKey,
+    nonce: u64,
+) -> UpdateRequest {
+    prepare_update_request_account(
+        UpdateMethod::SetCommissionRate {
+            node: *node_public_key,
+            commission,
+        },
+        secret_key,
+        nonce,
+    )
+}
+
+/// Prepare an `UpdateRequest` for `UpdateMethod::UpdateNodeInfo` signed with
+/// `AccountOwnerSecretKey`. Passing the private key around like this should only be done for
+/// testing.
+pub(crate) fn prepare_update_node_info_update(
+    node_public_key: &NodePublicKey,
+    update: NodeInfoUpdate,
+    secret_key: &AccountOwnerSecretKey,
+    nonce: u64,
+) -> UpdateRequest {
+    prepare_update_request_account(
+        UpdateMethod::UpdateNodeInfo {
+            node: *node_public_key,
+            update,
+        },
+        secret_key,
+        nonce,
+    )
+}
+
 /// Prepare an `UpdateRequest` for `UpdateMethod::SubmitDeliveryAcknowledgmentAggregation` signed
 /// with `NodeSecretKey`. Passing the private key around like this should only be done for testing.
 pub(crate) fn prepare_pod_request(
@@ -902,12 +1017,27 @@ pub(crate) fn get_staked(query_runner: &QueryRunner, pub_key: &NodePublicKey) ->
 
 /// Query Node's Locked amount
 pub(crate) fn get_locked(query_runner: &QueryRunner, pub_key: &NodePublicKey) -> HpUfixed<18> {
-    do_get_node_info::<HpUfixed<18>>(query_runner, pub_key, |n| n.stake.locked)
+    do_get_node_info::<HpUfixed<18>>(query_runner, pub_key, |n| n.stake.locked())
 }
 
-/// Query Node's Locked amount
+/// Query Node's unbonding entries
+pub(crate) fn get_unbonding(
+    query_runner: &QueryRunner,
+    pub_key: &NodePublicKey,
+) -> Vec<UnbondingEntry> {
+    do_get_node_info::<Vec<UnbondingEntry>>(query_runner, pub_key, |n| n.stake.unbonding.0)
+}
+
+/// Query the epoch until which the Node's latest unstake is locked
 pub(crate) fn get_locked_time(query_runner: &QueryRunner, pub_key: &NodePublicKey) -> Epoch {
-    do_get_node_info::<Epoch>(query_runner, pub_key, |n| n.stake.locked_until)
+    do_get_node_info::<Epoch>(query_runner, pub_key, |n| {
+        n.stake
+            .unbonding
+            .0
+            .last()
+            .map(|entry| entry.locked_until)
+            .unwrap_or_default()
+    })
 }
 
 /// Query Node's stake locked until
@@ -1076,8 +1206,7 @@ pub async fn build(&self) -> Result<TestNetwork> {
                     .with_stake(Staking {
                         staked: 1000u32.into(),
                         stake_locked_until: 0,
-                        locked: 0u32.into(),
-                        locked_until: 0,
+                        unbonding: Default::default(),
                     })
                     .with_is_committee(true)
                     .build(),
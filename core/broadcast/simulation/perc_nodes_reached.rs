@@ -17,6 +17,7 @@
 
 pub fn main() {
     const N: usize = 1500;
+    const SEED: u64 = 1;
     let mut lat_provider =
         simulon::latency::PingDataLatencyProvider::<ClampNormalDistribution>::default();
     lat_provider.init(N);
@@ -36,6 +37,7 @@ pub fn main() {
     let time = std::time::Instant::now();
     let report = SimulationBuilder::new(|| simulon::api::spawn(setup::exec(N)))
         .with_nodes(N + 1)
+        .with_seed(SEED)
         .set_latency_provider(lat_provider)
         .with_state(Arc::new(connections))
         .set_node_metrics_rate(Duration::ZERO)
@@ -145,7 +145,7 @@ pub async fn exec(n: usize) {
 /// Start a client loop which picks a random node and sends a message to it every
 /// few seconds.
 pub async fn run_client(n: usize) {
-    let mut rng = rand::thread_rng();
+    let mut rng = simulon::api::rng();
     simulon::api::sleep(Duration::from_secs(5)).await;
 
     for i in 0.. {
@@ -227,6 +227,7 @@ fn create_update_request(
         nonce,
         method,
         chain_id,
+        fee: None,
     };
     let digest = payload.to_digest();
     let signature = secret_key.sign(&digest);
@@ -431,8 +431,7 @@ async fn test_single_revealing_node_fully_slashed() {
                     Staking {
                         staked: 0u64.into(),
                         stake_locked_until: 0,
-                        locked: 0u64.into(),
-                        locked_until: 0,
+                        unbonding: Default::default(),
                     },
                     NodeRegistryChangeSlashReason::CommitteeBeaconNonReveal,
                 )),
@@ -640,8 +639,7 @@ async fn test_non_revealing_node_partially_slashed_insufficient_stake() {
                     Staking {
                         staked: 500u64.into(),
                         stake_locked_until: 0,
-                        locked: 0u64.into(),
-                        locked_until: 0,
+                        unbonding: Default::default(),
                     },
                     NodeRegistryChangeSlashReason::CommitteeBeaconNonReveal,
                 )),
@@ -851,8 +849,7 @@ async fn test_non_revealing_node_partially_slashed_sufficient_stake() {
                     Staking {
                         staked: 500u64.into(),
                         stake_locked_until: 0,
-                        locked: 0u64.into(),
-                        locked_until: 0,
+                        unbonding: Default::default(),
                     },
                     NodeRegistryChangeSlashReason::CommitteeBeaconNonReveal,
                 )),
@@ -4,7 +4,14 @@
 
 use fleek_crypto::NodePublicKey;
 use lightning_interfaces::prelude::*;
-use lightning_interfaces::types::{Block, Epoch, Metadata, NodeIndex, TransactionRequest};
+use lightning_interfaces::types::{
+    order_by_priority,
+    Block,
+    Epoch,
+    Metadata,
+    NodeIndex,
+    TransactionRequest,
+};
 use lightning_interfaces::Events;
 use lightning_metrics::increment_counter;
 use lightning_utils::application::QueryRunnerExt;
@@ -328,6 +335,13 @@ async fn submit_batch<P: PubSub<PubSubMsg>, Q: SyncQueryRunnerInterface, NE: Emi
             }
         })
         .collect::<Vec<_>>();
+    // Every node orders the transactions of the sub dag the same way, against the state left by
+    // the previous block, so the ordering stays deterministic across the network.
+    let transactions = order_by_priority(transactions, |account| {
+        ctx.query_runner
+            .get_account_info(account, |info| info.flk_balance)
+            .unwrap_or_default()
+    });
 
     let block = Block {
         digest,
@@ -346,7 +360,7 @@ async fn submit_batch<P: PubSub<PubSubMsg>, Q: SyncQueryRunnerInterface, NE: Emi
         response
             .txn_receipts
             .iter()
-            .filter_map(|r| r.event.clone())
+            .flat_map(|r| r.events.iter().cloned())
             .collect(),
     );
 
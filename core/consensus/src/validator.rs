@@ -7,6 +7,7 @@
     TransactionRequest,
     UpdateMethod,
     UpdateRequest,
+    MAX_BATCH_SIZE,
     MAX_DELIVERY_ACKNOWLEDGMENTS,
     MAX_MEASUREMENTS_PER_TX,
     MAX_UPDATES_CONTENT_REGISTRY,
@@ -81,29 +82,7 @@ fn validate_txn(&self, t: &[u8], mempool: bool) -> Result<()> {
                     }
                 }
 
-                match payload.method {
-                    UpdateMethod::SubmitReputationMeasurements { measurements } => {
-                        if measurements.len() > MAX_MEASUREMENTS_PER_TX {
-                            return Err(anyhow!("Too many reputation measurements"));
-                        }
-                    },
-                    UpdateMethod::UpdateContentRegistry { updates } => {
-                        if updates.len() > MAX_UPDATES_CONTENT_REGISTRY {
-                            return Err(anyhow!("Too many updates"));
-                        }
-                    },
-                    UpdateMethod::SubmitDeliveryAcknowledgmentAggregation {
-                        commodity: _,
-                        service_id: _,
-                        proofs,
-                        metadata: _,
-                    } => {
-                        if proofs.len() > MAX_DELIVERY_ACKNOWLEDGMENTS {
-                            return Err(anyhow!("Too many delivery acknowledgments"));
-                        }
-                    },
-                    _ => (),
-                }
+                validate_method(&payload.method)?;
             },
             TransactionRequest::EthereumRequest(mut eth_tx) => {
                 let sender: EthAddress = if let Ok(address) = eth_tx.tx.recover_from_mut() {
@@ -119,3 +98,39 @@ fn validate_txn(&self, t: &[u8], mempool: bool) -> Result<()> {
         Ok(())
     }
 }
+
+/// Checks the size limits of the given update method.
+fn validate_method(method: &UpdateMethod) -> Result<()> {
+    match method {
+        UpdateMethod::SubmitReputationMeasurements { measurements } => {
+            if measurements.len() > MAX_MEASUREMENTS_PER_TX {
+                return Err(anyhow!("Too many reputation measurements"));
+            }
+        },
+        UpdateMethod::UpdateContentRegistry { updates } => {
+            if updates.len() > MAX_UPDATES_CONTENT_REGISTRY {
+                return Err(anyhow!("Too many updates"));
+            }
+        },
+        UpdateMethod::SubmitDeliveryAcknowledgmentAggregation {
+            commodity: _,
+            service_id: _,
+            proofs,
+            metadata: _,
+        } => {
+            if proofs.len() > MAX_DELIVERY_ACKNOWLEDGMENTS {
+                return Err(anyhow!("Too many delivery acknowledgments"));
+            }
+        },
+        UpdateMethod::Batch(methods) => {
+            if methods.len() > MAX_BATCH_SIZE {
+                return Err(anyhow!("Too many methods in batch"));
+            }
+            for method in methods {
+                validate_method(method)?;
+            }
+        },
+        _ => (),
+    }
+    Ok(())
+}
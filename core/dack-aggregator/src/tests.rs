@@ -76,6 +76,9 @@ async fn init_aggregator(temp_dir: &TempDir) -> Node<TestBinding> {
             id: 0,
             owner: owner_public_key.into(),
             commodity_type: types::CommodityTypes::Bandwidth,
+            binary: None,
+            version: 0,
+            resources: Default::default(),
         }],
 
         commodity_prices: vec![GenesisPrices {
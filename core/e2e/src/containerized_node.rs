@@ -37,10 +37,7 @@ pub fn new(
         is_genesis_committee: bool,
         genesis_stake: Staking,
     ) -> Self {
-        let provider = MultiThreadedProvider::default();
-        provider.insert(config.clone());
-        let node =
-            ContainedNode::<FullNodeComponents>::new(provider, Some(format!("NODE-{index}")));
+        let node = new_contained_node(&config, index);
         Self {
             config,
             owner_secret_key,
@@ -67,6 +64,21 @@ pub fn shutdown(self) -> impl Future<Output = ()> {
         self.node.shutdown()
     }
 
+    /// Shut the node down while keeping its configuration and data directory around, so it can
+    /// be started again using [`ContainerizedNode::start`]. Unless the swarm was built with
+    /// persistence the node comes back with the genesis state.
+    pub async fn stop(&mut self) {
+        let node = std::mem::replace(&mut self.node, new_contained_node(&self.config, self.index));
+        node.shutdown().await;
+        self.started = false;
+    }
+
+    /// Stop the node and start it again.
+    pub async fn restart(&mut self) -> anyhow::Result<()> {
+        self.stop().await;
+        self.start().await
+    }
+
     pub fn is_started(&self) -> bool {
         self.started
     }
@@ -175,3 +187,12 @@ pub async fn wait_for_epoch_change(
         .map_err(|e| SwarmError::Internal(e.to_string()))
     }
 }
+
+fn new_contained_node(
+    config: &TomlConfigProvider<FullNodeComponents>,
+    index: NodeIndex,
+) -> ContainedNode<FullNodeComponents> {
+    let provider = MultiThreadedProvider::default();
+    provider.insert(config.clone());
+    ContainedNode::<FullNodeComponents>::new(provider, Some(format!("NODE-{index}")))
+}
@@ -1,3 +1,4 @@
+use fleek_crypto::NodePublicKey;
 use lightning_utils::poll::PollUntilError;
 use thiserror::Error;
 
@@ -6,6 +7,12 @@ pub enum SwarmError {
     #[error("Timeout waiting for swarm to be ready")]
     WaitForReadyTimeout,
 
+    #[error("Node {0} is not part of the swarm")]
+    UnknownNode(NodePublicKey),
+
+    #[error("The swarm was built without the network shim")]
+    NetworkShimDisabled,
+
     #[error("Internal: {0}")]
     Internal(String),
 }
@@ -1,4 +1,5 @@
 pub mod containerized_node;
 pub mod error;
+pub mod network_shim;
 pub mod swarm;
 pub mod utils;
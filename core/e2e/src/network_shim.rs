@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use tokio::net::UdpSocket;
+use tokio::task::JoinHandle;
+use types::NodeIndex;
+
+const MAX_DATAGRAM_SIZE: usize = 65535;
+
+/// A fault injected on the pool traffic from one node to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkFault {
+    /// Silently drop every datagram.
+    Drop,
+    /// Deliver every datagram after the given delay.
+    Delay(Duration),
+}
+
+/// The pool ports of a node behind the shim.
+#[derive(Debug, Clone, Copy)]
+pub struct ShimRoute {
+    pub index: NodeIndex,
+    /// The port advertised to the other nodes in the genesis, the shim listens on this port.
+    pub public_port: u16,
+    /// The port the pool of the node is actually bound to.
+    pub pool_port: u16,
+}
+
+/// An in-process UDP relay that sits in front of the pool of every node in the swarm.
+///
+/// Each node advertises the port of the shim instead of its own pool port. The shim knows the
+/// pool port of every node, so it can tell which node sent a datagram and apply the faults
+/// that are configured for that pair of nodes before relaying it.
+pub struct NetworkShim {
+    routes: Vec<ShimRoute>,
+    faults: Arc<RwLock<HashMap<(NodeIndex, NodeIndex), LinkFault>>>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl NetworkShim {
+    pub fn new(routes: Vec<ShimRoute>) -> Self {
+        Self {
+            routes,
+            faults: Default::default(),
+            tasks: Vec::new(),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.tasks.is_empty()
+    }
+
+    /// Bind the public ports and start relaying the traffic.
+    pub async fn start(&mut self) -> io::Result<()> {
+        if self.is_running() {
+            return Ok(());
+        }
+
+        let senders = Arc::new(
+            self.routes
+                .iter()
+                .map(|route| (route.pool_port, route.index))
+                .collect::<HashMap<_, _>>(),
+        );
+
+        for route in &self.routes {
+            let socket = UdpSocket::bind(localhost(route.public_port)).await?;
+            let relay = Relay {
+                route: *route,
+                socket: Arc::new(socket),
+                senders: senders.clone(),
+                faults: self.faults.clone(),
+            };
+            self.tasks.push(tokio::spawn(relay.run()));
+        }
+
+        Ok(())
+    }
+
+    /// Set the fault applied to the datagrams sent from `from` to `to`.
+    pub fn set_fault(&self, from: NodeIndex, to: NodeIndex, fault: LinkFault) {
+        self.faults.write().unwrap().insert((from, to), fault);
+    }
+
+    /// Remove the fault applied to the datagrams sent from `from` to `to`.
+    pub fn clear_fault(&self, from: NodeIndex, to: NodeIndex) {
+        self.faults.write().unwrap().remove(&(from, to));
+    }
+
+    /// Remove every configured fault.
+    pub fn clear_all(&self) {
+        self.faults.write().unwrap().clear();
+    }
+
+    /// Apply the fault on the traffic in both directions between the node and every other node.
+    pub fn isolate(&self, node: NodeIndex, fault: LinkFault) {
+        let mut faults = self.faults.write().unwrap();
+        for route in self.routes.iter().filter(|route| route.index != node) {
+            faults.insert((node, route.index), fault);
+            faults.insert((route.index, node), fault);
+        }
+    }
+
+    /// Remove the faults on the traffic in both directions between the node and every other node.
+    pub fn reconnect(&self, node: NodeIndex) {
+        self.faults
+            .write()
+            .unwrap()
+            .retain(|(from, to), _| *from != node && *to != node);
+    }
+}
+
+impl Drop for NetworkShim {
+    fn drop(&mut self) {
+        for task in self.tasks.drain(..) {
+            task.abort();
+        }
+    }
+}
+
+/// The relay for the public port of a single node.
+struct Relay {
+    route: ShimRoute,
+    socket: Arc<UdpSocket>,
+    senders: Arc<HashMap<u16, NodeIndex>>,
+    faults: Arc<RwLock<HashMap<(NodeIndex, NodeIndex), LinkFault>>>,
+}
+
+impl Relay {
+    async fn run(self) {
+        // For every remote peer we use a dedicated socket to talk to the pool of our node, so
+        // we know whom to send the responses back to.
+        let mut upstreams = HashMap::<SocketAddr, Upstream>::new();
+        let mut buffer = vec![0; MAX_DATAGRAM_SIZE];
+
+        loop {
+            let Ok((len, peer)) = self.socket.recv_from(&mut buffer).await else {
+                continue;
+            };
+
+            if !upstreams.contains_key(&peer) {
+                match self.upstream(peer).await {
+                    Ok(upstream) => {
+                        upstreams.insert(peer, upstream);
+                    },
+                    Err(e) => {
+                        tracing::error!("failed to create the upstream socket for {peer}: {e:?}");
+                        continue;
+                    },
+                }
+            }
+
+            let upstream = upstreams.get(&peer).expect("inserted above");
+            let fault = upstream
+                .sender
+                .and_then(|from| self.fault(from, self.route.index));
+            forward(
+                upstream.socket.clone(),
+                buffer[..len].to_vec(),
+                localhost(self.route.pool_port),
+                fault,
+            );
+        }
+    }
+
+    async fn upstream(&self, peer: SocketAddr) -> io::Result<Upstream> {
+        let sender = self.senders.get(&peer.port()).copied();
+        let socket = Arc::new(UdpSocket::bind(localhost(0)).await?);
+        socket.connect(localhost(self.route.pool_port)).await?;
+
+        // Relay the responses of our node back to the peer through the public socket.
+        let upstream = socket.clone();
+        let public = self.socket.clone();
+        let faults = self.faults.clone();
+        let index = self.route.index;
+        let task = tokio::spawn(async move {
+            let mut buffer = vec![0; MAX_DATAGRAM_SIZE];
+            loop {
+                let len = match upstream.recv(&mut buffer).await {
+                    Ok(len) => len,
+                    // Our node is down, it might be restarted later.
+                    Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => continue,
+                    Err(_) => break,
+                };
+
+                let fault = sender.and_then(|to| faults.read().unwrap().get(&(index, to)).copied());
+                forward(public.clone(), buffer[..len].to_vec(), peer, fault);
+            }
+        });
+
+        Ok(Upstream {
+            sender,
+            socket,
+            task,
+        })
+    }
+
+    fn fault(&self, from: NodeIndex, to: NodeIndex) -> Option<LinkFault> {
+        self.faults.read().unwrap().get(&(from, to)).copied()
+    }
+}
+
+struct Upstream {
+    /// The node on the other end, if the datagrams are coming from a node in the swarm.
+    sender: Option<NodeIndex>,
+    socket: Arc<UdpSocket>,
+    task: JoinHandle<()>,
+}
+
+impl Drop for Upstream {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+fn forward(socket: Arc<UdpSocket>, data: Vec<u8>, to: SocketAddr, fault: Option<LinkFault>) {
+    match fault {
+        Some(LinkFault::Drop) => {},
+        Some(LinkFault::Delay(delay)) => {
+            tokio::spawn(async move {
+                tokio::time::sleep(delay).await;
+                let _ = socket.send_to(&data, to).await;
+            });
+        },
+        None => {
+            // Just like the network we are allowed to lose the datagram if the buffer is full.
+            let _ = socket.try_send_to(&data, to);
+        },
+    }
+}
+
+fn localhost(port: u16) -> SocketAddr {
+    SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port)
+}
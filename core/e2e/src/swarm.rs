@@ -54,11 +54,13 @@
 
 use crate::containerized_node::ContainerizedNode;
 use crate::error::SwarmError;
+use crate::network_shim::{LinkFault, NetworkShim, ShimRoute};
 use crate::utils::networking::{PortAssigner, Transport};
 
 pub struct Swarm {
     nodes: HashMap<NodePublicKey, ContainerizedNode>,
     directory: ResolvedPathBuf,
+    shim: Option<NetworkShim>,
 }
 
 impl Drop for Swarm {
@@ -76,11 +78,13 @@ pub fn builder() -> SwarmBuilder {
     }
 
     pub async fn launch(&mut self) -> anyhow::Result<()> {
+        self.start_network_shim().await?;
         try_join_all(self.nodes.values_mut().map(|node| node.start())).await?;
         Ok(())
     }
 
     pub async fn launch_genesis_committee(&mut self) -> anyhow::Result<()> {
+        self.start_network_shim().await?;
         try_join_all(
             self.nodes
                 .values_mut()
@@ -92,6 +96,7 @@ pub async fn launch_genesis_committee(&mut self) -> anyhow::Result<()> {
     }
 
     pub async fn launch_non_genesis_committee(&mut self) -> anyhow::Result<()> {
+        self.start_network_shim().await?;
         try_join_all(
             self.nodes
                 .values_mut()
@@ -102,6 +107,109 @@ pub async fn launch_non_genesis_committee(&mut self) -> anyhow::Result<()> {
         Ok(())
     }
 
+    async fn start_network_shim(&mut self) -> anyhow::Result<()> {
+        if let Some(shim) = self.shim.as_mut() {
+            shim.start().await?;
+        }
+        Ok(())
+    }
+
+    /// Stop a single node. The node keeps its data directory and can be started again with
+    /// [`Swarm::start_node`]. Use [`SwarmBuilder::persistence`] for the node to keep its state.
+    pub async fn stop_node(&mut self, node: &NodePublicKey) -> Result<(), SwarmError> {
+        self.node_mut(node)?.stop().await;
+        Ok(())
+    }
+
+    /// Start a single node that was stopped or not launched yet.
+    pub async fn start_node(&mut self, node: &NodePublicKey) -> Result<(), SwarmError> {
+        self.node_mut(node)?
+            .start()
+            .await
+            .map_err(|e| SwarmError::Internal(e.to_string()))
+    }
+
+    /// Stop a single node and start it again, this simulates a crash of the node.
+    pub async fn restart_node(&mut self, node: &NodePublicKey) -> Result<(), SwarmError> {
+        self.node_mut(node)?
+            .restart()
+            .await
+            .map_err(|e| SwarmError::Internal(e.to_string()))
+    }
+
+    /// Pause the node by dropping all of the pool traffic to and from it. The node itself keeps
+    /// running, and will be reachable again after [`Swarm::resume_node`].
+    pub fn pause_node(&self, node: &NodePublicKey) -> Result<(), SwarmError> {
+        let index = self.node_index(node)?;
+        self.network_shim()?.isolate(index, LinkFault::Drop);
+        Ok(())
+    }
+
+    /// Resume a node that was paused, this also removes any other fault on its traffic.
+    pub fn resume_node(&self, node: &NodePublicKey) -> Result<(), SwarmError> {
+        let index = self.node_index(node)?;
+        self.network_shim()?.reconnect(index);
+        Ok(())
+    }
+
+    /// Drop the pool traffic between two nodes, in both directions.
+    pub fn drop_traffic(&self, a: &NodePublicKey, b: &NodePublicKey) -> Result<(), SwarmError> {
+        self.set_link_fault(a, b, LinkFault::Drop)
+    }
+
+    /// Delay the pool traffic between two nodes, in both directions.
+    pub fn delay_traffic(
+        &self,
+        a: &NodePublicKey,
+        b: &NodePublicKey,
+        delay: Duration,
+    ) -> Result<(), SwarmError> {
+        self.set_link_fault(a, b, LinkFault::Delay(delay))
+    }
+
+    /// Remove the faults on the pool traffic between two nodes.
+    pub fn heal_traffic(&self, a: &NodePublicKey, b: &NodePublicKey) -> Result<(), SwarmError> {
+        let (a, b) = (self.node_index(a)?, self.node_index(b)?);
+        let shim = self.network_shim()?;
+        shim.clear_fault(a, b);
+        shim.clear_fault(b, a);
+        Ok(())
+    }
+
+    /// Remove every fault on the pool traffic of the swarm.
+    pub fn heal_network(&self) -> Result<(), SwarmError> {
+        self.network_shim()?.clear_all();
+        Ok(())
+    }
+
+    fn set_link_fault(
+        &self,
+        a: &NodePublicKey,
+        b: &NodePublicKey,
+        fault: LinkFault,
+    ) -> Result<(), SwarmError> {
+        let (a, b) = (self.node_index(a)?, self.node_index(b)?);
+        let shim = self.network_shim()?;
+        shim.set_fault(a, b, fault);
+        shim.set_fault(b, a, fault);
+        Ok(())
+    }
+
+    fn network_shim(&self) -> Result<&NetworkShim, SwarmError> {
+        self.shim.as_ref().ok_or(SwarmError::NetworkShimDisabled)
+    }
+
+    fn node_mut(&mut self, node: &NodePublicKey) -> Result<&mut ContainerizedNode, SwarmError> {
+        self.nodes
+            .get_mut(node)
+            .ok_or(SwarmError::UnknownNode(*node))
+    }
+
+    fn node_index(&self, node: &NodePublicKey) -> Result<NodeIndex, SwarmError> {
+        self.get_node_index(node)
+            .ok_or(SwarmError::UnknownNode(*node))
+    }
+
     pub async fn shutdown(mut self) {
         let mut handles = Vec::new();
         for (_, node) in self.nodes.drain() {
@@ -188,6 +296,13 @@ pub fn get_non_genesis_committee_syncronizer(
             .collect()
     }
 
+    pub fn get_syncronizer(
+        &self,
+        node: &NodePublicKey,
+    ) -> Option<fdi::Ref<c!(FullNodeComponents::SyncronizerInterface)>> {
+        self.nodes.get(node).map(|node| node.take_syncronizer())
+    }
+
     pub fn get_blockstores(&self) -> Vec<Blockstore<FullNodeComponents>> {
         self.nodes
             .values()
@@ -321,6 +436,7 @@ pub struct SwarmBuilder {
     ping_timeout: Option<Duration>,
     ipfs_gateways: Option<Vec<Gateway>>,
     chain_id: Option<u32>,
+    network_shim: bool,
 }
 
 impl SwarmBuilder {
@@ -415,6 +531,13 @@ pub fn with_chain_id(mut self, chain_id: u32) -> Self {
         self
     }
 
+    /// Route the pool traffic of the nodes through an in-process network shim, which allows
+    /// the tests to pause nodes and drop or delay the traffic between them.
+    pub fn with_network_shim(mut self) -> Self {
+        self.network_shim = true;
+        self
+    }
+
     pub fn build(self) -> Swarm {
         let num_nodes = self.num_nodes.expect("Number of nodes must be provided.");
         let directory = self.directory.expect("Directory must be provided.");
@@ -472,6 +595,7 @@ pub fn build(self) -> Swarm {
 
         let mut index = 0;
         let mut committee_size = 0;
+        let mut shim_routes = Vec::new();
 
         let mut specific_nodes = self.specific_nodes.unwrap_or_default();
         if specific_nodes.len() > num_nodes {
@@ -505,9 +629,24 @@ pub fn build(self) -> Swarm {
             fs::create_dir_all(&root).expect("Failed to create node directory");
 
             let ports = assign_ports(&mut port_assigner);
+
+            // With the network shim the other nodes reach our pool through the port of the shim
+            // from the genesis, while the pool is actually bound to another port.
+            let mut local_ports = ports.clone();
+            if self.network_shim {
+                local_ports.pool = port_assigner
+                    .next_port(Transport::Udp)
+                    .expect("Could not get port");
+                shim_routes.push(ShimRoute {
+                    index: index as NodeIndex,
+                    public_port: ports.pool,
+                    pool_port: local_ports.pool,
+                });
+            }
+
             let config = build_config(
                 &root,
-                ports.clone(),
+                local_ports,
                 self.archiver,
                 self.syncronizer_delta.unwrap_or(Duration::from_secs(300)),
                 &self.services,
@@ -579,7 +718,13 @@ pub fn build(self) -> Swarm {
             nodes.insert(node_pk, node);
         }
 
-        Swarm { nodes, directory }
+        let shim = self.network_shim.then(|| NetworkShim::new(shim_routes));
+
+        Swarm {
+            nodes,
+            directory,
+            shim,
+        }
     }
 }
 
@@ -676,6 +821,8 @@ fn build_config(
     config.inject::<ServiceExecutor<FullNodeComponents>>(ServiceExecutorConfig {
         services: services.iter().copied().collect(),
         ipc_path: root.join("ipc").try_into().expect("Failed to resolve path"),
+        data_path: root.join("data").try_into().expect("Failed to resolve path"),
+        ..Default::default()
     });
 
     config.inject::<ReputationAggregator<FullNodeComponents>>(RepAggConfig {
@@ -0,0 +1,226 @@
+use std::time::{Duration, SystemTime};
+
+use fleek_blake3 as blake3;
+use lightning_e2e::swarm::Swarm;
+use lightning_interfaces::prelude::*;
+use lightning_rpc::interface::Fleek;
+use lightning_test_utils::logging;
+use lightning_utils::poll::{poll_until, PollUntilError};
+use tempfile::tempdir;
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+#[tokio::test]
+async fn e2e_epoch_change_with_crashed_committee_member() {
+    logging::setup(None);
+
+    let temp_dir = tempdir().unwrap();
+    let mut swarm = Swarm::builder()
+        .with_directory(temp_dir.path().to_path_buf().try_into().unwrap())
+        .with_min_port(11000)
+        .with_num_nodes(4)
+        .with_committee_size(4)
+        .with_epoch_time(15000)
+        .with_epoch_start(now())
+        .persistence(true)
+        .build();
+    swarm.launch().await.unwrap();
+    swarm.wait_for_rpc_ready().await;
+
+    // Crash one of the committee members in the middle of the epoch. The remaining 3 out of 4
+    // members are still enough to change the epoch.
+    let (crashed, _) = swarm
+        .get_genesis_committee_rpc_addresses()
+        .into_iter()
+        .next()
+        .unwrap();
+    swarm.stop_node(&crashed).await.unwrap();
+    assert_eq!(swarm.started_nodes().len(), 3);
+
+    // Wait for the epoch to change on the nodes that are still running.
+    swarm
+        .wait_for_epoch_change(1, Duration::from_secs(60))
+        .await
+        .unwrap();
+
+    // Bring the node back, it should catch up with the rest of the network from its persisted
+    // state.
+    swarm.start_node(&crashed).await.unwrap();
+    swarm.wait_for_rpc_ready().await;
+    swarm
+        .wait_for_epoch_change(1, Duration::from_secs(60))
+        .await
+        .unwrap();
+
+    swarm.shutdown().await;
+}
+
+#[tokio::test]
+async fn e2e_checkpoint_with_crashed_committee_member() {
+    logging::setup(None);
+
+    let temp_dir = tempdir().unwrap();
+    let mut swarm = Swarm::builder()
+        .with_directory(temp_dir.path().to_path_buf().try_into().unwrap())
+        .with_min_port(11100)
+        .with_num_nodes(4)
+        .with_committee_size(4)
+        .with_epoch_time(15000)
+        .with_epoch_start(now())
+        .persistence(true)
+        .build();
+    swarm.launch().await.unwrap();
+    swarm.wait_for_rpc_ready().await;
+
+    let (crashed, _) = swarm
+        .get_genesis_committee_rpc_addresses()
+        .into_iter()
+        .next()
+        .unwrap();
+    swarm.stop_node(&crashed).await.unwrap();
+
+    swarm
+        .wait_for_epoch_change(1, Duration::from_secs(60))
+        .await
+        .unwrap();
+
+    // The remaining committee members still have a supermajority, so they should agree on a
+    // checkpoint for the epoch.
+    let epoch_checkpoint_hash = poll_until(
+        || async {
+            let mut target_hash = None;
+            for node in swarm.started_nodes() {
+                let (epoch_hash, _) = node.get_rpc_client().get_last_epoch_hash().await.unwrap();
+                if *target_hash.get_or_insert(epoch_hash) != epoch_hash {
+                    return Err(PollUntilError::ConditionNotSatisfied);
+                }
+            }
+            let target_hash = target_hash.unwrap();
+
+            (target_hash != [0; 32])
+                .then_some(target_hash)
+                .ok_or(PollUntilError::ConditionNotSatisfied)
+        },
+        Duration::from_secs(5),
+        Duration::from_millis(100),
+    )
+    .await
+    .unwrap();
+
+    for node in swarm.started_nodes() {
+        let checkpoint = node
+            .take_blockstore()
+            .read_all_to_vec(&epoch_checkpoint_hash)
+            .await
+            .unwrap();
+        assert_eq!(blake3::hash(&checkpoint), epoch_checkpoint_hash);
+    }
+
+    swarm.shutdown().await;
+}
+
+#[tokio::test]
+async fn e2e_syncronizer_after_committee_member_crash() {
+    logging::setup(None);
+
+    let temp_dir = tempdir().unwrap();
+    let mut swarm = Swarm::builder()
+        .with_directory(temp_dir.path().to_path_buf().try_into().unwrap())
+        .with_min_port(11200)
+        .with_num_nodes(4)
+        .with_committee_size(4)
+        .with_epoch_time(10000)
+        .with_epoch_start(now())
+        .with_syncronizer_delta(Duration::from_secs(5))
+        .persistence(true)
+        .build();
+    swarm.launch().await.unwrap();
+    swarm.wait_for_rpc_ready().await;
+
+    let (crashed, _) = swarm
+        .get_genesis_committee_rpc_addresses()
+        .into_iter()
+        .next()
+        .unwrap();
+    swarm.stop_node(&crashed).await.unwrap();
+
+    // Let the rest of the network move on without the crashed node.
+    swarm
+        .wait_for_epoch_change(1, Duration::from_secs(60))
+        .await
+        .unwrap();
+
+    swarm.start_node(&crashed).await.unwrap();
+
+    // The restarted node is behind, so the syncronizer should download the latest checkpoint.
+    let syncronizer = swarm.get_syncronizer(&crashed).unwrap();
+    let ckpt_hash = syncronizer.next_checkpoint_hash().await.unwrap();
+
+    let blockstore = swarm.get_blockstore(&crashed).unwrap();
+    let checkpoint = blockstore.read_all_to_vec(&ckpt_hash).await.unwrap();
+    assert_eq!(blake3::hash(&checkpoint), ckpt_hash);
+
+    swarm.shutdown().await;
+}
+
+#[tokio::test]
+async fn e2e_epoch_change_with_faulty_pool_links() {
+    logging::setup(None);
+
+    let temp_dir = tempdir().unwrap();
+    let mut swarm = Swarm::builder()
+        .with_directory(temp_dir.path().to_path_buf().try_into().unwrap())
+        .with_min_port(11300)
+        .with_num_nodes(5)
+        .with_committee_size(4)
+        .with_epoch_time(15000)
+        .with_epoch_start(now())
+        .with_network_shim()
+        .build();
+    swarm.launch().await.unwrap();
+    swarm.wait_for_rpc_ready().await;
+
+    let committee = swarm
+        .get_genesis_committee_rpc_addresses()
+        .into_keys()
+        .collect::<Vec<_>>();
+    let (non_committee, _) = swarm
+        .get_non_genesis_committee_rpc_addresses()
+        .into_iter()
+        .next()
+        .unwrap();
+
+    // Slow down some of the links, cut one of them entirely and pause the node that is not on
+    // the committee.
+    swarm
+        .delay_traffic(&committee[0], &committee[1], Duration::from_millis(200))
+        .unwrap();
+    swarm
+        .delay_traffic(&committee[1], &committee[2], Duration::from_millis(50))
+        .unwrap();
+    swarm.drop_traffic(&committee[2], &committee[3]).unwrap();
+    swarm.pause_node(&non_committee).unwrap();
+
+    // The committee should still be able to change the epoch.
+    for node in swarm.started_nodes() {
+        if node.is_genesis_committee() {
+            node.wait_for_epoch_change(1, Duration::from_secs(60))
+                .await
+                .unwrap();
+        }
+    }
+
+    // Once the network is healed the paused node should catch up with the rest of the network.
+    swarm.heal_network().unwrap();
+    swarm
+        .wait_for_epoch_change(1, Duration::from_secs(60))
+        .await
+        .unwrap();
+
+    swarm.shutdown().await;
+}
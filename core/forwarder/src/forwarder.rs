@@ -1,8 +1,8 @@
 use std::marker::PhantomData;
 
-use affair::AsyncWorker;
+use affair::Socket;
 use lightning_interfaces::prelude::*;
-use lightning_interfaces::{spawn_worker, ShutdownWaiter};
+use lightning_interfaces::{spawn, ShutdownWaiter};
 
 use crate::config::ForwarderConfig;
 use crate::worker::Worker;
@@ -33,7 +33,16 @@ fn build_graph() -> fdi::DependencyGraph {
                 let node_public_key = keystore.get_ed25519_pk();
                 let query_runner = app.sync_query();
                 let worker = Worker::new(consensus_key, node_public_key, query_runner);
-                let socket = spawn_worker!(worker, "FORWARDER", waiter, crucial);
+                let (socket, socket_rx) = Socket::raw_bounded(2048);
+
+                let panic_waiter = waiter.clone();
+                spawn!(
+                    async move {
+                        waiter.run_until_shutdown(worker.run(socket_rx)).await;
+                    },
+                    "FORWARDER",
+                    crucial(panic_waiter)
+                );
 
                 Self {
                     socket,
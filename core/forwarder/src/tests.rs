@@ -1 +1,107 @@
-//! TODO
+use fleek_crypto::{AccountOwnerSignature, EthAddress, TransactionSignature};
+use hp_fixed::unsigned::HpUfixed;
+use lightning_interfaces::types::{TransactionRequest, UpdateMethod, UpdatePayload, UpdateRequest};
+
+use crate::worker::PendingQueue;
+
+fn transaction(account: u8, nonce: u64, fee: Option<u64>) -> TransactionRequest {
+    TransactionRequest::UpdateRequest(UpdateRequest {
+        signature: TransactionSignature::AccountOwner(AccountOwnerSignature([0; 65])),
+        payload: UpdatePayload {
+            sender: EthAddress([account; 20]).into(),
+            nonce,
+            method: UpdateMethod::IncrementNonce {},
+            chain_id: 1337,
+            fee: fee.map(Into::into),
+        },
+    })
+}
+
+fn push<T, F>(queue: &mut PendingQueue<T>, txn: TransactionRequest, item: T, flk_balance: F)
+where
+    F: FnOnce(&EthAddress) -> HpUfixed<18>,
+{
+    queue.push(txn.sender(), txn.priority(), item, flk_balance);
+}
+
+#[test]
+fn test_pending_queue_orders_by_priority() {
+    let mut queue = PendingQueue::default();
+    for (item, (account, nonce, fee)) in [
+        (1, 1, None),
+        (2, 1, Some(1)),
+        (3, 1, None),
+        (3, 2, Some(10)),
+        (2, 2, None),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        push(&mut queue, transaction(account, nonce, fee), item, |_| {
+            100_u64.into()
+        });
+    }
+
+    let mut order = Vec::new();
+    while let Some(item) = queue.pop() {
+        order.push(item);
+    }
+    // The fee of the second transaction of account 3 moves its first transaction up with it.
+    assert_eq!(order, vec![2, 3, 1, 0, 4]);
+}
+
+#[test]
+fn test_pending_queue_ignores_unfunded_fees() {
+    let flk_balance = |account: &EthAddress| {
+        if account.0 == [3; 20] {
+            1_u64.into()
+        } else {
+            0_u64.into()
+        }
+    };
+    let mut queue = PendingQueue::default();
+    push(&mut queue, transaction(1, 1, None), 0, flk_balance);
+    // A sender without any balance declares a large fee.
+    push(
+        &mut queue,
+        transaction(2, 1, Some(1_000_000)),
+        1,
+        flk_balance,
+    );
+    push(&mut queue, transaction(3, 1, Some(1)), 2, flk_balance);
+
+    let mut order = Vec::new();
+    while let Some(item) = queue.pop() {
+        order.push(item);
+    }
+    assert_eq!(order, vec![2, 0, 1]);
+}
+
+#[test]
+fn test_pending_queue_looks_up_balances_once_per_sender() {
+    let lookups = std::cell::Cell::new(0);
+    let flk_balance = |_: &EthAddress| {
+        lookups.set(lookups.get() + 1);
+        1_u64.into()
+    };
+    let mut queue = PendingQueue::default();
+    for nonce in 1..=3 {
+        push(
+            &mut queue,
+            transaction(1, nonce, Some(1)),
+            nonce,
+            flk_balance,
+        );
+    }
+    push(&mut queue, transaction(2, 1, Some(1)), 4, flk_balance);
+    assert_eq!(lookups.get(), 2);
+    assert_eq!(queue.len(), 4);
+
+    // The balance of account 1 only covers one of its fees, so account 2 goes first.
+    let mut order = Vec::new();
+    while let Some(item) = queue.pop() {
+        order.push(item);
+    }
+    assert_eq!(order, vec![4, 1, 2, 3]);
+    assert!(queue.is_empty());
+}
@@ -2,24 +2,40 @@
    The purpose of the forwarder is to provide a socket that other processess can send signed transactions too. The forwarder
    will then forward the transaction to an active narwhal committee member, prefering its own worker if the node is currently
    on the committee. Retry logic should be done on the proccess that has the sender side of the socket.
+
+   Transactions that queue up while the forwarder is busy are forwarded by priority: the ones that
+   drive the epoch change first, then the ones paying the highest fee, and the rest in the order
+   they arrived in.
 */
-use std::cmp;
+use std::cmp::{self, Reverse};
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, VecDeque};
 
-use affair::AsyncWorker;
+use affair::Task;
 use anyhow::Result;
-use fleek_crypto::{ConsensusPublicKey, NodePublicKey};
-use lightning_interfaces::types::{Epoch, EpochInfo, ForwarderError, NodeInfo, TransactionRequest};
+use fleek_crypto::{ConsensusPublicKey, EthAddress, NodePublicKey, TransactionSender};
+use hp_fixed::unsigned::HpUfixed;
+use lightning_interfaces::types::{
+    Epoch,
+    EpochInfo,
+    ForwarderError,
+    NodeInfo,
+    TransactionPriority,
+    TransactionRequest,
+};
 use lightning_interfaces::SyncQueryRunnerInterface;
 use lightning_utils::application::QueryRunnerExt;
 use narwhal_types::{TransactionProto, TransactionsClient};
 use rand::seq::SliceRandom;
+use tokio::sync::mpsc;
 use tokio::time::{timeout, Duration};
 use tonic::transport::channel::Channel;
 
 const TARGETED_CONNECTION_NUM: usize = 10;
 const TIMEOUT_DURATION: Duration = Duration::from_secs(4);
+/// Maximum number of transactions taken off the socket to be ordered by priority. The others wait
+/// in the socket, so that senders are held back once the forwarder falls behind.
+const MAX_PENDING: usize = 1024;
 
 pub struct Worker<Q: SyncQueryRunnerInterface> {
     /// Query runner used to read application state
@@ -62,6 +78,44 @@ pub fn new(
         }
     }
 
+    /// Forwards the transactions received on the socket, highest priority first, until the socket
+    /// is closed.
+    pub async fn run(
+        mut self,
+        mut socket_rx: mpsc::Receiver<Task<TransactionRequest, Result<(), ForwarderError>>>,
+    ) {
+        let mut pending = PendingQueue::default();
+        loop {
+            if pending.is_empty() {
+                let Some(task) = socket_rx.recv().await else {
+                    break;
+                };
+                let (sender, priority) = (task.request.sender(), task.request.priority());
+                pending.push(sender, priority, task, |account| self.flk_balance(account));
+            }
+            // Take the transactions that arrived while we were forwarding, so that they are
+            // ordered by their priority.
+            while pending.len() < MAX_PENDING {
+                let Ok(task) = socket_rx.try_recv() else {
+                    break;
+                };
+                let (sender, priority) = (task.request.sender(), task.request.priority());
+                pending.push(sender, priority, task, |account| self.flk_balance(account));
+            }
+
+            if let Some(task) = pending.pop() {
+                let response = self.handle_forward(&task.request).await;
+                task.respond(response);
+            }
+        }
+    }
+
+    fn flk_balance(&self, account: &EthAddress) -> HpUfixed<18> {
+        self.query_runner
+            .get_account_info(account, |info| info.flk_balance)
+            .unwrap_or_default()
+    }
+
     async fn handle_forward(&mut self, req: &TransactionRequest) -> Result<(), ForwarderError> {
         // Grab the epoch
         let epoch = self.query_runner.get_current_epoch();
@@ -188,11 +242,118 @@ async fn make_connections(&mut self) -> Result<(), ForwarderError> {
     }
 }
 
-impl<Q: SyncQueryRunnerInterface + 'static> AsyncWorker for Worker<Q> {
-    type Request = TransactionRequest;
-    type Response = Result<(), ForwarderError>;
+/// The transactions waiting to be forwarded, in one lane per sender.
+pub(crate) struct PendingQueue<T> {
+    lanes: HashMap<TransactionSender, Lane<T>>,
+    /// The priority of the lane of every sender, highest last.
+    order: BTreeSet<(TransactionPriority, Reverse<u64>, TransactionSender)>,
+    /// The number of transactions pushed so far, used to order them by their arrival.
+    pushed: u64,
+    len: usize,
+}
+
+/// The transactions of a sender, in the order they arrived in.
+struct Lane<T> {
+    items: VecDeque<(u64, TransactionPriority, T)>,
+    /// The priorities of the items, and the order they arrived in.
+    priorities: BTreeSet<(TransactionPriority, Reverse<u64>)>,
+    /// The FLK balance of the sender when its lane was created.
+    balance: HpUfixed<18>,
+    /// The sum of the fees of the items.
+    fees: HpUfixed<18>,
+}
+
+impl<T> Default for PendingQueue<T> {
+    fn default() -> Self {
+        Self {
+            lanes: HashMap::new(),
+            order: BTreeSet::new(),
+            pushed: 0,
+            len: 0,
+        }
+    }
+}
+
+impl<T> Lane<T> {
+    /// Returns the priority of the highest priority item, and when the earliest item with that
+    /// priority arrived. Fees only count if the balance of the sender covers all of them.
+    fn priority(&self) -> Option<(TransactionPriority, Reverse<u64>)> {
+        let highest = self.priorities.last()?;
+        if self.fees <= self.balance || highest.0 == TransactionPriority::System {
+            return Some(highest.clone());
+        }
+        let (first, ..) = self.items.front()?;
+        Some((TransactionPriority::Normal, Reverse(*first)))
+    }
+}
+
+impl<T> PendingQueue<T> {
+    /// Queues an item. The FLK balance of the sender is looked up once, when it has no queued
+    /// items yet.
+    pub fn push<F>(
+        &mut self,
+        sender: TransactionSender,
+        priority: TransactionPriority,
+        item: T,
+        flk_balance: F,
+    ) where
+        F: FnOnce(&EthAddress) -> HpUfixed<18>,
+    {
+        let index = self.pushed;
+        self.pushed += 1;
+        self.len += 1;
+
+        let lane = self.lanes.entry(sender).or_insert_with(|| Lane {
+            items: VecDeque::new(),
+            priorities: BTreeSet::new(),
+            balance: match &sender {
+                TransactionSender::AccountOwner(account) => flk_balance(account),
+                _ => HpUfixed::zero(),
+            },
+            fees: HpUfixed::zero(),
+        });
+        if let Some((priority, first)) = lane.priority() {
+            self.order.remove(&(priority, first, sender));
+        }
+        if let TransactionPriority::Fee(fee) = &priority {
+            lane.fees += fee.clone();
+        }
+        lane.priorities.insert((priority.clone(), Reverse(index)));
+        lane.items.push_back((index, priority, item));
+        if let Some((priority, first)) = lane.priority() {
+            self.order.insert((priority, first, sender));
+        }
+    }
+
+    /// Removes the next item to forward. This is the earliest item of the sender of the highest
+    /// priority item, so that the transactions of a sender are forwarded in the order of their
+    /// nonces. Fees only count if the sender's FLK balance covers all of its queued fees.
+    pub fn pop(&mut self) -> Option<T> {
+        let (_, _, sender) = self.order.pop_last()?;
+        let lane = self.lanes.get_mut(&sender)?;
+        let (index, priority, item) = lane.items.pop_front()?;
+        self.len -= 1;
+
+        lane.priorities.remove(&(priority.clone(), Reverse(index)));
+        if let TransactionPriority::Fee(fee) = priority {
+            lane.fees -= fee;
+        }
+        match lane.priority() {
+            Some((priority, first)) => {
+                self.order.insert((priority, first, sender));
+            },
+            None => {
+                self.lanes.remove(&sender);
+            },
+        }
+        Some(item)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
 
-    async fn handle(&mut self, req: Self::Request) -> Self::Response {
-        self.handle_forward(&req).await
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
     }
 }
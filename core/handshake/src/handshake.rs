@@ -11,6 +11,7 @@
 use futures::StreamExt;
 use lightning_interfaces::prelude::*;
 use lightning_interfaces::schema::handshake::{HandshakeRequestFrame, TerminationReason};
+use lightning_interfaces::ServiceCapabilities;
 use rand::RngCore;
 use tracing::warn;
 use triomphe::Arc;
@@ -164,6 +165,12 @@ pub fn new(provider: P, waiter: ShutdownWaiter, timeout: Duration) -> Self {
         }
     }
 
+    /// Returns the capabilities declared by the given service, or `None` if the service has not
+    /// declared them yet.
+    pub fn service_capabilities(&self, service: u32) -> Option<ServiceCapabilities> {
+        self.provider.capabilities(service)
+    }
+
     pub async fn handle_new_connection<S: TransportSender, R: TransportReceiver>(
         &self,
         request: HandshakeRequestFrame,
@@ -183,9 +190,23 @@ pub async fn handle_new_connection<S: TransportSender, R: TransportReceiver>(
                 // TODO: Verify proof of possession
                 // TODO: Send handshake response
 
+                // Fail fast while the service is down instead of waiting on its socket.
+                let status = self.provider.status(service);
+                if status.is_some_and(|status| !status.is_up()) {
+                    sender.terminate(TerminationReason::ServiceTerminated).await;
+                    warn!("service {service} is down");
+                    return;
+                }
+
                 // Attempt to connect to the service, getting the unix socket.
                 let Some(mut socket) = self.provider.connect(service).await else {
-                    sender.terminate(TerminationReason::InvalidService).await;
+                    // A known service that refuses the connection has terminated.
+                    let reason = if status.is_some() {
+                        TerminationReason::ServiceTerminated
+                    } else {
+                        TerminationReason::InvalidService
+                    };
+                    sender.terminate(reason).await;
                     warn!("failed to connect to service {service}");
                     return;
                 };
@@ -10,14 +10,18 @@
 use bytes::Bytes;
 use fleek_crypto::{ClientPublicKey, ClientSignature};
 use fn_sdk::header::{HttpMethod, HttpOverrides, TransportDetail};
-use lightning_interfaces::schema::handshake::{HandshakeRequestFrame, RequestFrame};
+use lightning_interfaces::schema::handshake::{
+    HandshakeRequestFrame,
+    RequestFrame,
+    TerminationReason,
+};
 use lightning_interfaces::ExecutorProviderInterface;
 use lightning_metrics::increment_counter;
 use tokio::sync::oneshot;
 use url::Url;
 
 use crate::handshake::Context;
-use crate::transports::http::{HttpReceiver, HttpSender, Service};
+use crate::transports::http::{HttpReceiver, HttpSender};
 
 pub async fn handler<P: ExecutorProviderInterface>(
     method: Method,
@@ -32,8 +36,12 @@ pub async fn handler<P: ExecutorProviderInterface>(
         .get("service")
         .ok_or((StatusCode::NOT_FOUND, "missing service id".to_string()))?;
     let service_id = u32::from_str(service_id)
-        .map_err(|_| (StatusCode::NOT_FOUND, "route not found".to_string()))
-        .and_then(Service::try_from)?;
+        .map_err(|_| (StatusCode::NOT_FOUND, "route not found".to_string()))?;
+    // Until the service declares its capabilities we don't know how to read its response.
+    let capabilities = provider.service_capabilities(service_id).ok_or((
+        StatusCode::SERVICE_UNAVAILABLE,
+        "service is not available".to_string(),
+    ))?;
 
     let method = match method {
         Method::GET => HttpMethod::GET,
@@ -49,7 +57,7 @@ pub async fn handler<P: ExecutorProviderInterface>(
     let body_frame = RequestFrame::ServicePayload { bytes: payload };
 
     let handshake_frame = HandshakeRequestFrame::Handshake {
-        service: service_id as u32,
+        service: service_id,
         pk: ClientPublicKey([0; 96]),
         pop: ClientSignature([0; 48]),
         retry: None,
@@ -65,7 +73,12 @@ pub async fn handler<P: ExecutorProviderInterface>(
     url.set_path(&path);
     url.set_query(uri.query());
 
-    let sender = HttpSender::new(service_id, frame_tx, body_tx, termination_tx);
+    let sender = HttpSender::new(
+        capabilities.http_overrides,
+        frame_tx,
+        body_tx,
+        termination_tx,
+    );
     let receiver = HttpReceiver::new(
         frame_rx,
         TransportDetail::HttpRequest {
@@ -96,7 +109,7 @@ pub async fn handler<P: ExecutorProviderInterface>(
     })?;
 
     {
-        let service_id = format!("{}", service_id as usize);
+        let service_id = service_id.to_string();
         increment_counter!(
             "handshake_http_sessions",
             Some("Counter for number of handshake sessions accepted over http"),
@@ -114,9 +127,9 @@ pub async fn handler<P: ExecutorProviderInterface>(
         response_builder = response_builder.header("Content-Type", content_type);
     }
 
-    // If the service is the javascript service. Await the first response as the possible header
-    // overrides and over ride the response headers
-    if matches!(service_id, Service::Js | Service::Fetcher) {
+    // If the service supports it, await the first response as the possible header overrides and
+    // override the response headers
+    if capabilities.http_overrides {
         let header_bytes = body_rx
             .recv()
             .await
@@ -144,6 +157,13 @@ pub async fn handler<P: ExecutorProviderInterface>(
     // If there is an error while streaming, the status header has already been sent,
     // this is a hacky way of returning an error status before beginning streaming the body.
     match termination_rx.await {
+        Ok(TerminationReason::InvalidService) => {
+            Err((StatusCode::NOT_FOUND, "route not found".to_string()))
+        },
+        Ok(TerminationReason::ServiceTerminated) => Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "service unavailable".to_string(),
+        )),
         Ok(reason) => Err(bad_request(format!("handshake failed: {reason:?}"))),
         Err(_) => response_builder
             .body(body)
@@ -172,3 +192,82 @@ pub async fn blink_support() -> Response {
 fn bad_request<T: AsRef<str> + Display>(msg: T) -> (StatusCode, String) {
     (StatusCode::BAD_REQUEST, msg.to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use lightning_interfaces::types::ServiceId;
+    use lightning_interfaces::{ServiceCapabilities, ShutdownController};
+    use tokio::net::UnixStream;
+
+    use super::*;
+
+    const SERVICE: u32 = 1001;
+
+    #[derive(Clone, Default)]
+    struct MockServiceProvider {
+        capabilities: Option<ServiceCapabilities>,
+        connects: Arc<AtomicUsize>,
+    }
+
+    impl ExecutorProviderInterface for MockServiceProvider {
+        async fn connect(&self, _service_id: ServiceId) -> Option<UnixStream> {
+            self.connects.fetch_add(1, Ordering::Relaxed);
+            None
+        }
+
+        fn capabilities(&self, _service_id: ServiceId) -> Option<ServiceCapabilities> {
+            self.capabilities
+        }
+    }
+
+    async fn request(provider: MockServiceProvider) -> Option<(StatusCode, String)> {
+        let shutdown = ShutdownController::default();
+        let ctx = Context::new(provider, shutdown.waiter(), Duration::from_secs(1));
+        let uri = format!("/services/{SERVICE}/index.html");
+        let map = HashMap::from([("service".to_string(), SERVICE.to_string())]);
+
+        handler(
+            Method::GET,
+            HeaderMap::new(),
+            OriginalUri(uri.parse().unwrap()),
+            Path(map),
+            Query(HashMap::new()),
+            Extension(ctx),
+            Bytes::new(),
+        )
+        .await
+        .err()
+    }
+
+    #[tokio::test]
+    async fn test_undeclared_capabilities_are_unavailable() {
+        let provider = MockServiceProvider::default();
+        let connects = provider.connects.clone();
+
+        let (status, _) = request(provider)
+            .await
+            .expect("the request should be rejected");
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(connects.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn test_declared_capabilities_are_routed() {
+        let provider = MockServiceProvider {
+            capabilities: Some(ServiceCapabilities::default()),
+            ..Default::default()
+        };
+        let connects = provider.connects.clone();
+
+        // The mock service refuses the connection once the request reaches it.
+        let (status, _) = request(provider)
+            .await
+            .expect("the request should be rejected");
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(connects.load(Ordering::Relaxed), 1);
+    }
+}
@@ -3,7 +3,6 @@
 
 use async_channel::{Receiver, Sender};
 use async_trait::async_trait;
-use axum::http::StatusCode;
 use axum::routing::any;
 use axum::Router;
 use bytes::{Bytes, BytesMut};
@@ -46,36 +45,9 @@ async fn accept(&mut self) -> Option<(HandshakeRequestFrame, Self::Sender, Self:
     }
 }
 
-// TODO(oz): remove this, handshake should not need changes to support a service
-#[derive(Clone, Copy)]
-#[repr(u32)]
-pub enum Service {
-    Fetcher = 0,
-    Js = 1,
-    Sgx = 3,
-}
-
-impl TryFrom<u32> for Service {
-    type Error = (StatusCode, String);
-
-    fn try_from(value: u32) -> Result<Self, Self::Error> {
-        match value {
-            0 => Ok(Service::Fetcher),
-            1 => Ok(Service::Js),
-            3 => Ok(Service::Sgx),
-            _ => Err((StatusCode::NOT_FOUND, "route not found".to_string())),
-        }
-    }
-}
-
-impl Service {
-    pub fn supports_http_overrides(&self) -> bool {
-        matches!(self, Service::Js)
-    }
-}
-
 pub struct HttpSender {
-    service: Service,
+    /// Whether the service sends the header overrides before the body.
+    http_overrides: bool,
     frame_tx: Sender<Option<RequestFrame>>,
     body_tx: Sender<anyhow::Result<Bytes>>,
     current_write: usize,
@@ -85,13 +57,13 @@ pub struct HttpSender {
 
 impl HttpSender {
     pub fn new(
-        service: Service,
+        http_overrides: bool,
         frame_tx: Sender<Option<RequestFrame>>,
         body_tx: Sender<anyhow::Result<Bytes>>,
         termination_tx: oneshot::Sender<TerminationReason>,
     ) -> Self {
         Self {
-            service,
+            http_overrides,
             frame_tx,
             body_tx,
             current_write: 0,
@@ -148,7 +120,7 @@ async fn terminate(mut self, reason: TerminationReason) {
     async fn start_write(&mut self, len: usize) {
         // if the header buffer is gone it means we sent the headers already and are ready to stream
         // the body
-        if self.header_buffer.is_none() || !self.service.supports_http_overrides() {
+        if self.header_buffer.is_none() || !self.http_overrides {
             self.termination_tx.take();
         }
 
@@ -169,7 +141,7 @@ async fn write(&mut self, buf: Bytes) -> anyhow::Result<usize> {
 
         self.current_write -= len;
 
-        if self.service.supports_http_overrides() {
+        if self.http_overrides {
             self.send_with_http_override(buf).await;
         } else {
             self.inner_send(buf).await;
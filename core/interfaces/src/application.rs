@@ -8,6 +8,7 @@
 use fdi::BuildGraph;
 use fleek_crypto::{ClientPublicKey, EthAddress, NodePublicKey};
 use fxhash::FxHashMap;
+use hp_fixed::unsigned::HpUfixed;
 use lightning_types::{
     AccountInfo,
     Blake3Hash,
@@ -15,13 +16,18 @@
     Committee,
     CommitteeSelectionBeaconCommit,
     CommitteeSelectionBeaconReveal,
+    Delegation,
+    DelegationPool,
     Genesis,
     NodeIndex,
+    NodeInfoUpdate,
     Nonce,
     ProtocolParamKey,
     ProtocolParamValue,
     StateProofKey,
     StateProofValue,
+    TokenInfo,
+    Tokens,
     TransactionRequest,
     TxHash,
     Value,
@@ -248,6 +254,30 @@ fn get_state_proof(
 
     /// Returns a list of withdraws
     fn get_withdraws(&self, paging: WithdrawPagingParams) -> Vec<WithdrawInfoWithId>;
+
+    /// Returns the delegation of an account to a node.
+    fn get_delegation(&self, delegator: &EthAddress, node: &NodeIndex) -> Option<Delegation>;
+
+    /// Returns the delegations of an account along with the nodes they are delegated to.
+    fn get_delegations(&self, delegator: &EthAddress) -> Vec<(NodeIndex, Delegation)>;
+
+    /// Returns the delegations of a node and the commission of its owner.
+    fn get_delegation_pool(&self, node: &NodeIndex) -> Option<DelegationPool>;
+
+    /// Returns the update of a node's info that takes effect at the next epoch, if any.
+    fn get_pending_node_info_update(&self, node: &NodeIndex) -> Option<NodeInfoUpdate>;
+
+    /// Returns the commission rate of a node that takes effect at the next epoch, if any.
+    fn get_pending_commission(&self, node: &NodeIndex) -> Option<u8>;
+
+    /// Returns the information about a registered token.
+    fn get_token_info(&self, token: &Tokens) -> Option<TokenInfo>;
+
+    /// Returns the tokens registered on the network along with their addresses.
+    fn get_tokens(&self) -> Vec<(EthAddress, TokenInfo)>;
+
+    /// Returns the balance of an account in the given token.
+    fn get_token_balance(&self, account: &EthAddress, token: &Tokens) -> HpUfixed<18>;
 }
 
 #[derive(Clone, Debug)]
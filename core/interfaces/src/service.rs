@@ -1,4 +1,5 @@
 use fdi::BuildGraph;
+use serde::{Deserialize, Serialize};
 use tokio::net::UnixStream;
 
 use crate::components::NodeComponents;
@@ -32,4 +33,61 @@ pub trait ServiceExecutorInterface<C: NodeComponents>: BuildGraph + Sized + Send
 pub trait ExecutorProviderInterface: Clone + Send + Sync + 'static {
     /// Make a connection to the provided service.
     async fn connect(&self, service_id: ServiceId) -> Option<UnixStream>;
+
+    /// Returns the status of the given service, or `None` if the service is not running on this
+    /// node.
+    fn status(&self, _service_id: ServiceId) -> Option<ServiceStatus> {
+        None
+    }
+
+    /// Returns the status of every service running on this node.
+    fn statuses(&self) -> Vec<(ServiceId, ServiceStatus)> {
+        Vec::new()
+    }
+
+    /// Returns the capabilities the given service declared at startup, or `None` if the service
+    /// is not running on this node or has not declared them yet.
+    fn capabilities(&self, _service_id: ServiceId) -> Option<ServiceCapabilities> {
+        None
+    }
+}
+
+/// The capabilities of a service, declared by the service over IPC at startup.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServiceCapabilities {
+    /// The service responds to HTTP requests with the header overrides before the body.
+    pub http_overrides: bool,
+}
+
+/// The status of the process of a service, as observed by the service executor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceStatus {
+    /// The process has been started but has not reported to be ready yet. Services that do not
+    /// answer the probes stay in this state.
+    Starting,
+    /// The process answers the probes and is ready to accept connections.
+    Ready,
+    /// The process is not running and waits to be restarted.
+    Down,
+    /// The process crashed too often in a short time, restarts are suspended for a while.
+    CrashLoop,
+}
+
+impl ServiceStatus {
+    /// Returns true if the process of the service is running.
+    pub fn is_up(&self) -> bool {
+        matches!(self, Self::Starting | Self::Ready)
+    }
+}
+
+impl std::fmt::Display for ServiceStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Starting => "starting",
+            Self::Ready => "ready",
+            Self::Down => "down",
+            Self::CrashLoop => "crash_loop",
+        })
+    }
 }
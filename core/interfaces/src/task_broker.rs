@@ -1,7 +1,10 @@
+use bytes::Bytes;
 use fdi::BuildGraph;
 use futures::Future;
+use serde::{Deserialize, Serialize};
 
 use crate::schema::task_broker::{TaskRequest, TaskResponse, TaskScope};
+use crate::types::{NodeIndex, ServiceId};
 use crate::NodeComponents;
 
 #[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
@@ -18,6 +21,55 @@ pub enum TaskError {
     InvalidResponse,
     #[error("Maximum depth reached: {_0:?}")]
     MaxDepth(u8),
+    #[error("Invalid quorum: {_0:?}")]
+    InvalidQuorum(String),
+    #[error("Quorum not reached: {agreed} of {min_agree} nodes agreed")]
+    NoQuorum { agreed: usize, min_agree: usize },
+    #[error("Invalid schedule: {_0:?}")]
+    InvalidSchedule(String),
+}
+
+impl TaskError {
+    /// Returns true if the error is specific to the peer that ran the task, so that running the
+    /// task on another peer might succeed.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            TaskError::Connect | TaskError::Timeout | TaskError::PeerDisconnect
+        )
+    }
+}
+
+/// The response agreed on by a quorum of nodes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuorumResponse {
+    /// The response of one of the nodes in the quorum.
+    pub response: TaskResponse,
+    /// The nodes that returned the agreed payload.
+    pub agreed: Vec<NodeIndex>,
+    /// The nodes that returned a different payload.
+    pub dissenting: Vec<NodeIndex>,
+    /// The nodes that failed to return a response.
+    pub failed: Vec<(NodeIndex, TaskError)>,
+}
+
+/// A task that the node runs periodically.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledTask {
+    /// Cron expression in the `minute hour day-of-month month day-of-week` format, in UTC.
+    pub cron: String,
+    pub scope: TaskScope,
+    pub service: ServiceId,
+    pub payload: Bytes,
+}
+
+/// The outcome of a run of a [`ScheduledTask`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledRun {
+    /// Unix timestamp in milliseconds at which the run started.
+    pub timestamp: u64,
+    /// The payload of the response, or the errors of the run.
+    pub result: Result<Bytes, String>,
 }
 
 /// Task broker for services on the node.
@@ -39,4 +91,35 @@ fn run(
         scope: TaskScope,
         task: TaskRequest,
     ) -> impl Future<Output = Vec<Result<TaskResponse, TaskError>>> + Send;
+
+    /// Run a task on `of` nodes in the current cluster, and return the response if at least
+    /// `min_agree` of them returned the same payload.
+    ///
+    /// Running a task with [`TaskScope::Quorum`] through [`TaskBrokerInterface::run`] returns
+    /// only the agreed response.
+    #[blank = async { Err(TaskError::NoQuorum { agreed: 0, min_agree: 0 }) }]
+    fn run_quorum(
+        &self,
+        depth: u8,
+        min_agree: u8,
+        of: u8,
+        task: TaskRequest,
+    ) -> impl Future<Output = Result<QuorumResponse, TaskError>> + Send;
+
+    /// Register a task to run on a schedule, returning the id of the schedule. Schedules are
+    /// persisted and survive restarts of the node.
+    #[blank = Err(TaskError::InvalidSchedule("not supported".into()))]
+    fn schedule(&self, task: ScheduledTask) -> Result<u64, TaskError>;
+
+    /// Remove a schedule, returning false if it does not exist.
+    #[blank = false]
+    fn unschedule(&self, id: u64) -> bool;
+
+    /// Get the registered schedules.
+    #[blank = vec![]]
+    fn get_schedules(&self) -> Vec<(u64, ScheduledTask)>;
+
+    /// Get the most recent runs of a schedule, oldest first.
+    #[blank = vec![]]
+    fn get_scheduled_runs(&self, id: u64) -> Vec<ScheduledRun>;
 }
@@ -1,7 +1,9 @@
 use jsonrpsee::core::RpcResult;
 use jsonrpsee::proc_macros::rpc;
 use lightning_firewall::FirewallCommand;
-use lightning_interfaces::types::Blake3Hash;
+use lightning_interfaces::schema::task_broker::TaskScope;
+use lightning_interfaces::types::{Blake3Hash, ServiceId};
+use lightning_interfaces::{ScheduledRun, ScheduledTask, ServiceStatus};
 
 #[rpc(client, server, namespace = "admin")]
 pub trait AdminApi {
@@ -22,4 +24,33 @@ async fn queue_firewall_command_and_wait_for_success(
 
     #[method(name = "ping")]
     async fn ping(&self) -> RpcResult<String>;
+
+    /// Run a javascript function periodically, returning the id of the schedule.
+    #[method(name = "schedule_function")]
+    async fn schedule_function(
+        &self,
+        hash: Blake3Hash,
+        param: Option<serde_json::Value>,
+        cron: String,
+        scope: TaskScope,
+    ) -> RpcResult<u64>;
+
+    /// Run a task for any service periodically, returning the id of the schedule.
+    #[method(name = "schedule_task")]
+    async fn schedule_task(&self, task: ScheduledTask) -> RpcResult<u64>;
+
+    /// Remove a schedule, returns false if it does not exist.
+    #[method(name = "unschedule")]
+    async fn unschedule(&self, id: u64) -> RpcResult<bool>;
+
+    #[method(name = "schedules")]
+    async fn schedules(&self) -> RpcResult<Vec<(u64, ScheduledTask)>>;
+
+    /// Get the most recent runs of a schedule, with their results or errors.
+    #[method(name = "scheduled_runs")]
+    async fn scheduled_runs(&self, id: u64) -> RpcResult<Vec<ScheduledRun>>;
+
+    /// Get the status of every service running on this node.
+    #[method(name = "service_statuses")]
+    async fn service_statuses(&self) -> RpcResult<Vec<(ServiceId, ServiceStatus)>>;
 }
@@ -9,18 +9,24 @@
     AccountInfo,
     AggregateCheckpoint,
     Blake3Hash,
+    Delegation,
+    DelegationPool,
     Epoch,
     EpochInfo,
     Event,
     EventType,
     NodeIndex,
     NodeInfo,
+    NodeInfoUpdate,
     NodeInfoWithIndex,
     NodeServed,
     PublicKeys,
     ReportedReputationMeasurements,
+    TokenInfo,
+    Tokens,
     TotalServed,
     TransactionRequest,
+    UnbondingEntry,
 };
 use lightning_interfaces::{NodePagingParams, WithdrawPagingParams};
 use lightning_openrpc_macros::open_rpc;
@@ -54,7 +60,7 @@ async fn get_locked(
         &self,
         public_key: NodePublicKey,
         epoch: Option<u64>,
-    ) -> RpcResult<HpUfixed<18>>;
+    ) -> RpcResult<Vec<UnbondingEntry>>;
 
     #[method(name = "get_staked")]
     async fn get_staked(
@@ -227,6 +233,46 @@ async fn get_withdraws(
         paging: WithdrawPagingParams,
     ) -> RpcResult<Vec<WithdrawInfoWithId>>;
 
+    #[method(name = "get_delegation")]
+    async fn get_delegation(
+        &self,
+        delegator: EthAddress,
+        public_key: NodePublicKey,
+        epoch: Option<u64>,
+    ) -> RpcResult<Option<Delegation>>;
+
+    #[method(name = "get_delegations")]
+    async fn get_delegations(
+        &self,
+        delegator: EthAddress,
+        epoch: Option<u64>,
+    ) -> RpcResult<Vec<(NodeIndex, Delegation)>>;
+
+    #[method(name = "get_delegation_pool")]
+    async fn get_delegation_pool(
+        &self,
+        public_key: NodePublicKey,
+        epoch: Option<u64>,
+    ) -> RpcResult<Option<DelegationPool>>;
+
+    #[method(name = "get_pending_node_info_update")]
+    async fn get_pending_node_info_update(
+        &self,
+        public_key: NodePublicKey,
+        epoch: Option<u64>,
+    ) -> RpcResult<Option<NodeInfoUpdate>>;
+
+    #[method(name = "get_tokens")]
+    async fn get_tokens(&self, epoch: Option<u64>) -> RpcResult<Vec<(EthAddress, TokenInfo)>>;
+
+    #[method(name = "get_token_balance")]
+    async fn get_token_balance(
+        &self,
+        public_key: EthAddress,
+        token: Tokens,
+        epoch: Option<u64>,
+    ) -> RpcResult<HpUfixed<18>>;
+
     #[subscription(name = "subscribe", item = Event)]
     async fn handle_subscription(&self, event_type: Option<EventType>) -> SubscriptionResult;
 }
@@ -1,3 +1,4 @@
+use std::fmt::Write;
 use std::fs::read_to_string;
 use std::net::SocketAddr;
 use std::path::PathBuf;
@@ -9,7 +10,8 @@
 use jsonrpsee::{Methods, RpcModule};
 use lightning_firewall::Firewall;
 use lightning_interfaces::prelude::*;
-use lightning_interfaces::{Events, FetcherSocket, MempoolSocket};
+use lightning_interfaces::types::ServiceId;
+use lightning_interfaces::{Events, FetcherSocket, MempoolSocket, ServiceStatus};
 use lightning_utils::config::LIGHTNING_HOME_DIR;
 use once_cell::sync::Lazy;
 use rand::{RngCore, SeedableRng};
@@ -113,6 +115,8 @@ pub(crate) struct Data<C: NodeComponents> {
     pub node_public_key: NodePublicKey,
     pub consensus_public_key: ConsensusPublicKey,
     pub archive: C::ArchiveInterface,
+    pub task_broker: C::TaskBrokerInterface,
+    pub service_provider: c!(C::ServiceExecutorInterface::Provider),
     pub events: Events,
 }
 
@@ -144,8 +148,18 @@ pub struct Rpc<C: NodeComponents> {
     ready: RpcReadyWaiter,
 }
 
-pub async fn health() -> &'static str {
-    "OK"
+/// Returns `OK` if every service is up, followed by the status of each service on its own line.
+/// Responds with `503 Service Unavailable` and `DEGRADED` if any service is not up.
+pub async fn health(services: &[(ServiceId, ServiceStatus)]) -> (StatusCode, String) {
+    let (status, mut res) = if services.iter().all(|(_, status)| status.is_up()) {
+        (StatusCode::OK, "OK".to_string())
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "DEGRADED".to_string())
+    };
+    for (id, status) in services {
+        let _ = write!(res, "\nservice-{id}: {status}");
+    }
+    (status, res)
 }
 
 pub async fn metrics() -> (StatusCode, String) {
@@ -164,6 +178,8 @@ fn init(
         blockstore: &C::BlockstoreInterface,
         fetcher: &C::FetcherInterface,
         keystore: &C::KeystoreInterface,
+        task_broker: &C::TaskBrokerInterface,
+        service_executor: &C::ServiceExecutorInterface,
         fdi::Cloned(archive): fdi::Cloned<c!(C::ArchiveInterface)>,
         fdi::Cloned(query_runner): fdi::Cloned<c!(C::ApplicationInterface::SyncExecutor)>,
         fdi::Cloned(checkpointer_query): fdi::Cloned<c!(C::CheckpointerInterface::Query)>,
@@ -178,6 +194,8 @@ fn init(
             node_public_key: keystore.get_ed25519_pk(),
             consensus_public_key: keystore.get_bls_pk(),
             archive,
+            task_broker: task_broker.clone(),
+            service_provider: service_executor.get_provider(),
             checkpointer_query: checkpointer_query.clone(),
             events: {
                 let (tx, _) = tokio::sync::broadcast::channel(8);
@@ -212,8 +230,13 @@ fn start(&self, shutdown: fdi::Cloned<ShutdownWaiter>) {
             stop.clone(),
         );
 
-        let rpc_server =
-            server::RpcService::new(json_rpc_service, admin_json_rpc_service, self.secret);
+        let data = self.data.clone();
+        let rpc_server = server::RpcService::new(
+            json_rpc_service,
+            admin_json_rpc_service,
+            self.secret,
+            Arc::new(move || data.service_provider.statuses()),
+        );
 
         let firewall = Firewall::from_config(self.config.firewall.clone(), shutdown.clone());
         let rpc_server = firewall.service(rpc_server);
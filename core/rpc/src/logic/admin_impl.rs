@@ -3,8 +3,9 @@
 use jsonrpsee::core::RpcResult;
 use lightning_firewall::{CommandCenter, FirewallCommand};
 use lightning_interfaces::prelude::*;
-use lightning_interfaces::types::Blake3Hash;
-use lightning_interfaces::FileTrustedWriter;
+use lightning_interfaces::schema::task_broker::TaskScope;
+use lightning_interfaces::types::{Blake3Hash, ServiceId};
+use lightning_interfaces::{FileTrustedWriter, ScheduledRun, ScheduledTask, ServiceStatus};
 
 use crate::api::AdminApiServer;
 use crate::error::RPCError;
@@ -79,4 +80,52 @@ async fn queue_firewall_command_and_wait_for_success(
     async fn ping(&self) -> RpcResult<String> {
         Ok("pong".to_string())
     }
+
+    async fn schedule_function(
+        &self,
+        hash: Blake3Hash,
+        param: Option<serde_json::Value>,
+        cron: String,
+        scope: TaskScope,
+    ) -> RpcResult<u64> {
+        // A request for the javascript service, to run the function with the given parameter.
+        let payload = serde_json::json!({
+            "origin": "Blake3",
+            "uri": hex::encode(hash),
+            "param": param,
+        });
+
+        self.schedule_task(ScheduledTask {
+            cron,
+            scope,
+            service: 1,
+            payload: serde_json::to_vec(&payload).unwrap().into(),
+        })
+        .await
+    }
+
+    async fn schedule_task(&self, task: ScheduledTask) -> RpcResult<u64> {
+        let id = self
+            .data
+            .task_broker
+            .schedule(task)
+            .map_err(|e| RPCError::custom(e.to_string()))?;
+        Ok(id)
+    }
+
+    async fn unschedule(&self, id: u64) -> RpcResult<bool> {
+        Ok(self.data.task_broker.unschedule(id))
+    }
+
+    async fn schedules(&self) -> RpcResult<Vec<(u64, ScheduledTask)>> {
+        Ok(self.data.task_broker.get_schedules())
+    }
+
+    async fn scheduled_runs(&self, id: u64) -> RpcResult<Vec<ScheduledRun>> {
+        Ok(self.data.task_broker.get_scheduled_runs(id))
+    }
+
+    async fn service_statuses(&self) -> RpcResult<Vec<(ServiceId, ServiceStatus)>> {
+        Ok(self.data.service_provider.statuses())
+    }
 }
@@ -29,7 +29,7 @@
 
 const FLEEK_CONTRACT_BYTES: &[u8; 172] = b"73000000000000000000000000000000000000000030146080604052600080fdfea264697066735822122012d3570051ca11eb882745693b7b2af91a10ad5074b3486da80280731d9af73164736f6c63430008120033";
 
-use lightning_interfaces::types::{Metadata, Value};
+use lightning_interfaces::types::{Metadata, ProtocolParamKey, ProtocolParamValue, Value};
 
 pub struct EthApi<C: NodeComponents> {
     data: Arc<Data<C>>,
@@ -39,6 +39,18 @@ impl<C: NodeComponents> EthApi<C> {
     pub(crate) fn new(data: Arc<Data<C>>) -> Self {
         Self { data }
     }
+
+    /// The gas price set by the protocol, there is no base fee so this is the whole price.
+    fn suggested_gas_price(&self) -> U256 {
+        match self
+            .data
+            .query_runner
+            .get_protocol_param(&ProtocolParamKey::GasPrice)
+        {
+            Some(ProtocolParamValue::GasPrice(price)) => U256::from(price),
+            _ => U256::zero(),
+        }
+    }
 }
 
 #[async_trait::async_trait]
@@ -175,10 +187,9 @@ async fn send_raw_transaction(&self, tx: Bytes) -> RpcResult<H256> {
         Ok(hash)
     }
 
-    /// Todo(n)
     async fn gas_price(&self) -> RpcResult<U256> {
         trace!(target: "rpc::eth", "Serving eth_gasPrice");
-        Ok(U256::zero())
+        Ok(self.suggested_gas_price())
     }
 
     /// todo(n)
@@ -204,7 +215,7 @@ async fn code(
 
     async fn max_priority_fee_per_gas(&self) -> RpcResult<U256> {
         trace!(target: "rpc::eth", "Serving eth_maxPriorityFeePerGas");
-        Ok(U256::zero())
+        Ok(self.suggested_gas_price())
     }
 
     async fn fee_history(&self) -> RpcResult<String> {
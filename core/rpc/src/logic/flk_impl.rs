@@ -10,6 +10,8 @@
 use lightning_interfaces::types::{
     AccountInfo,
     Blake3Hash,
+    Delegation,
+    DelegationPool,
     Epoch,
     EpochInfo,
     EventType,
@@ -19,13 +21,17 @@
     Metadata,
     NodeIndex,
     NodeInfo,
+    NodeInfoUpdate,
     NodeInfoWithIndex,
     NodeServed,
     OriginProvider,
     PublicKeys,
     ReportedReputationMeasurements,
+    TokenInfo,
+    Tokens,
     TotalServed,
     TransactionRequest,
+    UnbondingEntry,
     Value,
 };
 use lightning_interfaces::{NodePagingParams, WithdrawPagingParams};
@@ -73,7 +79,11 @@ async fn get_bandwidth_balance(&self, pk: EthAddress, epoch: Option<u64>) -> Rpc
             .unwrap_or(0))
     }
 
-    async fn get_locked(&self, pk: NodePublicKey, epoch: Option<u64>) -> RpcResult<HpUfixed<18>> {
+    async fn get_locked(
+        &self,
+        pk: NodePublicKey,
+        epoch: Option<u64>,
+    ) -> RpcResult<Vec<UnbondingEntry>> {
         Ok(self
             .data
             .query_runner(epoch)
@@ -82,9 +92,9 @@ async fn get_locked(&self, pk: NodePublicKey, epoch: Option<u64>) -> RpcResult<H
             .and_then(|node_idx| {
                 self.data
                     .query_runner
-                    .get_node_info::<HpUfixed<18>>(&node_idx, |n| n.stake.locked)
+                    .get_node_info::<Vec<UnbondingEntry>>(&node_idx, |n| n.stake.unbonding.0)
             })
-            .unwrap_or(HpUfixed::<18>::zero()))
+            .unwrap_or_default())
     }
 
     async fn get_staked(&self, pk: NodePublicKey, epoch: Option<u64>) -> RpcResult<HpUfixed<18>> {
@@ -110,8 +120,8 @@ async fn get_stables_balance(
             .data
             .query_runner(epoch)
             .await?
-            .get_account_info::<HpUfixed<6>>(&pk, |a| a.stables_balance)
-            .unwrap_or(HpUfixed::<6>::zero()))
+            .get_token_balance(&pk, &Tokens::USDC)
+            .convert_precision())
     }
 
     async fn get_stake_locked_until(
@@ -141,8 +151,16 @@ async fn get_locked_time(&self, pk: NodePublicKey, epoch: Option<u64>) -> RpcRes
             .and_then(|node_idx| {
                 self.data
                     .query_runner
-                    .get_node_info::<Epoch>(&node_idx, |n| n.stake.locked_until)
+                    .get_node_info::<Option<Epoch>>(&node_idx, |n| {
+                        n.stake
+                            .unbonding
+                            .0
+                            .iter()
+                            .map(|entry| entry.locked_until)
+                            .max()
+                    })
             })
+            .flatten()
             .unwrap_or(0))
     }
 
@@ -476,6 +494,69 @@ async fn get_withdraws(
         Ok(self.data.query_runner(epoch).await?.get_withdraws(paging))
     }
 
+    async fn get_delegation(
+        &self,
+        delegator: EthAddress,
+        pk: NodePublicKey,
+        epoch: Option<u64>,
+    ) -> RpcResult<Option<Delegation>> {
+        let query_runner = self.data.query_runner(epoch).await?;
+        Ok(query_runner
+            .pubkey_to_index(&pk)
+            .and_then(|node_idx| query_runner.get_delegation(&delegator, &node_idx)))
+    }
+
+    async fn get_delegations(
+        &self,
+        delegator: EthAddress,
+        epoch: Option<u64>,
+    ) -> RpcResult<Vec<(NodeIndex, Delegation)>> {
+        Ok(self
+            .data
+            .query_runner(epoch)
+            .await?
+            .get_delegations(&delegator))
+    }
+
+    async fn get_delegation_pool(
+        &self,
+        pk: NodePublicKey,
+        epoch: Option<u64>,
+    ) -> RpcResult<Option<DelegationPool>> {
+        let query_runner = self.data.query_runner(epoch).await?;
+        Ok(query_runner
+            .pubkey_to_index(&pk)
+            .and_then(|node_idx| query_runner.get_delegation_pool(&node_idx)))
+    }
+
+    async fn get_pending_node_info_update(
+        &self,
+        pk: NodePublicKey,
+        epoch: Option<u64>,
+    ) -> RpcResult<Option<NodeInfoUpdate>> {
+        let query_runner = self.data.query_runner(epoch).await?;
+        Ok(query_runner
+            .pubkey_to_index(&pk)
+            .and_then(|node_idx| query_runner.get_pending_node_info_update(&node_idx)))
+    }
+
+    async fn get_tokens(&self, epoch: Option<u64>) -> RpcResult<Vec<(EthAddress, TokenInfo)>> {
+        Ok(self.data.query_runner(epoch).await?.get_tokens())
+    }
+
+    async fn get_token_balance(
+        &self,
+        pk: EthAddress,
+        token: Tokens,
+        epoch: Option<u64>,
+    ) -> RpcResult<HpUfixed<18>> {
+        Ok(self
+            .data
+            .query_runner(epoch)
+            .await?
+            .get_token_balance(&pk, &token))
+    }
+
     async fn handle_subscription(
         &self,
         pending: PendingSubscriptionSink,
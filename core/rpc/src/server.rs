@@ -7,6 +7,8 @@
 use hmac::{Hmac, Mac};
 use hyper::body::HttpBody;
 use hyper::{Body, Request, Response};
+use lightning_interfaces::types::ServiceId;
+use lightning_interfaces::ServiceStatus;
 use sha2::Sha256;
 use tower::Service as TowerService;
 
@@ -19,12 +21,16 @@
 pub const LIGHTINING_NONCE_HEADER: &str = "X-Lightning-Nonce";
 pub const MAX_HMAC_BODY_SIZE: usize = 1024 * 10;
 
+/// Returns the status of the services running on the node, reported by the health endpoint.
+pub type ServiceStatuses = Arc<dyn Fn() -> Vec<(ServiceId, ServiceStatus)> + Send + Sync>;
+
 /// A struct that implements the Service trait for the RPC server.
 pub struct RpcService<MainModule, AdminModule> {
     pub main_server: MainModule,
     pub admin_server: AdminModule,
     secret: Arc<[u8; 32]>,
     nonce: Arc<AtomicU32>,
+    service_statuses: ServiceStatuses,
 }
 
 impl<X, Y> Clone for RpcService<X, Y>
@@ -38,6 +44,7 @@ fn clone(&self) -> Self {
             admin_server: self.admin_server.clone(),
             secret: self.secret.clone(),
             nonce: self.nonce.clone(),
+            service_statuses: self.service_statuses.clone(),
         }
     }
 }
@@ -71,12 +78,18 @@ impl<X> ServiceMarker for X
 }
 
 impl<MainModule, AdminModule> RpcService<MainModule, AdminModule> {
-    pub fn new(main_server: MainModule, admin_server: AdminModule, secret: [u8; 32]) -> Self {
+    pub fn new(
+        main_server: MainModule,
+        admin_server: AdminModule,
+        secret: [u8; 32],
+        service_statuses: ServiceStatuses,
+    ) -> Self {
         Self {
             main_server,
             admin_server,
             secret: Arc::new(secret),
             nonce: Arc::new(0.into()),
+            service_statuses,
         }
     }
 }
@@ -114,11 +127,12 @@ pub fn route(
         // ```
         match path.as_str() {
             "/health" => {
-                let fut = async {
-                    let res = health().await;
+                let services = (self.service_statuses)();
+                let fut = async move {
+                    let (status, res) = health(&services).await;
 
                     hyper::Response::builder()
-                        .status(hyper::StatusCode::OK)
+                        .status(status)
                         .body(hyper::Body::from(res))
                         .map_err(|e| e.into()) // box
                 };
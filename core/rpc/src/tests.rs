@@ -6,6 +6,7 @@
 use hp_fixed::unsigned::HpUfixed;
 use lightning_application::env::ApplicationStateTree;
 use lightning_interfaces::prelude::*;
+use lightning_interfaces::ServiceStatus;
 use lightning_test_utils::e2e::{
     DowncastToTestFullNode,
     TestFullNodeComponentsWithMockConsensus,
@@ -24,7 +25,10 @@
     Staking,
     StateProofKey,
     StateProofValue,
+    Tokens,
     TotalServed,
+    UnbondingEntry,
+    UnbondingQueue,
     UpdateMethod,
     Value,
 };
@@ -35,6 +39,7 @@
 use types::ProtocolParamKey;
 
 use crate::api::{AdminApiClient, FleekApiClient};
+use crate::health;
 
 #[tokio::test]
 async fn test_rpc_send_txn() {
@@ -132,8 +137,7 @@ async fn test_rpc_get_staked() {
             genesis.node_info[0].stake = Staking {
                 staked: 1_000_u32.into(),
                 stake_locked_until: 365,
-                locked: 0_u32.into(),
-                locked_until: 0,
+                unbonding: Default::default(),
             };
         })
         .build()
@@ -186,6 +190,48 @@ async fn test_rpc_get_stables_balance() {
     network.shutdown().await;
 }
 
+#[tokio::test]
+async fn test_rpc_get_tokens() {
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let eth_address: EthAddress = owner_secret_key.to_pk().into();
+    let mut network = TestNetwork::builder()
+        .with_committee_nodes::<TestFullNodeComponentsWithMockConsensus>(1)
+        .await
+        .with_genesis_mutator(move |genesis| {
+            genesis.account.push(GenesisAccount {
+                public_key: owner_secret_key.to_pk().into(),
+                flk_balance: HpUfixed::<18>::from(1_000_u32),
+                stables_balance: 100,
+                bandwidth_balance: 100,
+            });
+        })
+        .build()
+        .await
+        .unwrap();
+    let node = network
+        .node(0)
+        .downcast::<TestFullNodeComponentsWithMockConsensus>();
+    let client = node.rpc_client().unwrap();
+
+    let tokens = FleekApiClient::get_tokens(&client, None).await.unwrap();
+    let symbols: Vec<_> = tokens
+        .iter()
+        .map(|(_, info)| info.symbol.as_str())
+        .collect();
+    assert_eq!(symbols, vec!["USDC", "FLK"]);
+
+    let response = FleekApiClient::get_token_balance(&client, eth_address, Tokens::USDC, None)
+        .await
+        .unwrap();
+    assert_eq!(HpUfixed::<18>::from(1_00_u32), response);
+    let response = FleekApiClient::get_token_balance(&client, eth_address, Tokens::FLK, None)
+        .await
+        .unwrap();
+    assert_eq!(HpUfixed::<18>::from(1_000_u32), response);
+
+    network.shutdown().await;
+}
+
 #[tokio::test]
 async fn test_rpc_get_stake_locked_until() {
     let mut network = TestNetwork::builder()
@@ -195,8 +241,7 @@ async fn test_rpc_get_stake_locked_until() {
             genesis.node_info[0].stake = Staking {
                 staked: 1_000_u32.into(),
                 stake_locked_until: 365,
-                locked: 0_u32.into(),
-                locked_until: 0,
+                unbonding: Default::default(),
             };
         })
         .build()
@@ -227,8 +272,10 @@ async fn test_rpc_get_locked_time() {
             genesis.node_info[0].stake = Staking {
                 staked: 1_000_u32.into(),
                 stake_locked_until: 365,
-                locked: 0_u32.into(),
-                locked_until: 2,
+                unbonding: UnbondingQueue(vec![UnbondingEntry {
+                    amount: 500_u32.into(),
+                    locked_until: 2,
+                }]),
             };
         })
         .build()
@@ -259,8 +306,16 @@ async fn test_rpc_get_locked() {
             genesis.node_info[0].stake = Staking {
                 staked: 1_000_u32.into(),
                 stake_locked_until: 365,
-                locked: 500_u32.into(),
-                locked_until: 2,
+                unbonding: UnbondingQueue(vec![
+                    UnbondingEntry {
+                        amount: 500_u32.into(),
+                        locked_until: 2,
+                    },
+                    UnbondingEntry {
+                        amount: 200_u32.into(),
+                        locked_until: 4,
+                    },
+                ]),
             };
         })
         .build()
@@ -277,7 +332,19 @@ async fn test_rpc_get_locked() {
     )
     .await
     .unwrap();
-    assert_eq!(HpUfixed::<18>::from(500_u32), response);
+    assert_eq!(
+        vec![
+            UnbondingEntry {
+                amount: 500_u32.into(),
+                locked_until: 2,
+            },
+            UnbondingEntry {
+                amount: 200_u32.into(),
+                locked_until: 4,
+            },
+        ],
+        response
+    );
 
     network.shutdown().await;
 }
@@ -798,7 +865,6 @@ async fn test_rpc_get_state_proof() {
         value.clone(),
         StateProofValue::Accounts(AccountInfo {
             flk_balance: 1000u64.into(),
-            stables_balance: HpUfixed::zero(),
             bandwidth_balance: 0,
             nonce: 0,
             client_key: None,
@@ -813,7 +879,6 @@ async fn test_rpc_get_state_proof() {
             owner_eth_address,
             AccountInfo {
                 flk_balance: 1000u64.into(),
-                stables_balance: HpUfixed::zero(),
                 bandwidth_balance: 0,
                 nonce: 0,
                 client_key: None,
@@ -930,3 +995,14 @@ pub async fn wait_for_aggregate_checkpoint<F>(
     )
     .await
 }
+
+#[tokio::test]
+async fn test_health_is_unavailable_when_a_service_is_not_up() {
+    let (status, res) = health(&[(0, ServiceStatus::Ready), (1, ServiceStatus::Starting)]).await;
+    assert_eq!(status, reqwest::StatusCode::OK);
+    assert_eq!(res, "OK\nservice-0: ready\nservice-1: starting");
+
+    let (status, res) = health(&[(0, ServiceStatus::Ready), (1, ServiceStatus::CrashLoop)]).await;
+    assert_eq!(status, reqwest::StatusCode::SERVICE_UNAVAILABLE);
+    assert!(res.starts_with("DEGRADED\n"));
+}
@@ -19,6 +19,10 @@ pub enum TaskScope {
     Single,
     /// Cluster scope for duplicating a task with the current cluster
     Cluster,
+    /// Quorum scope for running a task on `of` random nodes in the current cluster, and
+    /// accepting the response only if at least `min_agree` of them returned the same payload.
+    /// `min_agree` must be a majority of `of`, so that at most one payload can reach it.
+    Quorum { min_agree: u8, of: u8 },
 }
 impl Display for TaskScope {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -26,6 +30,7 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
             TaskScope::Local => f.write_str("local"),
             TaskScope::Single => f.write_str("single"),
             TaskScope::Cluster => f.write_str("cluster"),
+            TaskScope::Quorum { min_agree, of } => write!(f, "quorum:{min_agree}/{of}"),
         }
     }
 }
@@ -36,12 +41,37 @@ fn from_str(s: &str) -> Result<Self, Self::Err> {
             "local" => Ok(TaskScope::Local),
             "single" => Ok(TaskScope::Single),
             "cluster" => Ok(TaskScope::Cluster),
+            s if s.starts_with("quorum:") => {
+                let (min_agree, of) = s["quorum:".len()..]
+                    .split_once('/')
+                    .ok_or_else(|| anyhow!("quorum scope should be 'quorum:<min_agree>/<of>'"))?;
+                TaskScope::quorum(min_agree.parse()?, of.parse()?)
+            },
             s => Err(anyhow!(
-                "scope '{s}' unknown, should be one of 'local', 'single', or 'cluster'"
+                "scope '{s}' unknown, should be one of 'local', 'single', 'cluster', or \
+                 'quorum:<min_agree>/<of>'"
             )),
         }
     }
 }
+impl TaskScope {
+    /// Create a quorum scope, ensuring that an agreement is possible and unambiguous.
+    pub fn quorum(min_agree: u8, of: u8) -> anyhow::Result<Self> {
+        if !is_majority(min_agree, of) {
+            return Err(anyhow!(
+                "invalid quorum {min_agree}/{of}, should be more than half and at most the number \
+                 of nodes"
+            ));
+        }
+        Ok(TaskScope::Quorum { min_agree, of })
+    }
+}
+/// Returns true if `min_agree` is more than half of `of`, and at most `of`.
+pub fn is_majority(min_agree: u8, of: u8) -> bool {
+    min_agree <= of && min_agree as u16 * 2 > of as u16
+}
+/// Note: A quorum scope can not be represented as a single byte, and is encoded as `3`.
+/// Decoding is only supported for the scopes without parameters.
 impl From<u8> for TaskScope {
     fn from(value: u8) -> Self {
         match value {
@@ -57,6 +87,7 @@ fn from(val: TaskScope) -> Self {
             TaskScope::Local => 0,
             TaskScope::Single => 1,
             TaskScope::Cluster => 2,
+            TaskScope::Quorum { .. } => 3,
         }
     }
 }
@@ -89,14 +120,28 @@ pub struct TaskRequest {
     pub service: ServiceId,
     pub timestamp: u64,
     pub payload: Bytes,
+    /// Unix timestamp (in milliseconds) after which a response is no longer useful.
+    #[serde(default)]
+    pub deadline: Option<u64>,
+    /// The service that submitted the task, unset for tasks submitted by the node itself.
+    #[serde(default)]
+    pub origin: Option<ServiceId>,
 }
 impl AutoImplSerde for TaskRequest {}
 impl ToDigest for TaskRequest {
     fn transcript(&self) -> ink_quill::TranscriptBuilder {
-        TranscriptBuilder::empty("FLEEK_TASK_REQUEST")
+        let transcript = TranscriptBuilder::empty("FLEEK_TASK_REQUEST")
             .with("SERVICE_ID", &self.service)
             .with("TIMESTAMP", &self.timestamp)
-            .with("PAYLOAD", &self.payload.as_ref())
+            .with("PAYLOAD", &self.payload.as_ref());
+        let transcript = match &self.deadline {
+            Some(deadline) => transcript.with("DEADLINE", deadline),
+            None => transcript,
+        };
+        match &self.origin {
+            Some(origin) => transcript.with("ORIGIN", origin),
+            None => transcript,
+        }
     }
 }
 
@@ -0,0 +1,199 @@
+//! Health probes and the restart policy of the service processes.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use lightning_interfaces::ServiceStatus;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HealthConfig {
+    /// How often the service is probed over its control socket.
+    #[serde(with = "humantime_serde")]
+    pub probe_interval: Duration,
+    /// The number of consecutive probes a service can miss before it is restarted. Only services
+    /// that announce themselves on the control socket are probed.
+    pub probe_failure_threshold: u32,
+    /// The delay before restarting a crashed service, doubled after every crash.
+    #[serde(with = "humantime_serde")]
+    pub initial_backoff: Duration,
+    /// The maximum delay before restarting a crashed service.
+    #[serde(with = "humantime_serde")]
+    pub max_backoff: Duration,
+    /// A process that ran for this long before crashing is restarted after the initial backoff.
+    #[serde(with = "humantime_serde")]
+    pub healthy_uptime: Duration,
+    /// The number of crashes within `crash_loop_window` after which restarts are suspended.
+    pub crash_loop_threshold: u32,
+    /// The time window in which the crashes of a service are counted.
+    #[serde(with = "humantime_serde")]
+    pub crash_loop_window: Duration,
+    /// How long restarts are suspended for once a service is crash looping.
+    #[serde(with = "humantime_serde")]
+    pub crash_loop_cooldown: Duration,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            probe_interval: Duration::from_secs(5),
+            probe_failure_threshold: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(30),
+            healthy_uptime: Duration::from_secs(60),
+            crash_loop_threshold: 5,
+            crash_loop_window: Duration::from_secs(120),
+            crash_loop_cooldown: Duration::from_secs(300),
+        }
+    }
+}
+
+/// The health of a service, shared between its control loop and its process supervisor.
+pub struct ServiceHealth {
+    status: Mutex<ServiceStatus>,
+    /// Notified when the process stops answering the probes and has to be restarted.
+    unresponsive: Notify,
+}
+
+impl Default for ServiceHealth {
+    fn default() -> Self {
+        Self {
+            status: Mutex::new(ServiceStatus::Starting),
+            unresponsive: Notify::new(),
+        }
+    }
+}
+
+impl ServiceHealth {
+    pub fn status(&self) -> ServiceStatus {
+        *self.status.lock().unwrap()
+    }
+
+    pub fn set_status(&self, status: ServiceStatus) {
+        *self.status.lock().unwrap() = status;
+    }
+
+    /// Update the readiness reported by the process. Ignored while the process is not running.
+    pub fn set_ready(&self, ready: bool) {
+        let mut status = self.status.lock().unwrap();
+        if status.is_up() {
+            *status = if ready {
+                ServiceStatus::Ready
+            } else {
+                ServiceStatus::Starting
+            };
+        }
+    }
+
+    /// Signal the supervisor that the process has stopped answering the probes.
+    pub fn report_unresponsive(&self) {
+        self.unresponsive.notify_waiters();
+    }
+
+    /// Wait until the process has stopped answering the probes.
+    pub async fn unresponsive(&self) {
+        self.unresponsive.notified().await
+    }
+}
+
+/// The probes sent over a single control connection, that is to a single process. The process
+/// announces that it answers probes by acknowledging the probe with nonce `0` on its own.
+#[derive(Default)]
+pub struct Prober {
+    nonce: u64,
+    pending: bool,
+    missed: u32,
+    answered: bool,
+}
+
+impl Prober {
+    /// Returns the nonce of the next probe to send, or `None` if the process does not answer
+    /// probes.
+    pub fn next_probe(&mut self) -> Option<u64> {
+        if !self.answered {
+            return None;
+        }
+        if self.pending {
+            self.missed += 1;
+        }
+        self.nonce += 1;
+        self.pending = true;
+        Some(self.nonce)
+    }
+
+    /// Returns true if the process missed the given number of probes in a row.
+    pub fn is_unresponsive(&self, failure_threshold: u32) -> bool {
+        self.missed >= failure_threshold
+    }
+
+    /// Handle the answer to a probe, returns false if it answers an outdated probe.
+    pub fn ack(&mut self, nonce: u64) -> bool {
+        if nonce != self.nonce {
+            return false;
+        }
+        self.pending = false;
+        self.missed = 0;
+        self.answered = true;
+        true
+    }
+}
+
+/// What to do after the process of a service has exited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Restart {
+    /// Restart the process after the given delay.
+    After(Duration),
+    /// The service is crash looping, restart it only after the given cooldown.
+    CrashLoop(Duration),
+}
+
+/// Exponential backoff of the restarts of a service, with a circuit breaker that suspends the
+/// restarts of a crash looping service.
+pub struct RestartPolicy {
+    config: HealthConfig,
+    backoff: Duration,
+    crashes: VecDeque<Instant>,
+}
+
+impl RestartPolicy {
+    pub fn new(config: HealthConfig) -> Self {
+        Self {
+            backoff: config.initial_backoff,
+            crashes: VecDeque::new(),
+            config,
+        }
+    }
+
+    /// Record the crash of a process that ran for `uptime`, and return when to restart it.
+    pub fn on_crash(&mut self, now: Instant, uptime: Duration) -> Restart {
+        if uptime >= self.config.healthy_uptime {
+            self.backoff = self.config.initial_backoff;
+        }
+
+        self.crashes.push_back(now);
+        while let Some(&crash) = self.crashes.front() {
+            if now.duration_since(crash) < self.config.crash_loop_window {
+                break;
+            }
+            self.crashes.pop_front();
+        }
+
+        if self.crashes.len() >= self.config.crash_loop_threshold as usize {
+            self.reset();
+            return Restart::CrashLoop(self.config.crash_loop_cooldown);
+        }
+
+        let delay = self.backoff;
+        self.backoff = (self.backoff * 2).min(self.config.max_backoff);
+        Restart::After(delay)
+    }
+
+    /// Forget about the previous crashes, for example when the binary of the service changes.
+    pub fn reset(&mut self) {
+        self.backoff = self.config.initial_backoff;
+        self.crashes.clear();
+    }
+}
@@ -1,6 +1,8 @@
 // it's not dead, it's just not born yet.
 #![allow(dead_code)]
 
+pub mod health;
+pub mod sandbox;
 pub mod service;
 pub mod shim;
 pub mod test_services;
@@ -0,0 +1,763 @@
+//! Resource limits and sandboxing of the service processes.
+
+use std::ffi::CString;
+use std::fs::{self, Permissions};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+use std::sync::OnceLock;
+use std::{fmt, io};
+
+use lightning_interfaces::types::ServiceId;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+/// The period of the cgroup CPU quota in microseconds.
+const CPU_PERIOD: u64 = 100_000;
+/// The mount point of the cgroup v2 hierarchy.
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SandboxConfig {
+    /// The limits of every service that has no entry in `services`.
+    pub limits: ResourceLimits,
+    /// The limits of individual services.
+    pub services: Vec<ServiceLimits>,
+}
+
+impl SandboxConfig {
+    /// Returns the limits of the given service.
+    pub fn limits(&self, service_id: ServiceId) -> &ResourceLimits {
+        self.services
+            .iter()
+            .find(|s| s.service_id == service_id)
+            .map(|s| &s.limits)
+            .unwrap_or(&self.limits)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ServiceLimits {
+    pub service_id: ServiceId,
+    pub limits: ResourceLimits,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResourceLimits {
+    /// The maximum size of the virtual memory of the process in megabytes.
+    pub max_memory_mb: Option<u64>,
+    /// The maximum CPU time the process can consume in seconds.
+    pub max_cpu_time_secs: Option<u64>,
+    /// The maximum number of file descriptors the process can open.
+    pub max_open_files: Option<u64>,
+    /// The maximum size of a file the process can write in megabytes.
+    pub max_file_size_mb: Option<u64>,
+    /// The memory quota of the cgroup of the service in megabytes. Requires cgroup v2 and a
+    /// cgroup delegated to the node, the service is not started otherwise.
+    pub cgroup_memory_mb: Option<u64>,
+    /// The CPU quota of the cgroup of the service in percent of a single core. Requires cgroup v2
+    /// and a cgroup delegated to the node, the service is not started otherwise.
+    pub cgroup_cpu_percent: Option<u32>,
+    /// Kill the process when it makes a system call that is not on the allowlist.
+    pub seccomp: bool,
+    /// Run the process in a private working directory that is cleared on every start.
+    pub private_working_dir: bool,
+}
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        Self {
+            max_memory_mb: None,
+            max_cpu_time_secs: None,
+            max_open_files: Some(4096),
+            max_file_size_mb: None,
+            cgroup_memory_mb: None,
+            cgroup_cpu_percent: None,
+            seccomp: false,
+            private_working_dir: false,
+        }
+    }
+}
+
+/// A limit that terminated a service process.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LimitViolation {
+    Memory,
+    CpuTime,
+    FileSize,
+    Seccomp,
+}
+
+impl LimitViolation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LimitViolation::Memory => "memory",
+            LimitViolation::CpuTime => "cpu_time",
+            LimitViolation::FileSize => "file_size",
+            LimitViolation::Seccomp => "seccomp",
+        }
+    }
+}
+
+impl fmt::Display for LimitViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The sandbox of a service, applied to the process every time it is (re-)started.
+pub struct Sandbox {
+    limits: ResourceLimits,
+    work_dir: PathBuf,
+    cgroup: Option<Cgroup>,
+}
+
+impl Sandbox {
+    /// Create the sandbox of a service. Cgroup limits require cgroup v2 and a cgroup of the node
+    /// that is delegated to it, creating the sandbox fails if they can not be applied.
+    pub fn new(name: &str, limits: ResourceLimits, work_dir: PathBuf) -> io::Result<Self> {
+        let cgroup = if limits.cgroup_memory_mb.is_some() || limits.cgroup_cpu_percent.is_some() {
+            Some(Cgroup::create(name, &limits)?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            limits,
+            work_dir,
+            cgroup,
+        })
+    }
+
+    /// Apply the limits to the command of the service.
+    pub fn apply(&mut self, command: &mut Command) -> io::Result<()> {
+        if self.limits.private_working_dir {
+            let _ = fs::remove_dir_all(&self.work_dir);
+            fs::create_dir_all(&self.work_dir)?;
+            fs::set_permissions(&self.work_dir, Permissions::from_mode(0o700))?;
+            command
+                .current_dir(&self.work_dir)
+                .env("HOME", &self.work_dir)
+                .env("TMPDIR", &self.work_dir);
+        }
+
+        // Everything is prepared here, since the child must not allocate after the fork.
+        let rlimits = self.rlimits();
+        let cgroup_procs = match &mut self.cgroup {
+            Some(cgroup) => {
+                cgroup.take_oom_kill();
+                Some(cgroup.procs_path()?)
+            },
+            None => None,
+        };
+        let mut filter = if self.limits.seccomp {
+            Some(seccomp::filter()?)
+        } else {
+            None
+        };
+
+        // Safety: The closure only makes system calls and doesn't allocate.
+        unsafe {
+            command.pre_exec(move || {
+                if let Some(path) = &cgroup_procs {
+                    join_cgroup(path);
+                }
+                for (resource, limit) in &rlimits {
+                    if libc::setrlimit(*resource, limit) != 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                }
+                if let Some(filter) = &mut filter {
+                    seccomp::install(filter)?;
+                }
+                Ok(())
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Returns the limit that terminated the process with the given exit status, if any.
+    pub fn violation(&mut self, status: ExitStatus) -> Option<LimitViolation> {
+        match status.signal()? {
+            libc::SIGSYS => Some(LimitViolation::Seccomp),
+            libc::SIGXCPU => Some(LimitViolation::CpuTime),
+            libc::SIGXFSZ => Some(LimitViolation::FileSize),
+            libc::SIGKILL => self
+                .cgroup
+                .as_mut()?
+                .take_oom_kill()
+                .then_some(LimitViolation::Memory),
+            _ => None,
+        }
+    }
+
+    /// Returns the resource limits of the process. A limit is never raised above the limit of the
+    /// node itself.
+    fn rlimits(&self) -> Vec<(RlimitResource, libc::rlimit)> {
+        const MB: u64 = 1024 * 1024;
+        let limits = [
+            (libc::RLIMIT_AS, self.limits.max_memory_mb.map(|v| v * MB)),
+            (libc::RLIMIT_CPU, self.limits.max_cpu_time_secs),
+            (libc::RLIMIT_NOFILE, self.limits.max_open_files),
+            (
+                libc::RLIMIT_FSIZE,
+                self.limits.max_file_size_mb.map(|v| v * MB),
+            ),
+        ];
+
+        limits
+            .into_iter()
+            .filter_map(|(resource, limit)| {
+                let limit = limit? as libc::rlim_t;
+                let mut current = libc::rlimit {
+                    rlim_cur: 0,
+                    rlim_max: 0,
+                };
+                // Safety: `current` is a valid pointer.
+                if unsafe { libc::getrlimit(resource, &mut current) } != 0 {
+                    return None;
+                }
+                let soft = limit.min(current.rlim_max);
+                // The process gets a signal at the soft CPU limit and is killed at the hard limit,
+                // leave room for the signal so that the violation can be told apart.
+                let hard = if resource == libc::RLIMIT_CPU {
+                    soft.saturating_add(1).min(current.rlim_max)
+                } else {
+                    soft
+                };
+                Some((
+                    resource,
+                    libc::rlimit {
+                        rlim_cur: soft,
+                        rlim_max: hard,
+                    },
+                ))
+            })
+            .collect()
+    }
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type RlimitResource = libc::__rlimit_resource_t;
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+type RlimitResource = libc::c_int;
+
+/// Move the calling process into the cgroup with the given `cgroup.procs` file. Failing to do so
+/// only means the process runs without the cgroup limits.
+fn join_cgroup(procs_path: &CString) {
+    // Safety: The path is a valid nul-terminated string.
+    unsafe {
+        let fd = libc::open(procs_path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+        if fd >= 0 {
+            libc::write(fd, b"0".as_ptr() as *const libc::c_void, 1);
+            libc::close(fd);
+        }
+    }
+}
+
+/// A cgroup v2 group of a service.
+struct Cgroup {
+    path: PathBuf,
+    oom_kills: u64,
+}
+
+impl Cgroup {
+    fn create(name: &str, limits: &ResourceLimits) -> io::Result<Self> {
+        let path = services_cgroup()?.join(name);
+        if !path.exists() {
+            fs::create_dir(&path)?;
+        }
+        if let Some(memory_mb) = limits.cgroup_memory_mb {
+            fs::write(
+                path.join("memory.max"),
+                (memory_mb * 1024 * 1024).to_string(),
+            )?;
+        }
+        if let Some(percent) = limits.cgroup_cpu_percent {
+            let quota = percent as u64 * CPU_PERIOD / 100;
+            fs::write(path.join("cpu.max"), format!("{quota} {CPU_PERIOD}"))?;
+        }
+
+        let oom_kills = read_oom_kills(&path);
+        Ok(Self { path, oom_kills })
+    }
+
+    fn procs_path(&self) -> io::Result<CString> {
+        CString::new(self.path.join("cgroup.procs").as_os_str().as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+    }
+
+    /// Returns true if a process of the group was killed for running out of memory since the
+    /// last call.
+    fn take_oom_kill(&mut self) -> bool {
+        let oom_kills = read_oom_kills(&self.path);
+        let killed = oom_kills > self.oom_kills;
+        self.oom_kills = oom_kills;
+        killed
+    }
+}
+
+/// Returns the cgroup the groups of the services are created in, which is the cgroup of the node.
+/// A cgroup with processes can't enable controllers for its children, so on first use the
+/// processes of the node are moved into a leaf group next to the groups of the services.
+fn services_cgroup() -> io::Result<&'static Path> {
+    static CGROUP: OnceLock<Result<PathBuf, String>> = OnceLock::new();
+    CGROUP
+        .get_or_init(|| delegate_cgroup().map_err(|e| e.to_string()))
+        .as_deref()
+        .map_err(|e| io::Error::other(e.clone()))
+}
+
+fn delegate_cgroup() -> io::Result<PathBuf> {
+    let parent = own_cgroup()?;
+    // The root cgroup is exempt from the rule.
+    if parent != Path::new(CGROUP_ROOT) {
+        let leaf = parent.join("node");
+        if !leaf.exists() {
+            fs::create_dir(&leaf)?;
+        }
+        for pid in fs::read_to_string(parent.join("cgroup.procs"))?.lines() {
+            match fs::write(leaf.join("cgroup.procs"), pid) {
+                // The process exited in the meantime.
+                Err(e) if e.raw_os_error() == Some(libc::ESRCH) => {},
+                res => res?,
+            }
+        }
+    }
+
+    fs::write(parent.join("cgroup.subtree_control"), "+memory +cpu").map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!(
+                "failed to enable the memory and cpu controllers of {}, the cgroup must be \
+                 delegated to the node: {e}",
+                parent.display()
+            ),
+        )
+    })?;
+    Ok(parent)
+}
+
+/// Returns the path of the cgroup v2 group of the node.
+fn own_cgroup() -> io::Result<PathBuf> {
+    let content = fs::read_to_string("/proc/self/cgroup")?;
+    // The unified hierarchy always has the id 0 and no controller list.
+    let path = content
+        .lines()
+        .find_map(|line| line.strip_prefix("0::"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "cgroup v2 is not available"))?;
+    Ok(Path::new(CGROUP_ROOT).join(path.trim_start_matches('/')))
+}
+
+fn read_oom_kills(path: &Path) -> u64 {
+    fs::read_to_string(path.join("memory.events"))
+        .ok()
+        .and_then(|events| {
+            events
+                .lines()
+                .find_map(|line| line.strip_prefix("oom_kill "))
+                .and_then(|count| count.trim().parse().ok())
+        })
+        .unwrap_or(0)
+}
+
+#[cfg(target_os = "linux")]
+mod seccomp {
+    use std::io;
+
+    use libc::sock_filter;
+
+    const AUDIT_ARCH_X86_64: u32 = 0xc000_003e;
+    const AUDIT_ARCH_AARCH64: u32 = 0xc000_00b7;
+
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: u32 = AUDIT_ARCH_X86_64;
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: u32 = AUDIT_ARCH_AARCH64;
+
+    /// The offsets of the fields of `seccomp_data`. Only the lower half of the first argument is
+    /// checked, which covers arguments of type `int`.
+    const NR_OFFSET: u32 = 0;
+    const ARCH_OFFSET: u32 = 4;
+    const ARG0_OFFSET: u32 = 16;
+
+    /// The values a system call only allows for its first argument.
+    enum FirstArg {
+        /// The process id of the service itself, which is only known once it is started.
+        OwnPid,
+        OneOf(&'static [u32]),
+    }
+
+    /// The system calls a service is only allowed to make with certain first arguments.
+    const RESTRICTED: &[(libc::c_long, FirstArg)] = &[
+        // Signals can only be sent to the service itself, and not to the node or other services.
+        (libc::SYS_kill, FirstArg::OwnPid),
+        (libc::SYS_tgkill, FirstArg::OwnPid),
+        // No raw, packet or netlink sockets.
+        (
+            libc::SYS_socket,
+            FirstArg::OneOf(&[
+                libc::AF_UNIX as u32,
+                libc::AF_INET as u32,
+                libc::AF_INET6 as u32,
+            ]),
+        ),
+    ];
+
+    /// A seccomp program, with the instructions that compare against the process id of the
+    /// service.
+    pub struct Filter {
+        program: Vec<sock_filter>,
+        own_pid: Vec<usize>,
+    }
+
+    /// The system calls a service is allowed to make. This covers the runtime of the services, but
+    /// nothing that changes the system or inspects other processes.
+    const ALLOWLIST: &[libc::c_long] = &[
+        libc::SYS_read,
+        libc::SYS_write,
+        libc::SYS_readv,
+        libc::SYS_writev,
+        libc::SYS_pread64,
+        libc::SYS_pwrite64,
+        libc::SYS_preadv,
+        libc::SYS_pwritev,
+        libc::SYS_openat,
+        libc::SYS_close,
+        libc::SYS_close_range,
+        libc::SYS_fstat,
+        libc::SYS_newfstatat,
+        libc::SYS_statx,
+        libc::SYS_statfs,
+        libc::SYS_fstatfs,
+        libc::SYS_lseek,
+        libc::SYS_mmap,
+        libc::SYS_mprotect,
+        libc::SYS_munmap,
+        libc::SYS_mremap,
+        libc::SYS_madvise,
+        libc::SYS_mincore,
+        libc::SYS_mlock,
+        libc::SYS_munlock,
+        libc::SYS_msync,
+        libc::SYS_brk,
+        libc::SYS_pkey_alloc,
+        libc::SYS_pkey_free,
+        libc::SYS_pkey_mprotect,
+        libc::SYS_rt_sigaction,
+        libc::SYS_rt_sigprocmask,
+        libc::SYS_rt_sigreturn,
+        libc::SYS_rt_sigtimedwait,
+        libc::SYS_sigaltstack,
+        libc::SYS_ioctl,
+        libc::SYS_fcntl,
+        libc::SYS_dup,
+        libc::SYS_dup3,
+        libc::SYS_pipe2,
+        libc::SYS_socketpair,
+        libc::SYS_connect,
+        libc::SYS_accept4,
+        libc::SYS_bind,
+        libc::SYS_listen,
+        libc::SYS_sendto,
+        libc::SYS_recvfrom,
+        libc::SYS_sendmsg,
+        libc::SYS_recvmsg,
+        libc::SYS_sendmmsg,
+        libc::SYS_recvmmsg,
+        libc::SYS_shutdown,
+        libc::SYS_getsockname,
+        libc::SYS_getpeername,
+        libc::SYS_setsockopt,
+        libc::SYS_getsockopt,
+        libc::SYS_clone,
+        libc::SYS_clone3,
+        // The filter is installed before the binary of the service is executed.
+        libc::SYS_execve,
+        libc::SYS_exit,
+        libc::SYS_exit_group,
+        libc::SYS_wait4,
+        libc::SYS_waitid,
+        libc::SYS_pidfd_open,
+        libc::SYS_uname,
+        libc::SYS_getcwd,
+        libc::SYS_chdir,
+        libc::SYS_fchdir,
+        libc::SYS_mkdirat,
+        libc::SYS_unlinkat,
+        libc::SYS_renameat2,
+        libc::SYS_linkat,
+        libc::SYS_symlinkat,
+        libc::SYS_readlinkat,
+        libc::SYS_fchmod,
+        libc::SYS_fchmodat,
+        libc::SYS_fchown,
+        libc::SYS_fchownat,
+        libc::SYS_faccessat,
+        libc::SYS_faccessat2,
+        libc::SYS_ftruncate,
+        libc::SYS_fallocate,
+        libc::SYS_fsync,
+        libc::SYS_fdatasync,
+        libc::SYS_flock,
+        libc::SYS_getdents64,
+        libc::SYS_copy_file_range,
+        libc::SYS_splice,
+        libc::SYS_utimensat,
+        libc::SYS_umask,
+        libc::SYS_gettimeofday,
+        libc::SYS_clock_gettime,
+        libc::SYS_clock_getres,
+        libc::SYS_clock_nanosleep,
+        libc::SYS_nanosleep,
+        libc::SYS_getitimer,
+        libc::SYS_setitimer,
+        libc::SYS_prlimit64,
+        libc::SYS_getrusage,
+        libc::SYS_sysinfo,
+        libc::SYS_times,
+        libc::SYS_getuid,
+        libc::SYS_geteuid,
+        libc::SYS_getgid,
+        libc::SYS_getegid,
+        libc::SYS_getresuid,
+        libc::SYS_getresgid,
+        libc::SYS_getgroups,
+        libc::SYS_getpid,
+        libc::SYS_getppid,
+        libc::SYS_gettid,
+        libc::SYS_getpgid,
+        libc::SYS_setpgid,
+        libc::SYS_futex,
+        libc::SYS_set_robust_list,
+        libc::SYS_get_robust_list,
+        libc::SYS_set_tid_address,
+        libc::SYS_rseq,
+        libc::SYS_membarrier,
+        libc::SYS_sched_yield,
+        libc::SYS_sched_getaffinity,
+        libc::SYS_sched_setaffinity,
+        libc::SYS_sched_getparam,
+        libc::SYS_sched_getscheduler,
+        libc::SYS_getpriority,
+        libc::SYS_prctl,
+        libc::SYS_capget,
+        libc::SYS_epoll_create1,
+        libc::SYS_epoll_ctl,
+        libc::SYS_epoll_pwait,
+        libc::SYS_eventfd2,
+        libc::SYS_timerfd_create,
+        libc::SYS_timerfd_settime,
+        libc::SYS_timerfd_gettime,
+        libc::SYS_signalfd4,
+        libc::SYS_inotify_init1,
+        libc::SYS_inotify_add_watch,
+        libc::SYS_inotify_rm_watch,
+        libc::SYS_ppoll,
+        libc::SYS_pselect6,
+        libc::SYS_getrandom,
+        libc::SYS_memfd_create,
+        libc::SYS_restart_syscall,
+        // The legacy system calls that only exist on x86_64.
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_open,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_stat,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_lstat,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_access,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_poll,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_select,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_pipe,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_dup2,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_epoll_wait,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_epoll_create,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_arch_prctl,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_fork,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_vfork,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_rename,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_renameat,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_mkdir,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_rmdir,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_unlink,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_readlink,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_getdents,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_time,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_getpgrp,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_getrlimit,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_sendfile,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_creat,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_link,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_symlink,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_chmod,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_chown,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_lchown,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_eventfd,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_signalfd,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_inotify_init,
+    ];
+
+    const fn stmt(code: u32, k: u32) -> sock_filter {
+        sock_filter {
+            code: code as u16,
+            jt: 0,
+            jf: 0,
+            k,
+        }
+    }
+
+    const fn jump(code: u32, k: u32, jt: u8, jf: u8) -> sock_filter {
+        sock_filter {
+            code: code as u16,
+            jt,
+            jf,
+            k,
+        }
+    }
+
+    /// Build the BPF program that kills the process on any system call outside of the allowlist.
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    pub fn filter() -> io::Result<Filter> {
+        use libc::{
+            BPF_ABS,
+            BPF_JEQ,
+            BPF_JMP,
+            BPF_K,
+            BPF_LD,
+            BPF_RET,
+            BPF_W,
+            SECCOMP_RET_ALLOW,
+            SECCOMP_RET_KILL_PROCESS,
+        };
+
+        let mut filter = vec![
+            // System calls of another architecture have different numbers.
+            stmt(BPF_LD | BPF_W | BPF_ABS, ARCH_OFFSET),
+            jump(BPF_JMP | BPF_JEQ | BPF_K, AUDIT_ARCH, 1, 0),
+            stmt(BPF_RET | BPF_K, SECCOMP_RET_KILL_PROCESS),
+            stmt(BPF_LD | BPF_W | BPF_ABS, NR_OFFSET),
+        ];
+        for &nr in ALLOWLIST {
+            filter.push(jump(BPF_JMP | BPF_JEQ | BPF_K, nr as u32, 0, 1));
+            filter.push(stmt(BPF_RET | BPF_K, SECCOMP_RET_ALLOW));
+        }
+
+        let mut own_pid = Vec::new();
+        for (nr, arg) in RESTRICTED {
+            let values = match arg {
+                FirstArg::OwnPid => {
+                    own_pid.push(filter.len() + 2);
+                    &[0][..]
+                },
+                FirstArg::OneOf(values) => *values,
+            };
+            let len = values.len() as u8;
+            // Skip the checks of the argument for other system calls. Once the argument is loaded
+            // every path returns, so the number of the system call is only overwritten here.
+            filter.push(jump(BPF_JMP | BPF_JEQ | BPF_K, *nr as u32, 0, len + 3));
+            filter.push(stmt(BPF_LD | BPF_W | BPF_ABS, ARG0_OFFSET));
+            for (i, &value) in values.iter().enumerate() {
+                filter.push(jump(BPF_JMP | BPF_JEQ | BPF_K, value, len - i as u8, 0));
+            }
+            filter.push(stmt(BPF_RET | BPF_K, SECCOMP_RET_KILL_PROCESS));
+            filter.push(stmt(BPF_RET | BPF_K, SECCOMP_RET_ALLOW));
+        }
+
+        filter.push(stmt(BPF_RET | BPF_K, SECCOMP_RET_KILL_PROCESS));
+        Ok(Filter {
+            program: filter,
+            own_pid,
+        })
+    }
+
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    pub fn filter() -> io::Result<Filter> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "seccomp is not supported on this architecture",
+        ))
+    }
+
+    /// Install the filter for the calling process. This is permanent and inherited by children.
+    pub fn install(filter: &mut Filter) -> io::Result<()> {
+        // Safety: `getpid` has no preconditions.
+        let pid = unsafe { libc::getpid() } as u32;
+        for &i in &filter.own_pid {
+            filter.program[i].k = pid;
+        }
+
+        let program = libc::sock_fprog {
+            len: filter.program.len() as libc::c_ushort,
+            filter: filter.program.as_mut_ptr(),
+        };
+        // Safety: The program points to a valid filter which outlives the calls.
+        unsafe {
+            // Required to install a filter without `CAP_SYS_ADMIN`.
+            if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if libc::prctl(
+                libc::PR_SET_SECCOMP,
+                libc::SECCOMP_MODE_FILTER,
+                &program as *const libc::sock_fprog,
+            ) != 0
+            {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod seccomp {
+    use std::io;
+
+    pub struct Filter;
+
+    pub fn filter() -> io::Result<Filter> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "seccomp is only supported on Linux",
+        ))
+    }
+
+    pub fn install(_filter: &mut Filter) -> io::Result<()> {
+        Ok(())
+    }
+}
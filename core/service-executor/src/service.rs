@@ -1,38 +1,64 @@
 use std::error::Error;
+use std::ffi::OsString;
+use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
-use std::process::Stdio;
+use std::process::{ExitStatus, Stdio};
+use std::sync::Mutex;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+use anyhow::{anyhow, bail, Context as _};
 use dashmap::DashMap;
 use fleek_crypto::{ClientPublicKey, NodePublicKey};
 use fn_sdk::ipc_types::{self, IpcMessage, IpcRequest, Response, DELIMITER_SIZE};
 use lightning_interfaces::prelude::*;
 use lightning_interfaces::schema::task_broker::TaskScope;
-use lightning_interfaces::types::{ProtocolParamKey, ProtocolParamValue};
+use lightning_interfaces::types::{
+    Blake3Hash,
+    DeliveryAcknowledgment,
+    DeliveryAcknowledgmentProof,
+    FetcherRequest,
+    FetcherResponse,
+    ProtocolParamKey,
+    ProtocolParamValue,
+    Service,
+    ServiceResources,
+};
+use lightning_interfaces::{ServiceCapabilities, ServiceStatus};
+use lightning_metrics::increment_counter;
 use lightning_utils::application::QueryRunnerExt;
 use rand::seq::SliceRandom;
 use rand::thread_rng;
 use tokio::io::{self, Interest};
 use tokio::net::{UnixListener, UnixStream};
-use tokio::process::Command;
-use tokio::sync::Notify;
+use tokio::process::{Child, Command};
+use tokio::sync::{watch, Notify};
 use tokio::task::JoinSet;
+use tokio::time::MissedTickBehavior;
 use tokio::{pin, select};
 use tracing::instrument;
 use triomphe::Arc;
 
+use crate::health::{HealthConfig, Prober, Restart, RestartPolicy, ServiceHealth};
+use crate::sandbox::{Sandbox, SandboxConfig};
+
 /// The shared object with every service.
 pub struct Context<C: NodeComponents> {
+    pub blockstore: C::BlockstoreInterface,
     pub blockstore_path: PathBuf,
     pub ipc_path: PathBuf,
+    pub data_path: PathBuf,
     pub fetcher_socket: FetcherSocket,
     pub query_runner: c!(C::ApplicationInterface::SyncExecutor),
     pub task_broker: C::TaskBrokerInterface,
+    pub dack_socket: DeliveryAcknowledgmentSocket,
     pub our_public_key: NodePublicKey,
+    pub sandbox: SandboxConfig,
+    pub health: HealthConfig,
 }
 
 impl<C: NodeComponents> Context<C> {
-    pub async fn run(&self, request: ipc_types::Request) -> ipc_types::Response {
+    /// Handle a request of the service with the given id.
+    pub async fn run(&self, service_id: u32, request: ipc_types::Request) -> ipc_types::Response {
         match request {
             ipc_types::Request::QueryClientBandwidth { pk } => {
                 let balance = self
@@ -95,8 +121,11 @@ pub async fn run(&self, request: ipc_types::Request) -> ipc_types::Response {
             ipc_types::Request::Task {
                 depth,
                 scope,
+                min_agree,
+                of,
                 service,
                 payload,
+                deadline,
             } => {
                 let (responses, signatures): (Vec<_>, Vec<_>) = self
                     .task_broker
@@ -105,6 +134,7 @@ pub async fn run(&self, request: ipc_types::Request) -> ipc_types::Response {
                         match scope {
                             0 => TaskScope::Local,
                             1 => TaskScope::Single,
+                            3 => TaskScope::Quorum { min_agree, of },
                             2.. => TaskScope::Cluster,
                         },
                         schema::task_broker::TaskRequest {
@@ -114,6 +144,8 @@ pub async fn run(&self, request: ipc_types::Request) -> ipc_types::Response {
                                 .unwrap()
                                 .as_secs(),
                             payload: payload.into(),
+                            deadline,
+                            origin: Some(service_id),
                         },
                     )
                     .await
@@ -154,6 +186,21 @@ pub async fn run(&self, request: ipc_types::Request) -> ipc_types::Response {
                     _ => ipc_types::Response::FetchSgxSharedPubKey { public_key: None },
                 }
             },
+            ipc_types::Request::SubmitDeliveryAcknowledgment {
+                commodity,
+                metadata,
+            } => {
+                let dack = DeliveryAcknowledgment {
+                    service_id,
+                    commodity,
+                    proof: DeliveryAcknowledgmentProof,
+                    metadata,
+                };
+                if let Err(e) = self.dack_socket.enqueue(dack).await {
+                    tracing::error!("Failed to submit delivery acknowledgment: {e:?}");
+                }
+                ipc_types::Response::SubmitDeliveryAcknowledgment {}
+            },
             _ => unreachable!(),
         }
     }
@@ -168,17 +215,65 @@ pub struct ServiceCollection {
 impl ServiceCollection {
     #[inline]
     pub fn get(&self, id: u32) -> Option<ServiceHandle> {
-        self.services.get(&id).map(|v| *v)
+        self.services.get(&id).map(|v| v.clone())
     }
 
     #[inline]
     pub fn insert(&self, id: u32, handle: ServiceHandle) {
         self.services.insert(id, handle);
     }
+
+    /// Returns the status of every service, ordered by the service id.
+    pub fn statuses(&self) -> Vec<(u32, ServiceStatus)> {
+        let mut statuses: Vec<_> = self
+            .services
+            .iter()
+            .map(|entry| (*entry.key(), entry.value().status()))
+            .collect();
+        statuses.sort_unstable_by_key(|(id, _)| *id);
+        statuses
+    }
+}
+
+#[derive(Clone)]
+pub struct ServiceHandle {
+    /// The on-chain record of the service, the running binary is swapped when it changes.
+    service: Arc<watch::Sender<Option<Service>>>,
+    health: Arc<ServiceHealth>,
+    /// The capabilities declared by the service, `None` until it declares them. They are kept
+    /// across restarts of the process and only reset when the binary changes.
+    capabilities: Arc<Mutex<Option<ServiceCapabilities>>>,
 }
 
-#[derive(Clone, Copy)]
-pub struct ServiceHandle {}
+impl ServiceHandle {
+    #[inline]
+    pub fn status(&self) -> ServiceStatus {
+        self.health.status()
+    }
+
+    #[inline]
+    pub fn capabilities(&self) -> Option<ServiceCapabilities> {
+        *self.capabilities.lock().unwrap()
+    }
+
+    fn set_capabilities(&self, capabilities: Option<ServiceCapabilities>) {
+        *self.capabilities.lock().unwrap() = capabilities;
+    }
+
+    /// Update the on-chain record of the service. The service is restarted if its binary or
+    /// version has changed, in which case the capabilities of the old binary are dropped.
+    pub fn update(&self, service: Option<Service>) {
+        let changed = self.service.send_if_modified(|current| {
+            let changed =
+                current.map(|s| (s.binary, s.version)) != service.map(|s| (s.binary, s.version));
+            *current = service;
+            changed
+        });
+        if changed {
+            self.set_capabilities(None);
+        }
+    }
+}
 
 #[allow(unused)]
 pub async fn spawn_service<C: NodeComponents>(
@@ -197,27 +292,29 @@ pub async fn spawn_service<C: NodeComponents>(
         .await
         .expect("Failed to create IPC directory for service.");
 
-    let mut cmd = match which::which(format!("fn-service-{id}")) {
-        // Use the standalone service binary
-        Ok(path) => Command::new(path),
-        Err(_) => {
-            // Otherwise, relaunch the current binary for running statically linked services
-            let mut args = std::env::args_os();
-            let program = args.next().unwrap();
-            let mut cmd = Command::new(program);
-            cmd.args(args);
-            cmd
-        },
+    // Unlike the IPC directory, the data directory is kept between restarts.
+    let data_dir = cx.data_path.join(format!("service-{id}"));
+    tokio::fs::create_dir_all(&data_dir)
+        .await
+        .expect("Failed to create data directory for service.");
+
+    let (node_index, peer_ips) = get_sgx_enclave_args(id, &cx).await;
+    let envs: Vec<(&'static str, OsString)> = vec![
+        ("SERVICE_ID", id.to_string().into()),
+        ("BLOCKSTORE_PATH", cx.blockstore_path.clone().into()),
+        ("IPC_PATH", ipc_dir.clone().into()),
+        ("DATA_PATH", data_dir.into()),
+        ("PEER_IPS", peer_ips.join(",").into()),
+        ("OUR_NODE_INDEX", node_index.unwrap_or(1).to_string().into()),
+    ];
+
+    let (service_tx, service_rx) = watch::channel(cx.query_runner.get_service_info(&id));
+    let health = Arc::new(ServiceHealth::default());
+    let handle = ServiceHandle {
+        service: Arc::new(service_tx),
+        health: health.clone(),
+        capabilities: Default::default(),
     };
-    let (node_index, peer_ips) = get_sgx_enclave_args(&cx).await;
-
-    cmd.env("SERVICE_ID", format!("{id}"))
-        .env("BLOCKSTORE_PATH", &cx.blockstore_path)
-        .env("IPC_PATH", &ipc_dir)
-        .env("PEER_IPS", peer_ips.join(","))
-        .env("OUR_NODE_INDEX", node_index.unwrap_or(1).to_string());
-
-    panic_report::add_context(format!("service_{id}"), format!("{cmd:?}"));
 
     let cmd_permit = Arc::new(Notify::new());
     let permit = cmd_permit.clone();
@@ -226,33 +323,38 @@ pub async fn spawn_service<C: NodeComponents>(
     #[cfg(not(test))]
     {
         let waiter = waiter.clone();
+        let cx = cx.clone();
+        let health = health.clone();
         tokio::spawn(async move {
             // Wait until we have the UDS listener listening.
             permit.notified().await;
             tracing::trace!("Starting the child process for service '{id}'.");
-            run_command(format!("service-{id}"), cmd, waiter, conn_path).await;
+            run_command(id, cx, envs, service_rx, health, waiter, conn_path).await;
             tracing::trace!("Exiting service '{id}' execution loop.");
         });
     }
 
+    let ctrl_handle = handle.clone();
     spawn!(
         async move {
             let waiter2 = waiter.clone();
             waiter
                 .run_until_shutdown(async move {
-                    run_ctrl_loop(&ipc_dir, cx, cmd_permit, waiter2).await;
+                    run_ctrl_loop(id, &ipc_dir, cx, ctrl_handle, cmd_permit, waiter2).await;
                 })
                 .await;
         },
         "SERVICE-EXECUTOR: shutdown waiter"
     );
 
-    ServiceHandle {}
+    handle
 }
 
 async fn run_ctrl_loop<C: NodeComponents>(
+    service_id: u32,
     ipc_path: &Path,
     ctx: Arc<Context<C>>,
+    handle: ServiceHandle,
     cmd_permit: Arc<Notify>,
     waiter: ShutdownWaiter,
 ) {
@@ -269,12 +371,13 @@ async fn run_ctrl_loop<C: NodeComponents>(
     while let Ok((stream, _)) = listener.accept().await {
         // spawn a new task to handle the stream
         let ctx = ctx.clone();
+        let handle = handle.clone();
         let waiter = waiter.clone();
         spawn!(
             async move {
                 waiter
                     .run_until_shutdown(async move {
-                        if let Err(e) = handle_stream(stream, ctx).await {
+                        if let Err(e) = handle_stream(service_id, stream, ctx, handle).await {
                             tracing::error!("Error while handling the unix stream: {e:?}");
                         }
                     })
@@ -285,10 +388,12 @@ async fn run_ctrl_loop<C: NodeComponents>(
     }
 }
 
-#[instrument(skip(stream, ctx))]
+#[instrument(skip(stream, ctx, handle))]
 async fn handle_stream<C: NodeComponents>(
+    service_id: u32,
     stream: UnixStream,
     ctx: Arc<Context<C>>,
+    handle: ServiceHandle,
 ) -> Result<(), Box<dyn Error>> {
     // incoming IpcRequests
     // start with a buffer of 8 bytes to read the length delimiter
@@ -301,6 +406,11 @@ async fn handle_stream<C: NodeComponents>(
 
     let mut task_set = JoinSet::<IpcMessage>::new();
 
+    // Probe the liveness and readiness of the process.
+    let mut prober = Prober::default();
+    let mut probe_interval = tokio::time::interval(ctx.health.probe_interval);
+    probe_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
     'outer: loop {
         // Theres no messages to write
         let ready = if write_buffer.is_empty() {
@@ -320,6 +430,15 @@ async fn handle_stream<C: NodeComponents>(
                 ready_result = stream.ready(Interest::READABLE) => {
                     ready_result?
                 },
+                _ = probe_interval.tick() => {
+                    if prober.is_unresponsive(ctx.health.probe_failure_threshold) {
+                        handle.health.report_unresponsive();
+                    } else if let Some(nonce) = prober.next_probe() {
+                        let msg = IpcMessage::Probe { nonce };
+                        IpcMessage::encode_length_delimited(&msg, &mut write_buffer)?;
+                    }
+                    continue 'outer;
+                },
             }
         } else {
             stream
@@ -397,31 +516,46 @@ async fn handle_stream<C: NodeComponents>(
                     continue 'read;
                 }
 
-                let request = IpcRequest::decode(&read_buffer)?;
+                let IpcRequest {
+                    request_ctx,
+                    request,
+                } = IpcRequest::decode(&read_buffer)?;
 
                 // reset the buffer back to 8 bytes for the next delimiter
                 read_buffer.resize(DELIMITER_SIZE, 0);
                 read_buffer_pos = 0;
                 reading_len = true;
 
-                if let Some(request_ctx) = request.request_ctx {
-                    let ctx = ctx.clone();
-                    task_set.spawn(async move {
-                        let response = ctx.run(request.request).await;
-                        IpcMessage::Response {
-                            request_ctx,
-                            response,
+                match (request_ctx, request) {
+                    // The requests about the process itself are handled right away.
+                    (_, ipc_types::Request::ProbeAck { nonce, ready }) => {
+                        if prober.ack(nonce) {
+                            handle.health.set_ready(ready);
                         }
-                    });
-                } else {
-                    // Only enqueue the request. We don't need to send the response back.
-                    let ctx = ctx.clone();
-                    spawn!(
-                        async move {
-                            ctx.run(request.request).await;
-                        },
-                        "SERVICE-EXECUTOR: run request"
-                    );
+                    },
+                    (_, ipc_types::Request::DeclareCapabilities { http_overrides }) => {
+                        handle.set_capabilities(Some(ServiceCapabilities { http_overrides }));
+                    },
+                    (Some(request_ctx), request) => {
+                        let ctx = ctx.clone();
+                        task_set.spawn(async move {
+                            let response = ctx.run(service_id, request).await;
+                            IpcMessage::Response {
+                                request_ctx,
+                                response,
+                            }
+                        });
+                    },
+                    (None, request) => {
+                        // Only enqueue the request. We don't need to send the response back.
+                        let ctx = ctx.clone();
+                        spawn!(
+                            async move {
+                                ctx.run(service_id, request).await;
+                            },
+                            "SERVICE-EXECUTOR: run request"
+                        );
+                    },
                 }
             }
         }
@@ -430,10 +564,14 @@ async fn handle_stream<C: NodeComponents>(
     Ok(())
 }
 
-/// Run the given command until the kill signal has been received. Restarting the child on failure.
-async fn run_command(
-    name: String,
-    mut command: Command,
+/// Run the service until the kill signal has been received. Restarting the child on failure and
+/// whenever its on-chain binary changes.
+async fn run_command<C: NodeComponents>(
+    id: u32,
+    cx: Arc<Context<C>>,
+    envs: Vec<(&'static str, OsString)>,
+    mut service: watch::Receiver<Option<Service>>,
+    health: Arc<ServiceHealth>,
     kill: ShutdownWaiter,
     conn_uds_path: PathBuf,
 ) {
@@ -441,43 +579,74 @@ async fn run_command(
         let kill_fut = kill.wait_for_shutdown();
     };
 
-    command
-        .stdin(Stdio::null())
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit());
-
-    let mut wait_time = 1000;
+    let name = format!("service-{id}");
+    let mut sandbox = match Sandbox::new(
+        &name,
+        cx.sandbox.limits(id).clone(),
+        cx.data_path.join(format!("work/service-{id}")),
+    ) {
+        Ok(sandbox) => sandbox,
+        Err(e) => {
+            // The service must never run without the limits it is configured with.
+            tracing::error!("Not starting '{name}', its sandbox could not be created: {e}");
+            health.set_status(ServiceStatus::Down);
+            return;
+        },
+    };
+    let mut policy = RestartPolicy::new(cx.health.clone());
     loop {
-        // Remove the `/ipc/conn` file before (re-)running
-        let _ = tokio::fs::remove_file(&conn_uds_path).await;
-
-        let last_start = Instant::now();
-        tracing::debug!("Starting child process '{name}' with {command:?}");
-
-        let mut child = command.spawn().expect("Command failed to start.");
-
-        select! {
-            biased;
-            _ = &mut kill_fut => {
-                tracing::trace!("Got the signal to kill. Killing '{name}'");
-                child.kill().await.expect("Failed to kill the child.");
-                tracing::trace!("Killed process '{name}'");
-                break;
-            },
-            _ = child.wait() => {
-                tracing::error!("Child process '{name}' failed.");
-                if Instant::now().duration_since(last_start) >= Duration::from_secs(2) {
-                    tracing::info!("Last run seemed to have been healthy. Waiting for 100ms.");
-                    // Restart the wait time.
-                    wait_time = 100;
-                } else if wait_time <= 100 {
-                    wait_time = 1000;
+        health.set_status(ServiceStatus::Starting);
+        let record = *service.borrow_and_update();
+        let uptime = match start_child(id, &cx, record, &envs, &mut sandbox, &conn_uds_path).await {
+            Ok(mut child) => {
+                let last_start = Instant::now();
+                select! {
+                    biased;
+                    _ = &mut kill_fut => {
+                        tracing::trace!("Got the signal to kill. Killing '{name}'");
+                        child.kill().await.expect("Failed to kill the child.");
+                        tracing::trace!("Killed process '{name}'");
+                        break;
+                    },
+                    Ok(()) = service.changed() => {
+                        tracing::info!("The binary of '{name}' has changed. Restarting it.");
+                        child.kill().await.expect("Failed to kill the child.");
+                        policy.reset();
+                        continue;
+                    },
+                    _ = health.unresponsive() => {
+                        tracing::error!("Child process '{name}' stopped answering the probes.");
+                        child.kill().await.expect("Failed to kill the child.");
+                    },
+                    status = child.wait() => {
+                        report_exit(&name, status.ok(), &mut sandbox);
+                    }
                 }
-            }
-        }
+                last_start.elapsed()
+            },
+            Err(e) => {
+                tracing::error!("Failed to start child process '{name}': {e:?}");
+                Duration::ZERO
+            },
+        };
 
-        let wait_dur = Duration::from_millis(wait_time);
-        tracing::info!("Waiting for {wait_dur:?} before restarting '{name}'");
+        health.set_status(ServiceStatus::Down);
+        let wait_dur = match policy.on_crash(Instant::now(), uptime) {
+            Restart::After(wait_dur) => {
+                tracing::info!("Waiting for {wait_dur:?} before restarting '{name}'");
+                wait_dur
+            },
+            Restart::CrashLoop(wait_dur) => {
+                tracing::error!("'{name}' is crash looping. Suspending restarts for {wait_dur:?}");
+                health.set_status(ServiceStatus::CrashLoop);
+                increment_counter!(
+                    "service_executor_crash_loops",
+                    Some("Services whose restarts were suspended for crashing repeatedly"),
+                    "service" => name.as_str()
+                );
+                wait_dur
+            },
+        };
 
         select! {
             biased;
@@ -485,29 +654,179 @@ async fn run_command(
                 tracing::trace!("Got the signal to stop '{name}'");
                 break;
             }
-            _ = tokio::time::sleep(wait_dur) => {
-                wait_time = wait_time * 12 / 10;
+            Ok(()) = service.changed() => {
+                policy.reset();
             }
+            _ = tokio::time::sleep(wait_dur) => {}
         }
     }
 
+    health.set_status(ServiceStatus::Down);
     tracing::info!("Exiting service execution loop [sid={name}]")
 }
 
+/// Start the process of the service within its sandbox.
+async fn start_child<C: NodeComponents>(
+    id: u32,
+    cx: &Context<C>,
+    service: Option<Service>,
+    envs: &[(&'static str, OsString)],
+    sandbox: &mut Sandbox,
+    conn_uds_path: &Path,
+) -> anyhow::Result<Child> {
+    let mut command = prepare_command(id, cx, service).await?;
+    sandbox.apply(&mut command)?;
+    command
+        .envs(envs.iter().cloned())
+        .stdin(Stdio::null())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit());
+    panic_report::add_context(format!("service_{id}"), format!("{command:?}"));
+
+    // Remove the `/ipc/conn` file before (re-)running
+    let _ = tokio::fs::remove_file(conn_uds_path).await;
+
+    tracing::debug!("Starting child process 'service-{id}' with {command:?}");
+    command.spawn().context("Command failed to start")
+}
+
+/// Log why the process of a service exited, counting the processes that exceeded a limit.
+fn report_exit(name: &str, status: Option<ExitStatus>, sandbox: &mut Sandbox) {
+    match status.and_then(|status| sandbox.violation(status)) {
+        Some(violation) => {
+            tracing::error!("Child process '{name}' exceeded its {violation} limit.");
+            increment_counter!(
+                "service_executor_limit_violations",
+                Some("Service processes terminated for exceeding a resource limit"),
+                "service" => name,
+                "limit" => violation.as_str()
+            );
+        },
+        None => tracing::error!("Child process '{name}' failed with {status:?}."),
+    }
+}
+
+/// Returns the command that runs the given service. Services with a binary registered on-chain
+/// run that binary, other services are looked up locally.
+async fn prepare_command<C: NodeComponents>(
+    id: u32,
+    cx: &Context<C>,
+    service: Option<Service>,
+) -> anyhow::Result<Command> {
+    if let Some((hash, service)) = service.and_then(|s| s.binary.map(|hash| (hash, s))) {
+        check_resources(&service.resources)?;
+        let path = fetch_binary(cx, hash, service.version).await?;
+        return Ok(Command::new(path));
+    }
+
+    let cmd = match which::which(format!("fn-service-{id}")) {
+        // Use the standalone service binary
+        Ok(path) => Command::new(path),
+        Err(_) => {
+            // Otherwise, relaunch the current binary for running statically linked services
+            let mut args = std::env::args_os();
+            let program = args.next().unwrap();
+            let mut cmd = Command::new(program);
+            cmd.args(args);
+            cmd
+        },
+    };
+    Ok(cmd)
+}
+
+/// Fetch the binary with the given hash and store it as an executable in the binary directory
+/// of the services. Returns the path of the executable.
+async fn fetch_binary<C: NodeComponents>(
+    cx: &Context<C>,
+    hash: Blake3Hash,
+    version: u32,
+) -> anyhow::Result<PathBuf> {
+    let bin_dir = cx.data_path.join("bin");
+    let path = bin_dir.join(format!("{version}-{}", hex::encode(hash)));
+    // The binary is only ever written after it has been verified.
+    if tokio::fs::try_exists(&path).await? {
+        return Ok(path);
+    }
+
+    match cx
+        .fetcher_socket
+        .run(FetcherRequest::Fetch { hash })
+        .await
+        .map_err(|e| anyhow!("{e}"))?
+    {
+        FetcherResponse::Fetch(result) => result.context("Failed to fetch the binary")?,
+        FetcherResponse::Put(_) => unreachable!(),
+    }
+
+    let content = cx
+        .blockstore
+        .read_all_to_vec(&hash)
+        .await
+        .context("The binary is missing from the blockstore")?;
+    if *fleek_blake3::hash(&content).as_bytes() != hash {
+        bail!("The binary does not match its hash");
+    }
+
+    // Write to a temporary file first, so a partially written binary is never executed.
+    tokio::fs::create_dir_all(&bin_dir).await?;
+    let tmp_path = path.with_extension("tmp");
+    tokio::fs::write(&tmp_path, &content).await?;
+    tokio::fs::set_permissions(&tmp_path, std::fs::Permissions::from_mode(0o755)).await?;
+    tokio::fs::rename(&tmp_path, &path).await?;
+
+    Ok(path)
+}
+
+/// Make sure that this machine has the minimum resources required by a service.
+fn check_resources(resources: &ServiceResources) -> anyhow::Result<()> {
+    let cpu_cores = std::thread::available_parallelism()
+        .map(|n| n.get() as u32)
+        .unwrap_or(1);
+    if cpu_cores < resources.cpu_cores {
+        bail!(
+            "The service requires {} cpu cores, only {cpu_cores} are available",
+            resources.cpu_cores
+        );
+    }
+
+    if let Some(memory_mb) = total_memory_mb() {
+        if memory_mb < resources.memory_mb {
+            bail!(
+                "The service requires {}MB of memory, only {memory_mb}MB are available",
+                resources.memory_mb
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the total memory of this machine in megabytes, if it can be determined.
+fn total_memory_mb() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo.lines().find(|line| line.starts_with("MemTotal:"))?;
+    let kb = line.split_whitespace().nth(1)?.parse::<u64>().ok()?;
+    Some(kb / 1024)
+}
+
 // todo(dalton): Replace this. We are only doing this because we cannot get the fleek sdk to compile
 // as a dependency and hit some sort of conflict with forttanix runner package We only need the sdk
 // for two pieces of information: This nodes node index, and a list of peers we might be able to
 // fetch the shared secret from for now we will just pass them in as env variables.
 async fn get_sgx_enclave_args<C: NodeComponents>(
+    service_id: u32,
     ctx: &Arc<Context<C>>,
 ) -> (Option<u32>, Vec<String>) {
-    let node_index = match ctx.run(ipc_types::Request::FetchNodeIndex {}).await {
+    let node_index = match ctx
+        .run(service_id, ipc_types::Request::FetchNodeIndex {})
+        .await
+    {
         Response::FetchNodeIndex { node_index } => node_index,
         _ => unreachable!(),
     };
 
     let peer_ips = match ctx
-        .run(ipc_types::Request::FetchPeerIps { amount: 10 })
+        .run(service_id, ipc_types::Request::FetchPeerIps { amount: 10 })
         .await
     {
         Response::FetchPeerIps { peer_ips } => peer_ips,
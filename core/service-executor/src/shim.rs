@@ -4,6 +4,7 @@
 use fxhash::FxHashSet;
 use lightning_interfaces::prelude::*;
 use lightning_interfaces::types::ServiceId;
+use lightning_interfaces::{ServiceCapabilities, ServiceStatus};
 use lightning_test_utils::config::LIGHTNING_TEST_HOME_DIR;
 use lightning_utils::config::LIGHTNING_HOME_DIR;
 use resolved_pathbuf::ResolvedPathBuf;
@@ -12,6 +13,8 @@
 use tracing::{error, trace};
 use triomphe::Arc;
 
+use crate::health::HealthConfig;
+use crate::sandbox::SandboxConfig;
 use crate::service::{spawn_service, Context, ServiceCollection};
 
 #[derive(Clone)]
@@ -29,6 +32,13 @@ pub struct ServiceExecutorConfig {
     /// The IPC directory is used to contain the Unix domain sockets that we use to communicate
     /// with the different services.
     pub ipc_path: ResolvedPathBuf,
+    /// The data directory is used to persist the state of the services between restarts, each
+    /// service gets its own sub directory.
+    pub data_path: ResolvedPathBuf,
+    /// The resource limits and sandboxing of the service processes.
+    pub sandbox: SandboxConfig,
+    /// The health probes and restart policy of the service processes.
+    pub health: HealthConfig,
 }
 
 impl Default for ServiceExecutorConfig {
@@ -39,6 +49,12 @@ fn default() -> Self {
                 .join("ipc")
                 .try_into()
                 .expect("Failed to resolve path"),
+            data_path: LIGHTNING_HOME_DIR
+                .join("data/services")
+                .try_into()
+                .expect("Failed to resolve path"),
+            sandbox: Default::default(),
+            health: Default::default(),
         }
     }
 }
@@ -51,6 +67,12 @@ pub fn test_default() -> Self {
                 .join("ipc")
                 .try_into()
                 .expect("Failed to resolve path"),
+            data_path: LIGHTNING_TEST_HOME_DIR
+                .join("data/services")
+                .try_into()
+                .expect("Failed to resolve path"),
+            sandbox: Default::default(),
+            health: Default::default(),
         }
     }
 }
@@ -70,17 +92,23 @@ fn init(
         keystore: &C::KeystoreInterface,
         fdi::Cloned(query_runner): fdi::Cloned<c!(C::ApplicationInterface::SyncExecutor)>,
         fdi::Cloned(task_broker): fdi::Cloned<C::TaskBrokerInterface>,
+        dack_aggregator: &C::DeliveryAcknowledgmentAggregatorInterface,
     ) -> anyhow::Result<Self> {
         let config = Arc::new(config.get::<Self>());
 
         let our_public_key = keystore.get_ed25519_pk();
         let ctx = Arc::new(Context {
+            blockstore: blockstore.clone(),
             blockstore_path: blockstore.get_root_dir(),
             ipc_path: config.ipc_path.to_path_buf(),
+            data_path: config.data_path.to_path_buf(),
             our_public_key,
             fetcher_socket: fetcher.get_socket(),
             query_runner,
             task_broker,
+            dack_socket: dack_aggregator.socket(),
+            sandbox: config.sandbox.clone(),
+            health: config.health.clone(),
         });
 
         Ok(ServiceExecutor {
@@ -94,16 +122,32 @@ fn init(
     async fn start(
         fdi::Cloned(this): fdi::Cloned<Self>,
         fdi::Cloned(query_runner): fdi::Cloned<c!(C::ApplicationInterface::SyncExecutor)>,
+        fdi::Cloned(notifier): fdi::Cloned<C::NotifierInterface>,
         fdi::Cloned(waiter): fdi::Cloned<ShutdownWaiter>,
     ) {
         spawn!(
             async move {
                 query_runner.wait_for_genesis().await;
 
+                let mut block_sub = notifier.subscribe_block_executed();
+
                 for &id in this.config.services.iter() {
                     let handle = spawn_service(id, this.ctx.clone(), waiter.clone()).await;
                     this.collection.insert(id, handle);
                 }
+
+                // Swap the binaries of the services when they are added or upgraded on-chain.
+                waiter
+                    .run_until_shutdown(async move {
+                        while block_sub.recv().await.is_some() {
+                            for &id in this.config.services.iter() {
+                                if let Some(handle) = this.collection.get(id) {
+                                    handle.update(query_runner.get_service_info(&id));
+                                }
+                            }
+                        }
+                    })
+                    .await;
             },
             "SERVICE-EXECUTOR spawn services"
         );
@@ -144,6 +188,14 @@ fn run_service(id: u32) {
             1001 => {
                 crate::test_services::io_stress::main();
             },
+            1002 => crate::test_services::misbehaving::memory_hog(),
+            1003 => crate::test_services::misbehaving::cpu_hog(),
+            1004 => crate::test_services::misbehaving::fd_hog(),
+            1005 => crate::test_services::misbehaving::file_hog(),
+            #[cfg(target_os = "linux")]
+            1006 => crate::test_services::misbehaving::forbidden_syscall(),
+            #[cfg(target_os = "linux")]
+            1007 => crate::test_services::misbehaving::signal_parent(),
             _ => error!("Service {id} not found."),
         }
     }
@@ -169,4 +221,20 @@ async fn connect(&self, service_id: ServiceId) -> Option<UnixStream> {
             },
         }
     }
+
+    fn status(&self, service_id: ServiceId) -> Option<ServiceStatus> {
+        self.collection
+            .get(service_id)
+            .map(|handle| handle.status())
+    }
+
+    fn statuses(&self) -> Vec<(ServiceId, ServiceStatus)> {
+        self.collection.statuses()
+    }
+
+    fn capabilities(&self, service_id: ServiceId) -> Option<ServiceCapabilities> {
+        self.collection
+            .get(service_id)
+            .and_then(|handle| handle.capabilities())
+    }
 }
@@ -0,0 +1,58 @@
+//! Services that deliberately exceed their resource limits, used to test the sandbox.
+
+use std::fs::File;
+use std::hint::black_box;
+use std::io::Write;
+
+/// Allocate 1GB of memory and exit.
+pub fn memory_hog() {
+    let chunks: Vec<Vec<u8>> = (0..1024)
+        // Fill the chunks, so that the memory is actually committed.
+        .map(|_| vec![1; 1024 * 1024])
+        .collect();
+    black_box(chunks);
+}
+
+/// Spin forever.
+pub fn cpu_hog() {
+    let mut counter = 0u64;
+    loop {
+        counter = black_box(counter.wrapping_add(1));
+    }
+}
+
+/// Open 10000 files at once and exit.
+pub fn fd_hog() {
+    let files: Vec<File> = (0..10_000)
+        .map(|_| File::open("/dev/null").expect("Failed to open file"))
+        .collect();
+    black_box(files);
+}
+
+/// Write a 64MB file to the temporary directory and exit.
+pub fn file_hog() {
+    let mut file =
+        File::create(std::env::temp_dir().join("file-hog")).expect("Failed to create file");
+    let chunk = vec![0; 1024 * 1024];
+    for _ in 0..64 {
+        file.write_all(&chunk).expect("Failed to write file");
+    }
+}
+
+/// Make a system call that no service needs.
+#[cfg(target_os = "linux")]
+pub fn forbidden_syscall() {
+    // Safety: Without a tracer attached the request has no effect.
+    unsafe {
+        libc::ptrace(libc::PTRACE_TRACEME, 0, 0, 0);
+    }
+}
+
+/// Probe whether the parent process can be signaled, which a sandboxed service must not do.
+#[cfg(target_os = "linux")]
+pub fn signal_parent() {
+    // Safety: The null signal only checks whether the signal could be sent.
+    unsafe {
+        libc::kill(libc::getppid(), 0);
+    }
+}
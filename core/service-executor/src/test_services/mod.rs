@@ -1 +1,2 @@
 pub mod io_stress;
+pub mod misbehaving;
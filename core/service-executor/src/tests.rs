@@ -1,4 +1,5 @@
-use std::time::Duration;
+use std::process::ExitStatus;
+use std::time::{Duration, Instant};
 
 use fleek_crypto::{
     AccountOwnerSecretKey,
@@ -13,6 +14,7 @@
 use lightning_blockstore::config::Config as BlockstoreConfig;
 use lightning_interfaces::prelude::*;
 use lightning_interfaces::types::{Genesis, GenesisAccount};
+use lightning_interfaces::ServiceCapabilities;
 use lightning_node::Node;
 use lightning_notifier::Notifier;
 use lightning_signer::Signer;
@@ -21,7 +23,10 @@
 use resolved_pathbuf::ResolvedPathBuf;
 use serial_test::serial;
 use tempfile::{tempdir, TempDir};
+use tokio::process::Command;
 
+use crate::health::{HealthConfig, Prober, Restart, RestartPolicy};
+use crate::sandbox::{LimitViolation, ResourceLimits, Sandbox};
 use crate::shim::{ServiceExecutor, ServiceExecutorConfig};
 
 partial_node_components!(TestBinding {
@@ -59,6 +64,9 @@ async fn init_service_executor(
                 .with::<ServiceExecutor<TestBinding>>(ServiceExecutorConfig {
                     services: [service_id].into_iter().collect(),
                     ipc_path: temp_dir.path().join("ipc").try_into().unwrap(),
+                    data_path: temp_dir.path().join("data").try_into().unwrap(),
+                    sandbox: Default::default(),
+                    health: Default::default(),
                 }),
         ),
     )
@@ -150,3 +158,257 @@ async fn test_query_missing_client_info() {
 
     node.shutdown().await
 }
+
+#[tokio::test]
+#[serial]
+async fn test_declare_capabilities() {
+    let temp_dir = tempdir().unwrap();
+
+    let mut genesis = Genesis::default();
+    genesis.node_info.clear();
+
+    let genesis_path = genesis
+        .write_to_dir(temp_dir.path().to_path_buf().try_into().unwrap())
+        .unwrap();
+
+    let mut node = init_service_executor(&temp_dir, genesis_path, 1071).await;
+
+    let provider = node
+        .provider
+        .get::<ServiceExecutor<TestBinding>>()
+        .get_provider();
+    // The service is running but has not declared its capabilities yet.
+    assert!(provider.status(1071).is_some());
+    assert_eq!(provider.capabilities(1071), None);
+    assert_eq!(provider.capabilities(1072), None);
+
+    // Start the service once the node listens for it.
+    let ctrl_path = temp_dir
+        .path()
+        .join("ipc")
+        .join("service-1071")
+        .join("ctrl");
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while !ctrl_path.exists() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("the node should listen for the service");
+    fn_sdk::ipc::init_from_env();
+    fn_sdk::api::declare_capabilities(fn_sdk::api::Capabilities {
+        http_overrides: true,
+    })
+    .await;
+
+    // Wait for the declaration to be handled.
+    let declared = Some(ServiceCapabilities {
+        http_overrides: true,
+    });
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while provider.capabilities(1071) != declared {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("the capabilities should be declared");
+
+    node.shutdown().await
+}
+
+/// Entry point of the misbehaving test services. The sandbox tests re-execute the test binary with
+/// only this test to run the service given by `SERVICE_ID`.
+#[test]
+#[ignore]
+fn run_test_service() {
+    if let Ok(id) = std::env::var("SERVICE_ID") {
+        ServiceExecutor::<TestBinding>::run_service(id.parse().unwrap());
+    }
+}
+
+/// Run the given test service in a sandbox with the given limits until it exits.
+async fn run_sandboxed(
+    service_id: u32,
+    limits: ResourceLimits,
+) -> (ExitStatus, Option<LimitViolation>) {
+    let temp_dir = tempdir().unwrap();
+    let mut sandbox = Sandbox::new(
+        &format!("test-service-{service_id}"),
+        limits,
+        temp_dir.path().join("work"),
+    )
+    .unwrap();
+
+    let mut command = Command::new(std::env::current_exe().unwrap());
+    command
+        .args(["--exact", "tests::run_test_service", "--ignored"])
+        .env("SERVICE_ID", service_id.to_string())
+        .kill_on_drop(true);
+    sandbox.apply(&mut command).unwrap();
+
+    let status = tokio::time::timeout(Duration::from_secs(30), command.status())
+        .await
+        .expect("The service was not stopped by the sandbox")
+        .unwrap();
+    (status, sandbox.violation(status))
+}
+
+#[tokio::test]
+async fn test_sandbox_memory_limit() {
+    let limits = ResourceLimits {
+        max_memory_mb: Some(512),
+        ..Default::default()
+    };
+    let (status, _) = run_sandboxed(1002, limits).await;
+    assert!(!status.success());
+}
+
+#[tokio::test]
+async fn test_sandbox_cpu_time_limit() {
+    let limits = ResourceLimits {
+        max_cpu_time_secs: Some(1),
+        ..Default::default()
+    };
+    let (_, violation) = run_sandboxed(1003, limits).await;
+    assert_eq!(violation, Some(LimitViolation::CpuTime));
+}
+
+#[tokio::test]
+async fn test_sandbox_open_files_limit() {
+    let limits = ResourceLimits {
+        max_open_files: Some(64),
+        ..Default::default()
+    };
+    let (status, _) = run_sandboxed(1004, limits).await;
+    assert!(!status.success());
+}
+
+#[tokio::test]
+async fn test_sandbox_file_size_limit() {
+    // The file is written to the private working directory.
+    let limits = ResourceLimits {
+        max_file_size_mb: Some(1),
+        private_working_dir: true,
+        ..Default::default()
+    };
+    let (_, violation) = run_sandboxed(1005, limits).await;
+    assert_eq!(violation, Some(LimitViolation::FileSize));
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_sandbox_seccomp() {
+    let limits = ResourceLimits {
+        seccomp: true,
+        ..Default::default()
+    };
+    let (_, violation) = run_sandboxed(1006, limits).await;
+    assert_eq!(violation, Some(LimitViolation::Seccomp));
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_sandbox_seccomp_signals() {
+    let limits = ResourceLimits {
+        seccomp: true,
+        ..Default::default()
+    };
+    let (_, violation) = run_sandboxed(1007, limits).await;
+    assert_eq!(violation, Some(LimitViolation::Seccomp));
+}
+
+#[tokio::test]
+async fn test_sandbox_allows_well_behaved_service() {
+    // The services exit by themselves when there are no limits.
+    let limits = ResourceLimits {
+        seccomp: cfg!(target_os = "linux"),
+        private_working_dir: true,
+        ..Default::default()
+    };
+    let (status, violation) = run_sandboxed(1005, limits).await;
+    assert!(status.success());
+    assert_eq!(violation, None);
+}
+
+#[test]
+fn test_restart_backoff() {
+    let config = HealthConfig {
+        initial_backoff: Duration::from_millis(100),
+        max_backoff: Duration::from_millis(500),
+        healthy_uptime: Duration::from_secs(10),
+        crash_loop_threshold: 100,
+        ..Default::default()
+    };
+    let mut policy = RestartPolicy::new(config);
+    let now = Instant::now();
+
+    let delays: Vec<_> = (0..5)
+        .map(|_| policy.on_crash(now, Duration::ZERO))
+        .collect();
+    assert_eq!(
+        delays,
+        [100, 200, 400, 500, 500].map(|ms| Restart::After(Duration::from_millis(ms)))
+    );
+
+    // A process that ran long enough resets the backoff.
+    assert_eq!(
+        policy.on_crash(now, Duration::from_secs(10)),
+        Restart::After(Duration::from_millis(100))
+    );
+}
+
+#[test]
+fn test_restart_crash_loop() {
+    let config = HealthConfig {
+        crash_loop_threshold: 3,
+        crash_loop_window: Duration::from_secs(60),
+        crash_loop_cooldown: Duration::from_secs(300),
+        ..Default::default()
+    };
+    let mut policy = RestartPolicy::new(config);
+    let now = Instant::now();
+
+    // Crashes outside of the window are forgotten.
+    assert!(matches!(
+        policy.on_crash(now, Duration::ZERO),
+        Restart::After(_)
+    ));
+    let now = now + Duration::from_secs(61);
+    assert!(matches!(
+        policy.on_crash(now, Duration::ZERO),
+        Restart::After(_)
+    ));
+    assert!(matches!(
+        policy.on_crash(now, Duration::ZERO),
+        Restart::After(_)
+    ));
+    assert_eq!(
+        policy.on_crash(now, Duration::ZERO),
+        Restart::CrashLoop(Duration::from_secs(300))
+    );
+
+    // The breaker is reset after it tripped.
+    assert!(matches!(
+        policy.on_crash(now, Duration::ZERO),
+        Restart::After(_)
+    ));
+}
+
+#[test]
+fn test_prober() {
+    let mut prober = Prober::default();
+
+    // A process that did not announce itself is never probed.
+    assert_eq!(prober.next_probe(), None);
+    assert!(prober.ack(0));
+
+    let nonce = prober.next_probe().unwrap();
+    assert!(!prober.ack(nonce - 1));
+    assert!(prober.ack(nonce));
+
+    prober.next_probe().unwrap();
+    prober.next_probe().unwrap();
+    assert!(!prober.is_unresponsive(2));
+    prober.next_probe().unwrap();
+    assert!(prober.is_unresponsive(2));
+}
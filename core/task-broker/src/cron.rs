@@ -0,0 +1,204 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Result};
+
+const MINUTES_PER_DAY: u64 = 24 * 60;
+/// How far ahead to look for the next run, since schedules like `0 0 30 2 *` never match.
+const MAX_DAYS: u64 = 5 * 366;
+
+/// A parsed cron expression in the `minute hour day-of-month month day-of-week` format.
+///
+/// Fields support `*`, values, ranges (`1-5`), steps (`*/15`, `0-30/10`) and lists (`1,15`).
+/// Days of the week go from 0 (sunday) to 7 (sunday again). The `@hourly`, `@daily`,
+/// `@weekly`, `@monthly` and `@yearly` shorthands are supported too. All times are in UTC.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Whether the day of the month and the day of the week are restricted. When both of them
+    /// are, a day matches if either of them matches.
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl FromStr for Schedule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = match s.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            s => s,
+        };
+
+        let [minutes, hours, days, months, weekdays] = s
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .try_into()
+            .map_err(|_| anyhow!("cron expression '{s}' should have 5 fields"))?;
+
+        let mut weekdays_bits = parse_field(weekdays, 0, 7).context("invalid day of the week")?;
+        // Sunday can be either 0 or 7
+        if weekdays_bits & (1 << 7) != 0 {
+            weekdays_bits = (weekdays_bits | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            minutes: parse_field(minutes, 0, 59).context("invalid minute")?,
+            hours: parse_field(hours, 0, 23).context("invalid hour")?,
+            days: parse_field(days, 1, 31).context("invalid day of the month")?,
+            months: parse_field(months, 1, 12).context("invalid month")?,
+            weekdays: weekdays_bits,
+            days_restricted: !days.starts_with('*'),
+            weekdays_restricted: !weekdays.starts_with('*'),
+        })
+    }
+}
+
+impl Schedule {
+    /// Get the unix timestamp (in seconds) of the first run strictly after the given timestamp,
+    /// or `None` if the schedule never matches.
+    pub fn next_after(&self, timestamp: u64) -> Option<u64> {
+        let start = timestamp / 60 + 1;
+        let (mut day, mut from) = (start / MINUTES_PER_DAY, start % MINUTES_PER_DAY);
+
+        for _ in 0..MAX_DAYS {
+            if self.matches_day(day) {
+                if let Some(minute) = (from..MINUTES_PER_DAY)
+                    .find(|m| has(self.hours, m / 60) && has(self.minutes, m % 60))
+                {
+                    return Some((day * MINUTES_PER_DAY + minute) * 60);
+                }
+            }
+            day += 1;
+            from = 0;
+        }
+
+        None
+    }
+
+    fn matches_day(&self, day: u64) -> bool {
+        let (month, day_of_month) = month_and_day(day);
+        if !has(self.months, month) {
+            return false;
+        }
+
+        // 1970-01-01 was a thursday
+        let day_of_week = (day + 4) % 7;
+        let day_matches = has(self.days, day_of_month);
+        let weekday_matches = has(self.weekdays, day_of_week);
+        match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day_matches || weekday_matches,
+            (true, false) => day_matches,
+            (false, true) => weekday_matches,
+            (false, false) => true,
+        }
+    }
+}
+
+fn has(bits: u64, value: u64) -> bool {
+    bits & (1 << value) != 0
+}
+
+/// Parse a field into a bit set of the allowed values.
+fn parse_field(field: &str, min: u64, max: u64) -> Result<u64> {
+    let mut bits = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u64>()?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            bail!("step of '{part}' should not be 0");
+        }
+
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (start.parse()?, end.parse()?),
+            // A single value with a step runs until the end of the range
+            None if step > 1 => (range.parse()?, max),
+            None => {
+                let value = range.parse()?;
+                (value, value)
+            },
+        };
+        if start < min || end > max || start > end {
+            bail!("'{part}' is out of the range {min}-{max}");
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+/// Get the month (1-12) and the day of the month (1-31) of a number of days since the unix
+/// epoch.
+///
+/// See <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+fn month_and_day(days: u64) -> (u64, u64) {
+    let z = days + 719_468;
+    let day_of_era = z % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2024-01-01T00:00:00Z, a monday
+    const JAN_1_2024: u64 = 1_704_067_200;
+
+    #[test]
+    fn test_next_after() {
+        let every_15 = "*/15 * * * *".parse::<Schedule>().unwrap();
+        assert_eq!(every_15.next_after(JAN_1_2024), Some(JAN_1_2024 + 15 * 60));
+        assert_eq!(
+            every_15.next_after(JAN_1_2024 + 1),
+            Some(JAN_1_2024 + 15 * 60)
+        );
+
+        let daily = "@daily".parse::<Schedule>().unwrap();
+        assert_eq!(daily.next_after(JAN_1_2024), Some(JAN_1_2024 + 86_400));
+
+        // First sunday of 2024 at 12:30
+        let sundays = "30 12 * * 7".parse::<Schedule>().unwrap();
+        assert_eq!(
+            sundays.next_after(JAN_1_2024),
+            Some(JAN_1_2024 + 6 * 86_400 + 12 * 3600 + 30 * 60)
+        );
+
+        // Leap day
+        let leap = "0 0 29 2 *".parse::<Schedule>().unwrap();
+        assert_eq!(leap.next_after(JAN_1_2024), Some(JAN_1_2024 + 59 * 86_400));
+
+        let never = "0 0 30 2 *".parse::<Schedule>().unwrap();
+        assert_eq!(never.next_after(JAN_1_2024), None);
+    }
+
+    #[test]
+    fn test_invalid_expressions() {
+        for expr in [
+            "* * * *",
+            "60 * * * *",
+            "* * 0 * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+        ] {
+            assert!(expr.parse::<Schedule>().is_err(), "{expr}");
+        }
+    }
+}
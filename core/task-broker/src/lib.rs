@@ -1,4 +1,6 @@
+mod cron;
 mod local;
+mod scheduler;
 mod task_broker;
 
 #[cfg(test)]
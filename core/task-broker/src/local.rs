@@ -54,6 +54,7 @@ async fn handle(&self, (depth, req): Self::Request) -> Self::Response {
                 transport_detail: fn_sdk::header::TransportDetail::Task {
                     depth,
                     payload: req.payload,
+                    origin: req.origin,
                 },
             },
             &mut stream,
@@ -0,0 +1,294 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use futures::Future;
+use lightning_interfaces::{ScheduledRun, ScheduledTask, TaskError};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+use crate::cron::Schedule;
+
+/// Number of runs kept in memory for every schedule.
+const MAX_RUNS: usize = 32;
+
+/// Keeps track of the scheduled tasks and runs them when they are due.
+pub struct Scheduler {
+    /// Where schedules are persisted, if anywhere.
+    path: Option<PathBuf>,
+    state: Mutex<State>,
+    changed: Notify,
+}
+
+#[derive(Default)]
+struct State {
+    next_id: u64,
+    entries: BTreeMap<u64, Entry>,
+}
+
+struct Entry {
+    task: ScheduledTask,
+    schedule: Schedule,
+    /// Unix timestamp in seconds of the next run, if there is one.
+    next: Option<u64>,
+    runs: VecDeque<ScheduledRun>,
+}
+
+impl Entry {
+    fn new(task: ScheduledTask, schedule: Schedule, now: u64) -> Self {
+        Self {
+            task,
+            next: schedule.next_after(now),
+            schedule,
+            runs: VecDeque::new(),
+        }
+    }
+}
+
+/// The schedules as persisted on disk.
+#[derive(Default, Serialize, Deserialize)]
+struct Persisted {
+    next_id: u64,
+    tasks: BTreeMap<u64, ScheduledTask>,
+}
+
+impl Scheduler {
+    /// Create a scheduler, loading the schedules previously persisted at the given path.
+    pub fn load(path: Option<PathBuf>) -> Result<Self> {
+        let persisted = match &path {
+            Some(path) if path.exists() => {
+                let bytes = std::fs::read(path).context("failed to read schedules")?;
+                serde_json::from_slice::<Persisted>(&bytes).context("corrupted schedules")?
+            },
+            _ => Persisted::default(),
+        };
+
+        let now = now_secs();
+        let mut entries = BTreeMap::new();
+        for (id, task) in persisted.tasks {
+            let schedule = task.cron.parse().context("invalid persisted schedule")?;
+            entries.insert(id, Entry::new(task, schedule, now));
+        }
+
+        Ok(Self {
+            path,
+            state: Mutex::new(State {
+                next_id: persisted.next_id,
+                entries,
+            }),
+            changed: Notify::new(),
+        })
+    }
+
+    pub fn schedule(&self, task: ScheduledTask) -> Result<u64, TaskError> {
+        let schedule = task
+            .cron
+            .parse::<Schedule>()
+            .map_err(|e| TaskError::InvalidSchedule(format!("{e:#}")))?;
+
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id;
+        state.next_id += 1;
+        state
+            .entries
+            .insert(id, Entry::new(task, schedule, now_secs()));
+        if let Err(e) = self.persist(&state) {
+            state.entries.remove(&id);
+            return Err(TaskError::Internal(format!(
+                "failed to persist schedule: {e}"
+            )));
+        }
+        drop(state);
+
+        info!("Registered scheduled task {id}");
+        self.changed.notify_one();
+        Ok(id)
+    }
+
+    pub fn unschedule(&self, id: u64) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.entries.remove(&id).is_none() {
+            return false;
+        }
+        if let Err(e) = self.persist(&state) {
+            warn!("Failed to persist schedules: {e}");
+        }
+        drop(state);
+
+        info!("Removed scheduled task {id}");
+        self.changed.notify_one();
+        true
+    }
+
+    pub fn schedules(&self) -> Vec<(u64, ScheduledTask)> {
+        let state = self.state.lock().unwrap();
+        state
+            .entries
+            .iter()
+            .map(|(id, entry)| (*id, entry.task.clone()))
+            .collect()
+    }
+
+    pub fn runs(&self, id: u64) -> Vec<ScheduledRun> {
+        let state = self.state.lock().unwrap();
+        state
+            .entries
+            .get(&id)
+            .map(|entry| entry.runs.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Run the tasks when they are due with the given function.
+    pub async fn run<F, Fut>(self: Arc<Self>, run_task: F)
+    where
+        F: Fn(ScheduledTask) -> Fut + Send,
+        Fut: Future<Output = Result<Bytes, String>> + Send + 'static,
+    {
+        loop {
+            let (due, next) = self.take_due(now_secs());
+            for (id, task) in due {
+                let this = self.clone();
+                let fut = run_task(task);
+                tokio::spawn(async move {
+                    let timestamp = now_millis();
+                    let result = fut.await;
+                    match &result {
+                        Ok(_) => info!("Scheduled task {id} succeeded"),
+                        Err(e) => warn!("Scheduled task {id} failed: {e}"),
+                    }
+                    this.record(id, ScheduledRun { timestamp, result });
+                });
+            }
+
+            match next {
+                Some(next) => {
+                    let wait = Duration::from_millis((next * 1000).saturating_sub(now_millis()));
+                    tokio::select! {
+                        _ = tokio::time::sleep(wait) => {},
+                        _ = self.changed.notified() => {},
+                    }
+                },
+                None => self.changed.notified().await,
+            }
+        }
+    }
+
+    /// Take the tasks that are due, and get the time of the next run of any task.
+    fn take_due(&self, now: u64) -> (Vec<(u64, ScheduledTask)>, Option<u64>) {
+        let mut state = self.state.lock().unwrap();
+        let mut due = Vec::new();
+        for (id, entry) in state.entries.iter_mut() {
+            if entry.next.is_some_and(|next| next <= now) {
+                due.push((*id, entry.task.clone()));
+                entry.next = entry.schedule.next_after(now);
+            }
+        }
+        let next = state.entries.values().filter_map(|entry| entry.next).min();
+        (due, next)
+    }
+
+    fn record(&self, id: u64, run: ScheduledRun) {
+        let mut state = self.state.lock().unwrap();
+        // The task might have been removed while running
+        if let Some(entry) = state.entries.get_mut(&id) {
+            if entry.runs.len() == MAX_RUNS {
+                entry.runs.pop_front();
+            }
+            entry.runs.push_back(run);
+        }
+    }
+
+    fn persist(&self, state: &State) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let persisted = Persisted {
+            next_id: state.next_id,
+            tasks: state
+                .entries
+                .iter()
+                .map(|(id, entry)| (*id, entry.task.clone()))
+                .collect(),
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec(&persisted)?)?;
+        std::fs::rename(tmp, path)?;
+        Ok(())
+    }
+}
+
+fn now_secs() -> u64 {
+    now_millis() / 1000
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use lightning_interfaces::schema::task_broker::TaskScope;
+
+    use super::*;
+
+    fn task(cron: &str) -> ScheduledTask {
+        ScheduledTask {
+            cron: cron.into(),
+            scope: TaskScope::Single,
+            service: 1,
+            payload: Bytes::from_static(b"{}"),
+        }
+    }
+
+    #[test]
+    fn test_schedules_are_persisted() {
+        let dir = tempdir::TempDir::new("scheduler").unwrap();
+        let path = dir.path().join("schedules.json");
+
+        let scheduler = Scheduler::load(Some(path.clone())).unwrap();
+        assert!(matches!(
+            scheduler.schedule(task("* * *")),
+            Err(TaskError::InvalidSchedule(_))
+        ));
+        let a = scheduler.schedule(task("*/5 * * * *")).unwrap();
+        let b = scheduler.schedule(task("@daily")).unwrap();
+        assert!(scheduler.unschedule(a));
+        assert!(!scheduler.unschedule(a));
+
+        let scheduler = Scheduler::load(Some(path)).unwrap();
+        assert_eq!(scheduler.schedules(), vec![(b, task("@daily"))]);
+        // Ids are never reused
+        assert_ne!(scheduler.schedule(task("@hourly")).unwrap(), a);
+    }
+
+    #[test]
+    fn test_take_due() {
+        let scheduler = Scheduler::load(None).unwrap();
+        let id = scheduler.schedule(task("* * * * *")).unwrap();
+
+        let next = scheduler.take_due(now_secs()).1.unwrap();
+        let (due, after) = scheduler.take_due(next);
+        assert_eq!(due, vec![(id, task("* * * * *"))]);
+        assert_eq!(after, Some(next + 60));
+
+        scheduler.record(
+            id,
+            ScheduledRun {
+                timestamp: next * 1000,
+                result: Ok(Bytes::from_static(b"ok")),
+            },
+        );
+        assert_eq!(scheduler.runs(id).len(), 1);
+    }
+}
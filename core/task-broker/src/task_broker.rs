@@ -1,26 +1,38 @@
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use affair::AsyncWorkerUnordered;
 use anyhow::Result;
+use bytes::Bytes;
 use fleek_crypto::{NodePublicKey, NodeSecretKey};
 use futures::stream::FuturesUnordered;
 use futures::{AsyncReadExt, StreamExt, TryStreamExt};
 use lightning_interfaces::prelude::*;
-use lightning_interfaces::{spawn_worker, RequestHeader, RequesterInterface, TaskError};
+use lightning_interfaces::{
+    spawn_worker,
+    QuorumResponse,
+    RequestHeader,
+    RequesterInterface,
+    ScheduledRun,
+    ScheduledTask,
+    TaskError,
+};
 use lightning_metrics::increment_counter;
+use lightning_utils::config::LIGHTNING_HOME_DIR;
 use rand::prelude::SliceRandom;
 use rand::thread_rng;
-use schema::task_broker::{TaskRequest, TaskResponse, TaskScope};
+use resolved_pathbuf::ResolvedPathBuf;
+use schema::task_broker::{is_majority, TaskRequest, TaskResponse, TaskScope};
 use serde::{Deserialize, Serialize};
 use tokio::sync::Semaphore;
 use tokio::task::JoinSet;
-use tokio::time::timeout;
+use tokio::time::{sleep_until, timeout, Instant};
 use tracing::{debug, warn};
 use types::NodeIndex;
 
 use crate::local::{LocalTaskSocket, LocalTaskWorker};
+use crate::scheduler::Scheduler;
 
 pub struct TaskBroker<C: NodeComponents> {
     socket: Arc<RwLock<Option<LocalTaskSocket>>>,
@@ -28,8 +40,12 @@ pub struct TaskBroker<C: NodeComponents> {
     topology: tokio::sync::watch::Receiver<Arc<Vec<Vec<NodePublicKey>>>>,
     query_runner: c!(C::ApplicationInterface::SyncExecutor),
     rep_reporter: c!(C::ReputationAggregatorInterface::ReputationReporter),
+    rep_query: c!(C::ReputationAggregatorInterface::ReputationQuery),
     max_depth: u8,
     connect_timeout: Duration,
+    task_timeout: Duration,
+    hedge: Option<HedgeConfig>,
+    scheduler: Arc<Scheduler>,
     temp: Option<RequestWorkerInner<C>>,
 }
 
@@ -51,6 +67,13 @@ pub struct TaskBrokerConfig {
     // Request timeout
     #[serde(with = "humantime_serde")]
     pub connect_timeout: Duration,
+    // Deadline for single node tasks that do not carry their own deadline
+    #[serde(with = "humantime_serde")]
+    pub task_timeout: Duration,
+    // Send a second copy of single node tasks to another peer if the first one is slow
+    pub hedge: Option<HedgeConfig>,
+    // File to persist scheduled tasks to, schedules only live in memory if unset
+    pub schedules_path: Option<ResolvedPathBuf>,
 }
 impl Default for TaskBrokerConfig {
     fn default() -> Self {
@@ -59,6 +82,32 @@ fn default() -> Self {
             max_peer_tasks: 128,
             max_tasks: 256,
             connect_timeout: Duration::from_secs(30),
+            task_timeout: Duration::from_secs(60),
+            hedge: None,
+            schedules_path: Some(
+                LIGHTNING_HOME_DIR
+                    .join("data/task_schedules.json")
+                    .try_into()
+                    .expect("Failed to resolve path"),
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(default)]
+pub struct HedgeConfig {
+    // Percentile of the latencies to the cluster peers to wait for before hedging
+    pub percentile: u8,
+    // Minimum time to wait before hedging
+    #[serde(with = "humantime_serde")]
+    pub min_delay: Duration,
+}
+impl Default for HedgeConfig {
+    fn default() -> Self {
+        Self {
+            percentile: 95,
+            min_delay: Duration::from_millis(500),
         }
     }
 }
@@ -71,8 +120,12 @@ fn clone(&self) -> Self {
             topology: self.topology.clone(),
             query_runner: self.query_runner.clone(),
             rep_reporter: self.rep_reporter.clone(),
+            rep_query: self.rep_query.clone(),
             max_depth: self.max_depth,
             connect_timeout: self.connect_timeout,
+            task_timeout: self.task_timeout,
+            hedge: self.hedge,
+            scheduler: self.scheduler.clone(),
             temp: None,
         }
     }
@@ -90,8 +143,11 @@ fn init(
         let config @ TaskBrokerConfig {
             max_depth,
             connect_timeout,
+            task_timeout,
+            hedge,
             ..
         } = config.get::<Self>();
+        let scheduler = Scheduler::load(config.schedules_path.as_ref().map(|p| p.to_path_buf()))?;
 
         let (req, responder) = pool.open_req_res(lightning_interfaces::ServiceScope::TaskBroker);
 
@@ -101,8 +157,12 @@ fn init(
             topology: topology.get_receiver(),
             query_runner,
             rep_reporter: reputation.get_reporter(),
+            rep_query: reputation.get_query(),
             max_depth,
             connect_timeout,
+            task_timeout,
+            hedge,
+            scheduler: Arc::new(scheduler),
             temp: Some(RequestWorkerInner {
                 sk: keystore.get_ed25519_sk(),
                 responder,
@@ -139,8 +199,50 @@ fn post_init(
             self.rep_reporter.clone(),
             socket.clone(),
             config.max_peer_tasks,
-            shutdown,
+            shutdown.clone(),
         );
+
+        // spawn the scheduler, running tasks through the broker when they are due
+        let broker = self.clone();
+        let scheduler = self.scheduler.clone().run(move |task| {
+            let broker = broker.clone();
+            async move { broker.run_scheduled_task(task).await }
+        });
+        spawn!(
+            async move { shutdown.run_until_shutdown(scheduler).await },
+            "TASK BROKER: Scheduler"
+        );
+    }
+
+    /// Run a scheduled task, returning the payload of the first response or the errors.
+    async fn run_scheduled_task(&self, task: ScheduledTask) -> Result<Bytes, String> {
+        let request = TaskRequest {
+            service: task.service,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u64,
+            payload: task.payload,
+            deadline: None,
+            origin: None,
+        };
+
+        let mut errors = Vec::new();
+        for result in self.run(0, task.scope, request).await {
+            match result {
+                Ok(response) => return Ok(response.payload),
+                Err(e) => errors.push(e.to_string()),
+            }
+        }
+        increment_counter!(
+            "task_broker_scheduled_task_failed",
+            Some("Number of scheduled task runs that failed")
+        );
+        if errors.is_empty() {
+            Err("no response".into())
+        } else {
+            Err(errors.join(", "))
+        }
     }
 
     /// Get current topology cluster
@@ -207,32 +309,210 @@ async fn run_task_on_peer(
         }
     }
 
-    /// Get a random peer and run the task on it
+    /// Get the instant after which a task is no longer useful
+    fn deadline(&self, task: &TaskRequest) -> Instant {
+        match task.deadline {
+            Some(deadline) => {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap()
+                    .as_millis() as u64;
+                Instant::now() + Duration::from_millis(deadline.saturating_sub(now))
+            },
+            None => Instant::now() + self.task_timeout,
+        }
+    }
+
+    /// Get the time to wait for a peer before sending a hedged request, based on the measured
+    /// latencies to the given peers.
+    fn hedge_delay(&self, peers: &[NodeIndex]) -> Option<Duration> {
+        let hedge = self.hedge?;
+        let measurements = self.rep_query.get_measurements();
+        let mut latencies = peers
+            .iter()
+            .filter_map(|peer| measurements.get(peer)?.latency)
+            .collect::<Vec<_>>();
+        latencies.sort();
+
+        let latency = match latencies.len() {
+            0 => Duration::ZERO,
+            n => latencies[((n - 1) * hedge.percentile.min(100) as usize) / 100],
+        };
+        Some(latency.max(hedge.min_delay))
+    }
+
+    /// Get random peers and run the task on them until one of them responds or the deadline
+    /// of the task is reached. If hedging is enabled, a second peer is tried concurrently when
+    /// the first one takes longer than usual to respond.
     async fn run_single_task(&self, task: TaskRequest) -> Result<TaskResponse, TaskError> {
+        let deadline = self.deadline(&task);
+
         // get the cluster and shuffle the nodes randomly. Cheap O(n) with our cluster size.
         // TODO: weight the shuffle based on reputation and/or latency?
         let mut cluster = self.get_cluster()?;
         cluster.shuffle(&mut thread_rng());
+        let cluster = cluster
+            .iter()
+            .map(|pub_key| {
+                self.query_runner
+                    .pubkey_to_index(pub_key)
+                    .expect("topology should never ever give unknown node pubkeys/indecies")
+            })
+            .collect::<Vec<_>>();
+
+        let run = |peer: NodeIndex, attempt: Attempt| {
+            let task = task.clone();
+            async move { (peer, attempt, self.run_task_on_peer(task, peer).await) }
+        };
 
-        for pub_key in cluster {
-            let peer = self
-                .query_runner
-                .pubkey_to_index(&pub_key)
-                .expect("topology should never ever give unknown node pubkeys/indecies");
+        // The hedge is sent once the first peer took longer than the delay to respond
+        let hedge_delay = self.hedge_delay(&cluster);
+        let hedge = sleep_until(Instant::now() + hedge_delay.unwrap_or_default());
+        tokio::pin!(hedge);
+        let mut hedged = false;
+        let mut peers = cluster.into_iter();
+        let mut pending = FuturesUnordered::new();
+        let mut attempt = Attempt::First;
+        let mut last_err = TaskError::Connect;
 
-            match self.run_task_on_peer(task.clone(), peer).await {
-                // If we have a successful response, return
-                Ok(res) => {
-                    self.rep_reporter
-                        .report_sat(peer, lightning_interfaces::Weight::Weak);
-                    return Ok(res);
+        loop {
+            if pending.is_empty() {
+                // Retry on the next peer, or give up if we tried them all
+                let Some(peer) = peers.next() else {
+                    return Err(last_err);
+                };
+                pending.push(run(peer, attempt));
+                attempt = Attempt::Retry;
+            }
+
+            tokio::select! {
+                _ = sleep_until(deadline) => {
+                    warn!("Deadline reached running task for service {}", task.service);
+                    return Err(TaskError::Timeout);
+                },
+                Some((peer, attempt, res)) = pending.next() => match res {
+                    // If we have a successful response, return
+                    Ok(res) => {
+                        self.rep_reporter
+                            .report_sat(peer, lightning_interfaces::Weight::Weak);
+                        let peer = peer.to_string();
+                        increment_counter!(
+                            "task_broker_single_task_winner",
+                            Some("Single node task responses per attempt and peer that won"),
+                            "attempt" => attempt.as_str(),
+                            "peer" => peer.as_str()
+                        );
+                        return Ok(res);
+                    },
+                    // Otherwise, print a warning and continue onto to the next peer, unless the
+                    // task would fail the same way on any peer
+                    Err(e) => {
+                        warn!("failed to run task on peer {peer}: {e:?}");
+                        if !e.is_retryable() {
+                            return Err(e);
+                        }
+                        last_err = e;
+                    },
+                },
+                _ = &mut hedge, if hedge_delay.is_some() && !hedged => {
+                    hedged = true;
+                    if let Some(peer) = peers.next() {
+                        debug!("Hedging task for service {} on peer {peer}", task.service);
+                        pending.push(run(peer, Attempt::Hedge));
+                    }
+                },
+            }
+        }
+    }
+
+    /// Run the task on `of` random peers in the cluster, and compare the digests of the
+    /// response payloads.
+    ///
+    /// Waits for every peer to respond, so the peers that disagree with the quorum can be
+    /// reported to the reputation aggregator.
+    async fn run_quorum_task(
+        &self,
+        task: TaskRequest,
+        min_agree: u8,
+        of: u8,
+    ) -> Result<QuorumResponse, TaskError> {
+        let mut cluster = self.get_cluster()?;
+        if !is_majority(min_agree, of) || of as usize > cluster.len() {
+            return Err(TaskError::InvalidQuorum(format!(
+                "{min_agree}/{of} with {} nodes in cluster",
+                cluster.len()
+            )));
+        }
+        cluster.shuffle(&mut thread_rng());
+
+        let task = &task;
+        let mut futs = cluster
+            .iter()
+            .take(of as usize)
+            .map(|v| {
+                self.query_runner
+                    .pubkey_to_index(v)
+                    .expect("topology should never give unknown node pubkeys/indecies")
+            })
+            .map(|idx| async move { (idx, self.run_task_on_peer(task.clone(), idx).await) })
+            .collect::<FuturesUnordered<_>>();
+
+        let mut responses = HashMap::<fleek_blake3::Hash, (TaskResponse, Vec<NodeIndex>)>::new();
+        let mut failed = Vec::new();
+        while let Some((peer, result)) = futs.next().await {
+            match result {
+                Ok(response) => {
+                    let hash = fleek_blake3::hash(&response.payload);
+                    responses
+                        .entry(hash)
+                        .or_insert_with(|| (response, Vec::new()))
+                        .1
+                        .push(peer);
                 },
-                // Otherwise, print a warning and continue onto to the next peer
-                Err(e) => warn!("failed to run task on peer {pub_key}: {e:?}"),
+                Err(e) => failed.push((peer, e)),
+            }
+        }
+
+        // Pick the payload most nodes agreed on. Since a quorum is a majority, only one payload can
+        // reach it and a tie never passes the check below.
+        let Some(hash) = responses
+            .iter()
+            .max_by_key(|(_, (_, peers))| peers.len())
+            .map(|(hash, _)| *hash)
+        else {
+            return Err(TaskError::NoQuorum {
+                agreed: 0,
+                min_agree: min_agree as usize,
+            });
+        };
+        let (response, agreed) = responses.remove(&hash).unwrap();
+        if agreed.len() < min_agree as usize {
+            return Err(TaskError::NoQuorum {
+                agreed: agreed.len(),
+                min_agree: min_agree as usize,
+            });
+        }
+
+        for &peer in &agreed {
+            self.rep_reporter
+                .report_sat(peer, lightning_interfaces::Weight::Weak);
+        }
+        let mut dissenting = Vec::new();
+        for (_, peers) in responses.into_values() {
+            for peer in peers {
+                warn!("Peer {peer} disagreed with the quorum");
+                self.rep_reporter
+                    .report_unsat(peer, lightning_interfaces::Weight::Strong);
+                dissenting.push(peer);
             }
         }
 
-        Err(TaskError::Connect)
+        Ok(QuorumResponse {
+            response,
+            agreed,
+            dissenting,
+            failed,
+        })
     }
 
     /// Get peers in the cluster and run the task on them
@@ -345,6 +625,72 @@ async fn run(
                 vec![res]
             },
             TaskScope::Cluster => self.run_cluster_task(task).await,
+            TaskScope::Quorum { min_agree, of } => {
+                let res = self
+                    .run_quorum(depth, min_agree, of, task)
+                    .await
+                    .map(|quorum| quorum.response);
+                vec![res]
+            },
+        }
+    }
+
+    async fn run_quorum(
+        &self,
+        depth: u8,
+        min_agree: u8,
+        of: u8,
+        task: TaskRequest,
+    ) -> Result<QuorumResponse, TaskError> {
+        if depth > self.max_depth {
+            increment_counter!(
+                "task_broker_task_blocked",
+                Some("Number of task requests blocked due to reaching max depth")
+            );
+            let err = TaskError::MaxDepth(self.max_depth);
+            warn!("{err}");
+            return Err(err);
+        }
+
+        let res = self.run_quorum_task(task, min_agree, of).await;
+        if let Err(e) = &res {
+            warn!("Failed to reach quorum: {e}");
+            task_failed_metric(TaskScope::Quorum { min_agree, of });
+        }
+        res
+    }
+
+    fn schedule(&self, task: ScheduledTask) -> Result<u64, TaskError> {
+        self.scheduler.schedule(task)
+    }
+
+    fn unschedule(&self, id: u64) -> bool {
+        self.scheduler.unschedule(id)
+    }
+
+    fn get_schedules(&self) -> Vec<(u64, ScheduledTask)> {
+        self.scheduler.schedules()
+    }
+
+    fn get_scheduled_runs(&self, id: u64) -> Vec<ScheduledRun> {
+        self.scheduler.runs(id)
+    }
+}
+
+/// The attempt of a single node task that produced a response
+#[derive(Clone, Copy)]
+enum Attempt {
+    First,
+    Retry,
+    Hedge,
+}
+
+impl Attempt {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Attempt::First => "first",
+            Attempt::Retry => "retry",
+            Attempt::Hedge => "hedge",
         }
     }
 }
@@ -425,6 +771,18 @@ fn handle_incoming_pool_task(
             e
         })?;
 
+        // Don't bother running tasks the requester is not waiting for anymore
+        if let Some(deadline) = request.deadline {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u64;
+            if deadline < now {
+                handle.reject(types::RejectReason::Other);
+                return Ok(());
+            }
+        }
+
         let socket = self.socket.clone();
         let lock = self.semaphore.clone();
         self.pending_tasks.spawn(async move {
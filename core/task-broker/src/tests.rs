@@ -75,6 +75,22 @@ async fn connect(&self, service_id: types::ServiceId) -> Option<UnixStream> {
                 }
             });
 
+            Some(client)
+        } else if service_id == 1 {
+            // Every node responds with a different payload
+            let (client, mut server) = UnixStream::pair().ok()?;
+            tokio::spawn(async move {
+                read_header(&mut server)
+                    .await
+                    .expect("Could not read hello frame.");
+                let mut framed =
+                    Framed::new(server, tokio_util::codec::LengthDelimitedCodec::new());
+                framed
+                    .send(rand::random::<[u8; 32]>().to_vec().into())
+                    .await
+                    .expect("failed to send task response");
+            });
+
             Some(client)
         } else {
             None
@@ -120,8 +136,7 @@ fn build_cluster(
                         Some(types::Staking {
                             staked: 420u64.into(),
                             stake_locked_until: 0,
-                            locked: 0u64.into(),
-                            locked_until: 0,
+                            unbonding: Default::default(),
                         }),
                         true,
                     )
@@ -156,6 +171,7 @@ fn build_cluster(
                                 })
                                 .with::<TaskBroker<TestBinding>>(TaskBrokerConfig {
                                     connect_timeout: Duration::from_secs(5),
+                                    schedules_path: None,
                                     ..Default::default()
                                 }),
                         )
@@ -198,6 +214,8 @@ async fn run_local_echo_task() -> anyhow::Result<()> {
             .unwrap()
             .as_secs(),
         payload: PAYLOAD.into(),
+        deadline: None,
+        origin: None,
     };
     let digest = request.to_digest();
 
@@ -234,6 +252,8 @@ async fn run_single_echo_task() -> anyhow::Result<()> {
             .unwrap()
             .as_secs(),
         payload: PAYLOAD.into(),
+        deadline: None,
+        origin: None,
     };
     let digest = request.to_digest();
 
@@ -293,6 +313,8 @@ async fn run_single_echo_task_with_fallback() -> anyhow::Result<()> {
                 .unwrap()
                 .as_secs(),
             payload: PAYLOAD.into(),
+            deadline: None,
+            origin: None,
         };
         let digest = request.to_digest();
 
@@ -339,6 +361,8 @@ async fn run_cluster_echo_task() -> anyhow::Result<()> {
             .unwrap()
             .as_secs(),
         payload: PAYLOAD.into(),
+        deadline: None,
+        origin: None,
     };
     let digest = request.to_digest();
 
@@ -384,6 +408,8 @@ async fn run_cluster_echo_task_1_offline_of_8() -> anyhow::Result<()> {
             .unwrap()
             .as_secs(),
         payload: PAYLOAD.into(),
+        deadline: None,
+        origin: None,
     };
     let digest = request.to_digest();
 
@@ -443,6 +469,8 @@ async fn run_cluster_echo_task_7_offline_of_8_should_fail() -> anyhow::Result<()
             .unwrap()
             .as_secs(),
         payload: PAYLOAD.into(),
+        deadline: None,
+        origin: None,
     };
     // let digest = request.to_digest();
 
@@ -463,3 +491,131 @@ async fn run_cluster_echo_task_7_offline_of_8_should_fail() -> anyhow::Result<()
 
     Ok(())
 }
+
+#[tokio::test]
+async fn run_quorum_echo_task() -> anyhow::Result<()> {
+    lightning_test_utils::logging::setup(None);
+    let (_, mut nodes) = build_cluster(8).await?;
+
+    let broker = nodes[0].provider.get::<TaskBroker<TestBinding>>();
+
+    const PAYLOAD: &[u8] = b"hello world";
+    let request = schema::task_broker::TaskRequest {
+        service: 0,
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+        payload: PAYLOAD.into(),
+        deadline: None,
+        origin: None,
+    };
+    let digest = request.to_digest();
+
+    let quorum = broker
+        .run_quorum(0, 3, 5, request.clone())
+        .await
+        .expect("quorum should be reached");
+
+    assert_eq!(quorum.response.payload, PAYLOAD);
+    assert_eq!(quorum.response.request, digest);
+    assert!(quorum.agreed.len() >= 3);
+    assert!(quorum.dissenting.is_empty());
+
+    // Quorums that can never be reached are rejected
+    let err = broker
+        .run_quorum(0, 4, 3, request.clone())
+        .await
+        .expect_err("quorum should be invalid");
+    assert!(matches!(err, TaskError::InvalidQuorum(_)));
+
+    // Quorums that are not a majority could be reached by two payloads at once
+    let err = broker
+        .run_quorum(0, 2, 4, request)
+        .await
+        .expect_err("quorum should be invalid");
+    assert!(matches!(err, TaskError::InvalidQuorum(_)));
+
+    // Shutdown all nodes
+    nodes
+        .iter_mut()
+        .map(|n| n.shutdown())
+        .collect::<FuturesUnordered<_>>()
+        .collect::<()>()
+        .await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn run_quorum_task_with_disagreeing_nodes_should_fail() -> anyhow::Result<()> {
+    lightning_test_utils::logging::setup(None);
+    let (_, mut nodes) = build_cluster(8).await?;
+
+    let broker = nodes[0].provider.get::<TaskBroker<TestBinding>>();
+
+    let request = schema::task_broker::TaskRequest {
+        service: 1,
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+        payload: b"hello world".as_slice().into(),
+        deadline: None,
+        origin: None,
+    };
+
+    // Every payload is returned by a single node, none of them wins
+    let err = broker
+        .run_quorum(0, 3, 4, request)
+        .await
+        .expect_err("quorum should not be reached");
+    assert!(matches!(err, TaskError::NoQuorum { min_agree: 3, .. }));
+
+    // Shutdown all nodes
+    nodes
+        .iter_mut()
+        .map(|n| n.shutdown())
+        .collect::<FuturesUnordered<_>>()
+        .collect::<()>()
+        .await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn run_single_echo_task_past_deadline_should_fail() -> anyhow::Result<()> {
+    lightning_test_utils::logging::setup(None);
+    let (_, mut nodes) = build_cluster(4).await?;
+
+    let broker = nodes[0].provider.get::<TaskBroker<TestBinding>>();
+
+    const PAYLOAD: &[u8] = b"hello world";
+    let request = schema::task_broker::TaskRequest {
+        service: 0,
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+        payload: PAYLOAD.into(),
+        // The deadline has already passed
+        deadline: Some(0),
+        origin: None,
+    };
+
+    let response = broker
+        .run(0, schema::task_broker::TaskScope::Single, request)
+        .await;
+
+    assert_eq!(response, vec![Err(TaskError::Timeout)]);
+
+    // Shutdown all nodes
+    nodes
+        .iter_mut()
+        .map(|n| n.shutdown())
+        .collect::<FuturesUnordered<_>>()
+        .collect::<()>()
+        .await;
+
+    Ok(())
+}
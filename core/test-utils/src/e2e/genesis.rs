@@ -120,12 +120,18 @@ pub fn build(self) -> Genesis {
                     owner: EthAddress::from_str("0xDC0A31F9eeb151f82BF1eE6831095284fC215Ee7")
                         .unwrap(),
                     commodity_type: CommodityTypes::Bandwidth,
+                    binary: None,
+                    version: 0,
+                    resources: Default::default(),
                 },
                 GenesisService {
                     id: 1,
                     owner: EthAddress::from_str("0x684166BDbf530a256d7c92Fa0a4128669aFd9B9F")
                         .unwrap(),
                     commodity_type: CommodityTypes::Compute,
+                    binary: None,
+                    version: 0,
+                    resources: Default::default(),
                 },
             ],
             account: self.accounts,
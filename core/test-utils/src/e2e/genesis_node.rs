@@ -69,8 +69,7 @@ pub fn build(self) -> GenesisNode {
             stake: self.stake.unwrap_or_else(|| Staking {
                 staked: HpUfixed::<18>::from(1000u32),
                 stake_locked_until: 0,
-                locked: HpUfixed::<18>::zero(),
-                locked_until: 0,
+                unbonding: Default::default(),
             }),
             reputation: None,
             current_epoch_served: None,
@@ -191,8 +191,7 @@ pub async fn build(mut self) -> Result<TestNetwork> {
                         .with_stake(Staking {
                             staked: 1000u32.into(),
                             stake_locked_until: 0,
-                            locked: 0u32.into(),
-                            locked_until: 0,
+                            unbonding: Default::default(),
                         })
                         .with_is_committee(node.is_genesis_committee())
                         .build(),
@@ -81,13 +81,9 @@ pub fn get_account_nonce(&self, account: EthAddress) -> u64 {
     }
 
     pub fn get_stables_balance(&self, account: EthAddress) -> HpUfixed<6> {
-        match self
-            .app_query()
-            .get_account_info(&account, |a| a.stables_balance)
-        {
-            Some(balance) => balance,
-            None => HpUfixed::<6>::zero(),
-        }
+        self.app_query()
+            .get_token_balance(&account, &Tokens::USDC)
+            .convert_precision()
     }
 
     pub fn get_flk_balance(&self, account: EthAddress) -> HpUfixed<18> {
@@ -16,6 +16,7 @@ pub fn get_update_transactions(num_txns: usize) -> Vec<UpdateRequest> {
                 nonce: 0,
                 method,
                 chain_id: 1337,
+                fee: None,
             };
             let digest = payload.to_digest();
             UpdateRequest {
@@ -7,7 +7,7 @@
 use hp_fixed::unsigned::HpUfixed;
 use serde::{Deserialize, Serialize};
 
-use crate::{BlockNumber, Epoch, Staking, Tokens, TransactionReceipt};
+use crate::{BlockNumber, Epoch, NodeInfoUpdate, Staking, Tokens, TransactionReceipt};
 
 /// Max number of updates allowed in a content registry update transaction.
 pub const MAX_UPDATES_CONTENT_REGISTRY: usize = 100;
@@ -15,6 +15,9 @@
 /// Max number of delivery acknowledgements allowed per transaction.
 pub const MAX_DELIVERY_ACKNOWLEDGMENTS: usize = 1000;
 
+/// Max number of update methods allowed in a batch transaction.
+pub const MAX_BATCH_SIZE: usize = 16;
+
 macro_rules! create_events {
     (
         pub enum Event {
@@ -69,6 +72,14 @@ pub enum Event {
             service_id: u32,
             event: Vec<u8>,
         },
+        NodeInfoUpdated {
+            node: NodePublicKey,
+            update: NodeInfoUpdate,
+        },
+        NodeInfoUpdateRejected {
+            node: NodePublicKey,
+            update: NodeInfoUpdate,
+        },
     }
 }
 
@@ -90,6 +101,14 @@ pub fn transfer(
     pub fn service_event(service_id: u32, event: Vec<u8>) -> Self {
         Self::ServiceEvent { service_id, event }
     }
+
+    pub fn node_info_updated(node: NodePublicKey, update: NodeInfoUpdate) -> Self {
+        Self::NodeInfoUpdated { node, update }
+    }
+
+    pub fn node_info_update_rejected(node: NodePublicKey, update: NodeInfoUpdate) -> Self {
+        Self::NodeInfoUpdateRejected { node, update }
+    }
 }
 
 /// The response generated from executing an entire batch of transactions (aka a block).
@@ -203,8 +222,6 @@ pub enum NodeRegistryChangeSlashReason {
 pub struct AccountInfo {
     /// The accounts FLK balance
     pub flk_balance: HpUfixed<18>,
-    /// the accounts stable coin balance
-    pub stables_balance: HpUfixed<6>,
     /// The accounts stables/bandwidth balance
     pub bandwidth_balance: u128,
     /// The nonce of the account. Added to each transaction before signed to prevent replays and
@@ -11,6 +11,7 @@
 use serde_with::{serde_as, DisplayFromStr};
 
 use crate::{
+    Blake3Hash,
     CommodityServed,
     CommodityTypes,
     Epoch,
@@ -18,6 +19,7 @@
     NodePorts,
     NodeServed,
     Participation,
+    ServiceResources,
     Staking,
     TotalServed,
 };
@@ -98,6 +100,12 @@ pub struct GenesisService {
     pub id: u32,
     pub owner: EthAddress,
     pub commodity_type: CommodityTypes,
+    #[serde(default)]
+    pub binary: Option<Blake3Hash>,
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub resources: ServiceResources,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -52,8 +52,8 @@ pub struct TransactionReceipt {
     pub to: TransactionDestination,
     /// The results of the transaction
     pub response: TransactionResponse,
-    /// The event that was emitted by the transaction
-    pub event: Option<Event>,
+    /// The events that were emitted by the transaction
+    pub events: Vec<Event>,
 }
 
 /// What state function a transaction was calling. If an ethereum transaction it will either be
@@ -108,6 +108,8 @@ pub enum ExecutionData {
     UInt(u128),
     EpochInfo(EpochInfo),
     EpochChange,
+    /// The results of the methods of a batch transaction, in order.
+    Batch(Vec<ExecutionData>),
 }
 
 /// Error type for transaction execution on the application layer
@@ -128,6 +130,7 @@ pub enum ExecutionError {
     CommitteeSelectionBeaconRoundNotFound,
     MissingCommittee,
     InsufficientBalance,
+    InsufficientBalanceForFee,
     InvalidChainId,
     InvalidSignature,
     InvalidNonce,
@@ -137,6 +140,9 @@ pub enum ExecutionError {
     InvalidStateFunction,
     InvalidConsensusKey,
     InvalidToken,
+    TokenAlreadyExists,
+    TokenNotBridged,
+    InvalidAmount,
     InvalidStateForContentRemoval,
     InvalidContentRemoval,
     NoLockedTokens,
@@ -153,10 +159,14 @@ pub enum ExecutionError {
     OnlyNode,
     OnlyGovernance,
     InvalidServiceId,
+    ServiceAlreadyExists,
+    InvalidServiceVersion,
     InsufficientStake,
     NodeNotParticipating,
     LockExceededMaxStakeLockTime,
     LockedTokensUnstakeForbidden,
+    NoDelegation,
+    InvalidCommission,
     EpochAlreadyChanged,
     EpochHasNotStarted,
     ConsensusKeyAlreadyIndexed,
@@ -165,6 +175,15 @@ pub enum ExecutionError {
     TooManyMeasurements,
     TooManyUpdates,
     TooManyUpdatesForContent,
+    // batch transaction error types
+    EmptyBatch,
+    TooManyBatchMethods,
+    InvalidBatchMethod,
+    /// A method of a batch transaction reverted, none of the changes of the batch were applied.
+    BatchMethodReverted {
+        index: u32,
+        error: Box<ExecutionError>,
+    },
     // approve and revoke client key error types
     InvalidClientKeyLength,
     DuplicateClientKey,
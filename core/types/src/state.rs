@@ -1,5 +1,6 @@
 //! The data types used in the application state
 use std::borrow::Cow;
+use std::collections::BTreeSet;
 use std::fmt::Display;
 use std::net::IpAddr;
 use std::time::Duration;
@@ -14,7 +15,7 @@
 use sha3::{Digest, Sha3_256};
 
 use super::ReputationMeasurements;
-use crate::NodeRegistryChanges;
+use crate::{Blake3Hash, NodeRegistryChanges};
 
 /// The Id of a Service
 pub type ServiceId = u32;
@@ -32,6 +33,8 @@
 pub enum Tokens {
     USDC,
     FLK,
+    /// A token registered through governance, identified by its address on the L2.
+    Erc20(EthAddress),
 }
 
 impl Tokens {
@@ -40,8 +43,48 @@ pub fn address(&self) -> EthAddress {
         match self {
             Tokens::USDC => EthAddress([0; 20]),
             Tokens::FLK => EthAddress([1; 20]),
+            Tokens::Erc20(address) => *address,
         }
     }
+
+    /// Returns true if this is FLK, which is kept on the accounts rather than in the token
+    /// balances.
+    pub fn is_flk(&self) -> bool {
+        self.address() == Tokens::FLK.address()
+    }
+
+    /// The tokens built into the network, registered at genesis.
+    pub fn builtin() -> [(Tokens, TokenInfo); 2] {
+        [
+            (
+                Tokens::USDC,
+                TokenInfo {
+                    symbol: "USDC".to_string(),
+                    decimals: 6,
+                    bridged: true,
+                },
+            ),
+            (
+                Tokens::FLK,
+                TokenInfo {
+                    symbol: "FLK".to_string(),
+                    decimals: 18,
+                    bridged: true,
+                },
+            ),
+        ]
+    }
+}
+
+/// A token registered on the network, keyed by its address in the tokens table.
+#[derive(Debug, Hash, PartialEq, Eq, Serialize, Deserialize, Clone, schemars::JsonSchema)]
+pub struct TokenInfo {
+    /// The ticker of the token.
+    pub symbol: String,
+    /// The number of decimals of the token on the L2, at most 18.
+    pub decimals: u8,
+    /// Whether the token can be deposited and withdrawn over the bridge.
+    pub bridged: bool,
 }
 
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default, schemars::JsonSchema)]
@@ -108,6 +151,7 @@ pub enum Metadata {
     CommitteeSelectionBeaconRound,
     EpochEra,
     WithdrawId,
+    StateVersion,
 }
 
 /// The Value enum is a data type used to represent values in a key-value pair for a metadata table
@@ -129,6 +173,7 @@ pub enum Value {
     CommitteeSelectionBeaconRound(CommitteeSelectionBeaconRound),
     EpochEra(u64),
     WithdrawId(u64),
+    StateVersion(u64),
 }
 
 impl Value {
@@ -281,6 +326,10 @@ pub enum ProtocolParamKey {
     CommitteeSelectionBeaconRevealPhaseDuration = 19,
     /// The slash amount for non-revealing nodes in the committee selection beacon process.
     CommitteeSelectionBeaconNonRevealSlashAmount = 20,
+    /// The percentage of the transaction fees that goes to the protocol fund, the rest is burned
+    FeeProtocolShare = 21,
+    /// The gas price in wei of FLK that is suggested to the ethereum tooling
+    GasPrice = 22,
 }
 
 /// The Value enum is a data type used to represent values in a key-value pair for a metadata table
@@ -307,6 +356,8 @@ pub enum ProtocolParamValue {
     CommitteeSelectionBeaconCommitPhaseDuration(u64),
     CommitteeSelectionBeaconRevealPhaseDuration(u64),
     CommitteeSelectionBeaconNonRevealSlashAmount(u64),
+    FeeProtocolShare(u16),
+    GasPrice(u64),
 }
 
 impl ProtocolParamValue {
@@ -341,6 +392,8 @@ pub fn get_bytes(&self) -> Cow<'_, [u8]> {
             ProtocolParamValue::CommitteeSelectionBeaconNonRevealSlashAmount(i) => {
                 Cow::Owned(i.to_le_bytes().to_vec())
             },
+            ProtocolParamValue::FeeProtocolShare(i) => Cow::Owned(i.to_le_bytes().to_vec()),
+            ProtocolParamValue::GasPrice(i) => Cow::Owned(i.to_le_bytes().to_vec()),
         }
     }
 }
@@ -385,6 +438,39 @@ pub struct NodeInfo {
     pub nonce: u64,
 }
 
+/// Changes to the network details of a node, the fields that are `None` are left unchanged.
+#[derive(
+    Debug, Hash, PartialEq, Eq, Serialize, Deserialize, Clone, Default, schemars::JsonSchema,
+)]
+pub struct NodeInfoUpdate {
+    /// The new BLS public key of the node
+    pub consensus_key: Option<ConsensusPublicKey>,
+    /// The new primary domain of the node
+    pub domain: Option<IpAddr>,
+    /// The new public key of the node's narwhal worker
+    pub worker_public_key: Option<NodePublicKey>,
+    /// The new domain of the node's workers
+    pub worker_domain: Option<IpAddr>,
+    /// The new ports of the node
+    pub ports: Option<NodePorts>,
+}
+
+impl NodeInfoUpdate {
+    /// Returns true if the update doesn't change anything.
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Merges a later update into this one, the fields set in `other` take precedence.
+    pub fn merge(&mut self, other: NodeInfoUpdate) {
+        self.consensus_key = other.consensus_key.or(self.consensus_key);
+        self.domain = other.domain.or(self.domain);
+        self.worker_public_key = other.worker_public_key.or(self.worker_public_key);
+        self.worker_domain = other.worker_domain.or(self.worker_domain);
+        self.ports = other.ports.or(self.ports.take());
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, schemars::JsonSchema)]
 pub struct NodeInfoWithIndex {
     pub index: NodeIndex,
@@ -506,10 +592,154 @@ pub struct Staking {
     pub staked: HpUfixed<18>,
     /// The epoch until all stakes are locked for boosting rewards
     pub stake_locked_until: u64,
-    /// How much FLK is locked pending withdraw
+    /// FLK that was unstaked and is locked pending withdraw
+    pub unbonding: UnbondingQueue,
+}
+
+impl Staking {
+    /// Returns the total amount of FLK that is locked pending withdraw.
+    pub fn locked(&self) -> HpUfixed<18> {
+        self.unbonding.total()
+    }
+}
+
+/// FLK from a single unstake that is locked pending withdraw.
+#[derive(
+    Debug,
+    Hash,
+    PartialEq,
+    PartialOrd,
+    Ord,
+    Eq,
+    Serialize,
+    Deserialize,
+    Clone,
+    Default,
+    schemars::JsonSchema,
+)]
+pub struct UnbondingEntry {
+    /// How much FLK is locked
+    pub amount: HpUfixed<18>,
+    /// The epoch the FLK is eligible to be withdrawn
+    pub locked_until: Epoch,
+}
+
+/// The unbonding entries of a stake, in the order the unstakes were made.
+#[derive(
+    Debug,
+    Hash,
+    PartialEq,
+    PartialOrd,
+    Ord,
+    Eq,
+    Serialize,
+    Deserialize,
+    Clone,
+    Default,
+    schemars::JsonSchema,
+)]
+#[serde(transparent)]
+pub struct UnbondingQueue(pub Vec<UnbondingEntry>);
+
+impl UnbondingQueue {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the total amount of FLK in the queue.
+    pub fn total(&self) -> HpUfixed<18> {
+        self.0
+            .iter()
+            .fold(HpUfixed::zero(), |total, entry| total + &entry.amount)
+    }
+
+    /// Returns the amount of FLK that is eligible to be withdrawn at the given epoch.
+    pub fn matured(&self, epoch: Epoch) -> HpUfixed<18> {
+        self.0
+            .iter()
+            .filter(|entry| entry.locked_until <= epoch)
+            .fold(HpUfixed::zero(), |total, entry| total + &entry.amount)
+    }
+
+    /// Adds a new entry of `amount` FLK locked until the given epoch.
+    pub fn push(&mut self, amount: HpUfixed<18>, locked_until: Epoch) {
+        self.0.push(UnbondingEntry {
+            amount,
+            locked_until,
+        });
+    }
+
+    /// Removes the entries that are eligible to be withdrawn at the given epoch and returns their
+    /// total amount.
+    pub fn withdraw_matured(&mut self, epoch: Epoch) -> HpUfixed<18> {
+        let matured = self.matured(epoch);
+        self.0.retain(|entry| entry.locked_until > epoch);
+        matured
+    }
+
+    /// Removes up to `amount` FLK from the queue, starting with the most recent entries, and
+    /// returns the amount that could not be covered by the queue.
+    pub fn slash(&mut self, mut amount: HpUfixed<18>) -> HpUfixed<18> {
+        while amount > HpUfixed::zero() {
+            let Some(entry) = self.0.last_mut() else {
+                break;
+            };
+            if entry.amount > amount {
+                entry.amount -= amount;
+                return HpUfixed::zero();
+            }
+            amount -= entry.amount.clone();
+            self.0.pop();
+        }
+        amount
+    }
+}
+
+/// Stake delegated by an account to a node it doesn't operate.
+#[derive(
+    Debug,
+    Hash,
+    PartialEq,
+    PartialOrd,
+    Ord,
+    Eq,
+    Serialize,
+    Deserialize,
+    Clone,
+    Default,
+    schemars::JsonSchema,
+)]
+pub struct Delegation {
+    /// How much FLK is currently delegated
+    pub staked: HpUfixed<18>,
+    /// FLK that was undelegated and is locked pending withdraw
+    pub unbonding: UnbondingQueue,
+}
+
+/// The delegations of a node, along with the commission its owner takes from the rewards of the
+/// delegators.
+#[derive(
+    Debug,
+    Hash,
+    PartialEq,
+    PartialOrd,
+    Ord,
+    Eq,
+    Serialize,
+    Deserialize,
+    Clone,
+    Default,
+    schemars::JsonSchema,
+)]
+pub struct DelegationPool {
+    /// Percentage of the rewards of the delegators that goes to the node owner
+    pub commission: u8,
+    /// How much FLK is currently delegated to the node, in total
+    pub delegated: HpUfixed<18>,
+    /// How much FLK undelegated from the node is locked pending withdraw, in total
     pub locked: HpUfixed<18>,
-    /// The epoch the locked FLK is eligible to be withdrawn
-    pub locked_until: u64,
+    /// Accounts with a delegation to the node, including the ones with only locked FLK left
+    pub delegators: BTreeSet<EthAddress>,
 }
 
 #[derive(Debug, Hash, PartialEq, PartialOrd, Ord, Eq, Serialize, Deserialize, Clone)]
@@ -522,7 +752,6 @@ pub struct Worker {
     pub mempool: Multiaddr,
 }
 
-/// Placeholder
 /// Information about the services
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, schemars::JsonSchema)]
 pub struct Service {
@@ -533,6 +762,24 @@ pub struct Service {
     pub commodity_type: CommodityTypes,
     /// TODO: List of circuits to prove a node should be slashed
     pub slashing: (),
+    /// The blake3 hash of the binary running the service, nodes fetch it and verify it against
+    /// this hash before running it. Services without a binary are built into the node.
+    pub binary: Option<Blake3Hash>,
+    /// The version of the binary, it increases with every upgrade of the service
+    pub version: u32,
+    /// The minimum resources a node needs to run the service
+    pub resources: ServiceResources,
+}
+
+/// The resources of a node required to run a service
+#[derive(
+    Clone, Copy, Debug, Default, Serialize, Deserialize, Hash, Eq, PartialEq, schemars::JsonSchema,
+)]
+pub struct ServiceResources {
+    /// The number of CPU cores
+    pub cpu_cores: u32,
+    /// The amount of memory in megabytes
+    pub memory_mb: u64,
 }
 
 #[derive(
@@ -560,8 +807,14 @@ impl TranscriptBuilderInput for Service {
     const TYPE: &'static str = "service";
 
     fn to_transcript_builder_input(&self) -> Vec<u8> {
-        self.commodity_type.to_transcript_builder_input()
+        let mut input = self.commodity_type.to_transcript_builder_input();
         // todo: check if implementation needs to change when slashing is implemented
+        input.push(self.binary.is_some() as u8);
+        input.extend_from_slice(&self.binary.unwrap_or_default());
+        input.extend_from_slice(&self.version.to_be_bytes());
+        input.extend_from_slice(&self.resources.cpu_cores.to_be_bytes());
+        input.extend_from_slice(&self.resources.memory_mb.to_be_bytes());
+        input
     }
 }
 
@@ -572,10 +825,22 @@ fn to_transcript_builder_input(&self) -> Vec<u8> {
         match self {
             Tokens::USDC => b"USDC".to_vec(),
             Tokens::FLK => b"FLK".to_vec(),
+            Tokens::Erc20(address) => address.0.to_vec(),
         }
     }
 }
 
+impl TranscriptBuilderInput for TokenInfo {
+    const TYPE: &'static str = "TokenInfo";
+
+    fn to_transcript_builder_input(&self) -> Vec<u8> {
+        let mut input = self.symbol.as_bytes().to_vec();
+        input.push(self.decimals);
+        input.push(self.bridged as u8);
+        input
+    }
+}
+
 impl TranscriptBuilderInput for CommodityTypes {
     const TYPE: &'static str = "commodity_types";
 
@@ -595,7 +860,10 @@ fn try_from(value: String) -> Result<Self, Self::Error> {
         match value.as_str() {
             "flk" | "FLK" => Ok(Tokens::FLK),
             "usdc" | "USDC" => Ok(Tokens::USDC),
-            _ => Err(anyhow!("Invalid token: {value}")),
+            _ => value
+                .parse()
+                .map(Tokens::Erc20)
+                .map_err(|_| anyhow!("Invalid token: {value}")),
         }
     }
 }
@@ -1,4 +1,4 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::net::IpAddr;
 
 use anyhow::Context;
@@ -23,17 +23,21 @@
     ReputationMeasurements,
     Service,
     ServiceId,
+    TokenInfo,
     Tokens,
 };
 use crate::content_registry::ContentUpdate;
 use crate::{
+    Blake3Hash,
     CommitteeSelectionBeaconCommit,
     CommitteeSelectionBeaconReveal,
     DeliveryAcknowledgmentProof,
     NodeIndex,
+    NodeInfoUpdate,
     NodePorts,
     ProtocolParamKey,
     ProtocolParamValue,
+    ServiceResources,
     TransactionDestination,
 };
 
@@ -45,6 +49,7 @@
 
 // TODO: Change this to capital and non-abrv version.
 const FN_TXN_PAYLOAD_DOMAIN: &str = "fleek_network_txn_payload";
+const FN_TXN_BATCH_DOMAIN: &str = "fleek_network_txn_batch";
 
 /// Create this wrapper so we can implement JsonSchema for EthersTransaction
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
@@ -180,28 +185,9 @@ pub fn chain_id(&self) -> ChainId {
     }
 
     pub fn event(&self) -> Option<Event> {
-        if let TransactionSender::AccountOwner(sender) = self.sender() {
-            match self {
-                Self::UpdateRequest(payload) => match &payload.payload.method {
-                    UpdateMethod::Transfer { amount, token, to } => Some(Event::transfer(
-                        token.address(),
-                        sender,
-                        *to,
-                        amount.clone(),
-                    )),
-                    UpdateMethod::SubmitDeliveryAcknowledgmentAggregation {
-                        service_id,
-                        metadata: event,
-                        ..
-                    } => event
-                        .to_owned()
-                        .map(|e| Event::service_event(*service_id, e)),
-                    _ => None,
-                },
-                _ => None,
-            }
-        } else {
-            None
+        match self {
+            Self::UpdateRequest(payload) => payload.payload.method.event(self.sender()),
+            _ => None,
         }
     }
 
@@ -211,6 +197,123 @@ pub fn nonce(&self) -> Nonce {
             Self::EthereumRequest(payload) => payload.nonce.as_u64(),
         }
     }
+
+    /// Returns the fee in FLK the sender offers for this transaction. For ethereum transactions
+    /// this is the gas limit times the priority fee per gas, or the gas price for legacy
+    /// transactions, in wei.
+    pub fn fee(&self) -> Option<HpUfixed<18>> {
+        match self {
+            Self::UpdateRequest(payload) => payload.payload.fee.clone(),
+            Self::EthereumRequest(payload) => payload
+                .max_priority_fee_per_gas
+                .or(payload.gas_price)
+                .and_then(|price| price.checked_mul(payload.gas))
+                .filter(|fee| !fee.is_zero())
+                .map(HpUfixed::from),
+        }
+    }
+
+    /// Returns the priority this transaction is ordered with. Transactions that drive the epoch
+    /// change come first, followed by the transactions of account owners that pay a fee.
+    pub fn priority(&self) -> TransactionPriority {
+        let sender = self.sender();
+        match self {
+            Self::UpdateRequest(payload)
+                if payload.payload.method.is_system() && !sender.is_account_owner() =>
+            {
+                TransactionPriority::System
+            },
+            _ if sender.is_account_owner() => match self.fee() {
+                Some(fee) if fee > HpUfixed::zero() => TransactionPriority::Fee(fee),
+                _ => TransactionPriority::Normal,
+            },
+            _ => TransactionPriority::Normal,
+        }
+    }
+}
+
+/// The priority lane a transaction is ordered in. Higher priorities are ordered first, and
+/// transactions paying a fee are ordered by the fee they pay.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TransactionPriority {
+    Normal,
+    Fee(HpUfixed<18>),
+    System,
+}
+
+/// Returns the account owners whose FLK balance does not cover the combined fees of their
+/// transactions. Charging the fee of their transactions reverts without charging anything, so
+/// they are not given the fee lane, otherwise declaring a fee would be a free way to jump ahead.
+fn unfunded_senders<'a, F>(
+    priorities: impl IntoIterator<Item = (&'a TransactionSender, &'a TransactionPriority)>,
+    flk_balance: F,
+) -> BTreeSet<EthAddress>
+where
+    F: Fn(&EthAddress) -> HpUfixed<18>,
+{
+    let mut fees = BTreeMap::<EthAddress, HpUfixed<18>>::new();
+    for (sender, priority) in priorities {
+        if let (TransactionSender::AccountOwner(account), TransactionPriority::Fee(fee)) =
+            (sender, priority)
+        {
+            *fees.entry(*account).or_default() += fee.clone();
+        }
+    }
+    fees.into_iter()
+        .filter(|(account, fee)| flk_balance(account) < *fee)
+        .map(|(account, _)| account)
+        .collect()
+}
+
+impl TransactionPriority {
+    /// Moves the transaction to the normal lane if its sender can not pay for its fee.
+    pub fn funded_by(self, sender: &TransactionSender, unfunded: &BTreeSet<EthAddress>) -> Self {
+        match (self, sender) {
+            (TransactionPriority::Fee(_), TransactionSender::AccountOwner(account))
+                if unfunded.contains(account) =>
+            {
+                TransactionPriority::Normal
+            },
+            (priority, _) => priority,
+        }
+    }
+}
+
+/// Orders the transactions by their priority, highest first. The transactions of a sender keep
+/// their relative order so that their nonces still line up, which is why a transaction is moved
+/// up together with the earlier transactions of its sender. Fees only count if the sender's FLK
+/// balance, as returned by `flk_balance`, covers all of them.
+pub fn order_by_priority<F>(
+    transactions: Vec<TransactionRequest>,
+    flk_balance: F,
+) -> Vec<TransactionRequest>
+where
+    F: Fn(&EthAddress) -> HpUfixed<18>,
+{
+    let priorities = transactions
+        .iter()
+        .map(|txn| (txn.sender(), txn.priority()))
+        .collect::<Vec<_>>();
+    let unfunded = unfunded_senders(priorities.iter().map(|(s, p)| (s, p)), flk_balance);
+
+    let mut sender_priority = BTreeMap::<TransactionSender, TransactionPriority>::new();
+    let mut prioritized = transactions
+        .into_iter()
+        .zip(priorities)
+        .rev()
+        .map(|(txn, (sender, priority))| {
+            let mut priority = priority.funded_by(&sender, &unfunded);
+            if let Some(later) = sender_priority.get(&sender) {
+                priority = priority.max(later.clone());
+            }
+            sender_priority.insert(sender, priority.clone());
+            (priority, txn)
+        })
+        .collect::<Vec<_>>();
+    prioritized.reverse();
+    // The sort is stable, so transactions with the same priority keep their order.
+    prioritized.sort_by(|(a, _), (b, _)| b.cmp(a));
+    prioritized.into_iter().map(|(_, txn)| txn).collect()
 }
 
 impl TryFrom<&TransactionRequest> for Vec<u8> {
@@ -329,6 +432,9 @@ pub struct UpdatePayload {
     pub method: UpdateMethod,
     /// The chain ID.
     pub chain_id: ChainId,
+    /// The optional fee in FLK the sender pays for the transaction to be prioritized. Only
+    /// account owners pay fees, it is ignored for the transactions sent by nodes.
+    pub fee: Option<HpUfixed<18>>,
 }
 
 /// All of the update functions in our logic, along their parameters.
@@ -349,7 +455,7 @@ pub enum UpdateMethod {
     },
     /// Withdraw tokens from the network back to the L2
     Withdraw {
-        /// The amount to withdraw.
+        /// The amount to withdraw, it can't be more precise than the token on the L2.
         amount: HpUfixed<18>,
         /// Which token to withdraw.
         token: Tokens,
@@ -367,7 +473,8 @@ pub enum UpdateMethod {
         /// The address to recieve these tokens on the network
         receiving_address: EthAddress,
     },
-    /// Submit of PoC from the bridge on the L2 to get the tokens in network
+    /// Submit of PoC from the bridge on the L2 to get the tokens in network. Deposits of USDC are
+    /// credited to the bandwidth balance of the account.
     Deposit {
         /// The proof of the bridge recieved from the L2,
         proof: ProofOfConsensus,
@@ -421,6 +528,34 @@ pub enum UpdateMethod {
         node: NodePublicKey,
         recipient: Option<EthAddress>,
     },
+    /// Delegate FLK to a node operated by someone else. The delegated FLK counts towards the
+    /// stake of the node, and the delegator receives a share of the rewards of the node.
+    Delegate {
+        node: NodePublicKey,
+        amount: HpUfixed<18>,
+    },
+    /// Undelegate FLK from a node, the tokens will be locked for a set amount of
+    /// time(ProtocolParameter::LockTime) before they can be withdrawn
+    Undelegate {
+        node: NodePublicKey,
+        amount: HpUfixed<18>,
+    },
+    /// Withdraw undelegated tokens from a node after lock period has passed
+    /// must be submitted by the delegator but optionally they can provide a different public key
+    /// to receive the tokens
+    WithdrawUndelegated {
+        node: NodePublicKey,
+        recipient: Option<EthAddress>,
+    },
+    /// Set the percentage of the rewards of the delegators that goes to the node owner, the new
+    /// rate takes effect at the next epoch
+    SetCommissionRate { node: NodePublicKey, commission: u8 },
+    /// Change the network details of a node, sent by the node itself or its owner. The change
+    /// takes effect immediately, or at the next epoch if the node is a committee member.
+    UpdateNodeInfo {
+        node: NodePublicKey,
+        update: NodeInfoUpdate,
+    },
     /// Sent by committee member to signal he is ready to change epoch
     ChangeEpoch { epoch: Epoch },
     /// Sent by nodes to commit their committee selection random beacon.
@@ -445,6 +580,24 @@ pub enum UpdateMethod {
         /// Service Id of the service to be removed
         service_id: ServiceId,
     },
+    /// Upgrading the binary of a service, sent by the owner of the service or the governance
+    UpgradeService {
+        service_id: ServiceId,
+        /// The blake3 hash of the new binary
+        binary: Blake3Hash,
+        /// The new version, must be greater than the current version of the service
+        version: u32,
+        /// The minimum resources required to run the new binary
+        resources: ServiceResources,
+    },
+    /// Registering a new token on the network, sent by the governance
+    RegisterToken {
+        /// The address of the token on the L2
+        address: EthAddress,
+        info: TokenInfo,
+    },
+    /// Enabling or disabling the bridging of a registered token, sent by the governance
+    SetTokenBridged { token: Tokens, bridged: bool },
     /// Provide proof of misbehavior to slash a node
     Slash {
         /// Service id of the service a node misbehaved in
@@ -475,6 +628,10 @@ pub enum UpdateMethod {
     UpdateContentRegistry { updates: Vec<ContentUpdate> },
     /// Increment the node nonce.
     IncrementNonce {},
+    /// Execute several update methods atomically and in order, with a single nonce. If any of
+    /// the methods reverts, the changes made by all of them are discarded. Batches can not be
+    /// nested, and can not contain the methods that drive the epoch change.
+    Batch(Vec<UpdateMethod>),
 }
 
 impl ToDigest for UpdatePayload {
@@ -491,6 +648,10 @@ fn transcript(&self) -> TranscriptBuilder {
             .with("nonce", &self.nonce)
             .with("chain_id", &self.chain_id);
 
+        if let Some(fee) = &self.fee {
+            transcript_builder = transcript_builder.with("fee", &HpUfixedWrapper(fee.clone()));
+        }
+
         match &self.sender {
             TransactionSender::NodeMain(public_key) => {
                 transcript_builder = transcript_builder.with("sender_node", &public_key.0);
@@ -504,7 +665,52 @@ fn transcript(&self) -> TranscriptBuilder {
         }
 
         // insert method fields
-        match &self.method {
+        self.method.append_transcript(transcript_builder)
+    }
+}
+
+impl UpdateMethod {
+    /// Returns true if this is one of the methods that drive the epoch change, which are ordered
+    /// before all the other transactions.
+    pub fn is_system(&self) -> bool {
+        matches!(
+            self,
+            UpdateMethod::ChangeEpoch { .. }
+                | UpdateMethod::CommitteeSelectionBeaconCommit { .. }
+                | UpdateMethod::CommitteeSelectionBeaconReveal { .. }
+                | UpdateMethod::CommitteeSelectionBeaconCommitPhaseTimeout
+                | UpdateMethod::CommitteeSelectionBeaconRevealPhaseTimeout
+        )
+    }
+
+    /// Returns the event emitted by the method when it succeeds. The methods of a batch emit their
+    /// events individually.
+    pub fn event(&self, sender: TransactionSender) -> Option<Event> {
+        let TransactionSender::AccountOwner(sender) = sender else {
+            return None;
+        };
+        match self {
+            UpdateMethod::Transfer { amount, token, to } => Some(Event::transfer(
+                token.address(),
+                sender,
+                *to,
+                amount.clone(),
+            )),
+            UpdateMethod::SubmitDeliveryAcknowledgmentAggregation {
+                service_id,
+                metadata: event,
+                ..
+            } => event
+                .to_owned()
+                .map(|e| Event::service_event(*service_id, e)),
+            _ => None,
+        }
+    }
+
+    /// Appends the name of the method along with the value of all of its parameters to the
+    /// transcript.
+    fn append_transcript(&self, mut transcript_builder: TranscriptBuilder) -> TranscriptBuilder {
+        match self {
             UpdateMethod::Deposit {
                 proof: _,
                 token,
@@ -631,6 +837,66 @@ fn transcript(&self) -> TranscriptBuilder {
                     .with("node", &node.0)
                     .with("recipient", &recipient.map_or([0u8; 20], |key| key.0));
             },
+            UpdateMethod::Delegate { node, amount } => {
+                transcript_builder = transcript_builder
+                    .with("transaction_name", &"delegate")
+                    .with_prefix("input".to_owned())
+                    .with("node", &node.0)
+                    .with("amount", &HpUfixedWrapper(amount.clone()));
+            },
+            UpdateMethod::Undelegate { node, amount } => {
+                transcript_builder = transcript_builder
+                    .with("transaction_name", &"undelegate")
+                    .with_prefix("input".to_owned())
+                    .with("node", &node.0)
+                    .with("amount", &HpUfixedWrapper(amount.clone()));
+            },
+            UpdateMethod::WithdrawUndelegated { node, recipient } => {
+                transcript_builder = transcript_builder
+                    .with("transaction_name", &"withdraw_undelegated")
+                    .with_prefix("input".to_owned())
+                    .with("node", &node.0)
+                    .with("recipient", &recipient.map_or([0u8; 20], |key| key.0));
+            },
+            UpdateMethod::SetCommissionRate { node, commission } => {
+                transcript_builder = transcript_builder
+                    .with("transaction_name", &"set_commission_rate")
+                    .with_prefix("input".to_owned())
+                    .with("node", &node.0)
+                    .with("commission", commission);
+            },
+            UpdateMethod::UpdateNodeInfo { node, update } => {
+                let ports = update.ports.as_ref();
+                transcript_builder = transcript_builder
+                    .with("transaction_name", &"update_node_info")
+                    .with_prefix("input".to_owned())
+                    .with("node", &node.0)
+                    .with(
+                        "consensus_key",
+                        &update.consensus_key.map_or([0u8; 96], |key| key.0),
+                    )
+                    .with("domain", &update.domain.map(|d| d.to_string()))
+                    .with(
+                        "worker_public_key",
+                        &update.worker_public_key.map_or([0u8; 32], |key| key.0),
+                    )
+                    .with(
+                        "worker_domain",
+                        &update.worker_domain.map(|d| d.to_string()),
+                    )
+                    .with("primary_port", &ports.map(|p| p.primary))
+                    .with("worker_port", &ports.map(|p| p.worker))
+                    .with("mempool_port", &ports.map(|p| p.mempool))
+                    .with("rpc_port", &ports.map(|p| p.rpc))
+                    .with("pool_port", &ports.map(|p| p.pool))
+                    .with("pinger_port", &ports.map(|p| p.pinger))
+                    .with("handshake_http_port", &ports.map(|p| p.handshake.http))
+                    .with("handshake_webrtc_port", &ports.map(|p| p.handshake.webrtc))
+                    .with(
+                        "handshake_webtransport_port",
+                        &ports.map(|p| p.handshake.webtransport),
+                    );
+            },
             UpdateMethod::ChangeEpoch { epoch } => {
                 transcript_builder = transcript_builder
                     .with("transaction_name", &"change_epoch")
@@ -677,6 +943,35 @@ fn transcript(&self) -> TranscriptBuilder {
                     .with_prefix("input".to_owned())
                     .with("service_id", service_id);
             },
+            UpdateMethod::UpgradeService {
+                service_id,
+                binary,
+                version,
+                resources,
+            } => {
+                transcript_builder = transcript_builder
+                    .with("transaction_name", &"upgrade_service")
+                    .with_prefix("input".to_owned())
+                    .with("service_id", service_id)
+                    .with("binary", binary)
+                    .with("version", version)
+                    .with("cpu_cores", &resources.cpu_cores)
+                    .with("memory_mb", &resources.memory_mb);
+            },
+            UpdateMethod::RegisterToken { address, info } => {
+                transcript_builder = transcript_builder
+                    .with("transaction_name", &"register_token")
+                    .with_prefix("input".to_owned())
+                    .with("address", &address.0)
+                    .with("info", info);
+            },
+            UpdateMethod::SetTokenBridged { token, bridged } => {
+                transcript_builder = transcript_builder
+                    .with("transaction_name", &"set_token_bridged")
+                    .with_prefix("input".to_owned())
+                    .with("token", token)
+                    .with("bridged", &(*bridged as u8));
+            },
             UpdateMethod::Slash {
                 service_id,
                 node,
@@ -731,6 +1026,17 @@ fn transcript(&self) -> TranscriptBuilder {
             UpdateMethod::IncrementNonce {} => {
                 transcript_builder = transcript_builder.with("transaction_name", &"inc_nonce");
             },
+            UpdateMethod::Batch(methods) => {
+                transcript_builder = transcript_builder.with("transaction_name", &"batch");
+                // Labels must be unique, so each method is added as the hash of its own transcript.
+                for (idx, method) in methods.iter().enumerate() {
+                    let method_transcript =
+                        method.append_transcript(TranscriptBuilder::empty(FN_TXN_BATCH_DOMAIN));
+                    transcript_builder = transcript_builder
+                        .with_prefix(idx.to_string())
+                        .with("method", &method_transcript.hash());
+                }
+            },
         }
 
         transcript_builder
@@ -801,6 +1107,7 @@ fn test_transaction_request_fleek() {
             nonce: 0,
             method: update_method,
             chain_id: CHAIN_ID,
+            fee: None,
         };
         let update_req = UpdateRequest {
             signature: TransactionSignature::AccountOwner(AccountOwnerSignature([0; 65])),
@@ -826,6 +1133,7 @@ fn test_update_payload_hash_includes_chain_id() {
             nonce: 0,
             method: update_method,
             chain_id: chain_id_1,
+            fee: None,
         };
 
         let mut payload_2 = payload_1.clone();
@@ -857,6 +1165,7 @@ fn test_block_to_bytes_from_bytes() {
                 nonce: 0,
                 method: UpdateMethod::ChangeEpoch { epoch: 0 },
                 chain_id: 69,
+                fee: None,
             },
         });
         let block = Block {
@@ -871,4 +1180,129 @@ fn test_block_to_bytes_from_bytes() {
 
         assert_eq!(block, new_block);
     }
+
+    fn account_txn(account: u8, nonce: u64, fee: Option<u64>) -> TransactionRequest {
+        TransactionRequest::UpdateRequest(UpdateRequest {
+            signature: TransactionSignature::AccountOwner(AccountOwnerSignature([0; 65])),
+            payload: UpdatePayload {
+                sender: TransactionSender::AccountOwner(EthAddress([account; 20])),
+                nonce,
+                method: UpdateMethod::IncrementNonce {},
+                chain_id: CHAIN_ID,
+                fee: fee.map(HpUfixed::from),
+            },
+        })
+    }
+
+    fn node_txn(node: u8, nonce: u64, method: UpdateMethod) -> TransactionRequest {
+        TransactionRequest::UpdateRequest(UpdateRequest {
+            signature: TransactionSignature::NodeMain(NodeSignature([0; 64])),
+            payload: UpdatePayload {
+                sender: TransactionSender::NodeMain(NodePublicKey([node; 32])),
+                nonce,
+                method,
+                chain_id: CHAIN_ID,
+                fee: None,
+            },
+        })
+    }
+
+    #[test]
+    fn test_update_payload_hash_includes_fee() {
+        let TransactionRequest::UpdateRequest(without_fee) = account_txn(1, 1, None) else {
+            unreachable!()
+        };
+        let TransactionRequest::UpdateRequest(with_fee) = account_txn(1, 1, Some(1)) else {
+            unreachable!()
+        };
+        assert_ne!(
+            without_fee.payload.to_digest(),
+            with_fee.payload.to_digest()
+        );
+    }
+
+    #[test]
+    fn test_transaction_priority() {
+        let change_epoch = node_txn(1, 1, UpdateMethod::ChangeEpoch { epoch: 0 });
+        assert_eq!(change_epoch.priority(), TransactionPriority::System);
+        let opt_in = node_txn(1, 1, UpdateMethod::OptIn {});
+        assert_eq!(opt_in.priority(), TransactionPriority::Normal);
+        assert_eq!(
+            account_txn(1, 1, Some(5)).priority(),
+            TransactionPriority::Fee(5_u64.into())
+        );
+        assert_eq!(
+            account_txn(1, 1, Some(0)).priority(),
+            TransactionPriority::Normal
+        );
+        assert_eq!(
+            account_txn(1, 1, None).priority(),
+            TransactionPriority::Normal
+        );
+
+        let eth_txn: TransactionRequest = EthersTransaction {
+            gas: U256::from(21_000),
+            max_priority_fee_per_gas: Some(U256::from(2)),
+            ..Default::default()
+        }
+        .into();
+        assert_eq!(
+            eth_txn.priority(),
+            TransactionPriority::Fee(HpUfixed::from(U256::from(42_000)))
+        );
+    }
+
+    #[test]
+    fn test_order_by_priority() {
+        let transactions = vec![
+            account_txn(1, 1, None),
+            account_txn(2, 1, Some(1)),
+            account_txn(3, 1, None),
+            // Pulls the first transaction of its sender up with it.
+            account_txn(3, 2, Some(10)),
+            node_txn(4, 1, UpdateMethod::ChangeEpoch { epoch: 0 }),
+            account_txn(2, 2, None),
+        ];
+        let ordered = order_by_priority(transactions.clone(), |_| 100_u64.into());
+        let expected = [4, 2, 3, 1, 0, 5]
+            .into_iter()
+            .map(|index| transactions[index].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(ordered, expected);
+    }
+
+    #[test]
+    fn test_order_by_priority_ignores_unfunded_fees() {
+        let funded = 1;
+        let unfunded = 2;
+        let flk_balance = |account: &EthAddress| {
+            if account.0 == [funded; 20] {
+                HpUfixed::from(10_u64)
+            } else {
+                HpUfixed::zero()
+            }
+        };
+
+        // A sender without any balance declares a large fee.
+        let transactions = vec![
+            account_txn(3, 1, None),
+            account_txn(unfunded, 1, Some(1_000_000)),
+            account_txn(funded, 1, Some(5)),
+        ];
+        let ordered = order_by_priority(transactions.clone(), flk_balance);
+        let expected = [2, 0, 1]
+            .into_iter()
+            .map(|index| transactions[index].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(ordered, expected);
+
+        // The combined fees of a sender have to be covered by its balance.
+        let transactions = vec![
+            account_txn(3, 1, None),
+            account_txn(funded, 1, Some(6)),
+            account_txn(funded, 2, Some(6)),
+        ];
+        let ordered = order_by_priority(transactions.clone(), flk_balance);
+        assert_eq!(ordered, transactions);
+    }
 }
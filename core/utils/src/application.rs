@@ -225,7 +225,7 @@ fn has_sufficient_stake(&self, id: &NodePublicKey) -> bool {
         let minimum_stake_amount = self.get_staking_amount().into();
         self.pubkey_to_index(id).is_some_and(|node_idx| {
             self.get_node_info(&node_idx, |n| n.stake)
-                .is_some_and(|stake| stake.staked + stake.locked >= minimum_stake_amount)
+                .is_some_and(|stake| stake.locked() + stake.staked >= minimum_stake_amount)
         })
     }
 
@@ -19,6 +19,7 @@ pub fn from_update(
             nonce,
             method,
             chain_id,
+            fee: None,
         };
         let digest = payload.to_digest();
         let signature = signer.sign(&digest);
@@ -7,7 +7,7 @@
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
-use crate::batch::{Operation, VerticalBatch};
+use crate::batch::{BoxedVec, Operation, VerticalBatch};
 use crate::db::TableId;
 use crate::keys::VerticalKeys;
 use crate::serder::SerdeBackend;
@@ -150,6 +150,11 @@ pub fn get_raw(&self, tid: TableId, key: &[u8]) -> Option<Vec<u8>> {
         self.persistence.get(tid, key)
     }
 
+    /// Return the raw byte representation of the keys of a table.
+    pub fn get_raw_keys(&self, tid: TableId) -> Vec<BoxedVec> {
+        self.persistence.keys(tid).collect()
+    }
+
     /// Returns the deserialized value associated with a key.
     pub fn get<V>(&self, tid: TableId, key: &[u8]) -> Option<V>
     where
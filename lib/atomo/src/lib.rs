@@ -20,4 +20,4 @@
 pub use key_iterator::KeyIterator;
 pub use serder::{BincodeSerde, SerdeBackend};
 pub use storage::{InMemoryStorage, StorageBackend, StorageBackendConstructor};
-pub use table::{ResolvedTableReference, TableRef, TableSelector};
+pub use table::{Checkpoint, ResolvedTableReference, TableRef, TableSelector};
@@ -1,6 +1,6 @@
 use std::any::{Any, TypeId};
 use std::borrow::Borrow;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::hash::Hash;
 use std::marker::PhantomData;
 use std::sync::Arc;
@@ -9,7 +9,7 @@
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
-use crate::batch::{BatchReference, Operation, VerticalBatch};
+use crate::batch::{BatchReference, BoxedVec, Operation, VerticalBatch};
 use crate::db::TableId;
 use crate::inner::AtomoInner;
 use crate::keys::VerticalKeys;
@@ -63,6 +63,28 @@ pub struct TableSelector<B: StorageBackend, S: SerdeBackend> {
     batch: VerticalBatch,
     /// The new version of the keys.
     keys: RefCell<VerticalKeys>,
+    /// The previous state of the keys changed since the oldest pending checkpoint, in the order
+    /// of the changes. Changes are only recorded while there are pending checkpoints.
+    undo: RefCell<Vec<Undo>>,
+    /// The number of checkpoints that are neither reverted nor committed yet.
+    checkpoints: Cell<usize>,
+}
+
+/// A point in the changes made in an execution context (i.e [`TableSelector`]). A checkpoint can
+/// be used to discard the changes made after it, see [`TableSelector::checkpoint`].
+pub struct Checkpoint {
+    /// The length of the undo log when the checkpoint was taken.
+    undo: usize,
+}
+
+/// The state of a key before it was changed in an execution context.
+struct Undo {
+    tid: TableId,
+    key: BoxedVec,
+    /// The operation on the key in the batch, if there was one.
+    operation: Option<Operation>,
+    /// Whether the key was in the keys of the table, if the iterator is enabled for the table.
+    indexed: Option<bool>,
 }
 
 /// A reference to a table inside an execution context (i.e [`TableSelector`]). A table reference
@@ -115,6 +137,8 @@ pub fn new(atomo: Arc<AtomoInner<B, S>>) -> Self {
             selected: RefCell::new(FxHashSet::default()),
             batch,
             keys: RefCell::new(keys),
+            undo: RefCell::new(Vec::new()),
+            checkpoints: Cell::new(0),
         }
     }
 
@@ -150,6 +174,83 @@ pub fn get_table<K, V>(&self, name: impl AsRef<str>) -> TableRef<K, V, B, S>
         self.atomo.resolve::<K, V>(name).get(self)
     }
 
+    /// Returns a checkpoint of the changes made so far in this run. The changes made after this
+    /// point can be discarded by passing the checkpoint to [`TableSelector::revert`], or kept with
+    /// [`TableSelector::commit`].
+    pub fn checkpoint(&self) -> Checkpoint {
+        self.checkpoints.set(self.checkpoints.get() + 1);
+        Checkpoint {
+            undo: self.undo.borrow().len(),
+        }
+    }
+
+    /// Discard the changes made in this run after the given checkpoint was taken. The table
+    /// references that are already claimed remain valid and observe the reverted state.
+    pub fn revert(&self, checkpoint: Checkpoint) {
+        let mut undo = self.undo.borrow_mut();
+        let mut keys = self.keys.borrow_mut();
+        // Reverting to an older checkpoint already discarded the changes of the newer ones
+        let start = checkpoint.undo.min(undo.len());
+        for change in undo.drain(start..).rev() {
+            // Safety:
+            //
+            // 1. The table id was recorded by a table reference of this selector.
+            //
+            // 2. A table reference only borrows the content of its slot for the duration of one of
+            // its methods, and we are not called from within one.
+            let mut batch = unsafe { self.batch.claim(change.tid as usize) };
+            match change.operation {
+                Some(operation) => batch.insert(change.key.clone(), operation),
+                None => batch.remove(&change.key),
+            };
+            keys.update(change.tid, |collection| {
+                if change.indexed == Some(true) {
+                    collection.insert(change.key);
+                } else {
+                    collection.remove(&change.key);
+                }
+            });
+        }
+        drop(undo);
+        self.release();
+    }
+
+    /// Keep the changes made in this run after the given checkpoint was taken. They can still be
+    /// discarded by reverting to a checkpoint taken before this one.
+    pub fn commit(&self, _checkpoint: Checkpoint) {
+        self.release();
+    }
+
+    /// Stop recording the changes once there are no pending checkpoints anymore.
+    fn release(&self) {
+        let checkpoints = self.checkpoints.get().saturating_sub(1);
+        self.checkpoints.set(checkpoints);
+        if checkpoints == 0 {
+            self.undo.borrow_mut().clear();
+        }
+    }
+
+    /// Record the previous state of a key that is about to be changed, if a pending checkpoint
+    /// might need to restore it.
+    #[inline]
+    fn record(&self, tid: TableId, key: &BoxedVec, batch: &BatchReference) {
+        if self.checkpoints.get() == 0 {
+            return;
+        }
+        let indexed = self
+            .keys
+            .borrow()
+            .get(tid)
+            .as_ref()
+            .map(|collection| collection.contains(key));
+        self.undo.borrow_mut().push(Undo {
+            tid,
+            key: key.clone(),
+            operation: batch.get(key).cloned(),
+            indexed,
+        });
+    }
+
     /// Get the raw, serialized bytes value of a given table and key. The key is also expected to be
     /// serialized bytes.
     /// Returns [`None`] if the table or the key is not found.
@@ -159,6 +260,17 @@ pub fn get_raw_value(&self, table: impl AsRef<str>, key: &[u8]) -> Option<Vec<u8
             .get(table.as_ref())
             .and_then(|tid| self.atomo.get_raw(*tid, key))
     }
+
+    /// Get the raw, serialized bytes keys of a given table, as they are in the storage. This does
+    /// not require the iterator functionality to be enabled for the table.
+    /// Returns an empty list if the table is not found.
+    pub fn get_raw_keys(&self, table: impl AsRef<str>) -> Vec<BoxedVec> {
+        self.atomo
+            .table_name_to_id
+            .get(table.as_ref())
+            .map(|tid| self.atomo.get_raw_keys(*tid))
+            .unwrap_or_default()
+    }
 }
 
 impl<K, V> ResolvedTableReference<K, V> {
@@ -221,6 +333,7 @@ impl<'selector, K, V, B: StorageBackend, S: SerdeBackend> TableRef<'selector, K,
     pub fn insert(&mut self, key: impl Borrow<K>, value: impl Borrow<V>) {
         let k = S::serialize(key.borrow()).into_boxed_slice();
         let v = S::serialize(value.borrow()).into_boxed_slice();
+        self.selector.record(self.tid, &k, &self.batch);
         self.selector
             .keys
             .borrow_mut()
@@ -233,6 +346,7 @@ pub fn insert(&mut self, key: impl Borrow<K>, value: impl Borrow<V>) {
     /// Remove the given key from the table.
     pub fn remove(&mut self, key: impl Borrow<K>) {
         let k = S::serialize(key.borrow()).into_boxed_slice();
+        self.selector.record(self.tid, &k, &self.batch);
         self.selector
             .keys
             .borrow_mut()
@@ -426,6 +540,73 @@ fn test_as_map() {
         });
     }
 
+    #[test]
+    fn test_revert_to_checkpoint() {
+        let mut db = new_test_db_with_enable_iter();
+        db.run(|ctx| {
+            let mut table = ctx.get_table::<String, String>("data");
+            table.insert("key1".to_string(), "value1".to_string());
+            table.insert("key2".to_string(), "value2".to_string());
+
+            let checkpoint = ctx.checkpoint();
+            table.insert("key1".to_string(), "value3".to_string());
+            table.remove("key2".to_string());
+            table.insert("key4".to_string(), "value4".to_string());
+            assert_eq!(table.keys().count(), 2);
+
+            ctx.revert(checkpoint);
+            assert_eq!(table.get("key1".to_string()), Some("value1".to_string()));
+            assert_eq!(table.get("key2".to_string()), Some("value2".to_string()));
+            assert_eq!(table.get("key4".to_string()), None);
+            assert_eq!(table.keys().collect::<Vec<_>>(), vec!["key1", "key2"]);
+        });
+        db.query().run(|ctx| {
+            let table = ctx.get_table::<String, String>("data");
+            assert_eq!(table.get("key1".to_string()), Some("value1".to_string()));
+            assert_eq!(table.get("key4".to_string()), None);
+        });
+    }
+
+    #[test]
+    fn test_nested_checkpoints() {
+        let mut db = new_test_db_with_enable_iter();
+        db.run(|ctx| {
+            let mut table = ctx.get_table::<String, String>("data");
+            let outer = ctx.checkpoint();
+            table.insert("key1".to_string(), "value1".to_string());
+
+            // Committed changes are still discarded by reverting an older checkpoint
+            let inner = ctx.checkpoint();
+            table.insert("key1".to_string(), "value2".to_string());
+            table.insert("key2".to_string(), "value2".to_string());
+            ctx.commit(inner);
+            assert_eq!(table.get("key1".to_string()), Some("value2".to_string()));
+
+            let inner = ctx.checkpoint();
+            table.remove("key2".to_string());
+            ctx.revert(inner);
+            assert_eq!(table.get("key2".to_string()), Some("value2".to_string()));
+
+            ctx.revert(outer);
+            assert_eq!(table.get("key1".to_string()), None);
+            assert_eq!(table.get("key2".to_string()), None);
+            assert_eq!(table.keys().count(), 0);
+
+            // Once every checkpoint is settled changes are no longer recorded
+            let checkpoint = ctx.checkpoint();
+            table.insert("key3".to_string(), "value3".to_string());
+            ctx.commit(checkpoint);
+            table.insert("key4".to_string(), "value4".to_string());
+            assert!(ctx.undo.borrow().is_empty());
+        });
+        db.query().run(|ctx| {
+            let table = ctx.get_table::<String, String>("data");
+            assert_eq!(table.get("key1".to_string()), None);
+            assert_eq!(table.get("key3".to_string()), Some("value3".to_string()));
+            assert_eq!(table.keys().collect::<Vec<_>>(), vec!["key3", "key4"]);
+        });
+    }
+
     #[test]
     #[should_panic]
     fn test_as_map_should_panic_without_enable_iter() {
@@ -0,0 +1,710 @@
+//! A client on top of the primary connection which survives the loss of a node.
+//!
+//! The [`Client`] keeps a list of nodes, obtained from a [`NodeSource`], and a single primary
+//! connection to one of them. If the connection breaks, the client first tries to resume the
+//! same session on the same node using the handshake retry frame, and otherwise falls back to
+//! a new session on the next node of the list.
+//!
+//! ```ignore
+//! use std::net::SocketAddr;
+//!
+//! use cdk_rust::client::Client;
+//! use cdk_rust::transport::tcp::TcpTransport;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let nodes: Vec<SocketAddr> = vec!["127.0.0.1:4221".parse().unwrap()];
+//!     let mut client = Client::builder([0; 32], 1)
+//!         .nodes(nodes.into_iter().map(TcpTransport::new).collect())
+//!         .build()
+//!         .unwrap();
+//!
+//!     let response = client.idempotent_request("hello".into()).await.unwrap();
+//! }
+//! ```
+
+use std::time::Duration;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use bytes::{Bytes, BytesMut};
+use fleek_crypto::ClientPublicKey;
+use thiserror::Error;
+
+use crate::connection;
+use crate::context::Context;
+use crate::mode::{ModeSetting, PrimaryMode};
+use crate::schema::{RequestFrame, ResponseFrame, TerminationReason};
+use crate::transport::{Transport, TransportReceiver, TransportSender};
+
+/// The ttl of the access token we request to learn the id of our connection.
+const DEFAULT_TOKEN_TTL: u64 = 60;
+
+/// The errors returned by the [`Client`].
+#[derive(Debug, Error)]
+pub enum ClientError {
+    /// The node terminated the connection.
+    #[error("connection terminated by the node: {0:?}")]
+    Terminated(TerminationReason),
+    /// The connection was lost after a request was sent. Since the request might have been
+    /// processed by the node it is not retried.
+    #[error("connection lost while waiting for the response")]
+    ConnectionLost,
+    /// None of the known nodes accepted a connection.
+    #[error("no node is available")]
+    NoNodeAvailable,
+    /// The node sent a frame that we did not expect.
+    #[error("unexpected frame from the node")]
+    UnexpectedFrame,
+    #[error("node discovery failed: {0}")]
+    Discovery(anyhow::Error),
+    #[error("transport error: {0}")]
+    Transport(anyhow::Error),
+}
+
+impl ClientError {
+    /// Returns true if the error is caused by the node or the network and the same request
+    /// could succeed on another node.
+    fn is_node_failure(&self) -> bool {
+        match self {
+            ClientError::Terminated(reason) => matches!(
+                reason,
+                TerminationReason::Timeout
+                    | TerminationReason::ServiceTerminated
+                    | TerminationReason::ConnectionInUse
+                    | TerminationReason::ResourcesUnavailable
+                    | TerminationReason::InternalError
+                    | TerminationReason::Shutdown
+                    | TerminationReason::Unknown
+            ),
+            ClientError::ConnectionLost | ClientError::Transport(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// A source of nodes for the [`Client`].
+#[async_trait]
+pub trait NodeSource<T: Transport>: Send + Sync + 'static {
+    /// Returns the transports to connect to each of the nodes, in the order of preference.
+    async fn nodes(&self) -> anyhow::Result<Vec<T>>;
+}
+
+/// Discover the nodes from the node registry of a Fleek Network RPC endpoint.
+#[cfg(feature = "rpc")]
+pub struct RpcDiscovery<F> {
+    endpoint: String,
+    client: reqwest::Client,
+    transport: F,
+}
+
+#[cfg(feature = "rpc")]
+impl<F> RpcDiscovery<F> {
+    /// Create a new discovery from the address of the RPC endpoint and a function which returns
+    /// the transport to use for a node, or `None` if the node should be skipped.
+    pub fn new(endpoint: impl Into<String>, transport: F) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            client: reqwest::Client::new(),
+            transport,
+        }
+    }
+}
+
+#[cfg(feature = "rpc")]
+#[async_trait]
+impl<T, F> NodeSource<T> for RpcDiscovery<F>
+where
+    T: Transport,
+    F: Fn(&lightning_types::NodeInfo) -> Option<T> + Send + Sync + 'static,
+{
+    async fn nodes(&self) -> anyhow::Result<Vec<T>> {
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "flk_get_node_registry",
+            "params": [],
+        });
+
+        let mut response = self
+            .client
+            .post(&self.endpoint)
+            .json(&request)
+            .send()
+            .await?
+            .json::<serde_json::Value>()
+            .await?;
+
+        if let Some(error) = response.get("error") {
+            return Err(anyhow!("rpc error: {error}"));
+        }
+
+        let nodes: Vec<lightning_types::NodeInfo> = serde_json::from_value(
+            response
+                .get_mut("result")
+                .map(serde_json::Value::take)
+                .ok_or_else(|| anyhow!("missing result in the rpc response"))?,
+        )?;
+
+        Ok(nodes
+            .iter()
+            .filter(|info| info.participation == lightning_types::Participation::True)
+            .filter_map(&self.transport)
+            .collect())
+    }
+}
+
+/// A fixed list of nodes.
+struct StaticNodes<T>(std::sync::Mutex<Option<Vec<T>>>);
+
+#[async_trait]
+impl<T: Transport> NodeSource<T> for StaticNodes<T> {
+    async fn nodes(&self) -> anyhow::Result<Vec<T>> {
+        // The list is only handed out once, since it can not change.
+        Ok(self.0.lock().unwrap().take().unwrap_or_default())
+    }
+}
+
+/// Builds a [`Client`].
+pub struct ClientBuilder<T: Transport> {
+    secret: [u8; 32],
+    service_id: u32,
+    pk: Option<ClientPublicKey>,
+    source: Option<Box<dyn NodeSource<T>>>,
+    max_attempts: usize,
+    backoff: Duration,
+    token_ttl: u64,
+}
+
+impl<T: Transport> ClientBuilder<T> {
+    /// Use a fixed list of nodes.
+    pub fn nodes(mut self, nodes: Vec<T>) -> Self {
+        self.source = Some(Box::new(StaticNodes(std::sync::Mutex::new(Some(nodes)))));
+        self
+    }
+
+    /// Use a custom source of nodes, such as an [`RpcDiscovery`].
+    pub fn node_source<S: NodeSource<T>>(mut self, source: S) -> Self {
+        self.source = Some(Box::new(source));
+        self
+    }
+
+    // Todo: Remove this method.
+    // Get public key from the secret.
+    pub fn pk(mut self, pk: ClientPublicKey) -> Self {
+        self.pk = Some(pk);
+        self
+    }
+
+    /// The number of times an idempotent request is attempted before giving up.
+    ///
+    /// # Default
+    ///
+    /// Default value is `3`.
+    pub fn max_attempts(mut self, attempts: usize) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// The time to wait before attempting a request again.
+    ///
+    /// # Default
+    ///
+    /// Default value is `100ms`.
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// The ttl of the access token requested on every new session.
+    pub fn token_ttl(mut self, ttl: u64) -> Self {
+        self.token_ttl = ttl;
+        self
+    }
+
+    pub fn build(self) -> anyhow::Result<Client<T>> {
+        let source = self
+            .source
+            .ok_or_else(|| anyhow!("a list of nodes or a node source is required"))?;
+        let ctx = Context::new(
+            ModeSetting::Primary(PrimaryMode {
+                _client_secret_key: self.secret,
+                service_id: self.service_id,
+            }),
+            // Todo: better default?
+            self.pk.unwrap_or(ClientPublicKey([1; 96])),
+        );
+
+        Ok(Client {
+            ctx,
+            source,
+            nodes: Vec::new(),
+            next: 0,
+            session: None,
+            resumable: None,
+            max_attempts: self.max_attempts,
+            backoff: self.backoff,
+            token_ttl: self.token_ttl,
+        })
+    }
+}
+
+/// A primary connection to one of the nodes that is transparently re-established.
+pub struct Client<T: Transport> {
+    ctx: Context,
+    source: Box<dyn NodeSource<T>>,
+    nodes: Vec<T>,
+    /// The index of the next node to use for a new session.
+    next: usize,
+    session: Option<Session<T>>,
+    /// The node and id of the last session, if it can be resumed.
+    resumable: Option<(usize, u64)>,
+    max_attempts: usize,
+    backoff: Duration,
+    token_ttl: u64,
+}
+
+struct Session<T: Transport> {
+    node: usize,
+    sender: T::Sender,
+    receiver: T::Receiver,
+    connection_id: Option<u64>,
+    resumed: bool,
+}
+
+impl<T: Transport> Client<T> {
+    /// Creates a client builder for connecting to the given service.
+    pub fn builder(client_secret_key: [u8; 32], service_id: u32) -> ClientBuilder<T> {
+        ClientBuilder {
+            secret: client_secret_key,
+            service_id,
+            pk: None,
+            source: None,
+            max_attempts: 3,
+            backoff: Duration::from_millis(100),
+            token_ttl: DEFAULT_TOKEN_TTL,
+        }
+    }
+
+    /// Send a request which is safe to be processed more than once. If the connection is lost
+    /// or the node fails, the request is sent again, on another node if needed.
+    pub async fn idempotent_request(&mut self, payload: Bytes) -> Result<Bytes, ClientError> {
+        let mut attempt = 1;
+        loop {
+            match self.try_request(payload.clone()).await {
+                Err(e) if e.is_node_failure() && attempt < self.max_attempts => {
+                    log::warn!("request failed on attempt {attempt}: {e}");
+                    attempt += 1;
+                    tokio::time::sleep(self.backoff).await;
+                },
+                result => return result,
+            }
+        }
+    }
+
+    /// Send a request which must not be processed more than once. The client reconnects before
+    /// sending the request if needed, but the request is not sent again once the connection is
+    /// lost after sending it.
+    pub async fn request(&mut self, payload: Bytes) -> Result<Bytes, ClientError> {
+        self.try_request(payload).await
+    }
+
+    async fn try_request(&mut self, payload: Bytes) -> Result<Bytes, ClientError> {
+        let frame = RequestFrame::ServicePayload { bytes: payload }.encode();
+
+        let session = self.session().await?;
+        if let Err(e) = session.sender.send(frame.as_ref()).await {
+            // Nothing has been delivered yet, so reconnecting and sending the request again is
+            // always safe.
+            self.fail_session(false);
+            let session = self.session().await?;
+            session
+                .sender
+                .send(frame.as_ref())
+                .await
+                .map_err(|_| ClientError::Transport(e))?;
+        }
+
+        let result = read_payload(&mut self.session.as_mut().unwrap().receiver).await;
+
+        match result {
+            Ok(bytes) => {
+                if let Some(session) = self.session.as_mut() {
+                    session.resumed = false;
+                }
+                Ok(bytes)
+            },
+            // The node does not know about our previous session anymore. The request was
+            // rejected during the handshake so we can try it on a new session.
+            Err(ClientError::Terminated(TerminationReason::InvalidToken))
+                if self.session.as_ref().is_some_and(|s| s.resumed) =>
+            {
+                self.fail_session(false);
+                let session = self.session().await?;
+                session
+                    .sender
+                    .send(frame.as_ref())
+                    .await
+                    .map_err(ClientError::Transport)?;
+                let result = read_payload(&mut session.receiver).await;
+                self.handle_result(result)
+            },
+            result => self.handle_result(result),
+        }
+    }
+
+    fn handle_result(&mut self, result: Result<Bytes, ClientError>) -> Result<Bytes, ClientError> {
+        if let Err(e) = &result {
+            // After a termination the node is not going to accept the session again.
+            let resumable = !matches!(e, ClientError::Terminated(_));
+            self.fail_session(resumable);
+        }
+        result
+    }
+
+    /// Drop the current session and remember if it can be resumed.
+    fn fail_session(&mut self, resumable: bool) {
+        let Some(session) = self.session.take() else {
+            return;
+        };
+
+        self.resumable = match (resumable, session.connection_id) {
+            (true, Some(id)) => Some((session.node, id)),
+            _ => None,
+        };
+
+        if self.resumable.is_none() {
+            // Move on to the next node.
+            self.next = session.node + 1;
+        }
+    }
+
+    /// Returns the current session, establishing one if there is none.
+    async fn session(&mut self) -> Result<&mut Session<T>, ClientError> {
+        if self.session.is_none() {
+            let session = self.connect().await?;
+            self.session = Some(session);
+        }
+
+        Ok(self.session.as_mut().unwrap())
+    }
+
+    async fn connect(&mut self) -> Result<Session<T>, ClientError> {
+        if let Some((node, connection_id)) = self.resumable.take() {
+            if let Some(transport) = self.nodes.get(node) {
+                match connection::resume(transport, &self.ctx, connection_id).await {
+                    Ok((sender, receiver)) => {
+                        return Ok(Session {
+                            node,
+                            sender,
+                            receiver,
+                            connection_id: Some(connection_id),
+                            resumed: true,
+                        });
+                    },
+                    Err(e) => {
+                        log::warn!("failed to resume the session on node {node}: {e}");
+                        self.next = node + 1;
+                    },
+                }
+            }
+        }
+
+        // Try every node we know about, and refresh the list if none of them is reachable.
+        for refresh in [false, true] {
+            if refresh || self.nodes.is_empty() {
+                let nodes = self.source.nodes().await.map_err(ClientError::Discovery)?;
+                if !nodes.is_empty() {
+                    self.nodes = nodes;
+                    self.next = 0;
+                }
+            }
+
+            for i in 0..self.nodes.len() {
+                let node = (self.next + i) % self.nodes.len();
+                match self.new_session(node).await {
+                    Ok(session) => {
+                        self.next = node;
+                        return Ok(session);
+                    },
+                    Err(e) => log::warn!("failed to connect to node {node}: {e}"),
+                }
+            }
+        }
+
+        Err(ClientError::NoNodeAvailable)
+    }
+
+    async fn new_session(&self, node: usize) -> Result<Session<T>, ClientError> {
+        let (mut sender, mut receiver) = connection::connect(&self.nodes[node], &self.ctx)
+            .await
+            .map_err(ClientError::Transport)?;
+
+        // The first 8 bytes of the access token are the id of the connection, which we need
+        // in order to resume the session later.
+        sender
+            .send(
+                RequestFrame::AccessToken {
+                    ttl: self.token_ttl,
+                }
+                .encode()
+                .as_ref(),
+            )
+            .await
+            .map_err(ClientError::Transport)?;
+
+        let connection_id = match receive(&mut receiver).await? {
+            ResponseFrame::AccessToken { access_token, .. } => {
+                u64::from_be_bytes(*arrayref::array_ref![access_token.as_ref(), 0, 8])
+            },
+            ResponseFrame::Termination { reason } => return Err(ClientError::Terminated(reason)),
+            _ => return Err(ClientError::UnexpectedFrame),
+        };
+
+        Ok(Session {
+            node,
+            sender,
+            receiver,
+            connection_id: Some(connection_id),
+            resumed: false,
+        })
+    }
+}
+
+async fn receive<R: TransportReceiver>(receiver: &mut R) -> Result<ResponseFrame, ClientError> {
+    let bytes = receiver.recv().await.ok_or(ClientError::ConnectionLost)?;
+    ResponseFrame::decode(bytes.as_ref()).map_err(|_| ClientError::UnexpectedFrame)
+}
+
+/// Read a complete service payload, which might be split into several chunks.
+async fn read_payload<R: TransportReceiver>(receiver: &mut R) -> Result<Bytes, ClientError> {
+    let mut buffer = BytesMut::new();
+    loop {
+        match receive(receiver).await? {
+            ResponseFrame::ServicePayloadChunk { bytes } => buffer.extend_from_slice(&bytes),
+            ResponseFrame::ServicePayload { bytes } => {
+                if buffer.is_empty() {
+                    return Ok(bytes);
+                }
+                buffer.extend_from_slice(&bytes);
+                return Ok(buffer.freeze());
+            },
+            ResponseFrame::Termination { reason } => return Err(ClientError::Terminated(reason)),
+            _ => return Err(ClientError::UnexpectedFrame),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::schema::HandshakeRequestFrame;
+
+    /// What a mock node does with a service payload.
+    enum Reply {
+        Echo,
+        /// Close the connection without responding.
+        Close,
+        Terminate(TerminationReason),
+    }
+
+    /// A node which serves the connections of a [`MockTransport`].
+    struct MockNode {
+        connection_id: u64,
+        /// Whether the node still knows the session when a client resumes it.
+        resumable: bool,
+        /// The replies to the next payloads, the node echoes the payloads once it runs out.
+        replies: Mutex<VecDeque<Reply>>,
+        /// The retry field of every handshake the node received.
+        handshakes: Mutex<Vec<Option<u64>>>,
+        /// The payloads the node received.
+        requests: Mutex<Vec<Bytes>>,
+    }
+
+    impl MockNode {
+        fn new(connection_id: u64, resumable: bool, replies: Vec<Reply>) -> Arc<Self> {
+            Arc::new(Self {
+                connection_id,
+                resumable,
+                replies: Mutex::new(replies.into()),
+                handshakes: Default::default(),
+                requests: Default::default(),
+            })
+        }
+
+        fn handshakes(&self) -> Vec<Option<u64>> {
+            self.handshakes.lock().unwrap().clone()
+        }
+
+        fn requests(&self) -> Vec<Bytes> {
+            self.requests.lock().unwrap().clone()
+        }
+
+        async fn serve(
+            self: Arc<Self>,
+            mut rx: mpsc::UnboundedReceiver<Bytes>,
+            tx: mpsc::UnboundedSender<Bytes>,
+        ) {
+            let Some(frame) = rx.recv().await else {
+                return;
+            };
+            let Ok(HandshakeRequestFrame::Handshake { retry, .. }) =
+                HandshakeRequestFrame::decode(&frame)
+            else {
+                panic!("expected a handshake");
+            };
+            self.handshakes.lock().unwrap().push(retry);
+
+            // An unknown session is rejected once the client sends its first request.
+            let mut reject = retry.is_some() && !self.resumable;
+
+            while let Some(frame) = rx.recv().await {
+                if reject {
+                    let reason = TerminationReason::InvalidToken;
+                    let _ = tx.send(ResponseFrame::Termination { reason }.encode());
+                    return;
+                }
+                match RequestFrame::decode(&frame).unwrap() {
+                    RequestFrame::AccessToken { ttl } => {
+                        let mut access_token = [0; 48];
+                        access_token[..8].copy_from_slice(&self.connection_id.to_be_bytes());
+                        let access_token = Box::new(access_token);
+                        let _ = tx.send(ResponseFrame::AccessToken { ttl, access_token }.encode());
+                    },
+                    RequestFrame::ServicePayload { bytes } => {
+                        self.requests.lock().unwrap().push(bytes.clone());
+                        let reply = self.replies.lock().unwrap().pop_front();
+                        match reply.unwrap_or(Reply::Echo) {
+                            Reply::Echo => {
+                                let _ = tx.send(ResponseFrame::ServicePayload { bytes }.encode());
+                            },
+                            Reply::Close => return,
+                            Reply::Terminate(reason) => {
+                                let _ = tx.send(ResponseFrame::Termination { reason }.encode());
+                                return;
+                            },
+                        }
+                    },
+                    _ => panic!("unexpected frame"),
+                }
+                reject = false;
+            }
+        }
+    }
+
+    struct MockTransport(Arc<MockNode>);
+
+    struct MockSender(mpsc::UnboundedSender<Bytes>);
+
+    struct MockReceiver(mpsc::UnboundedReceiver<Bytes>);
+
+    #[async_trait]
+    impl Transport for MockTransport {
+        type Sender = MockSender;
+        type Receiver = MockReceiver;
+
+        async fn connect(&self) -> anyhow::Result<(Self::Sender, Self::Receiver)> {
+            let (request_tx, request_rx) = mpsc::unbounded_channel();
+            let (response_tx, response_rx) = mpsc::unbounded_channel();
+            tokio::spawn(self.0.clone().serve(request_rx, response_tx));
+            Ok((MockSender(request_tx), MockReceiver(response_rx)))
+        }
+    }
+
+    #[async_trait]
+    impl TransportSender for MockSender {
+        async fn send(&mut self, data: &[u8]) -> anyhow::Result<()> {
+            self.0
+                .send(Bytes::copy_from_slice(data))
+                .map_err(|_| anyhow!("connection closed"))
+        }
+    }
+
+    #[async_trait]
+    impl TransportReceiver for MockReceiver {
+        async fn recv(&mut self) -> Option<Bytes> {
+            self.0.recv().await
+        }
+    }
+
+    fn client(nodes: &[Arc<MockNode>]) -> Client<MockTransport> {
+        Client::builder([0; 32], 1)
+            .nodes(nodes.iter().cloned().map(MockTransport).collect())
+            .backoff(Duration::ZERO)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_resume_session_with_retry_frame() {
+        let node = MockNode::new(7, true, vec![Reply::Close]);
+        let mut client = client(&[node.clone()]);
+
+        // The connection is lost, so the request is sent again on the resumed session.
+        let response = client.idempotent_request("hello".into()).await.unwrap();
+        assert_eq!(response, Bytes::from("hello"));
+        assert_eq!(node.handshakes(), vec![None, Some(7)]);
+        assert_eq!(node.requests(), vec![Bytes::from("hello"); 2]);
+    }
+
+    #[tokio::test]
+    async fn test_new_session_after_invalid_token() {
+        let node = MockNode::new(7, false, vec![Reply::Close]);
+        let mut client = client(&[node.clone()]);
+
+        let result = client.request("first".into()).await;
+        assert!(matches!(result, Err(ClientError::ConnectionLost)));
+
+        // The node doesn't know the session anymore, so a new one is started.
+        let response = client.request("second".into()).await.unwrap();
+        assert_eq!(response, Bytes::from("second"));
+        assert_eq!(node.handshakes(), vec![None, Some(7), None]);
+    }
+
+    #[tokio::test]
+    async fn test_idempotent_request_fails_over_to_next_node() {
+        let failing = MockNode::new(
+            1,
+            true,
+            vec![Reply::Terminate(TerminationReason::ServiceTerminated)],
+        );
+        let healthy = MockNode::new(2, true, vec![]);
+        let mut client = client(&[failing.clone(), healthy.clone()]);
+
+        let response = client.idempotent_request("hello".into()).await.unwrap();
+        assert_eq!(response, Bytes::from("hello"));
+        assert_eq!(failing.requests(), vec![Bytes::from("hello")]);
+        assert_eq!(healthy.requests(), vec![Bytes::from("hello")]);
+    }
+
+    #[tokio::test]
+    async fn test_request_does_not_fail_over() {
+        let failing = MockNode::new(
+            1,
+            true,
+            vec![Reply::Terminate(TerminationReason::ServiceTerminated)],
+        );
+        let healthy = MockNode::new(2, true, vec![]);
+        let mut client = client(&[failing.clone(), healthy.clone()]);
+
+        let result = client.request("hello".into()).await;
+        assert!(matches!(
+            result,
+            Err(ClientError::Terminated(
+                TerminationReason::ServiceTerminated
+            ))
+        ));
+        assert!(healthy.requests().is_empty());
+
+        // The next request goes to the next node.
+        let response = client.request("again".into()).await.unwrap();
+        assert_eq!(response, Bytes::from("again"));
+        assert_eq!(failing.requests(), vec![Bytes::from("hello")]);
+        assert_eq!(healthy.requests(), vec![Bytes::from("again")]);
+    }
+}
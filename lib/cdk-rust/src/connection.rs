@@ -17,7 +17,7 @@ pub async fn connect<T: Transport>(
 
     match ctx.mode() {
         ModeSetting::Primary(setting) => {
-            start_handshake::<T>(&mut sender, setting, *ctx.pk()).await?
+            start_handshake::<T>(&mut sender, setting, *ctx.pk(), None).await?
         },
         ModeSetting::Secondary(setting) => join_connection::<T>(&mut sender, setting).await?,
     }
@@ -25,13 +25,31 @@ pub async fn connect<T: Transport>(
     Ok((sender, receiver))
 }
 
+/// Reconnect to a primary connection that is still alive on the node, using the id of the
+/// connection. The id is the first 8 bytes of the access token of the connection.
+pub async fn resume<T: Transport>(
+    transport: &T,
+    ctx: &Context,
+    connection_id: u64,
+) -> Result<(T::Sender, T::Receiver)> {
+    let ModeSetting::Primary(setting) = ctx.mode() else {
+        return Err(anyhow::anyhow!("only primary connections can be resumed"));
+    };
+
+    let (mut sender, receiver) = transport.connect().await?;
+    start_handshake::<T>(&mut sender, setting, *ctx.pk(), Some(connection_id)).await?;
+
+    Ok((sender, receiver))
+}
+
 async fn start_handshake<T: Transport>(
     stream: &mut T::Sender,
     setting: &PrimaryMode,
     pk: ClientPublicKey,
+    retry: Option<u64>,
 ) -> Result<()> {
     let frame = HandshakeRequestFrame::Handshake {
-        retry: None,
+        retry,
         service: setting.service_id,
         pk,
         // Todo: Create signature.
@@ -49,6 +49,11 @@
 //!     let (sender, receiver) = connector.connect().await.unwrap().split();
 //! }
 //! ```
+//!
+//! ## Client with failover
+//!
+//! See [`client::Client`] for a primary connection that is resumed or moved to another node
+//! when the node it is connected to fails.
 
 mod builder;
 mod connection;
@@ -58,6 +63,7 @@
 #[cfg(not(feature = "cloudflare"))]
 mod tls;
 
+pub mod client;
 pub mod transport;
 
 pub use builder::Builder;
@@ -101,6 +101,15 @@ pub fn floor(&self) -> HpUfixed<P> {
         let num = self.0 / scale;
         HpUfixed::<P>(num * scale)
     }
+
+    /// Truncates the value to at most `digits` digits after the decimal point.
+    pub fn truncate(&self, digits: usize) -> HpUfixed<P> {
+        if digits >= P {
+            return self.clone();
+        }
+        let scale = U256::from(10u32).pow((P - digits).try_into().unwrap());
+        HpUfixed::<P>(self.0 / scale * scale)
+    }
 }
 
 impl<const P: usize> fmt::Display for HpUfixed<P> {
@@ -458,6 +467,15 @@ fn test_floor() {
         assert_eq!(num.floor(), HpUfixed::<6>::from(1223.0));
     }
 
+    #[test]
+    fn test_truncate() {
+        let num = HpUfixed::<6>::from(1223.91323);
+        assert_eq!(num.truncate(2), HpUfixed::<6>::from(1223.91));
+        assert_eq!(num.truncate(0), num.floor());
+        assert_eq!(num.truncate(6), num);
+        assert_eq!(num.truncate(18), num);
+    }
+
     #[test]
     fn test_try_into() {
         let large = HpUfixed::<20>::from(u64::MAX as u128 + 1_u128);
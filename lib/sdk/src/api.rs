@@ -1,7 +1,7 @@
 use fleek_crypto::{ClientPublicKey, NodeSignature};
 use lightning_schema::task_broker::TaskScope;
 
-use crate::ipc::send_and_await_response;
+use crate::ipc::{send_and_await_response, send_no_response};
 use crate::ipc_types::Request;
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -77,17 +77,27 @@ pub async fn fetch_sgx_shared_pub_key() -> Option<String> {
     }
 }
 
+/// Run a task on a service. The `deadline` is a unix timestamp in milliseconds after which the
+/// responses are no longer useful, the node waits for a default timeout without one.
 pub async fn run_task(
     depth: u8,
     scope: TaskScope,
     service: u32,
     payload: Vec<u8>,
+    deadline: Option<u64>,
 ) -> (Vec<Vec<u8>>, Vec<NodeSignature>) {
+    let (min_agree, of) = match scope {
+        TaskScope::Quorum { min_agree, of } => (min_agree, of),
+        _ => (0, 0),
+    };
     let req = Request::Task {
         scope: scope.into(),
+        min_agree,
+        of,
         depth,
         service,
         payload,
+        deadline,
     };
     let res = send_and_await_response(req).await;
     match res {
@@ -101,3 +111,32 @@ pub async fn run_task(
         _ => unreachable!(),
     }
 }
+
+/// Acknowledge the delivery of `commodity` units of the commodity of this service, for example
+/// bandwidth or compute.
+pub async fn submit_delivery_acknowledgment(commodity: u128, metadata: Option<Vec<u8>>) {
+    let req = Request::SubmitDeliveryAcknowledgment {
+        commodity,
+        metadata,
+    };
+    send_no_response(req).await
+}
+
+/// The capabilities of a service, see [`declare_capabilities`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// The service responds to HTTP requests with the
+    /// [`HttpOverrides`](crate::header::HttpOverrides) before the body, for example with
+    /// [`respond_with_headers`](crate::http_util::respond_with_headers).
+    pub http_overrides: bool,
+}
+
+/// Declare the capabilities of this service to the node. This should be called before
+/// [`conn_bind`](crate::ipc::conn_bind), the node does not route HTTP requests to a service until
+/// it has declared its capabilities.
+pub async fn declare_capabilities(capabilities: Capabilities) {
+    let req = Request::DeclareCapabilities {
+        http_overrides: capabilities.http_overrides,
+    };
+    send_no_response(req).await
+}
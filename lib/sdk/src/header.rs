@@ -54,6 +54,10 @@ pub enum TransportDetail {
     Task {
         depth: u8,
         payload: Bytes,
+        /// The service that submitted the task, as reported by the node that submitted it.
+        /// Unset for tasks submitted by the node itself, such as scheduled tasks.
+        #[serde(default)]
+        origin: Option<u32>,
     },
     Other,
 }
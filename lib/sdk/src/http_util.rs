@@ -81,10 +81,19 @@ pub async fn respond(connection: &mut Connection, response: &[u8]) -> anyhow::Re
 /// Send only the default headers, allowing for data to be streamed or sent directly afterwards.
 #[inline(always)]
 pub async fn respond_only_default_headers(connection: &mut Connection) -> anyhow::Result<()> {
+    respond_with_headers(connection, HttpOverrides::default()).await
+}
+
+/// Send only the given headers, allowing for data to be streamed afterwards.
+/// Panics in debug mode if the connection is not http.
+#[inline(always)]
+pub async fn respond_with_headers(
+    connection: &mut Connection,
+    headers: HttpOverrides,
+) -> anyhow::Result<()> {
     debug_assert!(connection.is_http_request());
 
-    let header_bytes =
-        serde_json::to_vec(&HttpOverrides::default()).context("Failed to serializez headers")?;
+    let header_bytes = serde_json::to_vec(&headers).context("Failed to serializez headers")?;
 
     // response with the headers first
     connection
@@ -9,10 +9,11 @@
 //! future, but for now it works for our use cases.
 
 use std::error::Error;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use lightning_schema::LightningMessage;
-use tokio::io::{self, Interest};
+use tokio::io::{self, AsyncWriteExt, Interest};
 use tokio::net::{UnixListener, UnixStream};
 use tokio::sync::mpsc;
 
@@ -23,17 +24,38 @@
 static mut SENDER: Option<tokio::sync::mpsc::Sender<IpcRequest>> = None;
 pub(crate) static mut IPC_PATH: Option<PathBuf> = None;
 pub(crate) static mut BLOCKSTORE: Option<PathBuf> = None;
+pub(crate) static mut DATA_PATH: Option<PathBuf> = None;
+static mut SERVICE_ID: Option<u32> = None;
+/// Whether the service is ready to accept connections, reported to the core in the probes.
+static READY: AtomicBool = AtomicBool::new(false);
+
+/// Returns the directory where the service can persist its state between restarts.
+pub fn data_path() -> &'static Path {
+    unsafe { DATA_PATH.as_deref() }.expect("Service setupt not complete.")
+}
+
+/// Returns the id of this service, if it was provided by the node.
+pub fn service_id() -> Option<u32> {
+    unsafe { SERVICE_ID }
+}
 
-/// Bind to the connection stream.
+/// Bind to the connection stream. The service is reported as ready once this is called.
 pub async fn conn_bind() -> ConnectionListener {
     let path = unsafe { IPC_PATH.as_ref() }
         .expect("Service setupt not complete.")
         .join("conn");
 
     let listener = UnixListener::bind(path).expect("IPC bind failed.");
+    set_ready(true);
     ConnectionListener::new(listener)
 }
 
+/// Set whether the service is ready to accept connections. While a service is not ready the node
+/// reports it as starting.
+pub fn set_ready(ready: bool) {
+    READY.store(ready, Ordering::Relaxed);
+}
+
 /// Init the service event loop using environment variables, and sets the parent death signal to
 /// SIGINT with [`prctl::set_death_signal`]. This method *MUST* only be called once.
 pub fn init_from_env() {
@@ -45,6 +67,15 @@ pub fn init_from_env() {
         .expect("Expected IPC_PATH env")
         .into();
 
+    // Older node versions do not provide a data directory, fallback to the IPC directory.
+    let data_path: PathBuf = std::env::var("DATA_PATH")
+        .map(Into::into)
+        .unwrap_or_else(|_| ipc_path.join("data"));
+
+    let service_id = std::env::var("SERVICE_ID")
+        .ok()
+        .and_then(|id| id.parse().ok());
+
     let (tx, rx) = mpsc::channel::<IpcRequest>(1024);
 
     // SAFETY: `init_from_env` is the entry function of the entire service process.
@@ -52,6 +83,8 @@ pub fn init_from_env() {
         SENDER = Some(tx);
         BLOCKSTORE = Some(blockstore_path);
         IPC_PATH = Some(ipc_path.clone());
+        DATA_PATH = Some(data_path);
+        SERVICE_ID = service_id;
     }
 
     // SIGINT: Only supported on linux. This tells the kernel to shut the child (this bin) down
@@ -69,7 +102,21 @@ pub async fn spawn_service_loop(
     ipc_path: PathBuf,
     rx: mpsc::Receiver<IpcRequest>,
 ) -> Result<(), Box<dyn Error>> {
-    let ipc_stream = UnixStream::connect(ipc_path.join("ctrl")).await?;
+    let mut ipc_stream = UnixStream::connect(ipc_path.join("ctrl")).await?;
+
+    // Announce that this service answers the health probes, the core does not probe services
+    // built against an older SDK.
+    let mut buffer = Vec::new();
+    IpcRequest {
+        request_ctx: None,
+        request: Request::ProbeAck {
+            nonce: 0,
+            ready: READY.load(Ordering::Relaxed),
+        },
+    }
+    .encode_length_delimited(&mut buffer)?;
+    ipc_stream.write_all(&buffer).await?;
+
     spawn_service_loop_inner(ipc_stream, rx).await
 }
 
@@ -190,7 +237,9 @@ pub(crate) async fn spawn_service_loop_inner(
                 read_buffer.resize(DELIMITER_SIZE, 0);
                 reading_len = true;
 
-                handle_message(message);
+                if let Some(req) = handle_message(message) {
+                    IpcRequest::encode_length_delimited(&req, &mut write_buffer)?;
+                }
             }
         }
     }
@@ -198,8 +247,9 @@ pub(crate) async fn spawn_service_loop_inner(
     Ok(())
 }
 
+/// Handle a message from the core, returns the request to send back if there is one.
 #[inline]
-fn handle_message(message: IpcMessage) {
+fn handle_message(message: IpcMessage) -> Option<IpcRequest> {
     match message {
         IpcMessage::Response {
             request_ctx,
@@ -207,7 +257,15 @@ fn handle_message(message: IpcMessage) {
         } => {
             // Wake up the future that is awaiting for the response.
             future_callback(request_ctx.into(), response);
+            None
         },
+        IpcMessage::Probe { nonce } => Some(IpcRequest {
+            request_ctx: None,
+            request: Request::ProbeAck {
+                nonce,
+                ready: READY.load(Ordering::Relaxed),
+            },
+        }),
     }
 }
 
@@ -356,4 +414,33 @@ async fn test_message_flow() {
         let took = started.elapsed();
         println!("took {took:?}");
     }
+
+    #[tokio::test]
+    async fn test_probe_ack() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (_tx, rx) = mpsc::channel::<IpcRequest>(1024);
+        let (s1, mut s2) = tokio::net::UnixStream::pair().unwrap();
+        tokio::spawn(async move {
+            spawn_service_loop_inner(s1, rx).await.unwrap();
+        });
+
+        for (nonce, ready) in [(1, false), (2, true)] {
+            set_ready(ready);
+
+            let mut buffer = Vec::new();
+            IpcMessage::Probe { nonce }
+                .encode_length_delimited(&mut buffer)
+                .unwrap();
+            s2.write_all(&buffer).await.unwrap();
+
+            let len = s2.read_u64_le().await.unwrap();
+            let mut buffer = vec![0; len as usize];
+            s2.read_exact(&mut buffer).await.unwrap();
+            let req = IpcRequest::decode(&buffer).unwrap();
+
+            assert_eq!(req.request_ctx, None);
+            assert_eq!(req.request, Request::ProbeAck { nonce, ready });
+        }
+    }
 }
@@ -76,6 +76,8 @@ pub enum IpcMessage {
         request_ctx: RequestCtxU64,
         response: Response,
     },
+    /// A health probe of the core, answered by the service with a [`Request::ProbeAck`].
+    Probe { nonce: u64 },
 }
 /// The size of the length delimiter in bytes.
 ///
@@ -221,10 +223,39 @@ fn encode_length_delimited<W: std::io::prelude::Write>(
     Task {
         depth: u8,
         scope: u8,
+        // Only used by the quorum scope.
+        min_agree: u8,
+        of: u8,
         service: u32,
         payload: Vec<u8>,
+        /// Unix timestamp in milliseconds after which a response is no longer useful.
+        deadline: Option<u64>,
         =>
         responses: Vec<Vec<u8>>,
         signatures: Vec<[u8; 64]>,
+    },
+    /// Acknowledge the delivery of some of the commodity of the service, so the node gets paid
+    /// for it.
+    SubmitDeliveryAcknowledgment {
+        /// How much of the commodity was served.
+        commodity: u128,
+        /// Optional metadata attached to the acknowledgment.
+        metadata: Option<Vec<u8>>,
+        =>
+    },
+    /// Declare the capabilities of the service, sent once at startup.
+    DeclareCapabilities {
+        /// Whether the service responds to HTTP requests with
+        /// [`HttpOverrides`](crate::header::HttpOverrides) before the body.
+        http_overrides: bool,
+        =>
+    },
+    /// Answer a health probe of the core, this is sent by the SDK itself.
+    ProbeAck {
+        /// The nonce of the probe.
+        nonce: u64,
+        /// Whether the service is ready to accept connections.
+        ready: bool,
+        =>
     }
 }
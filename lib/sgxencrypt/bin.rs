@@ -7,6 +7,7 @@
 use anyhow::{anyhow, Context};
 use bip32::{ChildNumber, XPub};
 use bpaf::{literal, positional, Bpaf, Parser};
+use fleek_crypto::{AccountOwnerSecretKey, SecretKey};
 
 /// Parse public key argument
 fn pubkey() -> impl Parser<XPub> {
@@ -144,6 +145,50 @@ enum SgxEncrypt {
         #[bpaf(external)]
         input: Input,
     },
+    /// Encrypt a json object of secrets for a javascript function
+    #[bpaf(command("js-secrets"), fallback_to_usage)]
+    JsSecrets {
+        /// Hex-encoded compressed public key of the javascript service
+        #[bpaf(
+            short('k'),
+            long("key"),
+            argument::<String>("PUBKEY"),
+            parse(|s| {
+                let v = hex::decode(s).context("invalid public key hex")?;
+                v.try_into().map_err(|_| anyhow!("public key must be 33 bytes"))
+            }),
+        )]
+        key: [u8; 33],
+        /// Revision of the secrets, which must be higher than the previous one to rotate them
+        #[bpaf(short('r'), long("revision"), argument("REVISION"))]
+        revision: u64,
+        /// Path to the PEM encoded account key of the owner of the function, which signs the
+        /// secrets
+        #[bpaf(
+            short('s'),
+            long("signer"),
+            argument::<PathBuf>("PEM"),
+            parse(|path| {
+                let pem = std::fs::read_to_string(path).context("failed to read signer key")?;
+                AccountOwnerSecretKey::decode_pem(&pem).context("invalid signer key")
+            }),
+        )]
+        signer: AccountOwnerSecretKey,
+        #[bpaf(external)]
+        output: Output,
+        /// Function to encrypt the secrets for
+        #[bpaf(
+            positional::<String>("FUNCTION HASH"),
+            parse(|s| {
+                let v = hex::decode(s).context("invalid blake3 hex")?;
+                v.try_into().map_err(|_| anyhow!("invalid blake3 hex length"))
+            }),
+        )]
+        function: [u8; 32],
+        /// Path to json file with the secrets to encrypt.
+        #[bpaf(external)]
+        input: Input,
+    },
 }
 
 impl SgxEncrypt {
@@ -215,6 +260,35 @@ fn execute(self) -> anyhow::Result<()> {
 
                 (pk.to_bytes(), file, bytes, output)
             },
+            SgxEncrypt::JsSecrets {
+                key,
+                revision,
+                signer,
+                function,
+                input,
+                output,
+            } => {
+                const HEADER_PREFIX: [u8; 16] = *b"FLEEK_JS_SECRETS";
+
+                let (file, bytes) = input.read()?;
+
+                // The owner signs the header + secrets
+                let mut signed = Vec::with_capacity(HEADER_PREFIX.len() + 32 + 8 + bytes.len());
+                signed.extend_from_slice(&HEADER_PREFIX);
+                signed.extend_from_slice(&function);
+                signed.extend_from_slice(&revision.to_be_bytes());
+                let header_len = signed.len();
+                signed.extend_from_slice(&bytes);
+                let signature = signer.sign(&signed);
+
+                // Encode header + signature + secrets as plaintext
+                let mut buf = Vec::with_capacity(signed.len() + 65);
+                buf.extend_from_slice(&signed[..header_len]);
+                buf.extend_from_slice(&signature.0);
+                buf.extend_from_slice(&bytes);
+
+                (key, file, buf, output)
+            },
         };
 
         // Encrypt the content
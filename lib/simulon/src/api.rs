@@ -3,6 +3,7 @@
 mod connection;
 mod listen;
 mod log;
+mod rng;
 mod spawn;
 mod storage;
 mod time;
@@ -15,6 +16,7 @@
 pub use connection::*;
 pub use listen::*;
 pub use log::*;
+pub use rng::*;
 pub use spawn::*;
 pub use storage::*;
 pub use time::*;
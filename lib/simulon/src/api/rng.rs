@@ -0,0 +1,37 @@
+use rand_core::{Error, RngCore};
+
+use crate::state::with_node;
+
+/// A handle to the random number generator of the current node. The generator is seeded
+/// from the simulation seed and the global index of the node, so every node observes its
+/// own stream of random values which is the same across runs of a seeded simulation.
+///
+/// This type is only a handle and can be cheaply created with [`rng`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SimRng;
+
+/// Returns a handle to the seeded random number generator of the current node.
+///
+/// Executors should use this instead of `rand::thread_rng` so that the simulation stays
+/// reproducible when it is built with [`crate::simulation::SimulationBuilder::with_seed`].
+pub fn rng() -> SimRng {
+    SimRng
+}
+
+impl RngCore for SimRng {
+    fn next_u32(&mut self) -> u32 {
+        with_node(|n| n.rng.next_u32())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        with_node(|n| n.rng.next_u64())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        with_node(|n| n.rng.fill_bytes(dest))
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        with_node(|n| n.rng.try_fill_bytes(dest))
+    }
+}
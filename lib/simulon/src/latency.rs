@@ -17,6 +17,11 @@ pub trait LatencyProvider: Default {
     /// This should perform any initialization necessary.
     fn init(&mut self, _number_of_nodes: usize) {}
 
+    /// Called before [`LatencyProvider::init`] when the simulation is built with a seed. The
+    /// providers that sample random values should derive their random number generators from
+    /// the provided seed.
+    fn seed(&mut self, _seed: u64) {}
+
     /// Return a latency between two nodes from the provided global indices.
     fn get(&mut self, a: usize, b: usize) -> Duration;
 }
@@ -96,6 +96,12 @@ fn init(&mut self, number_of_nodes: usize) {
         }
     }
 
+    fn seed(&mut self, seed: u64) {
+        self.rng = ChaCha8Rng::seed_from_u64(seed);
+        self.rng2 = ChaCha8Rng::seed_from_u64(seed);
+        self.rng2.set_stream(1);
+    }
+
     fn get(&mut self, a: usize, b: usize) -> Duration {
         if a == b {
             let micro = self.rng2.gen_range(14..25);
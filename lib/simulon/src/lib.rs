@@ -16,6 +16,9 @@
 /// The simulator engine.
 pub mod simulation;
 
+/// The event traces recorded from a simulation and the tools to walk through them.
+pub mod trace;
+
 mod future;
 mod message;
 mod state;
@@ -91,6 +91,24 @@ pub fn insert(&mut self, key: Option<usize>, metric: Metrics) {
             self.timeline.insert(key, metric);
         }
     }
+
+    /// Rebuild every map in the report by inserting the keys in sorted order. The iteration
+    /// order of a hash map depends on the order of insertion, which for the global timeline
+    /// depends on how the nodes were scheduled on the workers. After this call two reports
+    /// with the same content have the same serialized bytes.
+    pub(crate) fn canonicalize(&mut self) {
+        self.timeline.canonicalize();
+
+        for node in self.node.iter_mut() {
+            node.timeline.canonicalize();
+        }
+
+        let emitted = std::mem::take(&mut self.log.emitted);
+        self.log.emitted = sorted(emitted)
+            .into_iter()
+            .map(|(event, times)| (event, sorted(times).into_iter().collect()))
+            .collect();
+    }
 }
 
 impl Metrics {
@@ -119,6 +137,10 @@ pub fn insert(&mut self, key: usize, metric: Metrics) {
         }
     }
 
+    fn canonicalize(&mut self) {
+        self.0 = sorted(std::mem::take(&mut self.0)).into_iter().collect();
+    }
+
     #[inline(always)]
     fn insert_inner(&mut self, key: usize, metric: Metrics) {
         match self.entry(key) {
@@ -160,6 +182,13 @@ fn deref_mut(&mut self) -> &mut Self::Target {
     }
 }
 
+#[inline]
+fn sorted<K: Ord, V>(map: FxHashMap<K, V>) -> Vec<(K, V)> {
+    let mut entries = map.into_iter().collect::<Vec<_>>();
+    entries.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    entries
+}
+
 /// A [`Vec`] wrapper that implements pairwise addition.
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct VecWithAdd<T>(pub Vec<T>);
@@ -1,5 +1,6 @@
 use std::any::Any;
 use std::cell::UnsafeCell;
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::thread::JoinHandle;
 use std::time::Duration;
@@ -12,6 +13,7 @@
 use crate::report::{Metrics, Report};
 use crate::state::{hook_node, with_node, NodeState};
 use crate::storage::TypedStorage;
+use crate::trace::{Trace, TraceEvent, TraceStep, TraceWriter};
 use crate::{FRAME_DURATION, FRAME_TO_MS};
 
 /// Constructor for a simulation which allows you to set the parameters of a simulation.
@@ -24,6 +26,9 @@ pub struct SimulationBuilder<L = DefaultLatencyProvider> {
     storage: TypedStorage,
     latency_provider: Option<L>,
     show_progress: bool,
+    seed: Option<u64>,
+    record_trace: Option<PathBuf>,
+    replay_trace: Option<PathBuf>,
 }
 
 pub struct Simulation<L: LatencyProvider = DefaultLatencyProvider> {
@@ -39,6 +44,14 @@ pub struct Simulation<L: LatencyProvider = DefaultLatencyProvider> {
     latency_provider: L,
     /// Show progress bar or not.
     show_progress: bool,
+    /// The seed of the simulation, if set the simulation runs in deterministic mode.
+    seed: Option<u64>,
+    /// The messages of the current step before they are delivered.
+    pending: Vec<Message>,
+    /// The writer for the trace we are recording.
+    trace_writer: Option<TraceWriter>,
+    /// The trace that we are replaying and checking the simulation against.
+    replay: Option<Trace>,
 }
 
 #[derive(Default)]
@@ -72,6 +85,8 @@ struct SharedState {
     cursor: AtomicUsize,
     /// Number of threads that have done their execution and are ready to start the next frame.
     ready_workers: AtomicUsize,
+    /// If set we don't collect anything that depends on the wall clock.
+    deterministic: bool,
 }
 
 // Because `SyncUnsafeCell` is unstable and nightly.
@@ -97,6 +112,9 @@ pub fn new<E>(executor: E) -> Self
             storage: TypedStorage::default(),
             latency_provider: None,
             show_progress: false,
+            seed: None,
+            record_trace: None,
+            replay_trace: None,
         }
     }
 }
@@ -181,9 +199,43 @@ pub fn set_latency_provider<T: LatencyProvider>(self, provider: T) -> Simulation
             storage: self.storage,
             latency_provider: Some(provider),
             show_progress: self.show_progress,
+            seed: self.seed,
+            record_trace: self.record_trace,
+            replay_trace: self.replay_trace,
         }
     }
 
+    /// Run the simulation in deterministic mode using the provided seed.
+    ///
+    /// In this mode the random number generator returned by [`crate::api::rng`] on each node
+    /// and the latency provider are derived from the seed, the messages produced during a frame
+    /// are delivered in an order that does not depend on how nodes are scheduled on the workers,
+    /// and the wall clock cpu time is not collected. As long as the executor only uses the
+    /// [`crate::api`] as its source of randomness, two runs with the same seed produce the same
+    /// [`Report`] regardless of the number of workers.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Record every message delivered during the simulation to a trace file at the given path.
+    /// The trace can later be inspected step by step using [`Trace::open`].
+    pub fn record_trace(mut self, path: impl Into<PathBuf>) -> Self {
+        self.record_trace = Some(path.into());
+        self
+    }
+
+    /// Check the simulation against a previously recorded trace, step by step. This should be
+    /// used with the same seed that was used while recording the trace.
+    ///
+    /// # Panics
+    ///
+    /// When running the simulation, if a step diverges from the one in the trace.
+    pub fn replay_trace(mut self, path: impl Into<PathBuf>) -> Self {
+        self.replay_trace = Some(path.into());
+        self
+    }
+
     pub fn build(self) -> Simulation<L>
     where
         L: LatencyProvider,
@@ -197,8 +249,9 @@ pub fn build(self) -> Simulation<L>
         // Cap the number of workers to the number of nodes.
         let num_workers = num_workers.min(num_nodes);
         let storage = Arc::new(self.storage);
+        let seed = self.seed.unwrap_or(0);
         let nodes = (0..num_nodes)
-            .map(|i| NodeState::new(storage.clone(), num_nodes, i))
+            .map(|i| NodeState::new(storage.clone(), num_nodes, i, seed))
             .collect::<Vec<_>>()
             .into_boxed_slice();
 
@@ -225,15 +278,34 @@ pub fn build(self) -> Simulation<L>
             frame: AtomicUsize::new(0),
             cursor: AtomicUsize::new(0),
             ready_workers: AtomicUsize::new(0),
+            deterministic: self.seed.is_some(),
         };
 
+        let mut latency_provider = self.latency_provider.unwrap_or_default();
+        if let Some(seed) = self.seed {
+            latency_provider.seed(seed);
+        }
+
+        let trace_writer = self.record_trace.map(|path| {
+            TraceWriter::create(&path)
+                .unwrap_or_else(|e| panic!("Could not create trace file {path:?}: {e}"))
+        });
+
+        let replay = self.replay_trace.map(|path| {
+            Trace::open(&path).unwrap_or_else(|e| panic!("Could not open trace file {path:?}: {e}"))
+        });
+
         Simulation {
             now: 0,
             state: Arc::new(state),
             nodes,
             workers: Vec::with_capacity(num_workers),
-            latency_provider: self.latency_provider.unwrap_or_default(),
+            latency_provider,
             show_progress: self.show_progress,
+            seed: self.seed,
+            pending: Vec::new(),
+            trace_writer,
+            replay,
         }
     }
 
@@ -306,6 +378,17 @@ pub fn run(mut self, duration: Duration) -> Report {
     }
 
     fn finish(mut self) -> Report {
+        if let Some(writer) = self.trace_writer.as_mut() {
+            writer.flush();
+        }
+
+        if let Some(step) = self.replay.as_mut().and_then(|trace| trace.next()) {
+            panic!(
+                "Simulation ended before the trace. Next step in the trace is frame {}.",
+                step.frame
+            );
+        }
+
         let mut report = self
             .state
             .workers
@@ -328,28 +411,68 @@ fn finish(mut self) -> Report {
             report.node.push(std::mem::take(&mut node.metrics));
         }
 
+        if self.seed.is_some() {
+            report.canonicalize();
+        }
+
         report
     }
 
     fn run_post_frame(&mut self) -> Option<usize> {
-        // Move the messages generated by each worker to each of the destinations.
+        // Collect the messages generated by each worker.
         for messages in self
             .state
             .workers
             .iter()
             .map(|s| &mut unsafe { &mut *s.get() }.outgoing)
         {
-            for mut msg in messages.drain(..) {
-                let node_id = msg.receiver.0;
-                let latency = self
-                    .latency_provider
-                    .get(msg.sender.0, msg.receiver.0)
-                    .as_nanos();
+            self.pending.append(messages);
+        }
+
+        // Which worker executed a node depends on the scheduling, but the messages of a single
+        // node are always in the order they were produced. So a stable sort by the sender makes
+        // the order in which we sample the latencies deterministic.
+        if self.seed.is_some() {
+            self.pending.sort_by_key(|msg| msg.sender);
+        }
+
+        let tracing = self.trace_writer.is_some() || self.replay.is_some();
+        let mut events = Vec::new();
 
-                debug_assert!(latency > 0);
-                msg.time.0 += latency;
+        // Move the messages to each of the destinations.
+        for mut msg in self.pending.drain(..) {
+            let node_id = msg.receiver.0;
+            let latency = self
+                .latency_provider
+                .get(msg.sender.0, msg.receiver.0)
+                .as_nanos();
 
-                self.nodes[node_id].received.push(msg);
+            debug_assert!(latency > 0);
+            msg.time.0 += latency;
+
+            if tracing {
+                events.push(TraceEvent::from(&msg));
+            }
+
+            self.nodes[node_id].received.push(msg);
+        }
+
+        if !events.is_empty() {
+            let step = TraceStep {
+                frame: self.state.frame.load(Ordering::Relaxed) - 1,
+                events,
+            };
+
+            if let Some(writer) = self.trace_writer.as_mut() {
+                writer.write(&step);
+            }
+
+            if let Some(trace) = self.replay.as_mut() {
+                let expected = trace.next();
+                if expected.as_ref() != Some(&step) {
+                    self.stop_threads();
+                    panic_on_divergence(expected, step);
+                }
             }
         }
 
@@ -376,7 +499,8 @@ fn start_threads(&mut self) {
         let num_workers = self.state.workers.len();
         for i in 0..num_workers {
             let state = self.state.clone();
-            std::thread::spawn(move || worker_loop(i, state));
+            let handle = std::thread::spawn(move || worker_loop(i, state));
+            self.workers.push(handle);
         }
     }
 
@@ -451,8 +575,10 @@ fn execute_node(
 
     with_node(|n| {
         n.run_until_stalled();
-        let elapsed = started.elapsed();
-        n.current_metrics.cpu_time += elapsed.as_nanos();
+        if !state.deterministic {
+            let elapsed = started.elapsed();
+            n.current_metrics.cpu_time += elapsed.as_nanos();
+        }
 
         // Move the outgoing messages that this node generated to the worker's
         // outgoing message set.
@@ -505,7 +631,119 @@ fn wait_for_workers(state: &Arc<SharedState>) {
     }
 }
 
+fn panic_on_divergence(expected: Option<TraceStep>, actual: TraceStep) -> ! {
+    let Some(expected) = expected else {
+        panic!(
+            "Simulation diverged from the trace at frame {}: the trace has no more steps.",
+            actual.frame
+        );
+    };
+
+    if expected.frame != actual.frame {
+        panic!(
+            "Simulation diverged from the trace: expected a step at frame {} but got frame {}.",
+            expected.frame, actual.frame
+        );
+    }
+
+    let position = expected
+        .events
+        .iter()
+        .zip(actual.events.iter())
+        .position(|(a, b)| a != b)
+        .unwrap_or(expected.events.len().min(actual.events.len()));
+
+    panic!(
+        "Simulation diverged from the trace at frame {} event {position}: expected {:?}, got {:?}.",
+        actual.frame,
+        expected.events.get(position),
+        actual.events.get(position)
+    );
+}
+
 #[inline(always)]
 fn ceil_div(a: u128, b: u128) -> u128 {
     (a + b - 1) / b
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+    use crate::api;
+
+    const NODES: usize = 32;
+
+    async fn exec() {
+        let mut listener = api::listen(80);
+        api::spawn(async move {
+            while let Some(mut conn) = listener.accept().await {
+                api::spawn(async move {
+                    while let Some(n) = conn.recv::<u64>().await {
+                        conn.write(&n.wrapping_mul(3));
+                    }
+                });
+            }
+        });
+
+        let mut rng = api::rng();
+        for _ in 0..4 {
+            let wait = rng.gen_range(1..50);
+            api::sleep(Duration::from_millis(wait)).await;
+
+            let remote = api::RemoteAddr::from_global_index(rng.gen_range(0..NODES));
+            let mut conn = api::connect(remote, 80).await.unwrap();
+            conn.write(&rng.gen::<u64>());
+            let n = conn.recv::<u64>().await.unwrap();
+            api::emit(format!("{n}"));
+        }
+    }
+
+    fn run(seed: u64, workers: usize) -> Vec<u8> {
+        let report = SimulationBuilder::new(|| api::spawn(exec()))
+            .with_nodes(NODES)
+            .with_workers(workers)
+            .with_seed(seed)
+            .run(Duration::from_millis(500));
+        bincode::serialize(&report).unwrap()
+    }
+
+    #[test]
+    fn test_seeded_runs_are_identical() {
+        let first = run(7, 2);
+        assert_eq!(first, run(7, 2));
+        assert_eq!(first, run(7, 1));
+        assert_ne!(first, run(8, 2));
+    }
+
+    #[test]
+    fn test_replay_trace() {
+        let path = std::env::temp_dir().join(format!("simulon-trace-{}", std::process::id()));
+
+        let recorded = SimulationBuilder::new(|| api::spawn(exec()))
+            .with_nodes(NODES)
+            .with_workers(2)
+            .with_seed(3)
+            .record_trace(&path)
+            .run(Duration::from_millis(500));
+
+        let steps = Trace::open(&path).unwrap().collect::<Vec<_>>();
+        assert!(!steps.is_empty());
+        assert!(steps.windows(2).all(|w| w[0].frame < w[1].frame));
+
+        let replayed = SimulationBuilder::new(|| api::spawn(exec()))
+            .with_nodes(NODES)
+            .with_workers(1)
+            .with_seed(3)
+            .replay_trace(&path)
+            .run(Duration::from_millis(500));
+
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            bincode::serialize(&recorded).unwrap(),
+            bincode::serialize(&replayed).unwrap()
+        );
+    }
+}
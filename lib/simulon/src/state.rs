@@ -3,6 +3,8 @@
 
 use futures::executor::LocalPool;
 use fxhash::FxHashMap;
+use rand_chacha::ChaCha8Rng;
+use rand_core::SeedableRng;
 use triomphe::Arc;
 
 use crate::api::{ConnectError, RemoteAddr};
@@ -67,6 +69,8 @@ pub struct NodeState {
     pub storage: Arc<TypedStorage>,
     /// The already emitted events.
     pub emitted: FxHashMap<String, u128>,
+    /// The random number generator of this node, derived from the simulation seed.
+    pub rng: ChaCha8Rng,
     next_rid: usize,
     _clean_up: WithCleanUpDrop,
 }
@@ -128,7 +132,11 @@ pub enum Resource {
 
 impl NodeState {
     /// Create the empty state of a node.
-    pub fn new(storage: Arc<TypedStorage>, count_nodes: usize, node_id: usize) -> Self {
+    pub fn new(storage: Arc<TypedStorage>, count_nodes: usize, node_id: usize, seed: u64) -> Self {
+        // Every node uses the same key but its own stream, so the streams never overlap.
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        rng.set_stream(node_id as u64);
+
         Self {
             node_id,
             count_nodes,
@@ -142,6 +150,7 @@ pub fn new(storage: Arc<TypedStorage>, count_nodes: usize, node_id: usize) -> Se
             current_metrics: Metrics::default(),
             storage,
             emitted: FxHashMap::default(),
+            rng,
             next_rid: 0,
             _clean_up: WithCleanUpDrop,
         }
@@ -0,0 +1,121 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::message::{Message, MessageDetail};
+
+/// The set of messages delivered by the simulation after a single step. A step is the
+/// execution of one frame followed by the delivery of the messages the nodes produced
+/// during that frame.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceStep {
+    /// The frame that produced the messages.
+    pub frame: usize,
+    /// The delivered messages in the order they were handed to the receivers.
+    pub events: Vec<TraceEvent>,
+}
+
+/// A single message delivery recorded in a trace.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceEvent {
+    /// The time (in nanoseconds) at which the message is going to be processed by the receiver.
+    pub time: u128,
+    /// The global index of the sender.
+    pub sender: usize,
+    /// The global index of the receiver.
+    pub receiver: usize,
+    /// What is being delivered.
+    pub kind: TraceEventKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TraceEventKind {
+    Connect {
+        port: u16,
+    },
+    ConnectionAccepted,
+    ConnectionRefused,
+    ConnectionClosed,
+    /// We only keep the size and a digest of the payload to keep the traces small.
+    Data {
+        len: usize,
+        digest: u64,
+    },
+}
+
+impl From<&Message> for TraceEvent {
+    fn from(msg: &Message) -> Self {
+        let kind = match &msg.detail {
+            MessageDetail::Connect { port, .. } => TraceEventKind::Connect { port: *port },
+            MessageDetail::ConnectionAccepted { .. } => TraceEventKind::ConnectionAccepted,
+            MessageDetail::ConnectionRefused { .. } => TraceEventKind::ConnectionRefused,
+            MessageDetail::ConnectionClosed { .. } => TraceEventKind::ConnectionClosed,
+            MessageDetail::Data { data, .. } => TraceEventKind::Data {
+                len: data.len(),
+                digest: fxhash::hash64(data),
+            },
+            MessageDetail::WakeUp { .. } => unreachable!("Wake ups are never sent to other nodes."),
+        };
+
+        Self {
+            time: msg.time.0,
+            sender: msg.sender.0,
+            receiver: msg.receiver.0,
+            kind,
+        }
+    }
+}
+
+/// Writes the steps of a simulation to a trace file.
+pub(crate) struct TraceWriter {
+    writer: BufWriter<File>,
+}
+
+impl TraceWriter {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+        })
+    }
+
+    pub fn write(&mut self, step: &TraceStep) {
+        bincode::serialize_into(&mut self.writer, step).expect("Failed to write the trace.");
+    }
+
+    pub fn flush(&mut self) {
+        self.writer.flush().expect("Failed to flush the trace.");
+    }
+}
+
+/// A trace recorded by a simulation, which can be walked through step by step.
+///
+/// Traces are recorded using [`crate::simulation::SimulationBuilder::record_trace`] and can
+/// be checked against a new run with [`crate::simulation::SimulationBuilder::replay_trace`].
+pub struct Trace {
+    reader: BufReader<File>,
+}
+
+impl Trace {
+    /// Open a trace file that was previously recorded.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self {
+            reader: BufReader::new(File::open(path)?),
+        })
+    }
+}
+
+impl Iterator for Trace {
+    type Item = TraceStep;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match bincode::deserialize_from(&mut self.reader) {
+            Ok(step) => Some(step),
+            Err(e) => match *e {
+                bincode::ErrorKind::Io(e) if e.kind() == io::ErrorKind::UnexpectedEof => None,
+                e => panic!("Invalid trace file: {e}"),
+            },
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use std::borrow::Cow;
+use std::sync::Arc;
 
 use anyhow::{anyhow, bail, Context};
 use base64::Engine;
@@ -7,17 +8,22 @@
 use fn_sdk::header::TransportDetail;
 use url::Url;
 
-use crate::opts::{Encoding, Format, Service};
-use crate::runtime::{RunOutput, Session};
+use crate::opts::{Encoding, Format, OutputEncoding, Service};
+use crate::runtime::batch::Batcher;
+use crate::runtime::cache::{SessionKey, SESSIONS};
+use crate::runtime::{serialize, RunOutput, Session};
 use crate::{Origin, StartSession};
 
+/// Maximum size of the payloads that outputs are split into when written to the connection.
+const CHUNK_SIZE: usize = 1 << 20;
+
 pub async fn handle(mut connection: Connection) -> anyhow::Result<()> {
     if connection.is_http_request() {
         let TransportDetail::HttpRequest { url, .. } = &connection.header.transport_detail else {
             unreachable!()
         };
 
-        let (content_format, model_io_encoding) = parse_query_params(url)?;
+        let (content_format, model_io_encoding, output_encoding) = parse_query_params(url)?;
 
         let Some((service, origin, uri)) = parse_http_url(url) else {
             let _ = connection.write_payload(b"invalid request url").await;
@@ -36,18 +42,20 @@ pub async fn handle(mut connection: Connection) -> anyhow::Result<()> {
             .await
             .context("Could not read body")?;
 
-        // Load model.
-        let model = load_model(uri, origin).await?;
-        let session = Session::new(model, model_io_encoding)?;
+        let session = get_session(uri, origin, model_io_encoding).await?;
 
         let output = match service {
-            Service::Inference => serialize_output(session.run(body.freeze())?, &content_format)?,
-            Service::Info => serde_json::to_string(&session.model_info()?)?
+            Service::Inference => serialize_output(
+                session.run(body.freeze(), output_encoding).await?,
+                &content_format,
+            )?,
+            Service::Info => serde_json::to_string(&session.session().model_info()?)?
                 .into_bytes()
                 .into(),
         };
 
-        connection.write_payload(&output).await?;
+        // The payloads are streamed to the client as the body of the response.
+        write_chunked(&mut connection, &output).await?;
 
         return Ok(());
     }
@@ -60,17 +68,29 @@ pub async fn handle(mut connection: Connection) -> anyhow::Result<()> {
     let session_params = serde_json::from_slice::<StartSession>(initial_message.as_ref())
         .context("Could not deserialize initial message")?;
 
-    // Load model.
-    let model = load_model(session_params.model, session_params.origin).await?;
-    let session = Session::new(model, session_params.model_io_encoding)?;
+    let session = get_session(
+        session_params.model,
+        session_params.origin,
+        session_params.model_io_encoding,
+    )
+    .await?;
+    let output_encoding = session_params
+        .output_encoding
+        .unwrap_or(session_params.model_io_encoding.into());
 
     // Process incoming inference requests.
     while let Some(payload) = connection.read_payload().await {
         let output = serialize_output(
-            session.run(payload.freeze())?,
+            session.run(payload.freeze(), output_encoding).await?,
             &session_params.content_format,
         )?;
-        connection.write_payload(&output).await?;
+        if session_params.chunked {
+            write_chunked(&mut connection, &output).await?;
+            // An empty payload marks the end of the output.
+            connection.write_payload(&[]).await?;
+        } else {
+            connection.write_payload(&output).await?;
+        }
     }
 
     Ok(())
@@ -84,27 +104,58 @@ fn parse_http_url(url: &Url) -> Option<(String, Origin, String)> {
     Some((seg1.into(), seg2.into(), seg3.into()))
 }
 
-fn parse_query_params(url: &Url) -> anyhow::Result<(Format, Encoding)> {
+fn parse_query_params(url: &Url) -> anyhow::Result<(Format, Encoding, OutputEncoding)> {
     let mut content_format = None;
     let mut encoding = None;
+    let mut output_encoding = None;
     for (name, value) in url.query_pairs() {
         if name == "format" {
             content_format = Some(value);
         } else if name == "encoding" {
             encoding = Some(value);
+        } else if name == "output" {
+            output_encoding = Some(value);
         }
 
-        if content_format.is_some() && encoding.is_some() {
+        if content_format.is_some() && encoding.is_some() && output_encoding.is_some() {
             break;
         }
     }
+    let encoding: Encoding = encoding.unwrap_or(Cow::Borrowed("borsh")).parse()?;
+    let output_encoding = match output_encoding {
+        Some(output_encoding) => output_encoding.parse()?,
+        None => encoding.into(),
+    };
     Ok((
         content_format.unwrap_or(Cow::Borrowed("json")).parse()?,
-        encoding.unwrap_or(Cow::Borrowed("borsh")).parse()?,
+        encoding,
+        output_encoding,
     ))
 }
 
-async fn load_model(model: String, origin: Origin) -> anyhow::Result<Bytes> {
+/// Returns the session of a model from the cache, or loads the model and builds a new session.
+async fn get_session(
+    model: String,
+    origin: Origin,
+    encoding: Encoding,
+) -> anyhow::Result<Arc<Batcher>> {
+    let hash = resolve_model(model, origin).await?;
+    let key = SessionKey { hash, encoding };
+    if let Some(session) = SESSIONS.get(&key) {
+        return Ok(session);
+    }
+
+    let model = load_model(hash).await?;
+    let size = model.len();
+    let session = tokio::task::spawn_blocking(move || Session::new(model, encoding)).await??;
+    let session = Arc::new(Batcher::new(session));
+    SESSIONS.insert(key, session.clone(), size);
+
+    Ok(session)
+}
+
+/// Returns the blake3 hash of a model.
+async fn resolve_model(model: String, origin: Origin) -> anyhow::Result<[u8; 32]> {
     let hash = match origin {
         Origin::Blake3 => {
             let hash = hex::decode(model).context("failed to decode blake3 hash")?;
@@ -112,13 +163,7 @@ async fn load_model(model: String, origin: Origin) -> anyhow::Result<Bytes> {
                 return Err(anyhow!("invalid blake3 hash length"));
             }
 
-            let hash: [u8; 32] = hash.try_into().map_err(|_| anyhow!("invalid hash"))?;
-
-            if fn_sdk::api::fetch_blake3(hash).await {
-                hash
-            } else {
-                bail!("failed to fetch file")
-            }
+            hash.try_into().map_err(|_| anyhow!("invalid hash"))?
         },
         Origin::Ipfs => fn_sdk::api::fetch_from_origin(fn_sdk::api::Origin::IPFS, model.as_bytes())
             .await
@@ -132,6 +177,14 @@ async fn load_model(model: String, origin: Origin) -> anyhow::Result<Bytes> {
         },
     };
 
+    Ok(hash)
+}
+
+async fn load_model(hash: [u8; 32]) -> anyhow::Result<Bytes> {
+    if !fn_sdk::api::fetch_blake3(hash).await {
+        bail!("failed to fetch file")
+    }
+
     let model = fn_sdk::blockstore::ContentHandle::load(&hash)
         .await
         .context("failed to get resource from blockstore")?
@@ -142,23 +195,67 @@ async fn load_model(model: String, origin: Origin) -> anyhow::Result<Bytes> {
 }
 
 fn serialize_output(output: RunOutput, format: &Format) -> anyhow::Result<Bytes> {
-    let output = match output {
-        RunOutput::SafeTensors(bytes) => {
-            if format.is_json() {
-                serde_json::to_string(&serde_json::json!({
-                    "safetensors": base64::prelude::BASE64_STANDARD.encode(bytes),
-                }))?
-                .into_bytes()
-                .into()
-            } else {
-                bytes
-            }
-        },
+    let (key, bytes) = match output {
+        RunOutput::SafeTensors(bytes) => ("safetensors", bytes),
+        RunOutput::Npy(bytes) => ("npy", bytes),
+        RunOutput::Npz(bytes) => ("npz", bytes),
         RunOutput::Borsh(named_vectors) => {
-            // Todo: which binary encoding could we support?
-            serde_json::to_string(&named_vectors)?.into_bytes().into()
+            let output = if format.is_json() {
+                serde_json::to_string(&named_vectors)?.into_bytes().into()
+            } else {
+                serialize::borsh_encode_vectors(named_vectors)?
+            };
+            return Ok(output);
         },
     };
 
-    Ok(output)
+    if format.is_json() {
+        Ok(serde_json::to_string(&serde_json::json!({
+            key: base64::prelude::BASE64_STANDARD.encode(bytes),
+        }))?
+        .into_bytes()
+        .into())
+    } else {
+        Ok(bytes)
+    }
+}
+
+/// Writes the output to the connection in payloads of at most [`CHUNK_SIZE`] bytes.
+async fn write_chunked(connection: &mut Connection, output: &[u8]) -> anyhow::Result<()> {
+    for chunk in output.chunks(CHUNK_SIZE) {
+        connection.write_payload(chunk).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(query: &str) -> anyhow::Result<(Format, Encoding, OutputEncoding)> {
+        let url = Url::parse(&format!("http://fleek/infer/blake3/model?{query}")).unwrap();
+        parse_query_params(&url)
+    }
+
+    #[test]
+    fn test_parse_query_params() {
+        // Json with borsh by default, the outputs use the encoding of the inputs.
+        let (format, encoding, output) = parse("").unwrap();
+        assert!(format.is_json());
+        assert!(encoding.is_borsh());
+        assert_eq!(output, OutputEncoding::Borsh);
+
+        let (format, encoding, output) = parse("format=bin&encoding=safetensors").unwrap();
+        assert!(format.is_binary());
+        assert!(encoding.is_safe_tensors());
+        assert_eq!(output, OutputEncoding::SafeTensors);
+
+        let (_, encoding, output) = parse("encoding=safetensors&output=npz").unwrap();
+        assert!(encoding.is_safe_tensors());
+        assert_eq!(output, OutputEncoding::Npz);
+
+        assert!(parse("format=xml").is_err());
+        assert!(parse("encoding=npy").is_err());
+        assert!(parse("output=csv").is_err());
+    }
 }
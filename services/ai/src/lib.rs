@@ -4,7 +4,7 @@
 
 use serde::{Deserialize, Serialize};
 
-use crate::opts::{Device, Encoding, Format, Origin};
+use crate::opts::{Device, Encoding, Format, Origin, OutputEncoding};
 
 /// Header for stream-based protocols.
 ///
@@ -23,11 +23,19 @@ pub struct StartSession {
     pub content_format: Format,
     /// Encoding to use for the input and output arrays.
     pub model_io_encoding: Encoding,
+    /// Encoding to use for the output arrays. Defaults to the encoding of the input arrays.
+    #[serde(default)]
+    pub output_encoding: Option<OutputEncoding>,
+    /// Whether to split outputs into payloads of at most 1MB, followed by an empty payload that
+    /// marks the end of each output. Otherwise every output is sent in a single payload.
+    #[serde(default)]
+    pub chunked: bool,
 }
 
 #[tokio::main]
 pub async fn main() {
     fn_sdk::ipc::init_from_env();
+    fn_sdk::api::declare_capabilities(Default::default()).await;
     tracing::info!("Initialized AI service!");
 
     // Entry point of the Onnx runtime.
@@ -87,7 +87,7 @@ fn from(s: &str) -> Self {
     }
 }
 
-#[derive(Deserialize, Serialize, Clone, Copy, IsVariant)]
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash, IsVariant)]
 #[repr(u8)]
 pub enum Encoding {
     /// Data is a Borsh-encoded vector.
@@ -116,6 +116,53 @@ fn from_str(s: &str) -> Result<Self, Self::Err> {
     }
 }
 
+/// Encoding of the output arrays, which defaults to the encoding of the input arrays.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, IsVariant)]
+#[repr(u8)]
+pub enum OutputEncoding {
+    /// Data is a map of Borsh-encoded vectors.
+    #[serde(rename = "borsh")]
+    Borsh = 0,
+    /// Data is a safetensors-encoded collection of ndarrays.
+    #[serde(rename = "safetensors")]
+    SafeTensors = 1,
+    /// Data is a single ndarray in the NumPy `.npy` format.
+    #[serde(rename = "npy")]
+    Npy = 2,
+    /// Data is a zip archive of ndarrays in the NumPy `.npz` format.
+    #[serde(rename = "npz")]
+    Npz = 3,
+}
+
+impl FromStr for OutputEncoding {
+    type Err = std::io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let encoding = match s {
+            "borsh" => Self::Borsh,
+            "safetensors" => Self::SafeTensors,
+            "npy" => Self::Npy,
+            "npz" => Self::Npz,
+            _ => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "unknown output encoding",
+                ));
+            },
+        };
+        Ok(encoding)
+    }
+}
+
+impl From<Encoding> for OutputEncoding {
+    fn from(encoding: Encoding) -> Self {
+        match encoding {
+            Encoding::Borsh => Self::Borsh,
+            Encoding::SafeTensors => Self::SafeTensors,
+        }
+    }
+}
+
 pub type BorshNamedVectors = HashMap<String, BorshVector>;
 
 #[derive(Deserialize, Serialize)]
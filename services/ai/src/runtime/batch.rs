@@ -0,0 +1,167 @@
+//! Dynamic batching of inference requests.
+//!
+//! Concurrent requests for the same model are merged along the batch axis into a single run of
+//! the model, and the outputs are split back per request. Requests that can't be merged, or
+//! batches that fail to run, fall back to running every request on its own.
+
+use std::collections::HashMap;
+use std::mem::Discriminant;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
+use tracing::debug;
+
+use crate::opts::OutputEncoding;
+use crate::runtime::tensor::{NamedTensors, Tensor};
+use crate::runtime::{RunOutput, Session};
+
+/// Maximum number of requests merged into a single run.
+const MAX_BATCH_SIZE: usize = 32;
+/// How long to wait for more requests once the first request of a batch came in.
+const BATCH_WINDOW: Duration = Duration::from_millis(5);
+
+/// Runs the inference requests of a session, batching them when the model allows it.
+pub struct Batcher {
+    session: Arc<Session>,
+    /// Sends requests to the batching loop, if the model can be batched.
+    tx: Option<mpsc::UnboundedSender<Job>>,
+}
+
+struct Job {
+    inputs: NamedTensors,
+    /// Length of the batch axis of the inputs.
+    len: usize,
+    tx: oneshot::Sender<Result<NamedTensors>>,
+}
+
+impl Batcher {
+    pub fn new(session: Session) -> Self {
+        let session = Arc::new(session);
+        let tx = session.is_batchable().then(|| {
+            let (tx, rx) = mpsc::unbounded_channel();
+            tokio::spawn(run_batches(session.clone(), rx));
+            tx
+        });
+        Self { session, tx }
+    }
+
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Runs model on the input, along with the other requests that come in at the same time.
+    pub async fn run(&self, input: Bytes, output: OutputEncoding) -> Result<RunOutput> {
+        let inputs = self.session.decode(input)?;
+        let outputs = match (&self.tx, batch_len(&inputs)) {
+            (Some(tx), Some(len)) => {
+                let (job_tx, job_rx) = oneshot::channel();
+                tx.send(Job {
+                    inputs,
+                    len,
+                    tx: job_tx,
+                })
+                .map_err(|_| anyhow!("batching loop stopped"))?;
+                job_rx.await??
+            },
+            _ => {
+                let session = self.session.clone();
+                tokio::task::spawn_blocking(move || session.infer(inputs)).await??
+            },
+        };
+        self.session.encode(outputs, output)
+    }
+}
+
+/// Returns the length of the batch axis of the inputs, if they have one.
+fn batch_len(inputs: &NamedTensors) -> Option<usize> {
+    let mut len = None;
+    for tensor in inputs.values() {
+        let shape = tensor.shape();
+        // One dimensional inputs, like borsh vectors, don't have a batch axis
+        if shape.len() < 2 || len.is_some_and(|len| len != shape[0]) {
+            return None;
+        }
+        len = Some(shape[0]);
+    }
+    len
+}
+
+/// Inputs can only be merged if they have the same names, types, and shapes apart from the
+/// batch axis.
+type Signature = Vec<(String, Discriminant<Tensor>, Vec<usize>)>;
+
+fn signature(inputs: &NamedTensors) -> Signature {
+    let mut signature = inputs
+        .iter()
+        .map(|(name, tensor)| {
+            let shape = tensor.shape()[1..].to_vec();
+            (name.clone(), std::mem::discriminant(tensor), shape)
+        })
+        .collect::<Signature>();
+    signature.sort_by(|a, b| a.0.cmp(&b.0));
+    signature
+}
+
+async fn run_batches(session: Arc<Session>, mut rx: mpsc::UnboundedReceiver<Job>) {
+    while let Some(job) = rx.recv().await {
+        let mut jobs = vec![job];
+        let deadline = Instant::now() + BATCH_WINDOW;
+        while jobs.len() < MAX_BATCH_SIZE {
+            match tokio::time::timeout_at(deadline, rx.recv()).await {
+                Ok(Some(job)) => jobs.push(job),
+                _ => break,
+            }
+        }
+
+        // Requests keep queuing up while the batch runs, and are merged into the next one
+        let session = session.clone();
+        let _ = tokio::task::spawn_blocking(move || run_jobs(&session, jobs)).await;
+    }
+}
+
+fn run_jobs(session: &Session, jobs: Vec<Job>) {
+    let mut groups = HashMap::<Signature, Vec<Job>>::new();
+    for job in jobs {
+        groups.entry(signature(&job.inputs)).or_default().push(job);
+    }
+
+    for jobs in groups.into_values() {
+        if jobs.len() > 1 {
+            match run_merged(session, &jobs) {
+                Ok(outputs) => {
+                    for (job, outputs) in jobs.into_iter().zip(outputs) {
+                        let _ = job.tx.send(Ok(outputs));
+                    }
+                    continue;
+                },
+                Err(e) => debug!("Failed to run a batch of {} requests: {e:?}", jobs.len()),
+            }
+        }
+
+        for job in jobs {
+            let _ = job.tx.send(session.infer(job.inputs));
+        }
+    }
+}
+
+/// Run the inputs of the jobs in a single run, and split the outputs back per job.
+fn run_merged(session: &Session, jobs: &[Job]) -> Result<Vec<NamedTensors>> {
+    let mut inputs = NamedTensors::new();
+    for name in jobs[0].inputs.keys() {
+        let tensors = jobs.iter().map(|job| &job.inputs[name]).collect::<Vec<_>>();
+        inputs.insert(name.clone(), Tensor::concat(&tensors)?);
+    }
+
+    let lengths = jobs.iter().map(|job| job.len).collect::<Vec<_>>();
+    let mut outputs = vec![NamedTensors::new(); jobs.len()];
+    for (name, tensor) in session.infer(inputs)? {
+        for (outputs, part) in outputs.iter_mut().zip(tensor.split(&lengths)?) {
+            outputs.insert(name.clone(), part);
+        }
+    }
+    Ok(outputs)
+}
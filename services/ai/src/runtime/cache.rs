@@ -0,0 +1,135 @@
+//! Cache of the sessions of recently used models.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+
+use lazy_static::lazy_static;
+
+use crate::opts::Encoding;
+use crate::runtime::batch::Batcher;
+
+lazy_static! {
+    /// Sessions of the service, bounded by the size of their models.
+    pub static ref SESSIONS: SessionCache = SessionCache::new(cache_size());
+}
+
+/// Memory budget of the session cache in bytes, read in megabytes from the
+/// `AI_SESSION_CACHE_SIZE` environment variable. Defaults to 2GB.
+fn cache_size() -> usize {
+    std::env::var("AI_SESSION_CACHE_SIZE")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(2048)
+        << 20
+}
+
+/// Sessions are built for a model and the encoding of its inputs and outputs.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SessionKey {
+    /// Blake3 hash of the model.
+    pub hash: [u8; 32],
+    pub encoding: Encoding,
+}
+
+pub type SessionCache = LruCache<SessionKey, Arc<Batcher>>;
+
+/// A least recently used cache, bounded by the total size of its entries.
+pub struct LruCache<K, V> {
+    capacity: usize,
+    state: Mutex<State<K, V>>,
+}
+
+struct State<K, V> {
+    entries: HashMap<K, Entry<V>>,
+    /// Total size of the entries.
+    size: usize,
+    /// Incremented on every access, to order the entries by last use.
+    clock: u64,
+}
+
+struct Entry<V> {
+    value: V,
+    size: usize,
+    last_used: u64,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> LruCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Mutex::new(State {
+                entries: HashMap::new(),
+                size: 0,
+                clock: 0,
+            }),
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let mut state = self.state.lock().unwrap();
+        state.clock += 1;
+        let clock = state.clock;
+        let entry = state.entries.get_mut(key)?;
+        entry.last_used = clock;
+        Some(entry.value.clone())
+    }
+
+    /// Insert a value, evicting the least recently used entries until the cache fits in its
+    /// capacity. Values larger than the capacity are not cached.
+    pub fn insert(&self, key: K, value: V, size: usize) {
+        if size > self.capacity {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        state.clock += 1;
+        let entry = Entry {
+            value,
+            size,
+            last_used: state.clock,
+        };
+        if let Some(previous) = state.entries.insert(key, entry) {
+            state.size -= previous.size;
+        }
+        state.size += size;
+
+        while state.size > self.capacity {
+            let Some(lru) = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            if let Some(entry) = state.entries.remove(&lru) {
+                state.size -= entry.size;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let cache = LruCache::new(10);
+        cache.insert("a", 1, 4);
+        cache.insert("b", 2, 4);
+        assert_eq!(cache.get(&"a"), Some(1));
+
+        // "b" is the least recently used entry
+        cache.insert("c", 3, 4);
+        assert_eq!(cache.get(&"b"), None);
+        assert_eq!(cache.get(&"a"), Some(1));
+        assert_eq!(cache.get(&"c"), Some(3));
+
+        // Too large to be cached
+        cache.insert("d", 4, 11);
+        assert_eq!(cache.get(&"d"), None);
+        assert_eq!(cache.get(&"a"), Some(1));
+    }
+}
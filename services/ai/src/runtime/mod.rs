@@ -1,18 +1,23 @@
+pub mod batch;
+pub mod cache;
 mod deserialize;
 mod model;
-mod serialize;
+pub mod serialize;
+pub mod tensor;
 
 use std::collections::HashMap;
+use std::ops::Deref;
 use std::path::PathBuf;
 
 use anyhow::bail;
 use bytes::Bytes;
 use lazy_static::lazy_static;
 use lightning_utils::config::LIGHTNING_HOME_DIR;
-use ort::{TensorElementType, ValueType};
+use ort::{SessionInputs, TensorElementType, ValueType};
 
-use crate::opts::{BorshVector, Encoding};
+use crate::opts::{BorshNamedVectors, Encoding, OutputEncoding};
 use crate::runtime::model::Info;
+use crate::runtime::tensor::{NamedTensors, Tensor};
 
 // Todo: let's improve this.
 // Ideally we want every node to run onnx with runtime extensions.
@@ -26,7 +31,7 @@
 pub struct Session {
     /// The Onnx Runtime Session.
     onnx: ort::Session,
-    /// Encoding that will be used for the input and output throughout the session.
+    /// Encoding that will be used for the input throughout the session.
     encoding: Encoding,
 }
 
@@ -44,28 +49,83 @@ pub fn new(model: Bytes, encoding: Encoding) -> anyhow::Result<Self> {
     }
 
     /// Runs model on the input.
-    pub fn run(&self, input: Bytes) -> anyhow::Result<RunOutput> {
+    pub fn run(&self, input: Bytes, output: OutputEncoding) -> anyhow::Result<RunOutput> {
+        let inputs = self.decode(input)?;
+        let outputs = self.infer(inputs)?;
+        self.encode(outputs, output)
+    }
+
+    /// Decodes the input of the model with the encoding of the session.
+    pub fn decode(&self, input: Bytes) -> anyhow::Result<NamedTensors> {
         if input.is_empty() {
             bail!("invalid input length");
         }
 
-        // Process input and pass it to the model.
-        let output = match self.encoding {
-            Encoding::Borsh => {
-                let session_input = deserialize::deserialize_borsh(input)?;
-                let session_outputs = self.onnx.run(session_input)?;
-                RunOutput::Borsh(serialize::borsh_serialize_outputs(session_outputs)?)
-            },
-            Encoding::SafeTensors => {
-                let session_input = deserialize::deserialize_safetensors(input)?;
-                let session_outputs = self.onnx.run(session_input)?;
-                RunOutput::SafeTensors(serialize::safetensors_serialize_outputs(session_outputs)?)
+        match self.encoding {
+            Encoding::Borsh => deserialize::deserialize_borsh(input),
+            Encoding::SafeTensors => deserialize::deserialize_safetensors(input),
+        }
+    }
+
+    /// Runs model on decoded inputs.
+    pub fn infer(&self, inputs: NamedTensors) -> anyhow::Result<NamedTensors> {
+        let mut values = HashMap::new();
+        for (name, tensor) in inputs {
+            values.insert(name, tensor.into_value()?);
+        }
+        let session_inputs: SessionInputs<'static> = values.into();
+        let session_outputs = self.onnx.run(session_inputs)?;
+
+        let mut outputs = NamedTensors::new();
+        for (name, value) in session_outputs.deref().iter() {
+            outputs.insert(name.to_string(), Tensor::from_value(value)?);
+        }
+        Ok(outputs)
+    }
+
+    /// Encodes the outputs of the model with the given encoding.
+    pub fn encode(
+        &self,
+        outputs: NamedTensors,
+        encoding: OutputEncoding,
+    ) -> anyhow::Result<RunOutput> {
+        let output = match encoding {
+            OutputEncoding::Borsh => RunOutput::Borsh(serialize::borsh_serialize_outputs(outputs)?),
+            OutputEncoding::SafeTensors => {
+                RunOutput::SafeTensors(serialize::safetensors_serialize_outputs(outputs)?)
             },
+            OutputEncoding::Npy => RunOutput::Npy(serialize::npy_serialize_outputs(outputs)?),
+            OutputEncoding::Npz => RunOutput::Npz(serialize::npz_serialize_outputs(outputs)?),
         };
 
         Ok(output)
     }
 
+    /// Returns true if the first axis of every input and output of the model is dynamic, which by
+    /// convention is the batch axis, so that inputs of separate requests can be run together.
+    pub fn is_batchable(&self) -> bool {
+        let dynamic_batch = |value: &ValueType| match value {
+            ValueType::Tensor { dimensions, .. } => dimensions.first() == Some(&-1),
+            _ => false,
+        };
+        !self.onnx.inputs.is_empty()
+            && self
+                .onnx
+                .inputs
+                .iter()
+                .all(|input| dynamic_batch(&input.input_type))
+            && self
+                .onnx
+                .outputs
+                .iter()
+                .all(|output| dynamic_batch(&output.output_type))
+    }
+
+    /// Returns the encodings that can be used for the outputs of the model.
+    pub fn output_encodings(&self) -> Vec<OutputEncoding> {
+        output_encodings(self.onnx.outputs.iter().map(|output| &output.output_type))
+    }
+
     pub fn model_info(&self) -> anyhow::Result<Info> {
         let mut name = None;
         let mut description = None;
@@ -107,13 +167,56 @@ pub fn model_info(&self) -> anyhow::Result<Info> {
             producer,
             inputs,
             outputs,
+            output_encodings: self.output_encodings(),
         })
     }
 }
 
 pub enum RunOutput {
     SafeTensors(Bytes),
-    Borsh(HashMap<String, BorshVector>),
+    Borsh(BorshNamedVectors),
+    Npy(Bytes),
+    Npz(Bytes),
+}
+
+/// Returns the encodings that can be used for outputs of the given types.
+fn output_encodings<'a>(outputs: impl Iterator<Item = &'a ValueType>) -> Vec<OutputEncoding> {
+    let mut ranks = Vec::new();
+    for output in outputs {
+        match output {
+            ValueType::Tensor { ty, dimensions } if is_supported_type(*ty) => {
+                ranks.push(dimensions.len())
+            },
+            // Outputs that aren't tensors of a supported type can't be encoded at all
+            _ => return Vec::new(),
+        }
+    }
+
+    let mut encodings = vec![OutputEncoding::SafeTensors, OutputEncoding::Npz];
+    // Borsh vectors are always one dimensional
+    if ranks.iter().all(|rank| *rank <= 1) {
+        encodings.push(OutputEncoding::Borsh);
+    }
+    if ranks.len() == 1 {
+        encodings.push(OutputEncoding::Npy);
+    }
+    encodings
+}
+
+fn is_supported_type(t: TensorElementType) -> bool {
+    matches!(
+        t,
+        TensorElementType::Int8
+            | TensorElementType::Int16
+            | TensorElementType::Int32
+            | TensorElementType::Int64
+            | TensorElementType::Uint8
+            | TensorElementType::Uint16
+            | TensorElementType::Uint32
+            | TensorElementType::Uint64
+            | TensorElementType::Float32
+            | TensorElementType::Float64
+    )
 }
 
 fn element_type_to_str(t: TensorElementType) -> &'static str {
@@ -148,3 +251,44 @@ fn value_type_to_string(value: &ValueType) -> String {
         ValueType::Sequence(_) => "Sequence<_>".to_string(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tensor(ty: TensorElementType, dimensions: Vec<i64>) -> ValueType {
+        ValueType::Tensor { ty, dimensions }
+    }
+
+    #[test]
+    fn test_output_encodings() {
+        // A single vector can be encoded in every way.
+        let outputs = [tensor(TensorElementType::Float32, vec![-1])];
+        assert_eq!(
+            output_encodings(outputs.iter()),
+            vec![
+                OutputEncoding::SafeTensors,
+                OutputEncoding::Npz,
+                OutputEncoding::Borsh,
+                OutputEncoding::Npy
+            ]
+        );
+
+        // Borsh is only for vectors and npy only for a single output.
+        let outputs = [
+            tensor(TensorElementType::Float32, vec![-1, 3]),
+            tensor(TensorElementType::Int64, vec![-1]),
+        ];
+        assert_eq!(
+            output_encodings(outputs.iter()),
+            vec![OutputEncoding::SafeTensors, OutputEncoding::Npz]
+        );
+
+        // Unsupported outputs can't be encoded at all.
+        let outputs = [
+            tensor(TensorElementType::Float32, vec![-1]),
+            tensor(TensorElementType::Float16, vec![-1]),
+        ];
+        assert!(output_encodings(outputs.iter()).is_empty());
+    }
+}
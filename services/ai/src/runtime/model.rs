@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::opts::OutputEncoding;
+
 #[derive(Deserialize, Serialize)]
 pub struct Info {
     pub name: Option<String>,
@@ -7,4 +9,6 @@ pub struct Info {
     pub producer: Option<String>,
     pub inputs: Vec<String>,
     pub outputs: Vec<String>,
+    /// Encodings supported for the outputs of the model.
+    pub output_encodings: Vec<OutputEncoding>,
 }
@@ -1,64 +1,29 @@
-use std::collections::HashMap;
-use std::ops::Deref;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Cursor;
 
 use anyhow::bail;
 use bytes::Bytes;
-use ort::{SessionOutputs, TensorElementType, ValueType};
+use ndarray::ArrayD;
+use ndarray_npy::{NpzWriter, WriteNpyExt};
 use safetensors_ndarray::collection::Collection;
 
 use crate::opts::{BorshNamedVectors, BorshVector, BorshVectorType};
+use crate::runtime::tensor::{NamedTensors, Tensor};
 
-pub fn safetensors_serialize_outputs(session_outputs: SessionOutputs) -> anyhow::Result<Bytes> {
+pub fn safetensors_serialize_outputs(outputs: NamedTensors) -> anyhow::Result<Bytes> {
     let mut safetensors_out = Collection::new();
-    for (name, value) in session_outputs.deref().iter() {
-        let dtype = value.dtype()?;
-        match dtype {
-            ValueType::Tensor { ty, .. } => match ty {
-                TensorElementType::Int8 => {
-                    let array = value.extract_tensor::<i8>()?.view().deref().to_owned();
-                    safetensors_out.insert_array_i8(name.to_string(), array);
-                },
-                TensorElementType::Int16 => {
-                    let array = value.extract_tensor::<i16>()?.view().deref().to_owned();
-                    safetensors_out.insert_array_i16(name.to_string(), array);
-                },
-                TensorElementType::Int32 => {
-                    let array = value.extract_tensor::<i32>()?.view().deref().to_owned();
-                    safetensors_out.insert_array_i32(name.to_string(), array);
-                },
-                TensorElementType::Int64 => {
-                    let array = value.extract_tensor::<i64>()?.view().deref().to_owned();
-                    safetensors_out.insert_array_i64(name.to_string(), array);
-                },
-                TensorElementType::Uint8 => {
-                    let array = value.extract_tensor::<u8>()?.view().deref().to_owned();
-
-                    safetensors_out.insert_array_u8(name.to_string(), array);
-                },
-                TensorElementType::Uint16 => {
-                    let array = value.extract_tensor::<u16>()?.view().deref().to_owned();
-
-                    safetensors_out.insert_array_u16(name.to_string(), array);
-                },
-                TensorElementType::Uint32 => {
-                    let array = value.extract_tensor::<u32>()?.view().deref().to_owned();
-                    safetensors_out.insert_array_u32(name.to_string(), array);
-                },
-                TensorElementType::Uint64 => {
-                    let array = value.extract_tensor::<u64>()?.view().deref().to_owned();
-                    safetensors_out.insert_array_u64(name.to_string(), array);
-                },
-                TensorElementType::Float32 => {
-                    let array = value.extract_tensor::<f32>()?.view().deref().to_owned();
-                    safetensors_out.insert_array_f32(name.to_string(), array);
-                },
-                TensorElementType::Float64 => {
-                    let array = value.extract_tensor::<f64>()?.view().deref().to_owned();
-                    safetensors_out.insert_array_f64(name.to_string(), array);
-                },
-                _ => bail!("unsupported value type"),
-            },
-            _ => bail!("unsupported type for output"),
+    for (name, tensor) in outputs {
+        match tensor {
+            Tensor::Int8(array) => safetensors_out.insert_array_i8(name, array),
+            Tensor::Int16(array) => safetensors_out.insert_array_i16(name, array),
+            Tensor::Int32(array) => safetensors_out.insert_array_i32(name, array),
+            Tensor::Int64(array) => safetensors_out.insert_array_i64(name, array),
+            Tensor::Uint8(array) => safetensors_out.insert_array_u8(name, array),
+            Tensor::Uint16(array) => safetensors_out.insert_array_u16(name, array),
+            Tensor::Uint32(array) => safetensors_out.insert_array_u32(name, array),
+            Tensor::Uint64(array) => safetensors_out.insert_array_u64(name, array),
+            Tensor::Float32(array) => safetensors_out.insert_array_f32(name, array),
+            Tensor::Float64(array) => safetensors_out.insert_array_f64(name, array),
         }
     }
 
@@ -68,171 +33,171 @@ pub fn safetensors_serialize_outputs(session_outputs: SessionOutputs) -> anyhow:
         .map_err(Into::into)
 }
 
-pub fn borsh_serialize_outputs(
-    session_outputs: SessionOutputs,
-) -> anyhow::Result<BorshNamedVectors> {
+pub fn borsh_serialize_outputs(outputs: NamedTensors) -> anyhow::Result<BorshNamedVectors> {
     let mut borsh_out = HashMap::new();
-    for (name, value) in session_outputs.deref().iter() {
-        let name = name.to_string();
-        let dtype = value.dtype()?;
-        let dim = dtype.tensor_dimensions().map(|dim| dim.len()).unwrap_or(0);
-        match dtype {
-            ValueType::Tensor { ty, .. } => match ty {
-                TensorElementType::Int8 => {
-                    let array = value.extract_tensor::<i8>()?.view().deref().to_owned();
-                    if dim != 1 {
-                        bail!("cannot serialize array with dim {dim:?} using borsh");
-                    } else {
-                        let array = array.into_iter().collect::<Vec<i8>>();
-                        borsh_out.insert(
-                            name,
-                            BorshVector {
-                                dtype: BorshVectorType::Int8,
-                                data: borsh::to_vec::<Vec<i8>>(&array)?,
-                            },
-                        );
-                    }
-                },
-                TensorElementType::Int16 => {
-                    let array = value.extract_tensor::<i16>()?.view().deref().to_owned();
-                    if dim > 1 {
-                        bail!("cannot serialize array with dim {dim:?} using borsh");
-                    } else {
-                        let array = array.into_iter().collect::<Vec<i16>>();
-                        borsh_out.insert(
-                            name,
-                            BorshVector {
-                                dtype: BorshVectorType::Int8,
-                                data: borsh::to_vec::<Vec<i16>>(&array)?,
-                            },
-                        );
-                    }
-                },
-                TensorElementType::Int32 => {
-                    let array = value.extract_tensor::<i32>()?.view().deref().to_owned();
-                    if dim > 1 {
-                        bail!("cannot serialize array with dim {dim:?} using borsh");
-                    } else {
-                        let array = array.into_iter().collect::<Vec<i32>>();
-                        borsh_out.insert(
-                            name,
-                            BorshVector {
-                                dtype: BorshVectorType::Int8,
-                                data: borsh::to_vec::<Vec<i32>>(&array)?,
-                            },
-                        );
-                    }
-                },
-                TensorElementType::Int64 => {
-                    let array = value.extract_tensor::<i64>()?.view().deref().to_owned();
-                    if dim > 1 {
-                        bail!("cannot serialize array with dim {dim:?} using borsh");
-                    } else {
-                        let array = array.into_iter().collect::<Vec<i64>>();
-                        borsh_out.insert(
-                            name,
-                            BorshVector {
-                                dtype: BorshVectorType::Int8,
-                                data: borsh::to_vec::<Vec<i64>>(&array)?,
-                            },
-                        );
-                    }
-                },
-                TensorElementType::Uint8 => {
-                    let array = value.extract_tensor::<u8>()?.view().deref().to_owned();
-                    if dim > 1 {
-                        bail!("cannot serialize array with dim {dim:?} using borsh");
-                    } else {
-                        let array = array.into_iter().collect::<Vec<u8>>();
-                        borsh_out.insert(
-                            name,
-                            BorshVector {
-                                dtype: BorshVectorType::Int8,
-                                data: borsh::to_vec::<Vec<u8>>(&array)?,
-                            },
-                        );
-                    }
-                },
-                TensorElementType::Uint16 => {
-                    let array = value.extract_tensor::<u16>()?.view().deref().to_owned();
-                    if dim > 1 {
-                        bail!("cannot serialize array with dim {dim:?} using borsh");
-                    } else {
-                        let array = array.into_iter().collect::<Vec<u16>>();
-                        borsh_out.insert(
-                            name,
-                            BorshVector {
-                                dtype: BorshVectorType::Int8,
-                                data: borsh::to_vec::<Vec<u16>>(&array)?,
-                            },
-                        );
-                    }
-                },
-                TensorElementType::Uint32 => {
-                    let array = value.extract_tensor::<u32>()?.view().deref().to_owned();
-                    if dim > 1 {
-                        bail!("cannot serialize array with dim {dim:?} using borsh");
-                    } else {
-                        let array = array.into_iter().collect::<Vec<u32>>();
-                        borsh_out.insert(
-                            name,
-                            BorshVector {
-                                dtype: BorshVectorType::Int8,
-                                data: borsh::to_vec::<Vec<u32>>(&array)?,
-                            },
-                        );
-                    }
-                },
-                TensorElementType::Uint64 => {
-                    let array = value.extract_tensor::<u64>()?.view().deref().to_owned();
-                    if dim > 1 {
-                        bail!("cannot serialize array with dim {dim:?} using borsh");
-                    } else {
-                        let array = array.into_iter().collect::<Vec<u64>>();
-                        borsh_out.insert(
-                            name,
-                            BorshVector {
-                                dtype: BorshVectorType::Uint64,
-                                data: borsh::to_vec::<Vec<u64>>(&array)?,
-                            },
-                        );
-                    }
-                },
-                TensorElementType::Float32 => {
-                    let array = value.extract_tensor::<f32>()?.view().deref().to_owned();
-                    if dim > 1 {
-                        bail!("cannot serialize array with dim {dim:?} using borsh");
-                    } else {
-                        let array = array.into_iter().collect::<Vec<f32>>();
-                        borsh_out.insert(
-                            name,
-                            BorshVector {
-                                dtype: BorshVectorType::Int8,
-                                data: borsh::to_vec::<Vec<f32>>(&array)?,
-                            },
-                        );
-                    }
-                },
-                TensorElementType::Float64 => {
-                    let array = value.extract_tensor::<f64>()?.view().deref().to_owned();
-                    if dim > 1 {
-                        bail!("cannot serialize array with dim {dim:?} using borsh");
-                    } else {
-                        let array = array.into_iter().collect::<Vec<f64>>();
-                        borsh_out.insert(
-                            name,
-                            BorshVector {
-                                dtype: BorshVectorType::Int8,
-                                data: borsh::to_vec::<Vec<f64>>(&array)?,
-                            },
-                        );
-                    }
-                },
-                _ => bail!("unsupported value type"),
-            },
-            _ => bail!("unsupported type for output"),
-        }
+    for (name, tensor) in outputs {
+        let vector = match tensor {
+            Tensor::Int8(array) => borsh_vector(BorshVectorType::Int8, array)?,
+            Tensor::Int16(array) => borsh_vector(BorshVectorType::Int16, array)?,
+            Tensor::Int32(array) => borsh_vector(BorshVectorType::Int32, array)?,
+            Tensor::Int64(array) => borsh_vector(BorshVectorType::Int64, array)?,
+            Tensor::Uint8(array) => borsh_vector(BorshVectorType::Uint8, array)?,
+            Tensor::Uint16(array) => borsh_vector(BorshVectorType::Uint16, array)?,
+            Tensor::Uint32(array) => borsh_vector(BorshVectorType::Uint32, array)?,
+            Tensor::Uint64(array) => borsh_vector(BorshVectorType::Uint64, array)?,
+            Tensor::Float32(array) => borsh_vector(BorshVectorType::Float32, array)?,
+            Tensor::Float64(array) => borsh_vector(BorshVectorType::Float64, array)?,
+        };
+        borsh_out.insert(name, vector);
     }
 
     Ok(borsh_out)
 }
+
+fn borsh_vector<T: borsh::BorshSerialize>(
+    dtype: BorshVectorType,
+    array: ArrayD<T>,
+) -> anyhow::Result<BorshVector> {
+    let dim = array.ndim();
+    if dim > 1 {
+        bail!("cannot serialize array with dim {dim:?} using borsh");
+    }
+
+    let array = array.into_iter().collect::<Vec<T>>();
+    Ok(BorshVector {
+        dtype,
+        data: borsh::to_vec::<Vec<T>>(&array)?,
+    })
+}
+
+/// Encodes the vectors in binary as a borsh map from the name of each output to a tuple of its
+/// dtype tag and its borsh-encoded data, ordered by name.
+pub fn borsh_encode_vectors(vectors: BorshNamedVectors) -> anyhow::Result<Bytes> {
+    let vectors = vectors
+        .into_iter()
+        .map(|(name, vector)| (name, (vector.dtype as u8, vector.data)))
+        .collect::<BTreeMap<_, _>>();
+    Ok(borsh::to_vec(&vectors)?.into())
+}
+
+/// The `.npy` format holds a single array, so this only works for models with one output.
+pub fn npy_serialize_outputs(outputs: NamedTensors) -> anyhow::Result<Bytes> {
+    if outputs.len() != 1 {
+        bail!("cannot serialize {} outputs using npy", outputs.len());
+    }
+
+    let mut npy_out = Vec::new();
+    for tensor in outputs.into_values() {
+        match tensor {
+            Tensor::Int8(array) => array.write_npy(&mut npy_out)?,
+            Tensor::Int16(array) => array.write_npy(&mut npy_out)?,
+            Tensor::Int32(array) => array.write_npy(&mut npy_out)?,
+            Tensor::Int64(array) => array.write_npy(&mut npy_out)?,
+            Tensor::Uint8(array) => array.write_npy(&mut npy_out)?,
+            Tensor::Uint16(array) => array.write_npy(&mut npy_out)?,
+            Tensor::Uint32(array) => array.write_npy(&mut npy_out)?,
+            Tensor::Uint64(array) => array.write_npy(&mut npy_out)?,
+            Tensor::Float32(array) => array.write_npy(&mut npy_out)?,
+            Tensor::Float64(array) => array.write_npy(&mut npy_out)?,
+        }
+    }
+
+    Ok(npy_out.into())
+}
+
+pub fn npz_serialize_outputs(outputs: NamedTensors) -> anyhow::Result<Bytes> {
+    let mut npz_out = NpzWriter::new(Cursor::new(Vec::new()));
+    let mut outputs = outputs.into_iter().collect::<Vec<_>>();
+    outputs.sort_by(|a, b| a.0.cmp(&b.0));
+    for (name, tensor) in outputs {
+        match tensor {
+            Tensor::Int8(array) => npz_out.add_array(name, &array)?,
+            Tensor::Int16(array) => npz_out.add_array(name, &array)?,
+            Tensor::Int32(array) => npz_out.add_array(name, &array)?,
+            Tensor::Int64(array) => npz_out.add_array(name, &array)?,
+            Tensor::Uint8(array) => npz_out.add_array(name, &array)?,
+            Tensor::Uint16(array) => npz_out.add_array(name, &array)?,
+            Tensor::Uint32(array) => npz_out.add_array(name, &array)?,
+            Tensor::Uint64(array) => npz_out.add_array(name, &array)?,
+            Tensor::Float32(array) => npz_out.add_array(name, &array)?,
+            Tensor::Float64(array) => npz_out.add_array(name, &array)?,
+        }
+    }
+
+    Ok(npz_out.finish()?.into_inner().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{array, ArrayD};
+    use ndarray_npy::{NpzReader, ReadNpyExt};
+
+    use super::*;
+    use crate::runtime::deserialize::{deserialize_borsh, deserialize_safetensors};
+
+    fn outputs() -> NamedTensors {
+        NamedTensors::from([
+            (
+                "a".to_string(),
+                Tensor::Float32(array![1.0, 2.5, -3.0].into_dyn()),
+            ),
+            ("b".to_string(), Tensor::Int64(array![7, -8].into_dyn())),
+        ])
+    }
+
+    #[test]
+    fn test_safetensors_round_trip() {
+        let bytes = safetensors_serialize_outputs(outputs()).unwrap();
+        assert_eq!(deserialize_safetensors(bytes).unwrap(), outputs());
+    }
+
+    #[test]
+    fn test_borsh_round_trip() {
+        let vectors = borsh_serialize_outputs(outputs()).unwrap();
+        let json = serde_json::to_vec(&vectors).unwrap();
+        assert_eq!(deserialize_borsh(json.into()).unwrap(), outputs());
+
+        // The binary encoding is a map from the names to the dtype tags and the vectors.
+        let bytes = borsh_encode_vectors(borsh_serialize_outputs(outputs()).unwrap()).unwrap();
+        let decoded = borsh::from_slice::<BTreeMap<String, (u8, Vec<u8>)>>(&bytes).unwrap();
+        assert_eq!(decoded.keys().collect::<Vec<_>>(), vec!["a", "b"]);
+        let (dtype, data) = &decoded["a"];
+        assert_eq!(*dtype, BorshVectorType::Float32 as u8);
+        assert_eq!(
+            borsh::from_slice::<Vec<f32>>(data).unwrap(),
+            vec![1.0, 2.5, -3.0]
+        );
+        let (dtype, data) = &decoded["b"];
+        assert_eq!(*dtype, BorshVectorType::Int64 as u8);
+        assert_eq!(borsh::from_slice::<Vec<i64>>(data).unwrap(), vec![7, -8]);
+
+        // Borsh vectors are one dimensional.
+        let matrix = NamedTensors::from([(
+            "m".to_string(),
+            Tensor::Float32(array![[1.0], [2.0]].into_dyn()),
+        )]);
+        assert!(borsh_serialize_outputs(matrix).is_err());
+    }
+
+    #[test]
+    fn test_npy_round_trip() {
+        let matrix = array![[1.0, 2.0], [3.0, 4.0]].into_dyn();
+        let single = NamedTensors::from([("m".to_string(), Tensor::Float64(matrix.clone()))]);
+        let bytes = npy_serialize_outputs(single).unwrap();
+        assert_eq!(ArrayD::<f64>::read_npy(bytes.as_ref()).unwrap(), matrix);
+
+        // A `.npy` file holds a single array.
+        assert!(npy_serialize_outputs(outputs()).is_err());
+    }
+
+    #[test]
+    fn test_npz_round_trip() {
+        let bytes = npz_serialize_outputs(outputs()).unwrap();
+        let mut npz = NpzReader::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(npz.names().unwrap(), vec!["a", "b"]);
+        let a: ArrayD<f32> = npz.by_name("a").unwrap();
+        assert_eq!(Tensor::Float32(a), outputs()["a"]);
+        let b: ArrayD<i64> = npz.by_name("b").unwrap();
+        assert_eq!(Tensor::Int64(b), outputs()["b"]);
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::ops::Deref;
+
+use anyhow::{bail, Result};
+use ndarray::{concatenate, ArrayD, Axis, Slice};
+use ort::{TensorElementType, Value, ValueType};
+
+/// Inputs or outputs of a model by name, independently of how they are encoded.
+pub type NamedTensors = HashMap<String, Tensor>;
+
+macro_rules! tensor {
+    ($($variant:ident($ty:ty)),* $(,)?) => {
+        /// A tensor of any of the element types supported by the service.
+        #[derive(Debug, Clone, PartialEq)]
+        pub enum Tensor {
+            $($variant(ArrayD<$ty>),)*
+        }
+
+        impl Tensor {
+            pub fn shape(&self) -> &[usize] {
+                match self {
+                    $(Self::$variant(array) => array.shape(),)*
+                }
+            }
+
+            /// Convert the tensor into an input value of the onnx runtime.
+            pub fn into_value(self) -> Result<Value> {
+                let value = match self {
+                    $(Self::$variant(array) => array.try_into()?,)*
+                };
+                Ok(value)
+            }
+
+            /// Extract a tensor from an output value of the onnx runtime.
+            pub fn from_value(value: &Value) -> Result<Self> {
+                let ValueType::Tensor { ty, .. } = value.dtype()? else {
+                    bail!("unsupported type for output");
+                };
+                let tensor = match ty {
+                    $(TensorElementType::$variant => Self::$variant(
+                        value.extract_tensor::<$ty>()?.view().deref().to_owned(),
+                    ),)*
+                    _ => bail!("unsupported value type"),
+                };
+                Ok(tensor)
+            }
+
+            /// Concatenate tensors of the same type along their first axis.
+            pub fn concat(tensors: &[&Tensor]) -> Result<Self> {
+                let Some(first) = tensors.first() else {
+                    bail!("no tensors to concatenate");
+                };
+                let tensor = match first {
+                    $(Self::$variant(_) => {
+                        let mut views = Vec::with_capacity(tensors.len());
+                        for tensor in tensors {
+                            let Self::$variant(array) = tensor else {
+                                bail!("cannot concatenate tensors of different types");
+                            };
+                            views.push(array.view());
+                        }
+                        Self::$variant(concatenate(Axis(0), &views)?)
+                    },)*
+                };
+                Ok(tensor)
+            }
+
+            /// Split the tensor along its first axis, in parts of the given lengths.
+            pub fn split(&self, lengths: &[usize]) -> Result<Vec<Self>> {
+                if self.shape().first() != Some(&lengths.iter().sum()) {
+                    bail!("cannot split tensor of shape {:?} in {lengths:?}", self.shape());
+                }
+
+                let mut start = 0;
+                let mut parts = Vec::with_capacity(lengths.len());
+                for len in lengths {
+                    let slice = Slice::from(start..start + len);
+                    parts.push(match self {
+                        $(Self::$variant(array) => {
+                            Self::$variant(array.slice_axis(Axis(0), slice).to_owned())
+                        },)*
+                    });
+                    start += len;
+                }
+                Ok(parts)
+            }
+        }
+    };
+}
+
+tensor!(
+    Int8(i8),
+    Int16(i16),
+    Int32(i32),
+    Int64(i64),
+    Uint8(u8),
+    Uint16(u16),
+    Uint32(u32),
+    Uint64(u64),
+    Float32(f32),
+    Float64(f64),
+);
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{array, Array2};
+
+    use super::*;
+
+    #[test]
+    fn test_concat_and_split() {
+        let a = Tensor::Float32(array![[1.0, 2.0]].into_dyn());
+        let b = Tensor::Float32(array![[3.0, 4.0], [5.0, 6.0]].into_dyn());
+
+        let merged = Tensor::concat(&[&a, &b]).unwrap();
+        assert_eq!(merged.shape(), &[3, 2]);
+        assert_eq!(merged.split(&[1, 2]).unwrap(), vec![a.clone(), b]);
+        assert!(merged.split(&[1, 1]).is_err());
+
+        let c = Tensor::Int8(Array2::zeros((1, 2)).into_dyn());
+        assert!(Tensor::concat(&[&a, &c]).is_err());
+    }
+}
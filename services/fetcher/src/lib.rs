@@ -15,7 +15,7 @@
 use arrayref::array_ref;
 use bytes::{Buf, Bytes};
 use cid::Cid;
-use fn_sdk::api::Origin as ApiOrigin;
+use fn_sdk::api::{Capabilities, Origin as ApiOrigin};
 use fn_sdk::connection::Connection;
 use fn_sdk::header::TransportDetail;
 use fn_sdk::http_util::{respond_only_default_headers, respond_with_error};
@@ -54,6 +54,10 @@ fn from(val: Origin) -> Self {
 #[tokio::main]
 pub async fn main() {
     fn_sdk::ipc::init_from_env();
+    fn_sdk::api::declare_capabilities(Capabilities {
+        http_overrides: false,
+    })
+    .await;
     info!("Initialized IPFS fetcher service!");
 
     let mut listener = fn_sdk::ipc::conn_bind().await;
@@ -7,6 +7,8 @@
 use deno_canvas::deno_canvas;
 use deno_console::deno_console;
 use deno_crypto::deno_crypto;
+use deno_fleek::deployment::Deployment;
+use deno_fleek::kv::KvNamespace;
 use deno_fleek::{fleek, maybe_transpile_source, Permissions};
 use deno_fs::sync::MaybeArc;
 use deno_fs::InMemoryFs;
@@ -30,7 +32,7 @@ fn main() {
         deno_io::deno_io::init_ops_and_esm(Some(Default::default())),
         deno_fs::deno_fs::init_ops::<Permissions>(memory_fs.clone()),
         deno_node::deno_node::init_ops_and_esm::<Permissions>(None, memory_fs),
-        fleek::init_ops_and_esm(0),
+        fleek::init_ops_and_esm(0, KvNamespace([0; 32]), Deployment::default()),
     ];
 
     let snapshot = deno_core::snapshot::create_snapshot(
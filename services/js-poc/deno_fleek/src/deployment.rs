@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+/// Settings a function gets from the manifest of the deployment it is part of. Functions that
+/// are not deployed with a manifest use the default settings.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Deployment {
+    /// Public configuration values, exposed to the function as `Fleek.config`.
+    #[serde(default)]
+    pub config: serde_json::Value,
+    /// Hosts the function is allowed to connect to. Every host is allowed if there is no list.
+    #[serde(default)]
+    pub allowed_hosts: Option<Vec<String>>,
+}
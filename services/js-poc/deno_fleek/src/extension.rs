@@ -1,14 +1,22 @@
 use deno_core::extension;
 
+use crate::deployment::Deployment;
+use crate::kv::KvNamespace;
 use crate::ops::{
     fetch_blake3,
     fetch_from_origin,
+    kv_delete,
+    kv_get,
+    kv_list,
+    kv_put,
     load_content,
     log,
     op_bootstrap_color_depth,
     op_bootstrap_unstable_args,
     op_can_write_vectored,
     op_create_worker,
+    op_deployment_config,
+    op_env,
     op_host_post_message,
     op_host_recv_ctrl,
     op_host_recv_message,
@@ -17,6 +25,10 @@
     op_napi_open,
     op_raw_write_vectored,
     op_set_raw,
+    op_worker_close,
+    op_worker_get_name,
+    op_worker_post_message,
+    op_worker_recv_message,
     query_client_bandwidth_balance,
     query_client_flk_balance,
     read_block,
@@ -24,6 +36,8 @@
     TaskDepth,
 };
 use crate::permissions::Permissions;
+use crate::secrets::Secrets;
+use crate::usage::OpUsage;
 
 extension!(
     fleek,
@@ -51,6 +65,10 @@
         read_block,
         query_client_flk_balance,
         query_client_bandwidth_balance,
+        kv_get,
+        kv_put,
+        kv_delete,
+        kv_list,
         op_set_raw,
         op_can_write_vectored,
         op_raw_write_vectored,
@@ -58,10 +76,16 @@
         op_http_set_response_trailers,
         op_bootstrap_color_depth,
         op_create_worker,
+        op_deployment_config,
+        op_env,
         op_host_post_message,
         op_host_recv_ctrl,
         op_host_recv_message,
         op_host_terminate_worker,
+        op_worker_get_name,
+        op_worker_post_message,
+        op_worker_recv_message,
+        op_worker_close,
         op_napi_open
     ],
     esm_entry_point = "ext:fleek/bootstrap.js",
@@ -69,6 +93,7 @@
         dir "src/js",
         "fleek.js",
         "global.js",
+        "worker.js",
         "bootstrap.js",
         "ext:runtime/98_global_scope_shared.js" = "98_global_scope_shared.js",
         "ext:deno_http/00_serve.ts" = "00_serve.ts",
@@ -77,10 +102,14 @@
         "ext:deno_fs/30_fs.js" = "30_fs.js",
         "ext:runtime/40_process.js" = "40_process.js",
     ],
-    options = { depth: u8 },
+    options = { depth: u8, namespace: KvNamespace, deployment: Deployment },
     state = |state, config| {
         // initialize permissions
-        state.put(Permissions {});
+        state.put(Permissions::new(config.deployment.allowed_hosts.clone()));
+        state.put(config.deployment);
+        state.put(Secrets::default());
         state.put(TaskDepth(config.depth));
+        state.put(config.namespace);
+        state.put(OpUsage::default());
     }
 );
@@ -1,5 +1,6 @@
 import * as loc from "ext:deno_web/12_location.js";
 import { globalContext } from "ext:fleek/global.js";
+import { bootstrapWorker } from "ext:fleek/worker.js";
 
 /** Bootstrap function called at runtime before execution.
  *  Can only be called once.
@@ -18,3 +19,8 @@ globalThis.bootstrap = (time, url) => {
   // Block internal access to deno from the script scope
   delete globalThis.Deno;
 };
+
+/** Bootstrap function called on worker runtimes, after `bootstrap`.
+ *  Turns the global scope into a dedicated worker scope.
+ */
+globalThis.bootstrapWorker = bootstrapWorker;
@@ -13,10 +13,15 @@ const ServiceId = {
  * @param {Number} service - Service ID, must be a 32 bit unsigned integer
  * @param {ArrayBufferLike | string | any} body - Request body to send to a service. Buffers are sent directly,
  *                                                strings are encoded, and anything else is encoded as json.
- * @param {"local"|"single"|"cluster"} scope - Optional scope to run the task under. If undefined, defaults to local.
+ * @param {"local"|"single"|"cluster"|`quorum:${number}/${number}`} scope - Optional scope to run the task under. If undefined, defaults to local.
+ *                                                A quorum scope `quorum:<min_agree>/<of>` runs the task on `of` nodes, and only
+ *                                                returns the response if at least `min_agree` of them agreed on it. `min_agree`
+ *                                                must be more than half of `of`.
+ * @param {{ timeout?: number }} options - Optional time in milliseconds after which the responses are no longer
+ *                                         useful. If undefined, the node waits for its default timeout.
  * @returns {Promise<Uint8Array>} - Raw response body from the service
  */
-const runTask = async (service, body, scope = "local") => {
+const runTask = async (service, body, scope = "local", { timeout = 0 } = {}) => {
   // TODO: move this encoding to rust
   if (!ArrayBuffer.isView(body)) {
     let encoder = new TextEncoder();
@@ -26,7 +31,7 @@ const runTask = async (service, body, scope = "local") => {
       body = encoder.encode(JSON.stringify(body));;
     }
   }
-  return await ops.run_task(service, body, scope)
+  return await ops.run_task(service, body, scope, timeout)
 };
 
 /** Fetch some blake3 content
@@ -69,6 +74,51 @@ const queryClientBandwidthBalance = async (account) => {
   return BigInt(balance, 10);
 };
 
+/** Key-value storage of the function, shared by every invocation of the same function on a node.
+ * Entries are only visible to the function that wrote them.
+ */
+const kv = {
+  /** Get the value of a key
+   * @param {string} key - Key to get
+   * @returns {Promise<Uint8Array | null>} The value, or null if the key does not exist or expired
+   */
+  get: async (key) => await ops.kv_get(key),
+
+  /** Set the value of a key
+   * @param {string} key - Key to set
+   * @param {ArrayBufferLike | string | any} value - Value to set. Buffers are stored directly,
+   *                                                 strings are encoded, and anything else is encoded as json.
+   * @param {{ ttl?: number, replicate?: boolean }} options - Optional time to live in milliseconds, and whether
+   *                                                          to replicate the write to the other nodes in the cluster.
+   * @returns {Promise<void>}
+   */
+  put: async (key, value, { ttl = 0, replicate = false } = {}) => {
+    if (!ArrayBuffer.isView(value)) {
+      let encoder = new TextEncoder();
+      if (typeof value == "string") {
+        value = encoder.encode(value);
+      } else {
+        value = encoder.encode(JSON.stringify(value));
+      }
+    }
+    await ops.kv_put(key, value, ttl, replicate);
+  },
+
+  /** Delete a key
+   * @param {string} key - Key to delete
+   * @param {{ replicate?: boolean }} options - Whether to replicate the delete to the other nodes in the cluster.
+   * @returns {Promise<void>}
+   */
+  delete: async (key, { replicate = false } = {}) => await ops.kv_delete(key, replicate),
+
+  /** List the entries with a given key prefix, ordered by key
+   * @param {string} prefix - Prefix of the keys to list
+   * @param {number} limit - Maximum number of entries to return
+   * @returns {Promise<{ key: string, value: Uint8Array }[]>}
+   */
+  list: async (prefix = "", limit = 100) => await ops.kv_list(prefix, limit),
+};
+
 /** Handle to blockstore content.
  * Utility for traversing the proof and reading blocks from the blockstore.
  * @property {Uint8Array} proof - Blake3 proof of the content
@@ -103,6 +153,20 @@ class ContentHandle {
   * Fleek API namespace
   */
 export const Fleek = {
+  /** Public configuration values from the manifest of the deployment, or null if the function
+   * was not deployed with a manifest.
+   * @type {any}
+   */
+  get config() {
+    return ops.op_deployment_config();
+  },
+  /** Secrets of the function, decrypted from the secrets envelope of the request. Empty if the
+   * request has no envelope.
+   * @type {Readonly<Record<string, string>>}
+   */
+  get env() {
+    return Object.freeze(ops.op_env());
+  },
   ContentHandle,
   ServiceId,
   runTask,
@@ -111,4 +175,5 @@ export const Fleek = {
   loadContent,
   queryClientFlkBalance,
   queryClientBandwidthBalance,
+  kv,
 };
@@ -51,6 +51,7 @@ import { loadWebGPU } from "ext:deno_webgpu/00_init.js";
 import * as webgpuSurface from "ext:deno_webgpu/02_surface.js";
 
 import { Fleek } from "ext:fleek/fleek.js";
+import { Worker } from "ext:fleek/worker.js";
 
 // TODO:
 // structuredClone
@@ -240,6 +241,7 @@ const globalContext = {
   URLSearchParams: propNonEnumerable(URLSearchParams),
   MessageChannel: propNonEnumerable(MessageChannel),
   MessagePort: propNonEnumerable(MessagePort),
+  Worker: propNonEnumerable(Worker),
 
   // Fetch apis
   Headers: propNonEnumerable(headers.Headers),
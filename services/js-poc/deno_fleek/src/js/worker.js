@@ -0,0 +1,206 @@
+import { core } from "ext:core/mod.js";
+import * as webidl from "ext:deno_webidl/00_webidl.js";
+import { URL } from "ext:deno_url/00_url.js";
+import {
+  defineEventHandler,
+  ErrorEvent,
+  EventTarget,
+  MessageEvent,
+  setEventTargetData,
+} from "ext:deno_web/02_event.js";
+import { getLocationHref } from "ext:deno_web/12_location.js";
+import {
+  deserializeJsMessageData,
+  MessagePortPrototype,
+  serializeJsMessageData,
+} from "ext:deno_web/13_message_port.js";
+const { ops } = core;
+
+/** Get the transferables out of the options of `postMessage` */
+const getTransferables = (transferOrOptions) => {
+  if (Array.isArray(transferOrOptions)) {
+    return transferOrOptions;
+  }
+  return transferOrOptions?.transfer ?? [];
+};
+
+/** Turn a message from the other side into a `MessageEvent` */
+const messageEvent = (data) => {
+  const [message, transferables] = deserializeJsMessageData(data);
+  return new MessageEvent("message", {
+    cancelable: false,
+    data: message,
+    ports: transferables.filter((t) =>
+      Object.prototype.isPrototypeOf.call(MessagePortPrototype, t)
+    ),
+  });
+};
+
+/** Web worker, running a module in its own isolate.
+ *  Workers are terminated when the request that created them ends.
+ */
+class Worker extends EventTarget {
+  #id = 0;
+  #name = "";
+  /** @type {"RUNNING" | "CLOSED" | "TERMINATED"} */
+  #status = "RUNNING";
+
+  /**
+   * @param {string | URL} specifier - Url of the worker module, resolved against the location
+   * @param {{ type?: "module", name?: string }} options
+   */
+  constructor(specifier, options = {}) {
+    super();
+    specifier = String(specifier);
+    const { name = "", type = "classic" } = options;
+    this.#name = name;
+
+    const href = getLocationHref();
+    this.#id = ops.op_create_worker({
+      hasSourceCode: false,
+      sourceCode: "",
+      name,
+      permissions: null,
+      specifier: new URL(specifier, href).href,
+      workerType: type,
+    }, undefined);
+
+    this.#pollControl();
+    this.#pollMessages();
+  }
+
+  #handleError(e) {
+    const event = new ErrorEvent("error", {
+      cancelable: true,
+      message: e.message,
+      lineno: e.lineNumber ? e.lineNumber : undefined,
+      colno: e.columnNumber ? e.columnNumber : undefined,
+      filename: e.fileName,
+      error: null,
+    });
+    this.dispatchEvent(event);
+    if (!event.defaultPrevented) {
+      console.error(`Uncaught error in worker ${this.#name}: ${e.message}`);
+    }
+  }
+
+  #pollControl = async () => {
+    while (this.#status === "RUNNING") {
+      const { 0: type, 1: data } = await ops.op_host_recv_ctrl(this.#id);
+      if (this.#status === "TERMINATED") {
+        return;
+      }
+
+      switch (type) {
+        // Terminal error
+        case 1:
+          this.#status = "CLOSED";
+          this.#handleError(data);
+          return;
+        // Error
+        case 2:
+          this.#handleError(data);
+          break;
+        // Close
+        case 3:
+          this.#status = "CLOSED";
+          return;
+      }
+    }
+  };
+
+  #pollMessages = async () => {
+    while (this.#status !== "TERMINATED") {
+      const data = await ops.op_host_recv_message(this.#id);
+      if (this.#status === "TERMINATED" || data === null) {
+        return;
+      }
+
+      let event;
+      try {
+        event = messageEvent(data);
+      } catch (e) {
+        this.dispatchEvent(new MessageEvent("messageerror", { data: e }));
+        continue;
+      }
+      this.dispatchEvent(event);
+    }
+  };
+
+  /** Send a message to the worker, the message is cloned with the structured clone algorithm */
+  postMessage(message, transferOrOptions = {}) {
+    const prefix = "Failed to execute 'postMessage' on 'Worker'";
+    webidl.requiredArguments(arguments.length, 1, prefix);
+    const data = serializeJsMessageData(
+      message,
+      getTransferables(transferOrOptions),
+    );
+    if (this.#status === "RUNNING") {
+      ops.op_host_post_message(this.#id, data);
+    }
+  }
+
+  /** Stop the worker immediately */
+  terminate() {
+    if (this.#status !== "TERMINATED") {
+      this.#status = "TERMINATED";
+      ops.op_host_terminate_worker(this.#id);
+    }
+  }
+}
+
+defineEventHandler(Worker.prototype, "error");
+defineEventHandler(Worker.prototype, "message");
+defineEventHandler(Worker.prototype, "messageerror");
+
+/** Turn the global scope into a dedicated worker scope.
+ *  Called by the runtime after bootstrapping a worker.
+ */
+const bootstrapWorker = () => {
+  Object.setPrototypeOf(globalThis, EventTarget.prototype);
+  setEventTargetData(globalThis);
+  defineEventHandler(globalThis, "message");
+  defineEventHandler(globalThis, "messageerror");
+
+  const name = ops.op_worker_get_name();
+  Object.defineProperties(globalThis, {
+    name: { value: name, configurable: true, enumerable: true },
+    postMessage: {
+      value: (message, transferOrOptions = {}) => {
+        const data = serializeJsMessageData(
+          message,
+          getTransferables(transferOrOptions),
+        );
+        ops.op_worker_post_message(data);
+      },
+      configurable: true,
+      writable: true,
+    },
+    close: {
+      value: () => ops.op_worker_close(),
+      configurable: true,
+      writable: true,
+    },
+  });
+
+  // Dispatch messages from the parent until it goes away
+  (async () => {
+    while (true) {
+      const data = await ops.op_worker_recv_message();
+      if (data === null) {
+        return;
+      }
+
+      let event;
+      try {
+        event = messageEvent(data);
+      } catch (e) {
+        globalThis.dispatchEvent(new MessageEvent("messageerror", { data: e }));
+        continue;
+      }
+      globalThis.dispatchEvent(event);
+    }
+  })();
+};
+
+export { bootstrapWorker, Worker };
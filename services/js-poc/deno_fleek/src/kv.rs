@@ -0,0 +1,499 @@
+//! Key-value storage for javascript functions.
+//!
+//! Every function gets its own namespace, identified by the blake3 hash of the function. A
+//! namespace is kept in memory while it's in use, and every change is appended to a log file in
+//! the data directory of the service. The log is compacted once it grows much larger than the
+//! live entries of the namespace.
+//!
+//! All the methods of the store block on disk IO, and must not be called from the event loop of
+//! an isolate, see [`with_store`].
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Maximum size of a key in bytes.
+pub const MAX_KEY_SIZE: usize = 512;
+/// Maximum size of a value in bytes.
+pub const MAX_VALUE_SIZE: usize = 1 << 20;
+/// Maximum size of all the keys and values of a single namespace in bytes.
+pub const NAMESPACE_QUOTA: usize = 16 << 20;
+/// Maximum number of namespaces kept in memory.
+pub const MAX_RESIDENT_NAMESPACES: usize = 64;
+/// Size in bytes a log can always grow to before it is compacted.
+const MIN_COMPACTION_SIZE: u64 = 1 << 20;
+
+static STORE: OnceLock<KvStore> = OnceLock::new();
+
+/// Returns the store of the service, backed by the data directory.
+pub fn store() -> &'static KvStore {
+    STORE.get_or_init(|| {
+        KvStore::new(
+            fn_sdk::ipc::data_path().join("kv"),
+            NAMESPACE_QUOTA,
+            MAX_RESIDENT_NAMESPACES,
+        )
+    })
+}
+
+/// Run an operation on the store of the service on the blocking thread pool.
+pub async fn with_store<T, F>(f: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&'static KvStore) -> Result<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || f(store())).await?
+}
+
+/// Namespace of a function, used to isolate the storage of different functions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct KvNamespace(#[serde(with = "hex_bytes")] pub [u8; 32]);
+
+/// A write that is replicated to the other nodes of the cluster, sent as a task to the
+/// javascript service.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KvReplication {
+    pub namespace: KvNamespace,
+    pub write: KvWrite,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KvWrite {
+    Put {
+        key: String,
+        value: Vec<u8>,
+        expires: Option<u64>,
+    },
+    Delete {
+        key: String,
+    },
+}
+
+impl KvWrite {
+    /// Append the write to a log as a length prefixed record.
+    fn encode(&self, buffer: &mut Vec<u8>) -> Result<()> {
+        let record = bincode::serialize(self)?;
+        buffer.extend_from_slice(&(record.len() as u32).to_be_bytes());
+        buffer.extend_from_slice(&record);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    value: Vec<u8>,
+    /// Unix timestamp in milliseconds after which the entry is gone.
+    expires: Option<u64>,
+}
+
+impl Entry {
+    fn is_expired(&self, now: u64) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+}
+
+#[derive(Default)]
+struct Namespace {
+    entries: BTreeMap<String, Entry>,
+    size: usize,
+    /// The log of the namespace, opened on the first write.
+    log: Option<File>,
+    /// Size of the log in bytes.
+    log_size: u64,
+    /// Size of the log when it was loaded or last compacted.
+    compacted_size: u64,
+}
+
+impl Namespace {
+    /// Load a namespace by replaying its log.
+    fn load(path: &Path) -> Result<Self> {
+        let mut namespace = Self::default();
+        let bytes = match std::fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(namespace),
+            Err(e) => return Err(e).context("failed to read kv namespace"),
+        };
+
+        let mut offset = 0;
+        while let Some(len) = bytes.get(offset..offset + 4) {
+            let end = offset + 4 + u32::from_be_bytes(len.try_into().unwrap()) as usize;
+            let Some(record) = bytes.get(offset + 4..end) else {
+                break;
+            };
+            let write = bincode::deserialize(record).context("corrupted kv namespace")?;
+            namespace.write(write);
+            offset = end;
+        }
+
+        // A crash in the middle of an append leaves a partial record at the end of the log.
+        if offset < bytes.len() {
+            OpenOptions::new()
+                .write(true)
+                .open(path)?
+                .set_len(offset as u64)?;
+        }
+
+        namespace.log_size = offset as u64;
+        namespace.compacted_size = namespace.log_size;
+        namespace.remove_expired(now());
+        Ok(namespace)
+    }
+
+    /// Apply a write to the entries in memory.
+    fn write(&mut self, write: KvWrite) {
+        let (key, entry) = match write {
+            KvWrite::Put {
+                key,
+                value,
+                expires,
+            } => {
+                self.size += key.len() + value.len();
+                (key, Some(Entry { value, expires }))
+            },
+            KvWrite::Delete { key } => (key, None),
+        };
+
+        let replaced = match entry {
+            Some(entry) => self.entries.insert(key.clone(), entry),
+            None => self.entries.remove(&key),
+        };
+        if let Some(replaced) = replaced {
+            self.size -= key.len() + replaced.value.len();
+        }
+    }
+
+    fn remove_expired(&mut self, now: u64) {
+        let size = &mut self.size;
+        self.entries.retain(|key, entry| {
+            let expired = entry.is_expired(now);
+            if expired {
+                *size -= key.len() + entry.value.len();
+            }
+            !expired
+        });
+    }
+
+    /// Append a write to the log.
+    fn append(&mut self, path: &Path, write: &KvWrite) -> Result<()> {
+        let mut buffer = Vec::new();
+        write.encode(&mut buffer)?;
+
+        let log = match &mut self.log {
+            Some(log) => log,
+            None => self
+                .log
+                .insert(OpenOptions::new().create(true).append(true).open(path)?),
+        };
+        if let Err(e) = log.write_all(&buffer) {
+            // Drop what was written of the record, so the next appends are not lost behind it.
+            let _ = log.set_len(self.log_size);
+            return Err(e.into());
+        }
+
+        self.log_size += buffer.len() as u64;
+        Ok(())
+    }
+
+    /// Rewrite the log with only the live entries.
+    fn compact(&mut self, path: &Path) -> Result<()> {
+        let mut buffer = Vec::new();
+        for (key, entry) in &self.entries {
+            KvWrite::Put {
+                key: key.clone(),
+                value: entry.value.clone(),
+                expires: entry.expires,
+            }
+            .encode(&mut buffer)?;
+        }
+
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, &buffer)?;
+        // Renaming is atomic, so a crash never leaves a partially written log behind.
+        std::fs::rename(tmp, path)?;
+
+        self.log = Some(OpenOptions::new().append(true).open(path)?);
+        self.log_size = buffer.len() as u64;
+        self.compacted_size = self.log_size;
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Resident {
+    namespaces: HashMap<KvNamespace, (Arc<Mutex<Namespace>>, u64)>,
+    /// Incremented on every access, used to find the least recently used namespace.
+    clock: u64,
+}
+
+impl Resident {
+    /// Drop the least recently used namespace that is not in use. Every write is already in the
+    /// log of its namespace, so nothing is lost.
+    fn evict(&mut self) {
+        let lru = self
+            .namespaces
+            .iter()
+            .filter(|(_, (namespace, _))| Arc::strong_count(namespace) == 1)
+            .min_by_key(|(_, (_, last_used))| *last_used)
+            .map(|(ns, _)| *ns);
+        if let Some(ns) = lru {
+            self.namespaces.remove(&ns);
+        }
+    }
+}
+
+pub struct KvStore {
+    root: PathBuf,
+    quota: usize,
+    max_resident: usize,
+    resident: Mutex<Resident>,
+}
+
+impl KvStore {
+    pub fn new(root: PathBuf, quota: usize, max_resident: usize) -> Self {
+        Self {
+            root,
+            quota,
+            max_resident,
+            resident: Default::default(),
+        }
+    }
+
+    pub fn get(&self, ns: &KvNamespace, key: &str) -> Result<Option<Vec<u8>>> {
+        let namespace = self.namespace(ns)?;
+        let namespace = namespace.lock().unwrap();
+        Ok(namespace
+            .entries
+            .get(key)
+            .filter(|entry| !entry.is_expired(now()))
+            .map(|entry| entry.value.clone()))
+    }
+
+    /// List the entries with the given key prefix, in the order of the keys.
+    pub fn list(
+        &self,
+        ns: &KvNamespace,
+        prefix: &str,
+        limit: usize,
+    ) -> Result<Vec<(String, Vec<u8>)>> {
+        let namespace = self.namespace(ns)?;
+        let namespace = namespace.lock().unwrap();
+        let now = now();
+        Ok(namespace
+            .entries
+            .range(prefix.to_string()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .filter(|(_, entry)| !entry.is_expired(now))
+            .take(limit)
+            .map(|(key, entry)| (key.clone(), entry.value.clone()))
+            .collect())
+    }
+
+    pub fn apply(&self, ns: &KvNamespace, write: KvWrite) -> Result<()> {
+        let namespace = self.namespace(ns)?;
+        let mut namespace = namespace.lock().unwrap();
+
+        if let KvWrite::Put { key, value, .. } = &write {
+            if key.len() > MAX_KEY_SIZE {
+                bail!("key exceeds the maximum size of {MAX_KEY_SIZE} bytes");
+            }
+            if value.len() > MAX_VALUE_SIZE {
+                bail!("value exceeds the maximum size of {MAX_VALUE_SIZE} bytes");
+            }
+
+            namespace.remove_expired(now());
+            let replaced = namespace
+                .entries
+                .get(key)
+                .map(|entry| key.len() + entry.value.len())
+                .unwrap_or_default();
+            if namespace.size - replaced + key.len() + value.len() > self.quota {
+                bail!("namespace quota of {} bytes exceeded", self.quota);
+            }
+        }
+
+        let path = self.path(ns);
+        namespace.append(&path, &write)?;
+        namespace.write(write);
+
+        // Compacting once the log doubled in size keeps the cost of a write constant on average.
+        if namespace.log_size > MIN_COMPACTION_SIZE.max(2 * namespace.compacted_size) {
+            namespace.compact(&path)?;
+        }
+        Ok(())
+    }
+
+    /// Get a namespace, loading it from disk if it's not in memory.
+    fn namespace(&self, ns: &KvNamespace) -> Result<Arc<Mutex<Namespace>>> {
+        let mut resident = self.resident.lock().unwrap();
+        resident.clock += 1;
+        let clock = resident.clock;
+        if let Some((namespace, last_used)) = resident.namespaces.get_mut(ns) {
+            *last_used = clock;
+            return Ok(namespace.clone());
+        }
+
+        std::fs::create_dir_all(&self.root)?;
+        let namespace = Arc::new(Mutex::new(Namespace::load(&self.path(ns))?));
+        if resident.namespaces.len() >= self.max_resident {
+            resident.evict();
+        }
+        resident.namespaces.insert(*ns, (namespace.clone(), clock));
+        Ok(namespace)
+    }
+
+    fn path(&self, ns: &KvNamespace) -> PathBuf {
+        self.root.join(format!("{}.log", hex::encode(ns.0)))
+    }
+}
+
+/// Returns the current unix timestamp in milliseconds.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+mod hex_bytes {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 32], D::Error> {
+        let string = String::deserialize(deserializer)?;
+        let mut bytes = [0; 32];
+        hex::decode_to_slice(string, &mut bytes).map_err(D::Error::custom)?;
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn put(key: &str, value: &[u8], expires: Option<u64>) -> KvWrite {
+        KvWrite::Put {
+            key: key.into(),
+            value: value.to_vec(),
+            expires,
+        }
+    }
+
+    #[test]
+    fn test_namespaces_are_isolated_and_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b) = (KvNamespace([0; 32]), KvNamespace([1; 32]));
+
+        let store = KvStore::new(dir.path().to_path_buf(), 1024, 16);
+        store.apply(&a, put("counter", b"1", None)).unwrap();
+        store.apply(&a, put("user/1", b"alice", None)).unwrap();
+        store.apply(&a, put("user/2", b"bob", None)).unwrap();
+        store.apply(&a, put("expired", b"x", Some(1))).unwrap();
+        assert_eq!(store.get(&b, "counter").unwrap(), None);
+
+        // Reload from disk
+        let store = KvStore::new(dir.path().to_path_buf(), 1024, 16);
+        assert_eq!(store.get(&a, "counter").unwrap(), Some(b"1".to_vec()));
+        assert_eq!(store.get(&a, "expired").unwrap(), None);
+        assert_eq!(
+            store.list(&a, "user/", 10).unwrap(),
+            vec![
+                ("user/1".to_string(), b"alice".to_vec()),
+                ("user/2".to_string(), b"bob".to_vec())
+            ]
+        );
+
+        store
+            .apply(
+                &a,
+                KvWrite::Delete {
+                    key: "counter".into(),
+                },
+            )
+            .unwrap();
+        assert_eq!(store.get(&a, "counter").unwrap(), None);
+    }
+
+    #[test]
+    fn test_namespace_quota() {
+        let dir = tempfile::tempdir().unwrap();
+        let ns = KvNamespace([0; 32]);
+
+        let store = KvStore::new(dir.path().to_path_buf(), 16, 16);
+        store.apply(&ns, put("a", &[0; 10], None)).unwrap();
+        assert!(store.apply(&ns, put("b", &[0; 10], None)).is_err());
+        // Overwriting a key only counts the difference
+        store.apply(&ns, put("a", &[0; 15], None)).unwrap();
+    }
+
+    #[test]
+    fn test_log_is_compacted_and_recovered() {
+        let dir = tempfile::tempdir().unwrap();
+        let ns = KvNamespace([0; 32]);
+        let path = dir.path().join(format!("{}.log", hex::encode(ns.0)));
+
+        let store = KvStore::new(dir.path().to_path_buf(), NAMESPACE_QUOTA, 16);
+        for i in 0..64u8 {
+            store
+                .apply(&ns, put("counter", &[i; 64 << 10], None))
+                .unwrap();
+        }
+        // Every write is appended, but the overwritten values do not pile up.
+        let size = std::fs::metadata(&path).unwrap().len();
+        assert!(
+            size < 2 * MIN_COMPACTION_SIZE,
+            "log of {size} bytes was not compacted"
+        );
+
+        // A partial record at the end of the log is dropped on load.
+        let mut log = OpenOptions::new().append(true).open(&path).unwrap();
+        log.write_all(&[0, 0, 1, 0, 42]).unwrap();
+        let store = KvStore::new(dir.path().to_path_buf(), NAMESPACE_QUOTA, 16);
+        assert_eq!(store.get(&ns, "counter").unwrap(), Some(vec![63; 64 << 10]));
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), size);
+
+        store.apply(&ns, put("other", b"1", None)).unwrap();
+        let store = KvStore::new(dir.path().to_path_buf(), NAMESPACE_QUOTA, 16);
+        assert_eq!(store.get(&ns, "other").unwrap(), Some(b"1".to_vec()));
+    }
+
+    #[test]
+    fn test_resident_namespaces_are_bounded() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = KvStore::new(dir.path().to_path_buf(), 1024, 2);
+        for i in 0..4 {
+            store
+                .apply(&KvNamespace([i; 32]), put("key", &[i], None))
+                .unwrap();
+        }
+        assert_eq!(store.resident.lock().unwrap().namespaces.len(), 2);
+
+        // Evicted namespaces are loaded back from their log.
+        for i in 0..4 {
+            assert_eq!(
+                store.get(&KvNamespace([i; 32]), "key").unwrap(),
+                Some(vec![i])
+            );
+        }
+
+        // A namespace that is in use is never evicted.
+        let held = store.namespace(&KvNamespace([0; 32])).unwrap();
+        store.get(&KvNamespace([1; 32]), "key").unwrap();
+        store.get(&KvNamespace([2; 32]), "key").unwrap();
+        assert!(Arc::ptr_eq(
+            &held,
+            &store.namespace(&KvNamespace([0; 32])).unwrap()
+        ));
+    }
+}
@@ -1,7 +1,12 @@
+pub mod deployment;
 mod extension;
+pub mod kv;
 mod ops;
 mod permissions;
+pub mod secrets;
 mod transpiler;
+pub mod usage;
+pub mod worker;
 
 pub use extension::fleek;
 pub use permissions::Permissions;
@@ -1,9 +1,11 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::ffi::c_void;
 use std::io::{Read, Seek};
 use std::ops::Deref;
 use std::rc::Rc;
 use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use anyhow::{anyhow, bail, Context};
 use arrayref::array_ref;
@@ -12,7 +14,7 @@
 use cid::Cid;
 use deno_core::error::{AnyError, JsError};
 use deno_core::url::Url;
-use deno_core::{op2, v8, ByteString, JsBuffer, OpState, ResourceId};
+use deno_core::{op2, v8, ByteString, JsBuffer, OpState, RcRef, ResourceId};
 use deno_permissions::ChildPermissionsArg;
 use deno_web::JsMessageData;
 use fleek_crypto::{ClientPublicKey, NodeSignature};
@@ -22,6 +24,12 @@
 use serde_json::json;
 use tracing::info;
 
+use crate::deployment::Deployment;
+use crate::kv::{self, KvNamespace, KvReplication, KvWrite};
+use crate::secrets::Secrets;
+use crate::usage::OpUsage;
+use crate::worker::{CreateWorker, WorkerHost, WorkerId, WorkerScope, MAX_WORKERS};
+
 #[op2(async)]
 #[serde]
 pub async fn run_task(
@@ -29,15 +37,30 @@ pub async fn run_task(
     service: u32,
     #[buffer(copy)] body: Vec<u8>,
     #[string] scope: String,
+    #[number] timeout: u64,
 ) -> anyhow::Result<Task> {
     let scope = TaskScope::from_str(&scope)?;
+    let deadline = (timeout > 0).then(|| {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        now.as_millis() as u64 + timeout
+    });
+
+    // Replicated kv writes are only ever sent by the runtime itself, otherwise a function
+    // could write to the namespace of any other function.
+    if Some(service) == fn_sdk::ipc::service_id()
+        && serde_json::from_slice::<KvReplication>(&body).is_ok()
+    {
+        bail!("invalid task payload");
+    }
 
     let depth = {
-        let state = state.borrow();
+        let mut state = state.borrow_mut();
+        OpUsage::record_subtask(&mut state);
         state.borrow::<TaskDepth>().0
     };
 
-    let (responses, signatures) = fn_sdk::api::run_task(depth + 1, scope, service, body).await;
+    let (responses, signatures) =
+        fn_sdk::api::run_task(depth + 1, scope, service, body, deadline).await;
 
     Ok(Task {
         responses,
@@ -61,7 +84,10 @@ pub async fn fetch_blake3(#[buffer(copy)] hash: Vec<u8>) -> anyhow::Result<bool>
 
 #[op2(async)]
 #[buffer]
-pub async fn fetch_from_origin(#[string] raw_url: String) -> anyhow::Result<Box<[u8]>> {
+pub async fn fetch_from_origin(
+    state: Rc<RefCell<OpState>>,
+    #[string] raw_url: String,
+) -> anyhow::Result<Box<[u8]>> {
     let url = Url::parse(&raw_url).context("failed to parse origin url")?;
     let (origin, uri) = match url.scheme() {
         // ipfs://bafy...
@@ -88,13 +114,20 @@ pub async fn fetch_from_origin(#[string] raw_url: String) -> anyhow::Result<Box<
     let Some(hash) = fn_sdk::api::fetch_from_origin(origin, uri).await else {
         bail!("failed to fetch {raw_url} from origin");
     };
+    OpUsage::record_content(&mut state.borrow_mut(), hash);
 
     Ok(hash.to_vec().into_boxed_slice())
 }
 
 #[op2(async)]
 #[buffer]
-pub async fn load_content(#[buffer(copy)] hash: Vec<u8>) -> anyhow::Result<Box<[u8]>> {
+pub async fn load_content(
+    state: Rc<RefCell<OpState>>,
+    #[buffer(copy)] hash: Vec<u8>,
+) -> anyhow::Result<Box<[u8]>> {
+    if hash.len() != 32 {
+        return Err(anyhow!("blake3 hash must be 32 bytes"));
+    }
     let path = header_file(array_ref![hash, 0, 32]);
 
     // TODO: store proof on rust side, and only give javascript an id to reference the handle
@@ -102,6 +135,7 @@ pub async fn load_content(#[buffer(copy)] hash: Vec<u8>) -> anyhow::Result<Box<[
     file.seek(std::io::SeekFrom::Start(POSITION_START_HASHES as u64))?;
     let mut buffer = vec![];
     let _ = file.read_to_end(&mut buffer)?;
+    OpUsage::record_content(&mut state.borrow_mut(), *array_ref![hash, 0, 32]);
 
     Ok(buffer.to_vec().into_boxed_slice())
 }
@@ -150,6 +184,108 @@ pub async fn query_client_bandwidth_balance(
     )
 }
 
+#[op2]
+#[serde]
+pub fn op_deployment_config(state: &mut OpState) -> serde_json::Value {
+    state.borrow::<Deployment>().config.clone()
+}
+
+#[op2]
+#[serde]
+pub fn op_env(state: &mut OpState) -> HashMap<String, String> {
+    state.borrow::<Secrets>().0.clone()
+}
+
+#[op2(async)]
+#[buffer]
+pub async fn kv_get(
+    state: Rc<RefCell<OpState>>,
+    #[string] key: String,
+) -> anyhow::Result<Option<Vec<u8>>> {
+    let namespace = *state.borrow().borrow::<KvNamespace>();
+    kv::with_store(move |store| store.get(&namespace, &key)).await
+}
+
+#[op2(async)]
+pub async fn kv_put(
+    state: Rc<RefCell<OpState>>,
+    #[string] key: String,
+    #[buffer(copy)] value: Vec<u8>,
+    #[number] ttl: u64,
+    replicate: bool,
+) -> anyhow::Result<()> {
+    let expires = (ttl > 0).then(|| kv::now() + ttl);
+    let write = KvWrite::Put {
+        key,
+        value,
+        expires,
+    };
+    apply_kv_write(state, write, replicate).await
+}
+
+#[op2(async)]
+pub async fn kv_delete(
+    state: Rc<RefCell<OpState>>,
+    #[string] key: String,
+    replicate: bool,
+) -> anyhow::Result<()> {
+    apply_kv_write(state, KvWrite::Delete { key }, replicate).await
+}
+
+#[op2(async)]
+#[serde]
+pub async fn kv_list(
+    state: Rc<RefCell<OpState>>,
+    #[string] prefix: String,
+    #[smi] limit: u32,
+) -> anyhow::Result<Vec<KvEntry>> {
+    let namespace = *state.borrow().borrow::<KvNamespace>();
+    let entries =
+        kv::with_store(move |store| store.list(&namespace, &prefix, limit as usize)).await?;
+    Ok(entries
+        .into_iter()
+        .map(|(key, value)| KvEntry {
+            key,
+            value: value.into(),
+        })
+        .collect())
+}
+
+async fn apply_kv_write(
+    state: Rc<RefCell<OpState>>,
+    write: KvWrite,
+    replicate: bool,
+) -> anyhow::Result<()> {
+    let (namespace, depth) = {
+        let state = state.borrow();
+        (
+            *state.borrow::<KvNamespace>(),
+            state.borrow::<TaskDepth>().0,
+        )
+    };
+
+    let local = write.clone();
+    kv::with_store(move |store| store.apply(&namespace, local)).await?;
+
+    if replicate {
+        let service = fn_sdk::ipc::service_id().context("unknown javascript service id")?;
+        let body = serde_json::to_vec(&KvReplication { namespace, write })?;
+        let (responses, _) =
+            fn_sdk::api::run_task(depth + 1, TaskScope::Cluster, service, body, None).await;
+        if responses.is_empty() {
+            bail!("failed to replicate the write to the cluster");
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(serde::Serialize)]
+pub struct KvEntry {
+    key: String,
+    value: deno_core::ToJsBuffer,
+}
+
 #[derive(serde::Serialize)]
 pub struct Task {
     responses: Vec<Vec<u8>>,
@@ -207,13 +343,11 @@ pub fn op_bootstrap_color_depth(_state: &mut OpState) -> i32 {
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateWorkerArgs {
-    _has_source_code: bool,
-    _name: Option<String>,
-    _permissions: Option<ChildPermissionsArg>,
-    _source_code: String,
-    _specifier: String,
-    _worker_type: WebWorkerType,
-    _close_on_idle: bool,
+    has_source_code: bool,
+    name: Option<String>,
+    permissions: Option<ChildPermissionsArg>,
+    specifier: String,
+    worker_type: WebWorkerType,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -223,49 +357,119 @@ pub enum WebWorkerType {
     Module,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
-pub struct WorkerId(u32);
-
 #[op2]
 #[serde]
 pub fn op_create_worker(
-    _state: &mut OpState,
-    #[serde] _args: CreateWorkerArgs,
+    state: &mut OpState,
+    #[serde] args: CreateWorkerArgs,
     #[serde] _maybe_worker_metadata: Option<JsMessageData>,
 ) -> anyhow::Result<WorkerId, AnyError> {
-    unimplemented!()
+    let Some(create) = state.try_borrow::<CreateWorker>().cloned() else {
+        bail!("workers can not be created from a worker");
+    };
+    if args.has_source_code {
+        bail!("workers from source code are not supported");
+    }
+    if args.permissions.is_some() {
+        bail!("worker permissions are not supported");
+    }
+    if args.worker_type == WebWorkerType::Classic {
+        bail!("classic workers are not supported, use {{ type: \"module\" }}");
+    }
+
+    let specifier = Url::parse(&args.specifier).context("invalid worker specifier")?;
+    let name = args.name.unwrap_or_default();
+    WorkerHost::create(state, &create, specifier, name)
+        .with_context(|| format!("a request can create at most {MAX_WORKERS} workers"))
 }
 
 #[op2]
 pub fn op_host_post_message(
-    _state: &mut OpState,
-    #[serde] _id: WorkerId,
-    #[serde] _data: JsMessageData,
+    state: &mut OpState,
+    #[serde] id: WorkerId,
+    #[serde] data: JsMessageData,
 ) -> anyhow::Result<(), AnyError> {
-    unimplemented!()
+    // Messages to terminated workers are dropped
+    if let Some(worker) = WorkerHost::get(state, id) {
+        worker.port.send(state, data)?;
+    }
+    Ok(())
 }
 
 #[op2(async)]
 #[serde]
 pub async fn op_host_recv_ctrl(
-    _state: Rc<RefCell<OpState>>,
-    #[serde] _id: WorkerId,
+    state: Rc<RefCell<OpState>>,
+    #[serde] id: WorkerId,
 ) -> anyhow::Result<WorkerControlEvent, AnyError> {
-    unimplemented!()
+    let Some(worker) = WorkerHost::get(&state.borrow(), id) else {
+        return Ok(WorkerControlEvent::Close);
+    };
+    let mut ctrl = RcRef::map(worker, |worker| &worker.ctrl).borrow_mut().await;
+    Ok(ctrl.recv().await.unwrap_or(WorkerControlEvent::Close))
 }
 
 #[op2(async)]
 #[serde]
 pub async fn op_host_recv_message(
-    _state: Rc<RefCell<OpState>>,
-    #[serde] _id: WorkerId,
+    state: Rc<RefCell<OpState>>,
+    #[serde] id: WorkerId,
 ) -> anyhow::Result<Option<JsMessageData>, AnyError> {
-    unimplemented!()
+    let Some(worker) = WorkerHost::get(&state.borrow(), id) else {
+        return Ok(None);
+    };
+    worker.port.recv(state).await
 }
 
 #[op2]
-pub fn op_host_terminate_worker(_state: &mut OpState, #[serde] _id: WorkerId) {
-    unimplemented!()
+pub fn op_host_terminate_worker(state: &mut OpState, #[serde] id: WorkerId) {
+    if let Some(worker) = WorkerHost::remove(state, id) {
+        worker.terminate();
+    }
+}
+
+#[op2]
+#[string]
+pub fn op_worker_get_name(state: &mut OpState) -> anyhow::Result<String, AnyError> {
+    let scope = state
+        .try_borrow::<WorkerScope>()
+        .context("not running in a worker")?;
+    Ok(scope.name.clone())
+}
+
+#[op2]
+pub fn op_worker_post_message(
+    state: &mut OpState,
+    #[serde] data: JsMessageData,
+) -> anyhow::Result<(), AnyError> {
+    let port = state
+        .try_borrow::<WorkerScope>()
+        .context("not running in a worker")?
+        .port
+        .clone();
+    port.send(state, data)
+}
+
+#[op2(async)]
+#[serde]
+pub async fn op_worker_recv_message(
+    state: Rc<RefCell<OpState>>,
+) -> anyhow::Result<Option<JsMessageData>, AnyError> {
+    let port = state
+        .borrow()
+        .try_borrow::<WorkerScope>()
+        .context("not running in a worker")?
+        .port
+        .clone();
+    port.recv(state).await
+}
+
+#[op2(fast)]
+pub fn op_worker_close(state: &mut OpState) {
+    if let Some(scope) = state.try_borrow::<WorkerScope>() {
+        let _ = scope.ctrl.send(WorkerControlEvent::Close);
+        scope.terminated.notify_one();
+    }
 }
 
 #[op2(reentrant)]
@@ -283,7 +487,6 @@ pub fn op_napi_open<'scope>(
 
 /// Events that are sent to host from child
 /// worker.
-#[allow(unused)]
 pub enum WorkerControlEvent {
     Error(AnyError),
     TerminalError(AnyError),
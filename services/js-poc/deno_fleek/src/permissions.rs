@@ -1,7 +1,7 @@
 use std::borrow::Cow;
 use std::path::{Path, PathBuf};
 
-use anyhow::anyhow;
+use anyhow::{anyhow, bail};
 use deno_core::error::AnyError;
 use deno_core::url::Url;
 use deno_fetch::FetchPermissions;
@@ -14,19 +14,59 @@
 
 pub const FETCH_BLACKLIST: &[&str] = &["localhost", "127.0.0.1", "::1"];
 
-pub struct Permissions {}
+#[derive(Default)]
+pub struct Permissions {
+    /// Hosts allowed by the deployment of the function, either exact hosts or wildcards like
+    /// `*.example.com`. Every host that isn't blacklisted is allowed if there is no list.
+    allowed_hosts: Option<Vec<String>>,
+}
 
 impl Permissions {
+    pub fn new(allowed_hosts: Option<Vec<String>>) -> Self {
+        let allowed_hosts = allowed_hosts.map(|hosts| {
+            hosts
+                .into_iter()
+                .map(|host| host.to_ascii_lowercase())
+                .collect()
+        });
+        Self { allowed_hosts }
+    }
+
+    fn check_host(&self, host: &str) -> anyhow::Result<(), AnyError> {
+        if FETCH_BLACKLIST.contains(&host) {
+            bail!("{host} is blacklisted");
+        }
+        if let Some(allowed_hosts) = &self.allowed_hosts {
+            let host = host.to_ascii_lowercase();
+            if !allowed_hosts
+                .iter()
+                .any(|pattern| host_matches(pattern, &host))
+            {
+                bail!("{host} is not an allowed outbound host");
+            }
+        }
+        Ok(())
+    }
+
     fn check_net_url(&mut self, url: &Url, _api_name: &str) -> anyhow::Result<(), AnyError> {
         if let Some(host) = url.host_str() {
-            if FETCH_BLACKLIST.contains(&host) {
-                return Err(anyhow!("{host} is blacklisted"));
-            }
+            self.check_host(host)?;
         }
         Ok(())
     }
 }
 
+/// Returns true if the host matches the pattern, which is either a host or a wildcard for the
+/// subdomains of a host, like `*.example.com`.
+fn host_matches(pattern: &str, host: &str) -> bool {
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host
+            .strip_suffix(domain)
+            .is_some_and(|subdomain| subdomain.len() > 1 && subdomain.ends_with('.')),
+        None => pattern == host,
+    }
+}
+
 impl TimersPermission for Permissions {
     fn allow_hrtime(&mut self) -> bool {
         false
@@ -55,11 +95,7 @@ fn check_net<T: AsRef<str>>(
         host: &(T, Option<u16>),
         _api_name: &str,
     ) -> anyhow::Result<(), AnyError> {
-        if FETCH_BLACKLIST.contains(&host.0.as_ref()) {
-            Err(anyhow!("{} is blacklisted", host.0.as_ref()))
-        } else {
-            Ok(())
-        }
+        self.check_host(host.0.as_ref())
     }
     fn check_read(&mut self, _p: &str, _api_name: &str) -> anyhow::Result<PathBuf, AnyError> {
         // Disable reading file descriptors
@@ -179,3 +215,25 @@ fn check_write_with_api_name(
         Err(anyhow!("node permission operation is not allowed"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowed_hosts() {
+        let mut permissions = Permissions::default();
+        assert!(permissions.check_host("example.com").is_ok());
+        assert!(permissions.check_host("localhost").is_err());
+
+        permissions = Permissions::new(Some(vec![
+            "API.example.com".to_string(),
+            "*.example.org".to_string(),
+        ]));
+        assert!(permissions.check_host("api.example.com").is_ok());
+        assert!(permissions.check_host("example.com").is_err());
+        assert!(permissions.check_host("cdn.example.org").is_ok());
+        assert!(permissions.check_host("example.org").is_err());
+        assert!(permissions.check_host("badexample.org").is_err());
+    }
+}
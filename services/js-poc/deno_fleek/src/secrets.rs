@@ -0,0 +1,14 @@
+use std::collections::HashMap;
+use std::fmt;
+
+/// Secrets of the function, decrypted from the secrets envelope of the request and exposed to
+/// the function as `Fleek.env`.
+#[derive(Clone, Default)]
+pub struct Secrets(pub HashMap<String, String>);
+
+impl fmt::Debug for Secrets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Only the names of the secrets can show up in logs
+        f.debug_set().entries(self.0.keys()).finish()
+    }
+}
@@ -0,0 +1,54 @@
+//! Resources used by the ops of a function, which the op metrics of the runtime don't capture.
+
+use std::collections::HashSet;
+use std::io::{Read, Seek};
+
+use anyhow::Result;
+use b3fs::bucket::POSITION_START_HASHES;
+use b3fs::collections::HashTree;
+use deno_core::OpState;
+use fn_sdk::blockstore::{block_file, header_file};
+use tracing::warn;
+
+/// Usage of the ops of a function, kept in the op state of the runtime.
+#[derive(Debug, Default)]
+pub struct OpUsage {
+    /// Size of the content fetched or loaded by the function. Every content is only counted once.
+    pub fetched_bytes: u64,
+    /// Number of tasks spawned by the function.
+    pub subtasks: u64,
+    fetched: HashSet<[u8; 32]>,
+}
+
+impl OpUsage {
+    /// Count the size of the content with the given hash, unless it was counted already.
+    pub(crate) fn record_content(state: &mut OpState, hash: [u8; 32]) {
+        let usage = state.borrow_mut::<Self>();
+        if !usage.fetched.insert(hash) {
+            return;
+        }
+        match content_size(&hash) {
+            Ok(size) => usage.fetched_bytes += size,
+            Err(e) => warn!("Failed to get the size of {}: {e}", hex::encode(hash)),
+        }
+    }
+
+    pub(crate) fn record_subtask(state: &mut OpState) {
+        state.borrow_mut::<Self>().subtasks += 1;
+    }
+}
+
+/// Returns the size of a content in the blockstore.
+fn content_size(hash: &[u8; 32]) -> Result<u64> {
+    let mut file = std::fs::File::open(header_file(hash))?;
+    file.seek(std::io::SeekFrom::Start(POSITION_START_HASHES as u64))?;
+    let mut buffer = vec![];
+    file.read_to_end(&mut buffer)?;
+
+    let tree = HashTree::try_from(buffer.as_slice())?;
+    let mut size = 0;
+    for index in 0..tree.len() {
+        size += std::fs::metadata(block_file(tree.nth(index)))?.len();
+    }
+    Ok(size)
+}
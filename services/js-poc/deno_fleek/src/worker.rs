@@ -0,0 +1,199 @@
+//! Web workers for javascript functions.
+//!
+//! Workers are created by the service through [`CreateWorker`], which runs them in their own
+//! runtime on the same thread as the function that created them. Messages are passed through a
+//! pair of entangled message ports, like in the browser.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use deno_core::url::Url;
+use deno_core::{AsyncRefCell, OpState};
+use deno_web::MessagePort;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tokio::sync::Notify;
+
+pub use crate::ops::WorkerControlEvent;
+
+/// Maximum number of workers a single request can create.
+pub const MAX_WORKERS: usize = 8;
+
+/// Spawns the runtime of a worker, put in the op state by the service for every request.
+#[derive(Clone)]
+pub struct CreateWorker(pub Rc<dyn Fn(WorkerOptions)>);
+
+pub struct WorkerOptions {
+    /// Url of the main module of the worker.
+    pub specifier: Url,
+    /// State to put in the op state of the worker runtime.
+    pub scope: WorkerScope,
+    /// Report errors and the end of the worker to the function that created it.
+    pub ctrl: UnboundedSender<WorkerControlEvent>,
+    /// Notified once the worker is terminated, either by itself or by its parent.
+    pub terminated: Rc<Notify>,
+}
+
+/// The worker side of a worker, in the op state of the worker runtime.
+pub struct WorkerScope {
+    pub name: String,
+    pub(crate) port: Rc<MessagePort>,
+    pub(crate) ctrl: UnboundedSender<WorkerControlEvent>,
+    pub(crate) terminated: Rc<Notify>,
+}
+
+/// The parent side of a worker.
+pub(crate) struct WorkerHandle {
+    pub port: Rc<MessagePort>,
+    pub ctrl: AsyncRefCell<UnboundedReceiver<WorkerControlEvent>>,
+    terminated: Rc<Notify>,
+}
+
+impl WorkerHandle {
+    pub fn terminate(&self) {
+        self.terminated.notify_one();
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct WorkerId(u32);
+
+/// The workers created during the current request.
+#[derive(Default)]
+pub(crate) struct WorkerHost {
+    workers: HashMap<WorkerId, Rc<WorkerHandle>>,
+    created: usize,
+}
+
+impl WorkerHost {
+    pub fn get(state: &OpState, id: WorkerId) -> Option<Rc<WorkerHandle>> {
+        state.try_borrow::<Self>()?.workers.get(&id).cloned()
+    }
+
+    pub fn remove(state: &mut OpState, id: WorkerId) -> Option<Rc<WorkerHandle>> {
+        state.try_borrow_mut::<Self>()?.workers.remove(&id)
+    }
+
+    /// Create a worker, returning `None` once the request created too many workers.
+    pub fn create(
+        state: &mut OpState,
+        create: &CreateWorker,
+        specifier: Url,
+        name: String,
+    ) -> Option<WorkerId> {
+        if !state.has::<Self>() {
+            state.put(Self::default());
+        }
+        let host = state.borrow_mut::<Self>();
+        if host.created >= MAX_WORKERS {
+            return None;
+        }
+        host.created += 1;
+        let id = WorkerId(host.created as u32);
+
+        let (port, worker_port) = deno_web::create_entangled_message_port();
+        let (ctrl_tx, ctrl_rx) = tokio::sync::mpsc::unbounded_channel();
+        let terminated = Rc::new(Notify::new());
+        host.workers.insert(
+            id,
+            Rc::new(WorkerHandle {
+                port: Rc::new(port),
+                ctrl: AsyncRefCell::new(ctrl_rx),
+                terminated: terminated.clone(),
+            }),
+        );
+
+        (create.0)(WorkerOptions {
+            specifier,
+            scope: WorkerScope {
+                name,
+                port: Rc::new(worker_port),
+                ctrl: ctrl_tx.clone(),
+                terminated: terminated.clone(),
+            },
+            ctrl: ctrl_tx,
+            terminated,
+        });
+        Some(id)
+    }
+}
+
+/// Terminate the workers created during a request, and reset the number of workers it created.
+pub fn terminate_workers(state: &mut OpState) {
+    if let Some(host) = state.try_take::<WorkerHost>() {
+        for worker in host.workers.values() {
+            worker.terminate();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::HashSet;
+    use std::time::Duration;
+
+    use deno_core::{JsRuntime, RuntimeOptions};
+
+    use super::*;
+
+    /// Records the options of the created workers, instead of running them.
+    fn recorder() -> (CreateWorker, Rc<RefCell<Vec<WorkerOptions>>>) {
+        let created = Rc::new(RefCell::new(Vec::new()));
+        let create = {
+            let created = created.clone();
+            CreateWorker(Rc::new(move |options| created.borrow_mut().push(options)))
+        };
+        (create, created)
+    }
+
+    #[test]
+    fn test_max_workers() {
+        let runtime = JsRuntime::new(RuntimeOptions::default());
+        let state = runtime.op_state();
+        let mut state = state.borrow_mut();
+        let (create, created) = recorder();
+        let url = Url::parse("blake3://worker").unwrap();
+
+        let ids = (0..MAX_WORKERS)
+            .map(|i| WorkerHost::create(&mut state, &create, url.clone(), format!("{i}")).unwrap())
+            .collect::<HashSet<_>>();
+        assert_eq!(ids.len(), MAX_WORKERS);
+        assert!(WorkerHost::create(&mut state, &create, url.clone(), "extra".into()).is_none());
+        assert_eq!(created.borrow().len(), MAX_WORKERS);
+
+        // Workers that are gone still count towards the limit of the request
+        let id = *ids.iter().next().unwrap();
+        assert!(WorkerHost::remove(&mut state, id).is_some());
+        assert!(WorkerHost::create(&mut state, &create, url.clone(), "extra".into()).is_none());
+
+        // The next request can create workers again
+        terminate_workers(&mut state);
+        assert!(WorkerHost::create(&mut state, &create, url, "next".into()).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_terminate_workers() {
+        let runtime = JsRuntime::new(RuntimeOptions::default());
+        let state = runtime.op_state();
+        let (create, created) = recorder();
+        let url = Url::parse("blake3://worker").unwrap();
+
+        let ids = (0..2)
+            .map(|_| {
+                WorkerHost::create(&mut state.borrow_mut(), &create, url.clone(), String::new())
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        terminate_workers(&mut state.borrow_mut());
+
+        for options in created.take() {
+            tokio::time::timeout(Duration::from_secs(1), options.terminated.notified())
+                .await
+                .expect("worker to be terminated");
+        }
+        for id in ids {
+            assert!(WorkerHost::get(&state.borrow(), id).is_none());
+        }
+    }
+}
@@ -34,6 +34,7 @@ async fn main() -> anyhow::Result<()> {
                 uri,
                 path: None,
                 param,
+                secrets: None,
             })
             .expect("failed to encode request")
             .into(),
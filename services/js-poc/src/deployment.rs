@@ -0,0 +1,227 @@
+//! Deployments of functions as full apps.
+//!
+//! A deployment is a b3fs directory with a [`MANIFEST_FILE`] at its root, which maps the paths
+//! of requests either to modules handling them or to static files of the directory. Modules of a
+//! deployment are loaded from its directory, so they can import each other with relative paths.
+
+use anyhow::{bail, Context, Result};
+use b3fs::entry::BorrowedLink;
+use deno_fleek::deployment::Deployment;
+use fleek_crypto::EthAddress;
+use fn_sdk::blockstore::{blockstore_root, ContentHandle};
+use fn_sdk::connection::Connection;
+use fn_sdk::header::HttpResponse;
+use fn_sdk::http_util::{respond, respond_with_http_response};
+use serde::Deserialize;
+
+use crate::runtime::code_cache::code_cache;
+
+/// Name of the manifest file at the root of a deployment.
+pub const MANIFEST_FILE: &str = "fleek.json";
+/// File served for static routes resolving to a directory.
+const INDEX_FILE: &str = "index.html";
+
+#[derive(Debug, Deserialize)]
+pub struct Manifest {
+    /// Module handling the requests that don't match any route.
+    #[serde(default)]
+    pub entry: Option<String>,
+    /// Routes of the deployment, matched in order.
+    #[serde(default)]
+    pub routes: Vec<Route>,
+    /// Account whose signature is required on the secrets envelopes of the deployment.
+    #[serde(default, rename = "secretsOwner")]
+    pub secrets_owner: Option<EthAddress>,
+    #[serde(flatten)]
+    pub deployment: Deployment,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Route {
+    /// Path of the request, or a prefix of paths when it ends with `/*`.
+    pub path: String,
+    #[serde(flatten)]
+    pub target: Target,
+}
+
+/// Where a request is routed to, as a path in the directory of the deployment.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Target {
+    /// Module whose main function handles the request.
+    Handler(String),
+    /// File served as is. Prefix routes serve the rest of the path from a directory.
+    Static(String),
+}
+
+impl Manifest {
+    /// Load the manifest of a deployment, or returns `None` if the content is not a directory.
+    pub async fn load(hash: [u8; 32]) -> Result<Option<Self>> {
+        // Modules in the source cache are files
+        if code_cache().has_source(&hash) {
+            return Ok(None);
+        }
+        if !fn_sdk::api::fetch_blake3(hash).await {
+            bail!("Failed to fetch {}", hex::encode(hash));
+        }
+        if !blockstore_root().await.get(&hash).await?.is_dir() {
+            return Ok(None);
+        }
+
+        let manifest = resolve(hash, MANIFEST_FILE)
+            .await
+            .with_context(|| format!("Missing {MANIFEST_FILE} in deployment"))?;
+        let manifest = ContentHandle::load(&manifest).await?.read_to_end().await?;
+        serde_json::from_slice(&manifest)
+            .with_context(|| format!("Invalid {MANIFEST_FILE}"))
+            .map(Some)
+    }
+
+    /// Returns the target of the first route matching the path, or the entry module if there
+    /// is none.
+    pub fn route(&self, path: &str) -> Option<Target> {
+        // Query parameters and fragments are not part of the route
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        for route in &self.routes {
+            let Some(prefix) = route.path.strip_suffix("/*") else {
+                if route.path == path {
+                    return Some(route.target.clone());
+                }
+                continue;
+            };
+
+            let Some(rest) = path.strip_prefix(prefix) else {
+                continue;
+            };
+            if !rest.is_empty() && !rest.starts_with('/') {
+                continue;
+            }
+            return Some(match &route.target {
+                Target::Static(dir) => Target::Static(format!(
+                    "{}/{}",
+                    dir.trim_end_matches('/'),
+                    rest.trim_start_matches('/')
+                )),
+                target => target.clone(),
+            });
+        }
+
+        self.entry.clone().map(Target::Handler)
+    }
+}
+
+/// Resolve a path in a directory of the blockstore to the hash of its content.
+pub async fn resolve(root: [u8; 32], path: &str) -> Result<[u8; 32]> {
+    let bucket = blockstore_root().await;
+    let mut hash = root;
+    for name in path.split('/').filter(|name| !name.is_empty()) {
+        let name = urlencoding::decode(name)?;
+        let Some(mut dir) = bucket.get(&hash).await?.into_dir() else {
+            bail!("{path} not found");
+        };
+        let entry = dir
+            .get_entry(name.as_bytes())
+            .await?
+            .with_context(|| format!("{path} not found"))?;
+        hash = match entry.link {
+            BorrowedLink::Content(content) => *content,
+            BorrowedLink::Path(_) => bail!("Symbolic links are not supported"),
+        };
+    }
+    Ok(hash)
+}
+
+/// Respond with a static file of a deployment. Directories are served with their index file.
+pub async fn serve_static(connection: &mut Connection, root: [u8; 32], path: &str) -> Result<()> {
+    let mut hash = resolve(root, path).await?;
+    let mut path = path.to_string();
+    if blockstore_root().await.get(&hash).await?.is_dir() {
+        hash = resolve(hash, INDEX_FILE).await?;
+        path = INDEX_FILE.to_string();
+    }
+    let body = ContentHandle::load(&hash).await?.read_to_end().await?;
+
+    if !connection.is_http_request() {
+        return respond(connection, &body).await;
+    }
+    let response = HttpResponse {
+        headers: Some(vec![(
+            "Content-Type".to_string(),
+            vec![content_type(&path).to_string()],
+        )]),
+        status: None,
+        body,
+    };
+    respond_with_http_response(connection, response).await
+}
+
+/// Guess the content type of a static file from its extension.
+fn content_type(path: &str) -> &'static str {
+    let extension = path
+        .rsplit_once('.')
+        .map(|(_, ext)| ext)
+        .unwrap_or_default();
+    match extension.to_ascii_lowercase().as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" => "application/json",
+        "txt" => "text/plain; charset=utf-8",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "wasm" => "application/wasm",
+        "woff2" => "font/woff2",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route() {
+        let manifest: Manifest = serde_json::from_value(serde_json::json!({
+            "entry": "index.js",
+            "routes": [
+                { "path": "/api/*", "handler": "api/main.js" },
+                { "path": "/assets/*", "static": "public/assets/" },
+                { "path": "/", "static": "public/index.html" },
+            ],
+            "config": { "title": "app" },
+            "allowedHosts": ["api.example.com"],
+        }))
+        .unwrap();
+
+        assert_eq!(
+            manifest.route("/api/users?id=1"),
+            Some(Target::Handler("api/main.js".into()))
+        );
+        assert_eq!(
+            manifest.route("/api"),
+            Some(Target::Handler("api/main.js".into()))
+        );
+        assert_eq!(
+            manifest.route("/assets/css/main.css"),
+            Some(Target::Static("public/assets/css/main.css".into()))
+        );
+        assert_eq!(
+            manifest.route("/"),
+            Some(Target::Static("public/index.html".into()))
+        );
+        // Prefixes only match whole segments
+        assert_eq!(
+            manifest.route("/apis"),
+            Some(Target::Handler("index.js".into()))
+        );
+        assert_eq!(manifest.deployment.config["title"], "app");
+        assert_eq!(
+            manifest.deployment.allowed_hosts,
+            Some(vec!["api.example.com".to_string()])
+        );
+    }
+}
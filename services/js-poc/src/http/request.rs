@@ -4,6 +4,7 @@
 use fn_sdk::header::HttpMethod;
 use serde_json::json;
 
+use crate::params::SECRETS_HEADER;
 use crate::stream::{Origin, Request};
 
 pub fn extract(
@@ -66,6 +67,10 @@ pub fn extract(
         },
     );
     let query = (!query.is_empty()).then_some(query);
+    let secrets = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(SECRETS_HEADER))
+        .map(|(_, hash)| hash.clone());
     let headers = (!headers.is_empty()).then_some(headers);
 
     let param = Some(json!({
@@ -80,6 +85,7 @@ pub fn extract(
         uri,
         path: Some(path),
         param,
+        secrets,
     })
 }
 
@@ -108,6 +114,7 @@ async fn test_extract_request() {
                     "query": null,
                     "body": null,
                 })),
+                secrets: None,
             })
         );
 
@@ -130,6 +137,7 @@ async fn test_extract_request() {
                     "query": null,
                     "body": "foobar",
                 })),
+                secrets: None,
             })
         );
 
@@ -152,6 +160,7 @@ async fn test_extract_request() {
                     "query": null,
                     "body": { "foo": "bar" },
                 })),
+                secrets: None,
             })
         );
 
@@ -174,6 +183,7 @@ async fn test_extract_request() {
                     "query": null,
                     "body": null,
                 })),
+                secrets: None,
             })
         );
 
@@ -196,6 +206,7 @@ async fn test_extract_request() {
                     "query": null,
                     "body": null,
                 })),
+                secrets: None,
             })
         );
 
@@ -218,7 +229,21 @@ async fn test_extract_request() {
                     "query": { "a": "4" },
                     "body": null,
                 })),
+                secrets: None,
             })
         );
+
+        // Request with a secrets envelope
+        assert_eq!(
+            extract(
+                &Url::parse("http://fleek/blake3/content-hash/").unwrap(),
+                &HashMap::from([("X-Fleek-Secrets".to_string(), "envelope-hash".to_string())]),
+                HttpMethod::GET,
+                vec![],
+            )
+            .unwrap()
+            .secrets,
+            Some("envelope-hash".to_string())
+        );
     }
 }
@@ -36,9 +36,7 @@ pub fn parse(scope: &mut v8::HandleScope, value: v8::Local<v8::Value>) -> Result
     };
 
     let headers = if let Some(headers_value) = get_property(scope, v8_object, "headers") {
-        let parsed_headers = &serde_v8::from_v8::<serde_json::Value>(scope, headers_value)
-            .context("failed to deserialize headers")?
-            .clone();
+        let parsed_headers = &headers_to_json(scope, headers_value)?;
 
         if parsed_headers.is_null() {
             None
@@ -56,6 +54,151 @@ pub fn parse(scope: &mut v8::HandleScope, value: v8::Local<v8::Value>) -> Result
     })
 }
 
+/// A response whose body is a `ReadableStream`, read chunk by chunk.
+pub struct StreamingResponse {
+    pub status: Option<u16>,
+    pub headers: Option<Vec<(String, Vec<String>)>>,
+    /// Reader of the body stream, locked to this response.
+    pub reader: v8::Global<v8::Object>,
+}
+
+/// Parse a `ReadableStream`, or an object whose body is one such as a `Response`, into a
+/// streaming response. Returns `None` if the value is not a stream.
+pub fn parse_streaming(
+    scope: &mut v8::HandleScope,
+    value: v8::Local<v8::Value>,
+) -> Result<Option<StreamingResponse>> {
+    let Some(v8_object) = value.is_object().then(|| value.to_object(scope)).flatten() else {
+        return Ok(None);
+    };
+
+    if let Some(reader) = get_reader(scope, v8_object)? {
+        return Ok(Some(StreamingResponse {
+            status: None,
+            headers: None,
+            reader,
+        }));
+    }
+
+    let Some(body) = get_property(scope, v8_object, "body")
+        .filter(|body| body.is_object())
+        .and_then(|body| body.to_object(scope))
+    else {
+        return Ok(None);
+    };
+    let Some(reader) = get_reader(scope, body)? else {
+        return Ok(None);
+    };
+
+    let status = match get_property(scope, v8_object, "status") {
+        Some(status_value) if !status_value.is_null_or_undefined() => {
+            let parsed_status = serde_v8::from_v8::<serde_json::Value>(scope, status_value)
+                .context("failed to deserialize status")?;
+            Some(parse_status(&parsed_status)?)
+        },
+        _ => None,
+    };
+
+    let headers = match get_property(scope, v8_object, "headers") {
+        Some(headers_value) if !headers_value.is_null_or_undefined() => {
+            Some(parse_headers(&headers_to_json(scope, headers_value)?)?)
+        },
+        _ => None,
+    };
+
+    Ok(Some(StreamingResponse {
+        status,
+        headers,
+        reader,
+    }))
+}
+
+/// Call `read()` on the reader of a streaming response, returning the promise of the next chunk.
+pub fn read_chunk(
+    scope: &mut v8::HandleScope,
+    reader: &v8::Global<v8::Object>,
+) -> Result<v8::Global<v8::Value>> {
+    let reader = v8::Local::new(scope, reader);
+    let promise = call_method(scope, reader, "read", &[])?;
+    Ok(v8::Global::new(scope, promise))
+}
+
+/// Parse the resolved result of `read()`, returning `None` once the stream is done.
+pub fn parse_chunk(
+    scope: &mut v8::HandleScope,
+    result: v8::Local<v8::Value>,
+) -> Result<Option<Vec<u8>>> {
+    let result = result
+        .to_object(scope)
+        .context("invalid result from the stream reader")?;
+
+    if get_property(scope, result, "done").is_some_and(|done| done.is_true()) {
+        return Ok(None);
+    }
+
+    match get_property(scope, result, "value") {
+        Some(value) => parse_body(scope, value).map(Some),
+        None => Ok(Some(Vec::new())),
+    }
+}
+
+/// Lock the stream and get a reader, if the object is a `ReadableStream`.
+fn get_reader(
+    scope: &mut v8::HandleScope,
+    obj: v8::Local<v8::Object>,
+) -> Result<Option<v8::Global<v8::Object>>> {
+    let is_stream = get_property(scope, obj, "getReader").is_some_and(|f| f.is_function())
+        && get_property(scope, obj, "locked").is_some_and(|locked| locked.is_boolean());
+    if !is_stream {
+        return Ok(None);
+    }
+
+    let reader = call_method(scope, obj, "getReader", &[])?
+        .to_object(scope)
+        .context("invalid stream reader")?;
+    Ok(Some(v8::Global::new(scope, reader)))
+}
+
+fn call_method<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    obj: v8::Local<'s, v8::Object>,
+    name: &str,
+    args: &[v8::Local<'s, v8::Value>],
+) -> Result<v8::Local<'s, v8::Value>> {
+    let method = get_property(scope, obj, name)
+        .and_then(|method| v8::Local::<v8::Function>::try_from(method).ok())
+        .with_context(|| format!("{name} is not a function"))?;
+    method
+        .call(scope, obj.into(), args)
+        .with_context(|| format!("failed to call {name}"))
+}
+
+/// Deserialize headers into a json value. `Headers` and `Map` objects have no enumerable
+/// properties, so they are turned into an array of entries first.
+fn headers_to_json(
+    scope: &mut v8::HandleScope,
+    value: v8::Local<v8::Value>,
+) -> Result<serde_json::Value> {
+    let has_entries = value.is_object()
+        && !value.is_array()
+        && value
+            .to_object(scope)
+            .and_then(|obj| get_property(scope, obj, "entries"))
+            .is_some_and(|entries| entries.is_function());
+
+    let value = if has_entries {
+        let global = scope.get_current_context().global(scope);
+        let array = get_property(scope, global, "Array")
+            .and_then(|array| array.to_object(scope))
+            .context("missing Array global")?;
+        call_method(scope, array, "from", &[value])?
+    } else {
+        value
+    };
+
+    serde_v8::from_v8::<serde_json::Value>(scope, value).context("failed to deserialize headers")
+}
+
 fn get_property<'s>(
     scope: &mut v8::HandleScope<'s>,
     obj: v8::Local<'s, v8::Object>,
@@ -759,4 +902,56 @@ fn test_key_val_object_no_headers_no_status() {
 
         assert_eq!(http_res, target);
     }
+
+    #[test]
+    fn test_streaming_response() {
+        let mut runtime = setup_runtime();
+        let scope = &mut runtime.handle_scope();
+        let res = eval_to_value(
+            scope,
+            r###"(
+{
+  "headers": new Map([["content-type", "text/event-stream"]]),
+  "status": 200,
+  "body": {
+    "locked": false,
+    getReader() {
+      const chunks = ["data: 1\n\n", new Uint8Array([100, 97])];
+      return { read: () => ({ done: chunks.length == 0, value: chunks.shift() }) };
+    }
+  }
+}
+        )"###,
+        );
+
+        let response = parse_streaming(scope, res).unwrap().unwrap();
+        assert_eq!(response.status, Some(200));
+        assert_eq!(
+            response.headers,
+            Some(vec![(
+                "content-type".to_string(),
+                vec!["text/event-stream".to_string()]
+            )])
+        );
+
+        let mut chunks = Vec::new();
+        loop {
+            let result = read_chunk(scope, &response.reader).unwrap();
+            let result = v8::Local::new(scope, result);
+            match parse_chunk(scope, result).unwrap() {
+                Some(chunk) => chunks.push(chunk),
+                None => break,
+            }
+        }
+        assert_eq!(chunks, vec![b"data: 1\n\n".to_vec(), b"da".to_vec()]);
+    }
+
+    #[test]
+    fn test_non_streaming_response() {
+        let mut runtime = setup_runtime();
+        let scope = &mut runtime.handle_scope();
+        let res = eval_to_value(scope, r#"({ "body": "hello", "status": 200 })"#);
+
+        assert!(parse_streaming(scope, res).unwrap().is_none());
+    }
 }
@@ -1,34 +1,79 @@
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
 use anyhow::{bail, Context};
 use deno_core::futures::stream::FuturesUnordered;
 use deno_core::futures::StreamExt;
 use deno_core::v8::{Global, IsolateHandle, Value};
 use deno_core::{serde_v8, v8, JsRuntime, ModuleSpecifier};
+use deno_fleek::deployment::Deployment;
+use deno_fleek::kv::{self, KvNamespace, KvReplication};
+use deno_fleek::secrets::Secrets;
+use fn_sdk::api::Capabilities;
 use fn_sdk::connection::Connection;
-use fn_sdk::header::TransportDetail;
-use fn_sdk::http_util::{respond, respond_with_error, respond_with_http_response};
+use fn_sdk::header::{HttpOverrides, HttpResponse, TransportDetail};
+use fn_sdk::http_util::{
+    respond,
+    respond_with_error,
+    respond_with_headers,
+    respond_with_http_response,
+};
 use tokio::sync::mpsc::UnboundedSender;
 use tokio_util::task::LocalPoolHandle;
 use tracing::{debug, error, info};
 
+use crate::deployment::{Manifest, Target};
+use crate::http::response::StreamingResponse;
+use crate::runtime::code_cache::code_cache;
 use crate::runtime::guard::IsolateGuard;
-use crate::runtime::Runtime;
+use crate::runtime::pool::{self, PoolKey};
+use crate::runtime::watchdog::Watchdog;
+use crate::runtime::{worker, Runtime};
 use crate::stream::{Origin, Request};
 
+mod deployment;
 mod http;
 mod runtime;
+mod secrets;
 pub mod stream;
 pub mod params {
+    use std::sync::OnceLock;
     use std::time::Duration;
 
     pub const HEAP_INIT: usize = 1 << 10;
     pub const ALIGNED_SNAPSHOT_SIZE: usize = crate::runtime::SNAPSHOT.len().next_power_of_two();
     pub const HEAP_LIMIT: usize = (128 << 20) + ALIGNED_SNAPSHOT_SIZE;
+    /// Maximum time a request can stay idle, while executing or between chunks of a streaming
+    /// response.
     pub const REQ_TIMEOUT: Duration = Duration::from_secs(15);
+    pub const CACHE_STATS_INTERVAL: Duration = Duration::from_secs(60);
+    /// Http requests with this header get the usage report of the invocation in the
+    /// [`USAGE_HEADER`] response header.
+    pub const DEBUG_HEADER: &str = "x-fleek-debug";
+    pub const USAGE_HEADER: &str = "x-fleek-usage";
+    /// Http requests refer to the secrets envelope of the function with this header.
+    pub const SECRETS_HEADER: &str = "x-fleek-secrets";
+
+    /// Number of warm runtimes to keep per function and thread, read from the
+    /// `JS_WARM_ISOLATES` environment variable. Pooling is disabled by default.
+    pub fn warm_isolates() -> usize {
+        static WARM_ISOLATES: OnceLock<usize> = OnceLock::new();
+        *WARM_ISOLATES.get_or_init(|| {
+            std::env::var("JS_WARM_ISOLATES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0)
+        })
+    }
 }
 
 #[tokio::main]
 pub async fn main() {
     fn_sdk::ipc::init_from_env();
+    fn_sdk::api::declare_capabilities(Capabilities {
+        http_overrides: true,
+    })
+    .await;
 
     info!("Initialized POC JS service!");
 
@@ -37,18 +82,15 @@ pub async fn main() {
     // Explicitly initialize the v8 platform on the main thread
     JsRuntime::init_platform(None, false);
 
-    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<IsolateHandle>();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<(IsolateHandle, Arc<Watchdog>)>();
     tokio::task::spawn(async move {
         let mut isolates = FuturesUnordered::new();
         loop {
             tokio::select! {
                 next = rx.recv() => {
                     match next {
-                        Some(isolate) => {
-                            isolates.push(async move {
-                                tokio::time::sleep(params::REQ_TIMEOUT).await;
-                                isolate.terminate_execution();
-                            })
+                        Some((isolate, watchdog)) => {
+                            isolates.push(watchdog.watch(isolate, params::REQ_TIMEOUT))
                         }
                         None => break,
                     }
@@ -58,6 +100,13 @@ pub async fn main() {
         }
     });
 
+    tokio::task::spawn(async {
+        loop {
+            tokio::time::sleep(params::CACHE_STATS_INTERVAL).await;
+            info!("Cache stats: {}", code_cache().stats);
+        }
+    });
+
     let pool = LocalPoolHandle::new(num_cpus::get());
     while let Ok(conn) = listener.accept().await {
         let tx_clone = tx.clone();
@@ -73,7 +122,7 @@ pub async fn main() {
 
 async fn handle_connection(
     mut connection: Connection,
-    tx: UnboundedSender<IsolateHandle>,
+    tx: UnboundedSender<(IsolateHandle, Arc<Watchdog>)>,
 ) -> anyhow::Result<()> {
     match &connection.header.transport_detail {
         TransportDetail::HttpRequest { .. } => {
@@ -94,14 +143,32 @@ async fn handle_connection(
                 .context("failed to parse request")?;
 
             if let Err(e) = handle_request(0, &mut connection, tx, request).await {
-                respond_with_error(&mut connection, format!("{e:?}").as_bytes(), 400).await?;
+                if e.downcast_ref::<StreamInterrupted>().is_none() {
+                    respond_with_error(&mut connection, format!("{e:?}").as_bytes(), 400).await?;
+                }
                 return Err(e);
             }
         },
-        TransportDetail::Task { depth, payload } => {
+        TransportDetail::Task {
+            payload, origin, ..
+        } if let Ok(replication) = serde_json::from_slice::<KvReplication>(payload) => {
+            // Only the runtime of this service replicates writes, and it never lets a function
+            // send them itself.
+            let origin = *origin;
+            if origin.is_none() || origin != fn_sdk::ipc::service_id() {
+                respond_with_error(&mut connection, b"unauthorized kv replication", 403).await?;
+                bail!("rejected kv replication from service {origin:?}");
+            }
+            let KvReplication { namespace, write } = replication;
+            kv::with_store(move |store| store.apply(&namespace, write)).await?;
+            respond(&mut connection, b"ok").await?;
+        },
+        TransportDetail::Task { depth, payload, .. } => {
             let request: Request = serde_json::from_slice(payload)?;
             if let Err(e) = handle_request(*depth, &mut connection, tx, request).await {
-                respond_with_error(&mut connection, e.to_string().as_bytes(), 400).await?;
+                if e.downcast_ref::<StreamInterrupted>().is_none() {
+                    respond_with_error(&mut connection, e.to_string().as_bytes(), 400).await?;
+                }
                 return Err(e);
             }
         },
@@ -109,7 +176,9 @@ async fn handle_connection(
             while let Some(payload) = connection.read_payload().await {
                 let request: Request = serde_json::from_slice(&payload)?;
                 if let Err(e) = handle_request(0, &mut connection, tx.clone(), request).await {
-                    respond_with_error(&mut connection, e.to_string().as_bytes(), 400).await?;
+                    if e.downcast_ref::<StreamInterrupted>().is_none() {
+                        respond_with_error(&mut connection, e.to_string().as_bytes(), 400).await?;
+                    }
                     return Err(e);
                 };
             }
@@ -122,7 +191,7 @@ async fn handle_connection(
 async fn handle_request(
     depth: u8,
     connection: &mut Connection,
-    tx: UnboundedSender<IsolateHandle>,
+    tx: UnboundedSender<(IsolateHandle, Arc<Watchdog>)>,
     request: Request,
 ) -> anyhow::Result<()> {
     let Request {
@@ -130,12 +199,13 @@ async fn handle_request(
         uri,
         path,
         param,
+        secrets,
     } = request;
     if uri.is_empty() {
         bail!("Empty origin uri");
     }
 
-    let module_url = match origin {
+    let mut module_url = match origin {
         Origin::Blake3 => format!("blake3://{uri}"),
         Origin::Ipfs => format!("ipfs://{uri}"),
         Origin::Http => uri,
@@ -144,30 +214,127 @@ async fn handle_request(
     .parse::<ModuleSpecifier>()
     .context("Invalid origin URI")?;
 
-    let mut location = module_url.clone();
-    if let Some(path) = path {
-        location = location.join(&path).context("Invalid path string")?;
+    // Functions are namespaced by their blake3 hash, functions from other origins use the hash
+    // of their url instead.
+    let namespace = match origin {
+        Origin::Blake3 => {
+            let mut hash = [0; 32];
+            hex::decode_to_slice(&uri, &mut hash).context("Invalid blake3 hash")?;
+            KvNamespace(hash)
+        },
+        _ => KvNamespace(*fleek_blake3::hash(module_url.as_str().as_bytes()).as_bytes()),
+    };
+
+    // Deployments route the request either to one of their modules, or to a static file
+    let mut deployment = Deployment::default();
+    let mut secrets_owner = None;
+    if origin == Origin::Blake3 {
+        if let Some(manifest) = Manifest::load(namespace.0).await? {
+            let route_path = path.as_deref().unwrap_or("/");
+            match manifest.route(route_path) {
+                Some(Target::Handler(module)) => {
+                    module_url = module_url
+                        .join(&format!("/{}", module.trim_start_matches('/')))
+                        .context("Invalid handler path")?;
+                },
+                Some(Target::Static(file)) => {
+                    return deployment::serve_static(connection, namespace.0, &file).await;
+                },
+                None => bail!("No route for {route_path}"),
+            }
+            deployment = manifest.deployment;
+            secrets_owner = manifest.secrets_owner;
+        }
     }
 
-    // Create runtime and execute the source.
-    let mut runtime =
-        Runtime::new(location.clone(), depth).context("Failed to initialize runtime")?;
-    tx.send(runtime.deno.v8_isolate().thread_safe_handle())?;
+    let (secrets, envelope) = match secrets {
+        Some(hash) => {
+            let mut envelope = [0; 32];
+            hex::decode_to_slice(&hash, &mut envelope).context("Invalid secrets hash")?;
+            let secrets = secrets::load(envelope, namespace.0, secrets_owner)
+                .await
+                .context("Failed to load secrets")?;
+            (secrets, Some(envelope))
+        },
+        None => (Secrets::default(), None),
+    };
 
-    unsafe {
-        runtime.deno.v8_isolate().exit();
+    let mut location = module_url.clone();
+    if let Some(path) = path {
+        location = location.join(&path).context("Invalid path string")?;
     }
 
+    // Reuse a warm runtime or create a new one, and execute the source.
+    let warm_isolates = params::warm_isolates();
+    let key = PoolKey {
+        location: location.clone(),
+        depth,
+        secrets: envelope,
+    };
+    let stats = &code_cache().stats;
+    let mut runtime = match (warm_isolates > 0).then(|| pool::take(&key)).flatten() {
+        Some(mut runtime) => {
+            stats.warm_hits.fetch_add(1, Ordering::Relaxed);
+            runtime
+                .deno
+                .v8_isolate()
+                .thread_safe_handle()
+                .cancel_terminate_execution();
+            runtime
+        },
+        None => {
+            if warm_isolates > 0 {
+                stats.warm_misses.fetch_add(1, Ordering::Relaxed);
+            }
+            let mut runtime = Runtime::new(location.clone(), depth, namespace, deployment)
+                .context("Failed to initialize runtime")?;
+            unsafe {
+                runtime.deno.v8_isolate().exit();
+            }
+            runtime
+        },
+    };
+    // Warm runtimes had their secrets reset when they were rewound
+    runtime.deno.op_state().borrow_mut().put(secrets);
+    let watchdog = Arc::new(Watchdog::default());
+    let watched = tx
+        .send((
+            runtime.deno.v8_isolate().thread_safe_handle(),
+            watchdog.clone(),
+        ))
+        .context("Failed to watch the isolate");
+    worker::install(&mut runtime, depth, namespace, tx, watchdog.clone());
+
+    // Nothing returns early until the invocation is billed and the isolate is entered again
     let mut guard = IsolateGuard::new(runtime);
-    let res = guard
-        .guard(|rt| {
-            Box::pin(handle_request_and_respond(
-                rt, connection, module_url, param,
-            ))
-        })
-        .await;
+    let res = match watched {
+        // Functions never run without a watchdog
+        Ok(()) => {
+            guard
+                .guard(|rt| {
+                    Box::pin(handle_request_and_respond(
+                        rt, connection, module_url, param, &watchdog,
+                    ))
+                })
+                .await
+        },
+        Err(e) => Err(e),
+    };
 
     let mut runtime = guard.destroy();
+    watchdog.finish();
+    deno_fleek::worker::terminate_workers(&mut runtime.deno.op_state().borrow_mut());
+
+    // Bill the invocation, whether it succeeded or not
+    let usage = runtime.usage();
+    debug!("{usage:?}");
+    fn_sdk::api::submit_delivery_acknowledgment(usage.compute_units, None).await;
+
+    if res.is_ok() && warm_isolates > 0 {
+        debug!("{:?}", runtime.rewind());
+        pool::give(key, runtime, warm_isolates);
+        return Ok(());
+    }
 
     unsafe {
         runtime.deno.v8_isolate().enter();
@@ -186,6 +353,7 @@ async fn handle_request_and_respond(
     connection: &mut Connection,
     module_url: ModuleSpecifier,
     param: Option<serde_json::Value>,
+    watchdog: &Watchdog,
 ) -> anyhow::Result<()> {
     let res = match runtime.exec(&module_url, param).await? {
         Some(res) => res,
@@ -201,7 +369,7 @@ async fn handle_request_and_respond(
         .await
         .context("Execution timeout")??;
 
-    parse_and_respond(connection, runtime, res).await?;
+    parse_and_respond(connection, runtime, res, watchdog).await?;
 
     Ok(())
 }
@@ -210,7 +378,20 @@ async fn parse_and_respond(
     connection: &mut Connection,
     runtime: &mut Runtime,
     res: Global<Value>,
+    watchdog: &Watchdog,
 ) -> anyhow::Result<()> {
+    let usage = usage_header(connection, runtime);
+
+    // Stream the body if it is a `ReadableStream`
+    let streaming = {
+        let scope = &mut runtime.deno.handle_scope();
+        let local = v8::Local::new(scope, &res);
+        http::response::parse_streaming(scope, local)?
+    };
+    if let Some(response) = streaming {
+        return stream_response(connection, runtime, response, usage, watchdog).await;
+    }
+
     // Handle the return data
     let scope = &mut runtime.deno.handle_scope();
     let local = v8::Local::new(scope, res);
@@ -221,17 +402,18 @@ async fn parse_and_respond(
             Ok(slice) => slice.to_vec(),
             Err(e) => bail!("failed to parse bytes: {e}"),
         };
-        respond(connection, &bytes).await?;
+        respond_with_usage(connection, &bytes, usage).await?;
     } else if local.is_string() {
         // Likewise for string types
         let string = serde_v8::from_v8::<String>(scope, local)
             .context("failed to deserialize response string")?;
 
-        respond(connection, string.as_bytes()).await?;
+        respond_with_usage(connection, string.as_bytes(), usage).await?;
     } else {
         // Attempt to parse and use the value as an http response override object
         if connection.is_http_request() {
-            if let Ok(http_response) = http::response::parse(scope, local) {
+            if let Ok(mut http_response) = http::response::parse(scope, local) {
+                http_response.headers = with_usage(http_response.headers, usage);
                 respond_with_http_response(connection, http_response).await?;
                 return Ok(());
             }
@@ -244,7 +426,120 @@ async fn parse_and_respond(
 
         // Otherwise, send the data as a json string
         let res = serde_json::to_string(&value).context("failed to encode json response")?;
-        respond(connection, res.as_bytes()).await?;
+        respond_with_usage(connection, res.as_bytes(), usage).await?;
+    }
+
+    Ok(())
+}
+
+/// Returns the usage report header of the invocation, if the request is an http request asking
+/// for it with [`params::DEBUG_HEADER`].
+fn usage_header(connection: &Connection, runtime: &Runtime) -> Option<(String, Vec<String>)> {
+    let TransportDetail::HttpRequest { header, .. } = &connection.header.transport_detail else {
+        return None;
+    };
+    if !header
+        .keys()
+        .any(|key| key.eq_ignore_ascii_case(params::DEBUG_HEADER))
+    {
+        return None;
+    }
+
+    let usage = serde_json::to_string(&runtime.usage()).ok()?;
+    Some((params::USAGE_HEADER.to_string(), vec![usage]))
+}
+
+fn with_usage(
+    headers: Option<Vec<(String, Vec<String>)>>,
+    usage: Option<(String, Vec<String>)>,
+) -> Option<Vec<(String, Vec<String>)>> {
+    let Some(usage) = usage else {
+        return headers;
+    };
+    let mut headers = headers.unwrap_or_default();
+    headers.push(usage);
+    Some(headers)
+}
+
+/// Respond with some bytes, adding the usage report header if there is one.
+async fn respond_with_usage(
+    connection: &mut Connection,
+    body: &[u8],
+    usage: Option<(String, Vec<String>)>,
+) -> anyhow::Result<()> {
+    if usage.is_none() {
+        return respond(connection, body).await;
+    }
+
+    let response = HttpResponse {
+        headers: with_usage(None, usage),
+        status: None,
+        body: body.to_vec(),
+    };
+    respond_with_http_response(connection, response).await
+}
+
+/// Context of errors that happen after a streaming response has started, at which point the
+/// error can't be sent to the client anymore.
+#[derive(Debug)]
+struct StreamInterrupted;
+
+impl std::fmt::Display for StreamInterrupted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Response stream interrupted")
+    }
+}
+
+/// Forward the chunks of a streaming response to the connection as they are produced. The next
+/// chunk is only read once the previous one has been written, and the request times out once no
+/// chunk has been produced for [`params::REQ_TIMEOUT`].
+async fn stream_response(
+    connection: &mut Connection,
+    runtime: &mut Runtime,
+    response: StreamingResponse,
+    usage: Option<(String, Vec<String>)>,
+    watchdog: &Watchdog,
+) -> anyhow::Result<()> {
+    if connection.is_http_request() {
+        let headers = HttpOverrides {
+            status: response.status,
+            headers: with_usage(response.headers, usage),
+        };
+        respond_with_headers(connection, headers).await?;
+    }
+    watchdog.touch();
+
+    loop {
+        let read = {
+            let scope = &mut runtime.deno.handle_scope();
+            http::response::read_chunk(scope, &response.reader).context(StreamInterrupted)?
+        };
+
+        #[allow(deprecated)]
+        let result = tokio::time::timeout(params::REQ_TIMEOUT, runtime.deno.resolve_value(read))
+            .await
+            .context("Stream idle timeout")
+            .context(StreamInterrupted)?
+            .context(StreamInterrupted)?;
+
+        let chunk = {
+            let scope = &mut runtime.deno.handle_scope();
+            let local = v8::Local::new(scope, result);
+            http::response::parse_chunk(scope, local).context(StreamInterrupted)?
+        };
+        let Some(chunk) = chunk else {
+            break;
+        };
+
+        // Writing waits for the connection, which gives backpressure to the stream
+        if !chunk.is_empty() {
+            connection
+                .write_payload(&chunk)
+                .await
+                .context("failed to send chunk")
+                .context(StreamInterrupted)?;
+        }
+        watchdog.touch();
     }
 
     Ok(())
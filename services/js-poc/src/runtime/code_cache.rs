@@ -0,0 +1,169 @@
+//! Cache of module sources and V8 code cache data, keyed by the blake3 hash of the modules.
+//!
+//! Sources are only kept in memory, since they are already persisted by the blockstore. Code
+//! cache data is also persisted in the data directory of the service, so it survives restarts.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use tracing::warn;
+
+/// Maximum size of the module sources kept in memory.
+const MAX_SOURCES_SIZE: usize = 64 << 20;
+
+static CACHE: OnceLock<CodeCache> = OnceLock::new();
+
+/// Returns the code cache of the service.
+pub fn code_cache() -> &'static CodeCache {
+    CACHE.get_or_init(|| CodeCache::new(fn_sdk::ipc::data_path().join("code_cache")))
+}
+
+/// Initialize the code cache in `dir`, for tests that run without the service setup.
+#[cfg(test)]
+pub fn init_code_cache(dir: PathBuf) {
+    CACHE.get_or_init(|| CodeCache::new(dir));
+}
+
+/// Put the source of a module in the code cache, and return its url.
+#[cfg(test)]
+pub fn test_module(source: &str) -> deno_core::url::Url {
+    init_code_cache(std::env::temp_dir().join(format!("code-cache-{}", std::process::id())));
+    let hash = *fleek_blake3::hash(source.as_bytes()).as_bytes();
+    code_cache().put_source(hash, source.as_bytes().into());
+    deno_core::url::Url::parse(&format!("blake3://{}", hex::encode(hash))).unwrap()
+}
+
+pub struct CodeCache {
+    dir: PathBuf,
+    sources: Mutex<Sources>,
+    pub stats: CacheStats,
+}
+
+#[derive(Default)]
+pub struct CacheStats {
+    pub source_hits: AtomicU64,
+    pub source_misses: AtomicU64,
+    pub code_hits: AtomicU64,
+    pub code_misses: AtomicU64,
+    pub code_rejected: AtomicU64,
+    pub warm_hits: AtomicU64,
+    pub warm_misses: AtomicU64,
+}
+
+impl CacheStats {
+    fn hit_rate(hits: &AtomicU64, misses: &AtomicU64) -> f64 {
+        let hits = hits.load(Ordering::Relaxed) as f64;
+        let total = hits + misses.load(Ordering::Relaxed) as f64;
+        if total == 0. { 0. } else { hits / total }
+    }
+}
+
+impl std::fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "source hit rate {:.2}, code cache hit rate {:.2} ({} rejected), warm isolate hit \
+             rate {:.2}",
+            Self::hit_rate(&self.source_hits, &self.source_misses),
+            Self::hit_rate(&self.code_hits, &self.code_misses),
+            self.code_rejected.load(Ordering::Relaxed),
+            Self::hit_rate(&self.warm_hits, &self.warm_misses),
+        )
+    }
+}
+
+/// Module sources in memory, the oldest ones are evicted first.
+#[derive(Default)]
+struct Sources {
+    map: HashMap<[u8; 32], Arc<[u8]>>,
+    order: VecDeque<[u8; 32]>,
+    size: usize,
+}
+
+impl CodeCache {
+    pub fn new(dir: PathBuf) -> Self {
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            warn!("failed to create the code cache directory: {e}");
+        }
+
+        Self {
+            dir,
+            sources: Default::default(),
+            stats: Default::default(),
+        }
+    }
+
+    pub fn get_source(&self, hash: &[u8; 32]) -> Option<Arc<[u8]>> {
+        let source = self.sources.lock().unwrap().map.get(hash).cloned();
+        let counter = match source {
+            Some(_) => &self.stats.source_hits,
+            None => &self.stats.source_misses,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        source
+    }
+
+    /// Returns true if the source of the module is in memory, without counting it as a hit.
+    pub fn has_source(&self, hash: &[u8; 32]) -> bool {
+        self.sources.lock().unwrap().map.contains_key(hash)
+    }
+
+    pub fn put_source(&self, hash: [u8; 32], source: Arc<[u8]>) {
+        if source.len() > MAX_SOURCES_SIZE {
+            return;
+        }
+
+        let mut sources = self.sources.lock().unwrap();
+        if sources.map.contains_key(&hash) {
+            return;
+        }
+        while sources.size + source.len() > MAX_SOURCES_SIZE {
+            let Some(oldest) = sources.order.pop_front() else {
+                break;
+            };
+            if let Some(evicted) = sources.map.remove(&oldest) {
+                sources.size -= evicted.len();
+            }
+        }
+        sources.size += source.len();
+        sources.order.push_back(hash);
+        sources.map.insert(hash, source);
+    }
+
+    /// Get the V8 code cache data of a module.
+    pub fn get(&self, hash: &[u8; 32]) -> Option<Vec<u8>> {
+        let data = std::fs::read(self.path(hash)).ok();
+        let counter = match data {
+            Some(_) => &self.stats.code_hits,
+            None => &self.stats.code_misses,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        data
+    }
+
+    /// Store the V8 code cache data of a module. This is also called when V8 rejected the data
+    /// we gave it, in which case the data is replaced.
+    pub fn put(&self, hash: &[u8; 32], data: &[u8]) {
+        let path = self.path(hash);
+        if path.exists() {
+            self.stats.code_rejected.fetch_add(1, Ordering::Relaxed);
+        }
+
+        // Write to a temporary file first, so a concurrent read never sees partial data.
+        let tmp = path.with_extension(format!("{:?}.tmp", std::thread::current().id()));
+        if let Err(e) = std::fs::write(&tmp, data).and_then(|_| std::fs::rename(&tmp, &path)) {
+            warn!("failed to write the code cache: {e}");
+        }
+    }
+
+    fn path(&self, hash: &[u8; 32]) -> PathBuf {
+        self.dir.join(format!("{}.v8", hex::encode(hash)))
+    }
+}
+
+/// The hash given to V8 alongside the code cache data of a module.
+pub fn cache_key(hash: &[u8; 32]) -> u64 {
+    u64::from_le_bytes(*arrayref::array_ref![hash, 0, 8])
+}
@@ -1,9 +1,11 @@
 use std::future::Future;
 use std::pin::Pin;
+use std::rc::Rc;
 use std::task::Poll;
 
-use deno_core::v8::OwnedIsolate;
+use deno_core::v8::{HeapStatistics, OwnedIsolate};
 
+use crate::runtime::usage::{thread_cpu_time, Meter};
 use crate::runtime::Runtime;
 
 /// Guards the Isolate of a Deno runtime instance.
@@ -27,8 +29,10 @@ pub fn guard<'a, F>(&'a mut self, f: F) -> GuardedIsolateFuture
         F: FnOnce(&'a mut Runtime) -> Pin<Box<dyn Future<Output = anyhow::Result<()>> + 'a>>,
     {
         let ptr = self.rt.deno.v8_isolate() as *mut _;
+        let meter = self.rt.meter.clone();
         GuardedIsolateFuture {
             ptr,
+            meter,
             fut: f(&mut self.rt),
         }
     }
@@ -40,6 +44,7 @@ pub fn destroy(self) -> Runtime {
 
 pub struct GuardedIsolateFuture<'a> {
     ptr: *mut OwnedIsolate,
+    meter: Rc<Meter>,
     fut: Pin<Box<dyn Future<Output = anyhow::Result<()>> + 'a>>,
 }
 
@@ -51,6 +56,14 @@ fn enter(&mut self) {
         }
     }
 
+    /// Returns the size of the heap, the isolate must be entered.
+    fn heap_size(&mut self) -> usize {
+        let runtime = unsafe { self.ptr.as_mut().expect("Pointer to be non-null") };
+        let mut stats = HeapStatistics::default();
+        runtime.get_heap_statistics(&mut stats);
+        stats.used_heap_size()
+    }
+
     fn exit(&mut self) {
         let runtime = unsafe { self.ptr.as_mut().expect("Pointer to be non-null") };
         unsafe {
@@ -63,9 +76,14 @@ impl<'a> Future for GuardedIsolateFuture<'a> {
     type Output = anyhow::Result<()>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        // Bill the CPU time of the thread, so time it is preempted by other work on the node is not
+        let start = thread_cpu_time();
         self.as_mut().enter();
         let res = self.as_mut().fut.as_mut().poll(cx);
+        let heap = self.as_mut().heap_size();
         self.as_mut().exit();
+        self.meter
+            .record(thread_cpu_time().saturating_sub(start), heap);
         res
     }
 }
@@ -12,8 +12,12 @@
 use deno_core::serde_v8::{self, to_v8};
 use deno_core::url::Url;
 use deno_core::v8::{self, CreateParams, Global, Value};
-use deno_core::{JsRuntime, ModuleSpecifier, PollEventLoopOptions, RuntimeOptions};
+use deno_core::{JsRuntime, ModuleId, ModuleSpecifier, PollEventLoopOptions, RuntimeOptions};
 use deno_crypto::deno_crypto;
+use deno_fleek::deployment::Deployment;
+use deno_fleek::kv::KvNamespace;
+use deno_fleek::secrets::Secrets;
+use deno_fleek::usage::OpUsage;
 use deno_fleek::{fleek, maybe_transpile_source, Permissions};
 use deno_fs::sync::MaybeArc;
 use deno_fs::InMemoryFs;
@@ -23,11 +27,17 @@
 
 use self::module_loader::FleekModuleLoader;
 use self::tape::{Punch, Tape};
+use self::usage::{Meter, Usage};
 use crate::params::{HEAP_INIT, HEAP_LIMIT};
 
+pub mod code_cache;
 pub mod guard;
 pub mod module_loader;
+pub mod pool;
 pub mod tape;
+pub mod usage;
+pub mod watchdog;
+pub mod worker;
 
 /// Snapshot of the runtime after javascript modules have been initialized
 pub const SNAPSHOT: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/snapshot.bin"));
@@ -35,11 +45,19 @@
 pub struct Runtime {
     pub deno: JsRuntime,
     tape: Tape,
+    meter: Rc<Meter>,
+    /// The main module, once it has been loaded and evaluated.
+    main: Option<(ModuleSpecifier, ModuleId)>,
 }
 
 impl Runtime {
     /// Create a new runtime
-    pub fn new(location: Url, depth: u8) -> Result<Self> {
+    pub fn new(
+        location: Url,
+        depth: u8,
+        namespace: KvNamespace,
+        deployment: Deployment,
+    ) -> Result<Self> {
         let memory_fs = MaybeArc::new(InMemoryFs::default());
         let tape = Tape::new(location.clone());
         let mut deno = JsRuntime::new(RuntimeOptions {
@@ -59,7 +77,7 @@ pub fn new(location: Url, depth: u8) -> Result<Self> {
                 deno_fs::deno_fs::init_ops::<Permissions>(memory_fs.clone()),
                 deno_node::deno_node::init_ops::<Permissions>(Default::default(), memory_fs),
                 // Fleek runtime
-                fleek::init_ops(depth),
+                fleek::init_ops(depth, namespace, deployment),
             ],
             startup_snapshot: Some(SNAPSHOT),
             op_metrics_factory_fn: Some(tape.op_metrics_factory_fn()),
@@ -99,7 +117,12 @@ pub fn new(location: Url, depth: u8) -> Result<Self> {
                 .expect("Failed to execute bootstrap");
         }
 
-        Ok(Self { deno, tape })
+        Ok(Self {
+            deno,
+            tape,
+            meter: Default::default(),
+            main: None,
+        })
     }
 
     /// Execute javascript source on the runtime
@@ -108,11 +131,19 @@ pub async fn exec(
         specifier: &ModuleSpecifier,
         param: Option<serde_json::Value>,
     ) -> anyhow::Result<Option<Global<Value>>> {
-        let id = self.deno.load_main_es_module(specifier).await?;
-        self.deno
-            .run_event_loop(PollEventLoopOptions::default())
-            .await?;
-        self.deno.mod_evaluate(id).await?;
+        // Warm runtimes already evaluated the module, so we only need to call main again
+        let id = match &self.main {
+            Some((main, id)) if main == specifier => *id,
+            _ => {
+                let id = self.deno.load_main_es_module(specifier).await?;
+                self.deno
+                    .run_event_loop(PollEventLoopOptions::default())
+                    .await?;
+                self.deno.mod_evaluate(id).await?;
+                self.main = Some((specifier.clone(), id));
+                id
+            },
+        };
 
         {
             let main = self.deno.get_module_namespace(id)?;
@@ -150,8 +181,35 @@ pub async fn exec(
         }
     }
 
+    /// Returns the resources used since the runtime was created, or since the last rewind.
+    pub fn usage(&self) -> Usage {
+        let state = self.deno.op_state();
+        let state = state.borrow();
+        let op_usage = state.borrow::<OpUsage>();
+        Usage::new(
+            self.meter.cpu_time().as_micros() as u64,
+            self.meter.peak_heap() as u64,
+            self.tape.ops(),
+            op_usage.fetched_bytes,
+            op_usage.subtasks,
+        )
+    }
+
     /// End and collect the punch tape
     pub fn end(self) -> Vec<Punch> {
         self.tape.end()
     }
+
+    /// Collect the punch tape, and start a new one for the next execution. Usage and secrets are
+    /// reset as well.
+    pub fn rewind(&self) -> Vec<Punch> {
+        self.meter.reset();
+        {
+            let state = self.deno.op_state();
+            let mut state = state.borrow_mut();
+            state.put(OpUsage::default());
+            state.put(Secrets::default());
+        }
+        self.tape.rewind()
+    }
 }
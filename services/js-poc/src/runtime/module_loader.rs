@@ -1,3 +1,11 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Arc;
+
 use anyhow::{anyhow, bail, Context};
 use arrayref::array_ref;
 use cid::Cid;
@@ -10,19 +18,63 @@
     ModuleSpecifier,
     ModuleType,
     RequestedModuleType,
+    SourceCodeCacheInfo,
 };
 use fn_sdk::api::fetch_from_origin;
 use fn_sdk::blockstore::ContentHandle;
 use tracing::trace;
 
-pub struct FleekModuleLoader {}
+use super::code_cache::{cache_key, code_cache};
+use crate::deployment;
+
+pub struct FleekModuleLoader {
+    /// The blake3 hash of every module we loaded, used to store the code cache V8 generates.
+    hashes: Rc<RefCell<HashMap<ModuleSpecifier, [u8; 32]>>>,
+}
 
 impl FleekModuleLoader {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            hashes: Default::default(),
+        }
     }
 }
 
+/// Load a module from the blockstore, along with its code cache.
+async fn load_module(
+    hash: [u8; 32],
+    module_specifier: ModuleSpecifier,
+    module_type: ModuleType,
+    hashes: Rc<RefCell<HashMap<ModuleSpecifier, [u8; 32]>>>,
+) -> anyhow::Result<ModuleSource> {
+    let cache = code_cache();
+    let source = match cache.get_source(&hash) {
+        Some(source) => source,
+        None => {
+            let mut handle = ContentHandle::load(&hash).await?;
+            let source: Arc<[u8]> = handle.read_to_end().await?.into();
+            cache.put_source(hash, source.clone());
+            source
+        },
+    };
+
+    // V8 only produces code cache data for javascript modules
+    let code_cache = matches!(module_type, ModuleType::JavaScript).then(|| {
+        hashes.borrow_mut().insert(module_specifier.clone(), hash);
+        SourceCodeCacheInfo {
+            hash: cache_key(&hash),
+            data: cache.get(&hash).map(Cow::Owned),
+        }
+    });
+
+    Ok(ModuleSource::new(
+        module_type,
+        ModuleSourceCode::Bytes(source.to_vec().into_boxed_slice().into()),
+        &module_specifier,
+        code_cache,
+    ))
+}
+
 impl ModuleLoader for FleekModuleLoader {
     fn resolve(
         &self,
@@ -79,21 +131,23 @@ fn load(
                     )));
                 }
 
-                let hash = *array_ref![bytes, 0, 32];
+                let mut hash = *array_ref![bytes, 0, 32];
+                let path = module_specifier.path().trim_start_matches('/').to_string();
+                let hashes = self.hashes.clone();
                 ModuleLoadResponse::Async(Box::pin(async move {
-                    if !fn_sdk::api::fetch_blake3(hash).await {
+                    // Content in the source cache is known to be in the blockstore already
+                    if !code_cache().has_source(&hash) && !fn_sdk::api::fetch_blake3(hash).await {
                         bail!("Failed to fetch {module_specifier}")
                     }
 
-                    let mut handle = ContentHandle::load(&hash).await?;
-                    let source = handle.read_to_end().await?.into_boxed_slice();
+                    // Modules of deployments are loaded from their directory
+                    if !path.is_empty() {
+                        hash = deployment::resolve(hash, &path)
+                            .await
+                            .with_context(|| format!("Failed to resolve {module_specifier}"))?;
+                    }
 
-                    Ok(ModuleSource::new(
-                        module_type,
-                        deno_core::ModuleSourceCode::Bytes(source.into()),
-                        &module_specifier,
-                        None,
-                    ))
+                    load_module(hash, module_specifier, module_type, hashes).await
                 }))
             },
             "ipfs" => {
@@ -104,6 +158,7 @@ fn load(
                     return ModuleLoadResponse::Sync(Err(anyhow!("Invalid ipfs cid")));
                 };
 
+                let hashes = self.hashes.clone();
                 ModuleLoadResponse::Async(Box::pin(async move {
                     let hash = fetch_from_origin(fn_sdk::api::Origin::IPFS, cid.to_bytes())
                         .await
@@ -111,16 +166,7 @@ fn load(
                             format!("Failed to fetch {module_specifier} from origin")
                         })?;
 
-                    let mut handle = ContentHandle::load(&hash).await?;
-                    let bytes = handle.read_to_end().await?;
-
-                    let module = ModuleSource::new(
-                        module_type,
-                        ModuleSourceCode::Bytes(bytes.into_boxed_slice().into()),
-                        &module_specifier,
-                        None,
-                    );
-                    Ok(module)
+                    load_module(hash, module_specifier, module_type, hashes).await
                 }))
             },
             "https" | "http" => {
@@ -134,6 +180,7 @@ fn load(
                     )));
                 }
 
+                let hashes = self.hashes.clone();
                 ModuleLoadResponse::Async(Box::pin(async move {
                     let hash = fn_sdk::api::fetch_from_origin(
                         fn_sdk::api::Origin::HTTP,
@@ -142,19 +189,23 @@ fn load(
                     .await
                     .with_context(|| format!("Failed to fetch {module_specifier} from origin"))?;
 
-                    let mut handle = ContentHandle::load(&hash).await?;
-                    let bytes = handle.read_to_end().await?;
-
-                    let module = ModuleSource::new(
-                        module_type,
-                        ModuleSourceCode::Bytes(bytes.into_boxed_slice().into()),
-                        &module_specifier,
-                        None,
-                    );
-                    Ok(module)
+                    load_module(hash, module_specifier, module_type, hashes).await
                 }))
             },
             _ => ModuleLoadResponse::Sync(Err(anyhow!("Unknown import url scheme"))),
         }
     }
+
+    fn code_cache_ready(
+        &self,
+        module_specifier: ModuleSpecifier,
+        _hash: u64,
+        code_cache: &[u8],
+    ) -> Pin<Box<dyn Future<Output = ()>>> {
+        if let Some(hash) = self.hashes.borrow().get(&module_specifier) {
+            trace!("Caching code for {module_specifier}");
+            super::code_cache::code_cache().put(hash, code_cache);
+        }
+        Box::pin(async {})
+    }
 }
@@ -0,0 +1,137 @@
+//! Pool of warm runtimes, which already loaded and evaluated a function.
+//!
+//! Reusing a runtime skips creating the isolate and loading the modules, but the global state
+//! of the function is shared between invocations, so pooling is opt in. Since a function can
+//! keep its secrets in its global state, runtimes are only shared by requests with the same
+//! secrets.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use deno_core::url::Url;
+
+use super::Runtime;
+
+/// Maximum number of runtimes kept warm by a single thread.
+const MAX_WARM_RUNTIMES_PER_THREAD: usize = 32;
+
+/// Runtimes can only be reused for the same function at the same location and depth, and with
+/// the same secrets envelope.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct PoolKey {
+    pub location: Url,
+    pub depth: u8,
+    pub secrets: Option<[u8; 32]>,
+}
+
+thread_local! {
+    static POOL: RefCell<HashMap<PoolKey, Vec<WarmRuntime>>> = Default::default();
+}
+
+/// Take a warm runtime out of the pool. The isolate of the runtime is not entered.
+pub fn take(key: &PoolKey) -> Option<Runtime> {
+    POOL.with_borrow_mut(|pool| {
+        let runtimes = pool.get_mut(key)?;
+        let runtime = runtimes.pop()?.0.take();
+        if runtimes.is_empty() {
+            pool.remove(key);
+        }
+        runtime
+    })
+}
+
+/// Put a runtime back in the pool, as long as there is room for it. The isolate of the runtime
+/// must not be entered.
+pub fn give(key: PoolKey, runtime: Runtime, max_per_function: usize) {
+    let runtime = WarmRuntime(Some(runtime));
+    POOL.with_borrow_mut(|pool| {
+        let total = pool.values().map(Vec::len).sum::<usize>();
+        let runtimes = pool.entry(key).or_default();
+        if runtimes.len() < max_per_function && total < MAX_WARM_RUNTIMES_PER_THREAD {
+            runtimes.push(runtime);
+        }
+    })
+}
+
+/// A runtime whose isolate is not entered, which has to be entered again before dropping it.
+struct WarmRuntime(Option<Runtime>);
+
+impl Drop for WarmRuntime {
+    fn drop(&mut self) {
+        if let Some(mut runtime) = self.0.take() {
+            unsafe {
+                runtime.deno.v8_isolate().enter();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use deno_core::{serde_v8, v8};
+    use deno_fleek::deployment::Deployment;
+    use deno_fleek::kv::KvNamespace;
+    use deno_fleek::secrets::Secrets;
+
+    use super::*;
+    use crate::runtime::code_cache::test_module;
+
+    /// Call the main function of the module, which has to return synchronously.
+    async fn call(runtime: &mut Runtime, main: &Url) -> serde_json::Value {
+        let res = runtime.exec(main, None).await.unwrap().unwrap();
+        let scope = &mut runtime.deno.handle_scope();
+        let local = v8::Local::new(scope, res);
+        serde_v8::from_v8(scope, local).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_runtimes_are_not_shared_across_secrets() {
+        let main = test_module(
+            "export const main = () => (globalThis.token ??= Fleek.env.TOKEN) ?? null;",
+        );
+        let key = |secrets| PoolKey {
+            location: main.clone(),
+            depth: 0,
+            secrets,
+        };
+
+        // The first request keeps its secret in the global scope.
+        let mut runtime =
+            Runtime::new(main.clone(), 0, KvNamespace([0; 32]), Deployment::default()).unwrap();
+        let secrets = Secrets(HashMap::from([("TOKEN".to_string(), "a".to_string())]));
+        runtime.deno.op_state().borrow_mut().put(secrets);
+        assert_eq!(call(&mut runtime, &main).await, "a");
+        runtime.rewind();
+        unsafe {
+            runtime.deno.v8_isolate().exit();
+        }
+        give(key(Some([1; 32])), runtime, 1);
+
+        // Requests with other secrets, or none, don't get the runtime.
+        assert!(take(&key(None)).is_none());
+        assert!(take(&key(Some([2; 32]))).is_none());
+
+        // A request with the same secrets gets it, with the secrets removed from the op state.
+        let mut runtime = take(&key(Some([1; 32]))).unwrap();
+        unsafe {
+            runtime.deno.v8_isolate().enter();
+        }
+        assert!(
+            runtime
+                .deno
+                .op_state()
+                .borrow()
+                .borrow::<Secrets>()
+                .0
+                .is_empty()
+        );
+
+        // A fresh runtime doesn't see the global state of the first request.
+        drop(runtime);
+        let mut runtime =
+            Runtime::new(main.clone(), 0, KvNamespace([0; 32]), Deployment::default()).unwrap();
+        assert_eq!(call(&mut runtime, &main).await, serde_json::Value::Null);
+    }
+}
@@ -45,6 +45,23 @@ pub fn op_metrics_factory_fn(&self) -> OpMetricsFactoryFn {
         Box::new(move |_, _, _| Some(s.op_metrics_fn()))
     }
 
+    /// Returns the number of ops dispatched since the start of the feed.
+    pub fn ops(&self) -> u64 {
+        let feed = self.feed.borrow();
+        feed.iter()
+            .filter(|punch| matches!(punch, Punch::OpDispatched(_) | Punch::OpDispatchedAsync(_)))
+            .count() as u64
+    }
+
+    /// Returns the feed, and starts a new one for the same location
+    pub fn rewind(&self) -> Vec<Punch> {
+        let mut feed = self.feed.borrow_mut();
+        let start = feed[0].clone();
+        let mut feed = std::mem::replace(&mut *feed, vec![start]);
+        feed.push(Punch::End);
+        feed
+    }
+
     /// Consumes the inner refcell and returns the feed
     pub fn end(self) -> Vec<Punch> {
         let mut feed = self.feed.take();
@@ -0,0 +1,118 @@
+//! Resource accounting of function invocations, billed as compute.
+
+use std::cell::Cell;
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// Compute units charged per millisecond spent executing in the isolate.
+const UNITS_PER_CPU_MS: u128 = 1;
+/// Compute units charged per thousand ops called.
+const UNITS_PER_THOUSAND_OPS: u128 = 1;
+/// Compute units charged per mebibyte of content fetched or loaded.
+const UNITS_PER_FETCHED_MIB: u128 = 1;
+/// Compute units charged per task spawned.
+const UNITS_PER_SUBTASK: u128 = 10;
+
+/// Resources used by a single invocation of a function.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Usage {
+    /// CPU time spent executing in the isolate, in microseconds.
+    pub cpu_time: u64,
+    /// Peak size of the javascript heap, in bytes.
+    pub peak_heap: u64,
+    /// Number of ops called.
+    pub ops: u64,
+    /// Size of the content fetched or loaded, in bytes.
+    pub fetched_bytes: u64,
+    /// Number of tasks spawned.
+    pub subtasks: u64,
+    /// Compute units the invocation is billed for.
+    pub compute_units: u128,
+}
+
+impl Usage {
+    pub fn new(cpu_time: u64, peak_heap: u64, ops: u64, fetched_bytes: u64, subtasks: u64) -> Self {
+        // The heap is capped by the heap limit of the isolate, so it is reported but not billed
+        let units = (cpu_time as u128).div_ceil(1000) * UNITS_PER_CPU_MS
+            + (ops as u128 / 1000) * UNITS_PER_THOUSAND_OPS
+            + (fetched_bytes as u128 >> 20) * UNITS_PER_FETCHED_MIB
+            + subtasks as u128 * UNITS_PER_SUBTASK;
+
+        Self {
+            cpu_time,
+            peak_heap,
+            ops,
+            fetched_bytes,
+            subtasks,
+            compute_units: units.max(1),
+        }
+    }
+}
+
+/// Measures the CPU time spent executing in an isolate and the peak size of its heap, updated by
+/// the [`IsolateGuard`](super::guard::IsolateGuard) on every poll.
+#[derive(Default)]
+pub struct Meter {
+    cpu_time: Cell<Duration>,
+    peak_heap: Cell<usize>,
+}
+
+impl Meter {
+    pub fn record(&self, elapsed: Duration, heap: usize) {
+        self.cpu_time.set(self.cpu_time.get() + elapsed);
+        self.peak_heap.set(self.peak_heap.get().max(heap));
+    }
+
+    pub fn cpu_time(&self) -> Duration {
+        self.cpu_time.get()
+    }
+
+    pub fn peak_heap(&self) -> usize {
+        self.peak_heap.get()
+    }
+
+    pub fn reset(&self) {
+        self.cpu_time.take();
+        self.peak_heap.take();
+    }
+}
+
+/// Returns the CPU time used by the current thread, which unlike the wall time does not include
+/// the time the thread was preempted or blocked.
+pub fn thread_cpu_time() -> Duration {
+    let mut time = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: `time` is a valid pointer for the duration of the call.
+    unsafe {
+        libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut time);
+    }
+    Duration::new(time.tv_sec as u64, time.tv_nsec as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compute_units() {
+        // Every invocation is billed at least one unit
+        assert_eq!(Usage::new(0, 0, 0, 0, 0).compute_units, 1);
+        assert_eq!(Usage::new(1, 1 << 30, 999, 1000, 0).compute_units, 1);
+        assert_eq!(
+            Usage::new(1500, 0, 2000, 3 << 20, 1).compute_units,
+            2 + 2 + 3 + 10
+        );
+    }
+
+    #[test]
+    fn test_thread_cpu_time() {
+        // Blocking the thread does not use CPU time
+        let start = thread_cpu_time();
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(thread_cpu_time() - start < Duration::from_millis(50));
+    }
+}
@@ -0,0 +1,61 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use deno_core::v8::IsolateHandle;
+
+/// Terminates the isolate of a request once it has been idle for too long.
+///
+/// Activity is recorded with [`Watchdog::touch`], for example for every chunk of a streaming
+/// response, so long running requests are fine as long as they keep making progress.
+pub struct Watchdog {
+    start: Instant,
+    /// Milliseconds since `start` of the last activity.
+    last_active: AtomicU64,
+    done: AtomicBool,
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            last_active: AtomicU64::new(0),
+            done: AtomicBool::new(false),
+        }
+    }
+}
+
+impl Watchdog {
+    /// Record activity, pushing back the termination of the isolate.
+    pub fn touch(&self) {
+        self.last_active
+            .store(self.start.elapsed().as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Mark the request as done, after which the isolate is never terminated.
+    pub fn finish(&self) {
+        self.done.store(true, Ordering::Relaxed);
+    }
+
+    fn idle(&self) -> Duration {
+        let last_active = Duration::from_millis(self.last_active.load(Ordering::Relaxed));
+        self.start.elapsed().saturating_sub(last_active)
+    }
+
+    /// Wait until the request is done, or terminate the isolate once it was idle for `timeout`.
+    pub async fn watch(self: Arc<Self>, isolate: IsolateHandle, timeout: Duration) {
+        loop {
+            // Warm isolates might be running another request by now
+            if self.done.load(Ordering::Relaxed) {
+                return;
+            }
+
+            let idle = self.idle();
+            if idle >= timeout {
+                isolate.terminate_execution();
+                return;
+            }
+            tokio::time::sleep(timeout - idle).await;
+        }
+    }
+}
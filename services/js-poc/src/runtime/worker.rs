@@ -0,0 +1,258 @@
+//! Runtimes of the web workers created by functions.
+//!
+//! Workers run on the same thread as the function that created them, each in its own isolate
+//! with the same heap limits, deployment settings and secrets. Their isolates are watched with the
+//! watchdog of the request, and they are terminated once the request ends.
+
+use std::rc::Rc;
+use std::sync::Arc;
+
+use anyhow::Result;
+use deno_core::url::Url;
+use deno_core::v8::IsolateHandle;
+use deno_core::PollEventLoopOptions;
+use deno_fleek::deployment::Deployment;
+use deno_fleek::kv::KvNamespace;
+use deno_fleek::secrets::Secrets;
+use deno_fleek::worker::{CreateWorker, WorkerControlEvent, WorkerOptions};
+use tokio::sync::mpsc::UnboundedSender;
+
+use super::guard::IsolateGuard;
+use super::watchdog::Watchdog;
+use super::Runtime;
+
+/// Allow the function running on the runtime to create workers for the current request.
+pub fn install(
+    runtime: &mut Runtime,
+    depth: u8,
+    namespace: KvNamespace,
+    tx: UnboundedSender<(IsolateHandle, Arc<Watchdog>)>,
+    watchdog: Arc<Watchdog>,
+) {
+    let (deployment, secrets) = {
+        let state = runtime.deno.op_state();
+        let state = state.borrow();
+        (
+            state.borrow::<Deployment>().clone(),
+            state.borrow::<Secrets>().clone(),
+        )
+    };
+    let create = CreateWorker(Rc::new(move |options| {
+        tokio::task::spawn_local(run(
+            options,
+            depth,
+            namespace,
+            deployment.clone(),
+            secrets.clone(),
+            tx.clone(),
+            watchdog.clone(),
+        ));
+    }));
+    runtime.deno.op_state().borrow_mut().put(create);
+}
+
+async fn run(
+    options: WorkerOptions,
+    depth: u8,
+    namespace: KvNamespace,
+    deployment: Deployment,
+    secrets: Secrets,
+    tx: UnboundedSender<(IsolateHandle, Arc<Watchdog>)>,
+    watchdog: Arc<Watchdog>,
+) {
+    let ctrl = options.ctrl.clone();
+    let event = match run_worker(options, depth, namespace, deployment, secrets, tx, watchdog).await
+    {
+        Ok(()) => WorkerControlEvent::Close,
+        Err(e) => WorkerControlEvent::TerminalError(e),
+    };
+    // The parent might be gone already
+    let _ = ctrl.send(event);
+}
+
+async fn run_worker(
+    options: WorkerOptions,
+    depth: u8,
+    namespace: KvNamespace,
+    deployment: Deployment,
+    secrets: Secrets,
+    tx: UnboundedSender<(IsolateHandle, Arc<Watchdog>)>,
+    watchdog: Arc<Watchdog>,
+) -> Result<()> {
+    let WorkerOptions {
+        specifier,
+        scope,
+        terminated,
+        ..
+    } = options;
+
+    // The isolate is entered after creation, until it is guarded
+    let mut runtime = Runtime::new(specifier.clone(), depth, namespace, deployment)?;
+    runtime.deno.op_state().borrow_mut().put(scope);
+    runtime.deno.op_state().borrow_mut().put(secrets);
+    runtime
+        .deno
+        .execute_script("[fleek:worker]", "globalThis.bootstrapWorker()".to_string())?;
+    unsafe {
+        runtime.deno.v8_isolate().exit();
+    }
+    let _ = tx.send((runtime.deno.v8_isolate().thread_safe_handle(), watchdog));
+
+    let mut guard = IsolateGuard::new(runtime);
+    let res = guard
+        .guard(|rt| {
+            Box::pin(async move {
+                tokio::select! {
+                    biased;
+                    _ = terminated.notified() => Ok(()),
+                    res = evaluate(rt, &specifier) => res,
+                }
+            })
+        })
+        .await;
+
+    // The isolate must be entered when it is dropped
+    let mut runtime = guard.destroy();
+    unsafe {
+        runtime.deno.v8_isolate().enter();
+    }
+    drop(runtime);
+
+    res
+}
+
+/// Evaluate the main module of the worker, and run it until it is done.
+async fn evaluate(runtime: &mut Runtime, specifier: &Url) -> Result<()> {
+    let id = runtime.deno.load_main_es_module(specifier).await?;
+    let evaluation = runtime.deno.mod_evaluate(id);
+    runtime
+        .deno
+        .run_event_loop(PollEventLoopOptions::default())
+        .await?;
+    evaluation.await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use anyhow::Context;
+    use deno_core::{serde_v8, v8};
+    use deno_fleek::worker::MAX_WORKERS;
+    use tokio::task::LocalSet;
+
+    use super::*;
+    use crate::runtime::code_cache::test_module as module;
+
+    /// Run the main function of a module with workers, like a request does, and wait until all
+    /// the workers it created are done.
+    async fn run(main: Url) -> Result<serde_json::Value> {
+        let namespace = KvNamespace([0; 32]);
+        let mut runtime = Runtime::new(main.clone(), 0, namespace, Deployment::default())?;
+        unsafe {
+            runtime.deno.v8_isolate().exit();
+        }
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let watchdog = Arc::new(Watchdog::default());
+        install(&mut runtime, 0, namespace, tx, watchdog.clone());
+
+        let mut value = serde_json::Value::Null;
+        let out = &mut value;
+        let mut guard = IsolateGuard::new(runtime);
+        let res = guard
+            .guard(|rt| {
+                Box::pin(async move {
+                    let res = rt.exec(&main, None).await?.context("No response")?;
+                    #[allow(deprecated)]
+                    let res = rt.deno.resolve_value(res).await?;
+                    let scope = &mut rt.deno.handle_scope();
+                    let local = v8::Local::new(scope, res);
+                    *out = serde_v8::from_v8(scope, local)?;
+                    Ok(())
+                })
+            })
+            .await;
+
+        let mut runtime = guard.destroy();
+        watchdog.finish();
+        deno_fleek::worker::terminate_workers(&mut runtime.deno.op_state().borrow_mut());
+        unsafe {
+            runtime.deno.v8_isolate().enter();
+        }
+        drop(runtime);
+
+        // Every worker holds a sender until its runtime is dropped
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while rx.recv().await.is_some() {}
+        })
+        .await
+        .context("Workers were not terminated")?;
+
+        res.map(|()| value)
+    }
+
+    #[tokio::test]
+    async fn test_post_message() {
+        LocalSet::new()
+            .run_until(async {
+                let worker = module("globalThis.onmessage = (e) => postMessage(`${e.data} pong`);");
+                let main = module(&format!(
+                    r#"export const main = () => new Promise((resolve, reject) => {{
+                        const worker = new Worker("{worker}", {{ type: "module" }});
+                        worker.onmessage = (e) => resolve(e.data);
+                        worker.onerror = (e) => reject(new Error(e.message));
+                        worker.postMessage("ping");
+                    }});"#
+                ));
+                assert_eq!(run(main).await.unwrap(), "ping pong");
+            })
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_max_workers() {
+        LocalSet::new()
+            .run_until(async {
+                let worker = module("export {};");
+                let main = module(&format!(
+                    r#"export const main = () => {{
+                        for (let i = 0; i < {MAX_WORKERS}; i++) {{
+                            new Worker("{worker}", {{ type: "module" }});
+                        }}
+                        try {{
+                            new Worker("{worker}", {{ type: "module" }});
+                            return "created";
+                        }} catch (e) {{
+                            return e.message;
+                        }}
+                    }};"#
+                ));
+                let message = run(main).await.unwrap();
+                assert!(
+                    message
+                        .as_str()
+                        .unwrap()
+                        .contains(&format!("at most {MAX_WORKERS} workers"))
+                );
+            })
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_workers_are_terminated_with_the_request() {
+        LocalSet::new()
+            .run_until(async {
+                // The worker never ends by itself
+                let worker = module(r#"postMessage("ready"); setInterval(() => {}, 1000);"#);
+                let main = module(&format!(
+                    r#"export const main = () => new Promise((resolve) => {{
+                        const worker = new Worker("{worker}", {{ type: "module" }});
+                        worker.onmessage = (e) => resolve(e.data);
+                    }});"#
+                ));
+                assert_eq!(run(main).await.unwrap(), "ready");
+            })
+            .await;
+    }
+}
@@ -0,0 +1,265 @@
+//! Encrypted secrets of functions.
+//!
+//! Secrets are given to a function with an envelope, which is encrypted with ECIES for the key
+//! held by the nodes and stored in the blockstore. Requests refer to the envelope by its blake3
+//! hash, and the envelope is bound to a single function, so it can't be used to read the
+//! secrets from any other function.
+//!
+//! The plaintext of an envelope is [`ENVELOPE_PREFIX`], followed by the hash of the function
+//! the envelope is bound to, the version of the envelope as a big endian `u64`, the signature of
+//! the owner of the function, and the secrets as a json object of strings. The owner signs the
+//! plaintext without the signature. Anyone can encrypt an envelope for the nodes, so only the
+//! envelopes signed by the owner named in the manifest of the deployment are accepted.
+//!
+//! Secrets are rotated by uploading a new envelope with a higher version. Once a node has seen a
+//! version of an envelope for a function, it rejects the envelopes with lower versions. The
+//! versions are persisted, so old envelopes stay revoked when the service restarts.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+use anyhow::{anyhow, bail, Context, Result};
+use arrayref::array_ref;
+use deno_fleek::secrets::Secrets;
+use fleek_crypto::{AccountOwnerSignature, EthAddress};
+use fn_sdk::blockstore::ContentHandle;
+use tracing::warn;
+
+pub const ENVELOPE_PREFIX: [u8; 16] = *b"FLEEK_JS_SECRETS";
+const SIGNATURE_OFFSET: usize = ENVELOPE_PREFIX.len() + 32 + 8;
+const HEADER_LEN: usize = SIGNATURE_OFFSET + 65;
+
+/// Latest versions of the envelopes seen for every function.
+fn versions() -> &'static Versions {
+    static VERSIONS: OnceLock<Versions> = OnceLock::new();
+    VERSIONS.get_or_init(|| Versions::new(fn_sdk::ipc::data_path().join("secrets_versions")))
+}
+
+/// Secret keys the envelopes can be encrypted for, read from the `JS_SECRETS_KEYS` environment
+/// variable as comma separated hex encoded keys. Keeping the previous keys in the list allows to
+/// rotate the node key without breaking existing envelopes.
+fn secret_keys() -> &'static [Vec<u8>] {
+    static KEYS: OnceLock<Vec<Vec<u8>>> = OnceLock::new();
+    KEYS.get_or_init(|| {
+        let Ok(keys) = std::env::var("JS_SECRETS_KEYS") else {
+            return Vec::new();
+        };
+        keys.split(',')
+            .filter_map(|key| match hex::decode(key.trim()) {
+                Ok(key) => Some(key),
+                Err(_) => {
+                    warn!("Ignoring invalid key in JS_SECRETS_KEYS");
+                    None
+                },
+            })
+            .collect()
+    })
+}
+
+/// Decrypted content of a secrets envelope.
+pub struct Envelope {
+    /// Hash of the function the envelope is bound to.
+    pub function: [u8; 32],
+    pub version: u64,
+    /// Signature of the owner of the function over the rest of the plaintext.
+    pub signature: AccountOwnerSignature,
+    pub secrets: Secrets,
+    /// The plaintext without the signature, which is what the owner signs.
+    signed: Vec<u8>,
+}
+
+impl Envelope {
+    /// Decrypt an envelope with any of the given keys.
+    pub fn decrypt(cipher: &[u8], keys: &[Vec<u8>]) -> Result<Self> {
+        // Errors never include the plaintext, which would leak the secrets in the responses
+        let plaintext = keys
+            .iter()
+            .find_map(|key| ecies::decrypt(key, cipher).ok())
+            .context("Failed to decrypt secrets")?;
+        if plaintext.len() < HEADER_LEN || plaintext[..ENVELOPE_PREFIX.len()] != ENVELOPE_PREFIX {
+            bail!("Invalid secrets envelope");
+        }
+
+        let function = *array_ref![plaintext, ENVELOPE_PREFIX.len(), 32];
+        let version = u64::from_be_bytes(*array_ref![plaintext, ENVELOPE_PREFIX.len() + 32, 8]);
+        let signature = AccountOwnerSignature(*array_ref![plaintext, SIGNATURE_OFFSET, 65]);
+        let secrets = serde_json::from_slice::<HashMap<String, String>>(&plaintext[HEADER_LEN..])
+            .map_err(|_| anyhow!("Invalid secrets envelope"))?;
+
+        let mut signed = plaintext[..SIGNATURE_OFFSET].to_vec();
+        signed.extend_from_slice(&plaintext[HEADER_LEN..]);
+        Ok(Self {
+            function,
+            version,
+            signature,
+            secrets: Secrets(secrets),
+            signed,
+        })
+    }
+
+    /// Returns true if the envelope is signed by the given owner.
+    pub fn is_signed_by(&self, owner: &EthAddress) -> bool {
+        owner.verify(&self.signature, &self.signed)
+    }
+}
+
+/// Load the secrets of a function from the envelope with the given hash. Functions without an
+/// owner can't have secrets, since nothing would tell apart the envelopes of their owner.
+pub async fn load(
+    hash: [u8; 32],
+    function: [u8; 32],
+    owner: Option<EthAddress>,
+) -> Result<Secrets> {
+    let Some(owner) = owner else {
+        bail!("Secrets require a secretsOwner in the manifest of the deployment");
+    };
+    if !fn_sdk::api::fetch_blake3(hash).await {
+        bail!("Failed to fetch secrets");
+    }
+    let cipher = ContentHandle::load(&hash).await?.read_to_end().await?;
+
+    let envelope = Envelope::decrypt(&cipher, secret_keys())?;
+    if envelope.function != function {
+        bail!("Secrets are not bound to this function");
+    }
+    // Checked before the version, so forged envelopes can't revoke the real ones
+    if !envelope.is_signed_by(&owner) {
+        bail!("Secrets are not signed by the owner of the function");
+    }
+    versions().check(function, envelope.version)?;
+
+    Ok(envelope.secrets)
+}
+
+/// Latest versions of the envelopes seen for every function, kept in memory and in a file per
+/// function.
+struct Versions {
+    dir: PathBuf,
+    latest: Mutex<BTreeMap<[u8; 32], u64>>,
+}
+
+impl Versions {
+    fn new(dir: PathBuf) -> Self {
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            warn!("failed to create the secrets versions directory: {e}");
+        }
+
+        Self {
+            dir,
+            latest: Default::default(),
+        }
+    }
+
+    /// Reject envelopes older than the latest version seen for the function.
+    fn check(&self, function: [u8; 32], version: u64) -> Result<()> {
+        let mut versions = self.latest.lock().unwrap();
+        let path = self.dir.join(hex::encode(function));
+        let latest = match versions.get(&function) {
+            Some(latest) => *latest,
+            None => std::fs::read(&path)
+                .ok()
+                .and_then(|bytes| Some(u64::from_be_bytes(bytes.try_into().ok()?)))
+                .unwrap_or_default(),
+        };
+        if version < latest {
+            bail!("Secrets have been rotated");
+        }
+
+        if version > latest || !path.exists() {
+            // Write to a temporary file first, so a crash never leaves a partial version.
+            let tmp = path.with_extension("tmp");
+            std::fs::write(&tmp, version.to_be_bytes())
+                .and_then(|_| std::fs::rename(&tmp, &path))
+                .context("Failed to persist the version of the secrets")?;
+        }
+        versions.insert(function, version);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fleek_crypto::{AccountOwnerSecretKey, SecretKey};
+
+    use super::*;
+
+    fn encrypt(
+        function: [u8; 32],
+        version: u64,
+        secrets: &HashMap<String, String>,
+        owner: &AccountOwnerSecretKey,
+        public_key: &[u8],
+    ) -> Vec<u8> {
+        let mut signed = ENVELOPE_PREFIX.to_vec();
+        signed.extend_from_slice(&function);
+        signed.extend_from_slice(&version.to_be_bytes());
+        let secrets = serde_json::to_vec(secrets).unwrap();
+
+        let mut plaintext = signed.clone();
+        signed.extend_from_slice(&secrets);
+        plaintext.extend_from_slice(&owner.sign(&signed).0);
+        plaintext.extend_from_slice(&secrets);
+        ecies::encrypt(public_key, &plaintext).unwrap()
+    }
+
+    #[test]
+    fn test_envelope() {
+        let (secret_key, public_key) = ecies::utils::generate_keypair();
+        let (other_key, _) = ecies::utils::generate_keypair();
+        let owner = AccountOwnerSecretKey::generate();
+        let secrets = HashMap::from([("API_KEY".into(), "hunter2".into())]);
+        let cipher = encrypt([1; 32], 2, &secrets, &owner, &public_key.serialize());
+
+        let keys = vec![
+            other_key.serialize().to_vec(),
+            secret_key.serialize().to_vec(),
+        ];
+        let decrypted = Envelope::decrypt(&cipher, &keys).unwrap();
+        assert_eq!(decrypted.function, [1; 32]);
+        assert_eq!(decrypted.version, 2);
+        assert_eq!(decrypted.secrets.0["API_KEY"], "hunter2");
+        assert!(decrypted.is_signed_by(&owner.to_pk().into()));
+
+        let error = Envelope::decrypt(&cipher, &keys[..1]).err().unwrap();
+        assert!(!format!("{error:?}").contains("hunter2"));
+    }
+
+    #[test]
+    fn test_forged_envelope() {
+        let (secret_key, public_key) = ecies::utils::generate_keypair();
+        let owner: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+        let attacker = AccountOwnerSecretKey::generate();
+        let secrets = HashMap::from([("API_KEY".into(), "forged".into())]);
+
+        // Anyone can encrypt an envelope for the nodes and bind it to the function.
+        let cipher = encrypt(
+            [1; 32],
+            u64::MAX,
+            &secrets,
+            &attacker,
+            &public_key.serialize(),
+        );
+        let envelope = Envelope::decrypt(&cipher, &[secret_key.serialize().to_vec()]).unwrap();
+        assert!(!envelope.is_signed_by(&owner));
+    }
+
+    #[test]
+    fn test_rotation() {
+        let dir = std::env::temp_dir().join(format!("secrets-versions-{}", std::process::id()));
+        let function = [2; 32];
+
+        let versions = Versions::new(dir.clone());
+        assert!(versions.check(function, 1).is_ok());
+        assert!(versions.check(function, 2).is_ok());
+        assert!(versions.check(function, 2).is_ok());
+        assert!(versions.check(function, 1).is_err());
+
+        // The versions seen before a restart are still enforced.
+        let versions = Versions::new(dir.clone());
+        assert!(versions.check(function, 1).is_err());
+        assert!(versions.check(function, 3).is_ok());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
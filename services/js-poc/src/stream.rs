@@ -20,6 +20,9 @@ pub struct Request {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(alias = "params", alias = "parameter", alias = "parameters")]
     pub param: Option<serde_json::Value>,
+    /// Hex encoded blake3 hash of the encrypted secrets envelope of the function, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secrets: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]